@@ -0,0 +1,46 @@
+//! How fast lines are followed to their exits with the routing packed into
+//! bits, against walking the board's arrays of pieces, on an empty board and
+//! well into a game. Run with `cargo bench --features bitboard --bench bitboard`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use passtally_rs::bitboard::PackedRouting;
+use passtally_rs::game::Game;
+use passtally_rs::rules::Ruleset;
+
+const TRACES: usize = 1_000_000;
+
+fn main() {
+    let opening = Game::new_with_rules(Ruleset::new(2), 1);
+    let mut middle = opening.clone();
+    for _ in 0..20 {
+        let turn = middle.legal_turns_iter().next().unwrap();
+        middle.play_turn(turn).unwrap();
+    }
+
+    for (name, game) in &[("opening", &opening), ("middle", &middle)] {
+        let board = game.board;
+        let packed = PackedRouting::from_board(&board);
+
+        let start = Instant::now();
+        for i in 0..TRACES {
+            black_box(black_box(&board).trace((i % 24) as u8).exit);
+        }
+        let arrays = start.elapsed();
+
+        let start = Instant::now();
+        for i in 0..TRACES {
+            black_box(black_box(&packed).exit((i % 24) as u8));
+        }
+        let bits = start.elapsed();
+
+        let per_trace = |elapsed: std::time::Duration| elapsed.as_nanos() as f64 / TRACES as f64;
+        println!(
+            "{:<8} arrays: {:>6.1} ns/trace, packed: {:>6.1} ns/trace",
+            name,
+            per_trace(arrays),
+            per_trace(bits)
+        );
+    }
+}
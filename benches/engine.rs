@@ -0,0 +1,114 @@
+//! Criterion benchmarks of the engine's hot paths: setting up a game,
+//! placing a piece, tracing a line from where it enters the board and just
+//! following it to where it leaves, playing a whole turn, finding the legal
+//! actions and turns, and a 1000 playout MCTS search. Run with
+//! `cargo bench --bench engine`, criterion compares each run against the
+//! last one and flags regressions.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use passtally_rs::ai::{Agent, Limits, MctsAgent, Preset};
+use passtally_rs::board::edge_square;
+use passtally_rs::game::{Action, Game};
+use passtally_rs::rules::Ruleset;
+
+/// Walks through every item, so none are skipped: `count` alone counts
+/// the actions and turns without generating them.
+fn walk<T>(items: impl Iterator<Item = T>) -> usize {
+    items.fold(0, |found, item| {
+        black_box(item);
+        found + 1
+    })
+}
+
+/// A game twelve turns in, so the board has stacks and long lines.
+fn middle_game() -> Game {
+    let mut game = Game::new_with_rules(Ruleset::new(2), 1);
+    for _ in 0..12 {
+        let turn = game.legal_turns_iter().next().unwrap();
+        game.play_turn(turn).unwrap();
+    }
+    game
+}
+
+fn setup(c: &mut Criterion) {
+    let rules = Ruleset::new(2);
+    let mut seed = 0;
+    c.bench_function("new_game", |b| {
+        b.iter(|| {
+            seed += 1;
+            Game::new_with_rules(black_box(rules.clone()), seed)
+        })
+    });
+}
+
+fn board(c: &mut Criterion) {
+    let game = middle_game();
+    let piece = game
+        .legal_actions_iter()
+        .find_map(|action| match action {
+            Action::PlacePiece(piece) => Some(piece),
+            _ => None,
+        })
+        .unwrap();
+
+    c.bench_function("place_piece", |b| {
+        b.iter(|| {
+            let mut board = black_box(game.board);
+            board.place_piece(black_box(piece.clone())).unwrap();
+            board
+        })
+    });
+    c.bench_function("trace", |b| {
+        let mut entry = 0;
+        b.iter(|| {
+            entry = (entry + 1) % 24;
+            black_box(&game.board).trace(entry)
+        })
+    });
+    c.bench_function("enter", |b| {
+        let mut entry = 0;
+        b.iter(|| {
+            entry = (entry + 1) % 24;
+            let (pos, side) = edge_square(entry);
+            black_box(&game.board).enter(pos, side)
+        })
+    });
+}
+
+fn turns(c: &mut Criterion) {
+    let game = middle_game();
+    let turn = game.legal_turns_iter().next().unwrap();
+
+    c.bench_function("play_turn", |b| {
+        b.iter_batched(
+            || game.clone(),
+            |mut game| game.play_turn(black_box(turn.clone())).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("legal_actions", |b| {
+        b.iter(|| walk(black_box(&game).legal_actions_iter()))
+    });
+    c.bench_function("legal_turns", |b| {
+        b.iter(|| walk(black_box(&game).legal_turns_iter()))
+    });
+}
+
+fn search(c: &mut Criterion) {
+    let game = middle_game();
+    let params = Preset::default().mcts;
+
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    group.bench_function("mcts_1000_playouts", |b| {
+        b.iter(|| {
+            let mut agent = MctsAgent::new(params.clone(), 1);
+            agent.choose_turn(black_box(&game), &Limits::nodes(1000))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, setup, board, turns, search);
+criterion_main!(benches);
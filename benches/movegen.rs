@@ -0,0 +1,59 @@
+//! How long finding every legal action and every legal turn takes, in the
+//! opening and in the middle of a game. Every action is generated and looked
+//! at, as `count` alone only counts them, which is timed on its own.
+//!
+//! Counting stays well under a microsecond, but generating every action
+//! doesn't: building each one costs a few nanoseconds, so the hundreds of
+//! the opening take a couple of microseconds. Run with
+//! `cargo bench --bench movegen`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use passtally_rs::game::Game;
+use passtally_rs::rules::Ruleset;
+
+const ROUNDS: u32 = 100_000;
+
+/// Walks through every item, so none are skipped.
+fn walk<T>(items: impl Iterator<Item = T>) -> usize {
+    items.fold(0, |found, item| {
+        black_box(item);
+        found + 1
+    })
+}
+
+/// The average time of `f` over `rounds` calls.
+fn time(rounds: u32, mut f: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        black_box(f());
+    }
+    start.elapsed() / rounds
+}
+
+fn main() {
+    let opening = Game::new_with_rules(Ruleset::new(2), 1);
+    let mut middle = opening.clone();
+    for _ in 0..12 {
+        let turn = middle.legal_turns_iter().next().unwrap();
+        middle.play_turn(turn).unwrap();
+    }
+
+    for (name, game) in &[("opening", &opening), ("middle", &middle)] {
+        let count = game.legal_actions_iter().count();
+        let counting = time(ROUNDS, || black_box(*game).legal_actions_iter().count());
+        let actions = time(ROUNDS, || walk(black_box(*game).legal_actions_iter()));
+        let turns = time(ROUNDS / 100, || walk(black_box(*game).legal_turns_iter()));
+        println!(
+            "{:<8} {:>4} actions counted in {:>4} ns, found in {:>5} ns ({:.1} ns each), {:>6} turns in {:>8.0?}",
+            name,
+            count,
+            counting.as_nanos(),
+            actions.as_nanos(),
+            actions.as_nanos() as f64 / count as f64,
+            game.legal_turns_iter().count(),
+            turns,
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! How many positions alpha-beta searches two turns ahead with its move
+//! ordering (lines scored first, then killer moves, then the history table)
+//! and without it. Run with `cargo bench --bench ordering`.
+//!
+//! The bots as shipped search one turn ahead, see
+//! [`DEFAULT_DEPTH`](passtally_rs::ai::alphabeta::DEFAULT_DEPTH), and get no
+//! benefit from the ordering: every action is the searching player's, so
+//! nothing is ever cut off. The saving shown here only applies to bots given
+//! a depth of two or more.
+//!
+//! Two turns ahead the positions in the test suite don't finish in minutes,
+//! so the searches start two turns before the end of seeded random games,
+//! where the opponent's reply is the last turn and both searches finish. A
+//! search stopped by the node limit is reported as unfinished and left out
+//! of the totals.
+
+use std::time::Instant;
+
+use passtally_rs::ai::endgame::winning_turn;
+use passtally_rs::ai::{Agent, AlphaBetaAgent, Limits, Preset, RandomAgent};
+use passtally_rs::game::Game;
+use passtally_rs::rules::Ruleset;
+
+const DEPTH: u32 = 2;
+/// Turns before the end of the game the searches start.
+const TURNS_LEFT: usize = 2;
+const GAMES: u64 = 8;
+const NODES: u64 = 5_000_000;
+
+/// The position `TURNS_LEFT` turns before the end of a random game.
+fn late_position(seed: u64) -> Game {
+    let mut game = Game::new_with_rules(Ruleset::new(2), seed);
+    let mut agent = RandomAgent::new(seed);
+    let mut positions = vec![game.clone()];
+    while !game.is_finished() {
+        let turn = agent.choose_turn(&game, &Limits::none());
+        game.play_turn(turn).expect("Bots play legal turns");
+        positions.push(game.clone());
+    }
+    positions.swap_remove(positions.len() - 1 - TURNS_LEFT)
+}
+
+/// The nodes the agent searched, if it finished the search.
+fn search(agent: &mut AlphaBetaAgent, game: &Game, seed: u64, name: &str) -> Option<u64> {
+    let start = Instant::now();
+    agent.choose_turn(game, &Limits::nodes(NODES));
+    let finished = agent.depth_reached() == DEPTH;
+    println!(
+        "game {:<2} {:<9} {:>8} nodes{} in {:?}",
+        seed,
+        name,
+        agent.nodes(),
+        if finished { "" } else { ", unfinished," },
+        start.elapsed(),
+    );
+    Some(agent.nodes()).filter(|_| finished)
+}
+
+fn main() {
+    let weights = Preset::default().weights;
+    let (mut ordered, mut unordered) = (0, 0);
+    for seed in 0..GAMES {
+        let game = late_position(seed);
+        if winning_turn(&game, &Limits::none()).is_some() {
+            println!("game {:<2} won outright, the bots don't search", seed);
+            continue;
+        }
+        let mut with = AlphaBetaAgent::new(weights.clone(), DEPTH);
+        let mut without = AlphaBetaAgent::new(weights.clone(), DEPTH).without_ordering();
+        let with = search(&mut with, &game, seed, "ordered");
+        let without = search(&mut without, &game, seed, "unordered");
+        if let (Some(with), Some(without)) = (with, without) {
+            ordered += with;
+            unordered += without;
+        }
+    }
+    println!(
+        "Searches finished by both: {} nodes ordered, {} unordered",
+        ordered, unordered
+    );
+}
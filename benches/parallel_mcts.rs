@@ -0,0 +1,42 @@
+//! How many playouts a second [`ParallelMctsAgent`] gets through with more
+//! and more threads, from one up to every core. Run with
+//! `cargo bench --features parallel --bench parallel_mcts`.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use passtally_rs::ai::{Agent, Limits, ParallelMctsAgent, Preset};
+use passtally_rs::game::Game;
+use passtally_rs::rules::Ruleset;
+
+/// How long each thread count searches for.
+const SEARCH_TIME: Duration = Duration::from_secs(2);
+
+fn main() {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let game = Game::new_with_rules(Ruleset::new(2), 1);
+    let params = Preset::default().mcts;
+
+    let mut single = None;
+    let mut threads = 1;
+    while threads <= cores {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let mut agent = ParallelMctsAgent::new(params.clone(), threads, 1);
+        let start = Instant::now();
+        pool.install(|| agent.choose_turn(&game, &Limits::time(SEARCH_TIME)));
+        let rate = agent.playouts() as f64 / start.elapsed().as_secs_f64();
+        let speedup = rate / *single.get_or_insert(rate);
+        println!(
+            "{:>3} threads: {:>10.0} playouts/s ({:.2}x)",
+            threads, rate, speedup
+        );
+        threads = if threads == cores {
+            cores + 1
+        } else {
+            (threads * 2).min(cores)
+        };
+    }
+}
@@ -0,0 +1,83 @@
+//! How fast lines pass through pieces with the lookup table, against
+//! rotating into the piece and back on every hop, and how fast whole boards
+//! are traced, one edge at a time and all at once. Run with
+//! `cargo bench --bench pass_table`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use passtally_rs::game::Game;
+use passtally_rs::piece::{PartialPiece, RotatedPartialPiece, Side};
+use passtally_rs::rules::Ruleset;
+
+const HOPS: usize = 20_000_000;
+const TRACES: usize = 200_000;
+
+/// The pass as it was worked out before the table.
+fn rotated_pass(partial_piece: PartialPiece, rotation: u8, side: Side) -> Side {
+    partial_piece
+        .pass(side.rotate(4 - rotation))
+        .rotate(rotation)
+}
+
+fn main() {
+    let partial_pieces = [
+        PartialPiece::TopBottom_LeftRight,
+        PartialPiece::TopLeft_BottomRight,
+        PartialPiece::TopRight_BottomLeft,
+    ];
+    let pieces: Vec<(PartialPiece, u8)> = partial_pieces
+        .iter()
+        .flat_map(|&partial_piece| (0..4).map(move |rotation| (partial_piece, rotation)))
+        .collect();
+    let rotated: Vec<RotatedPartialPiece> = pieces
+        .iter()
+        .map(|&(partial_piece, rotation)| RotatedPartialPiece::new(partial_piece, rotation))
+        .collect();
+
+    let start = Instant::now();
+    let mut side = Side::Top;
+    for i in 0..HOPS {
+        let (partial_piece, rotation) = pieces[i % pieces.len()];
+        side = rotated_pass(black_box(partial_piece), black_box(rotation), side);
+    }
+    black_box(side);
+    let rotating = start.elapsed();
+
+    let start = Instant::now();
+    let mut side = Side::Top;
+    for i in 0..HOPS {
+        side = black_box(&rotated[i % rotated.len()]).pass(side);
+    }
+    black_box(side);
+    let table = start.elapsed();
+
+    let per_hop = |elapsed: std::time::Duration| elapsed.as_nanos() as f64 / HOPS as f64;
+    println!("rotating: {:>6.2} ns/hop", per_hop(rotating));
+    println!("table:    {:>6.2} ns/hop", per_hop(table));
+
+    // A board well into a game, so lines run a long way.
+    let mut game = Game::new_with_rules(Ruleset::new(2), 1);
+    for _ in 0..20 {
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+    }
+    let start = Instant::now();
+    for i in 0..TRACES {
+        black_box(game.board.trace(black_box((i % 24) as u8)));
+    }
+    println!(
+        "traces:   {:>6.0} ns/trace",
+        start.elapsed().as_nanos() as f64 / TRACES as f64
+    );
+
+    // Every edge at once, against the 24 traces it replaces.
+    let start = Instant::now();
+    for _ in 0..TRACES / 24 {
+        black_box(black_box(&game.board).trace_all_edges());
+    }
+    println!(
+        "all:      {:>6.0} ns/trace",
+        start.elapsed().as_nanos() as f64 / (TRACES / 24 * 24) as f64
+    );
+}
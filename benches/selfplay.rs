@@ -0,0 +1,36 @@
+//! How many complete games a minute self-play gets through between random
+//! bots, on one thread and on every core. Run with
+//! `cargo bench --bench selfplay`.
+
+use std::thread;
+use std::time::Instant;
+
+use passtally_rs::ai::selfplay::{self, SelfPlayConfig};
+use passtally_rs::ai::AgentSpec;
+
+const GAMES: usize = 200;
+
+fn main() {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let mut thread_counts = vec![1];
+    if cores > 1 {
+        thread_counts.push(cores);
+    }
+    for threads in thread_counts {
+        let config = SelfPlayConfig {
+            threads,
+            seed: 1,
+            ..SelfPlayConfig::new(vec![AgentSpec::Random, AgentSpec::Random], GAMES)
+        };
+        let start = Instant::now();
+        let games = selfplay::run(&config).unwrap();
+        let elapsed = start.elapsed();
+        let turns: usize = games.iter().map(|game| game.replay.turns.len()).sum();
+        println!(
+            "{:>3} threads: {:>7.0} games/minute, {:>4.1} turns a game",
+            threads,
+            GAMES as f64 / elapsed.as_secs_f64() * 60.0,
+            turns as f64 / GAMES as f64,
+        );
+    }
+}
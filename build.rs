@@ -0,0 +1,21 @@
+//! Writes the C header of the `ffi` feature to `include/passtally.h`.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "ffi")]
+    {
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=src/ffi/handles.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir))
+            .expect("cbindgen.toml should be valid");
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi.rs", dir))
+            .with_src(format!("{}/src/ffi/handles.rs", dir))
+            .generate()
+            .expect("The C header should generate")
+            .write_to_file(format!("{}/include/passtally.h", dir));
+    }
+}
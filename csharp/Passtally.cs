@@ -0,0 +1,175 @@
+// P/Invoke bindings to the handle interface of the `ffi` feature, see
+// src/ffi/handles.rs. Put the shared library in Assets/Plugins (passtally_rs.dll,
+// libpasstally_rs.so or libpasstally_rs.dylib) and this file anywhere under
+// Assets. Games are safe to use from any thread.
+
+using System;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
+using System.Text;
+
+namespace Passtally
+{
+    public enum Status
+    {
+        Ok = 0,
+        InvalidHandle = 1,
+        InvalidArgument = 2,
+        BadNotation = 3,
+        IllegalMove = 4,
+        BufferTooSmall = 5,
+        Panic = 6,
+    }
+
+    public class PasstallyException : Exception
+    {
+        public readonly Status Status;
+
+        public PasstallyException(Status status)
+            : base(Marshal.PtrToStringAnsi(Native.passtally_status_message(status)))
+        {
+            Status = status;
+        }
+    }
+
+    internal static class Native
+    {
+        const string Library = "passtally_rs";
+
+        public delegate Status StringReader(ulong handle, byte[] buffer, UIntPtr length, out UIntPtr needed);
+
+        [DllImport(Library)] public static extern Status passtally_game_create(byte players, ulong seed, out ulong handle);
+        [DllImport(Library)] public static extern Status passtally_game_from_notation(byte[] notation, out ulong handle);
+        [DllImport(Library)] public static extern Status passtally_game_clone(ulong handle, out ulong copy);
+        [DllImport(Library)] public static extern Status passtally_game_destroy(ulong handle);
+        [DllImport(Library)] public static extern Status passtally_game_to_notation(ulong handle, byte[] buffer, UIntPtr length, out UIntPtr needed);
+        [DllImport(Library)] public static extern Status passtally_game_legal_turns(ulong handle, byte[] buffer, UIntPtr length, out UIntPtr needed);
+        [DllImport(Library)] public static extern Status passtally_game_play_turn(ulong handle, byte[] turn, out uint points);
+        [DllImport(Library)] public static extern Status passtally_game_do_action(ulong handle, byte[] action);
+        [DllImport(Library)] public static extern Status passtally_game_next_player(ulong handle, out byte player);
+        [DllImport(Library)] public static extern Status passtally_game_round(ulong handle, out uint round);
+        [DllImport(Library)] public static extern Status passtally_game_score(ulong handle, byte player, out uint score);
+        [DllImport(Library)] public static extern Status passtally_game_is_finished(ulong handle, [MarshalAs(UnmanagedType.U1)] out bool finished);
+        [DllImport(Library)] public static extern IntPtr passtally_status_message(Status status);
+    }
+
+    /// A game of passtally. Dispose it, or the finalizer frees it.
+    public sealed class Game : IDisposable
+    {
+        ulong handle;
+
+        Game(ulong handle)
+        {
+            this.handle = handle;
+        }
+
+        /// A game between 1 to 4 players with the decks shuffled by the seed.
+        public Game(byte players, ulong seed)
+        {
+            Check(Native.passtally_game_create(players, seed, out handle));
+        }
+
+        public static Game FromNotation(string notation)
+        {
+            Check(Native.passtally_game_from_notation(Utf8(notation), out var handle));
+            return new Game(handle);
+        }
+
+        public Game Clone()
+        {
+            Check(Native.passtally_game_clone(handle, out var copy));
+            return new Game(copy);
+        }
+
+        public string ToNotation()
+        {
+            return ReadString(Native.passtally_game_to_notation);
+        }
+
+        /// Every turn the player to move could play, in notation.
+        public IReadOnlyList<string> LegalTurns()
+        {
+            var turns = ReadString(Native.passtally_game_legal_turns);
+            return turns.Length == 0 ? new string[0] : turns.Split('\n');
+        }
+
+        /// Plays a turn in notation, like "P:Pink@c3r1+M:4>7", and returns the points scored.
+        public uint PlayTurn(string turn)
+        {
+            Check(Native.passtally_game_play_turn(handle, Utf8(turn), out var points));
+            return points;
+        }
+
+        /// Takes a single action in notation, like "resign".
+        public void DoAction(string action)
+        {
+            Check(Native.passtally_game_do_action(handle, Utf8(action)));
+        }
+
+        public byte NextPlayer
+        {
+            get { Check(Native.passtally_game_next_player(handle, out var player)); return player; }
+        }
+
+        public uint Round
+        {
+            get { Check(Native.passtally_game_round(handle, out var round)); return round; }
+        }
+
+        public bool IsFinished
+        {
+            get { Check(Native.passtally_game_is_finished(handle, out var finished)); return finished; }
+        }
+
+        public uint Score(byte player)
+        {
+            Check(Native.passtally_game_score(handle, player, out var score));
+            return score;
+        }
+
+        public void Dispose()
+        {
+            if (handle != 0)
+            {
+                Native.passtally_game_destroy(handle);
+                handle = 0;
+            }
+            GC.SuppressFinalize(this);
+        }
+
+        ~Game()
+        {
+            if (handle != 0)
+            {
+                Native.passtally_game_destroy(handle);
+            }
+        }
+
+        static void Check(Status status)
+        {
+            if (status != Status.Ok)
+            {
+                throw new PasstallyException(status);
+            }
+        }
+
+        static byte[] Utf8(string s)
+        {
+            return Encoding.UTF8.GetBytes(s + "\0");
+        }
+
+        /// Calls the function with a buffer, growing it once if it's too small.
+        string ReadString(Native.StringReader read)
+        {
+            var buffer = new byte[256];
+            var status = read(handle, buffer, (UIntPtr)buffer.Length, out var needed);
+            if (status == Status.BufferTooSmall)
+            {
+                buffer = new byte[(int)needed];
+                status = read(handle, buffer, (UIntPtr)buffer.Length, out needed);
+            }
+            Check(status);
+            return Encoding.UTF8.GetString(buffer, 0, (int)needed - 1);
+        }
+    }
+}
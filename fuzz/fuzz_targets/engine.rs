@@ -0,0 +1,121 @@
+//! Plays whatever the fuzzer comes up with against the engine, legal or
+//! not, and checks it never panics and never bends the rules: stacks only
+//! grow, every player keeps their markers and scores only go up. Run with
+//! `cargo +nightly fuzz run engine` from the repository root.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use passtally_rs::board::BoardPosition;
+use passtally_rs::game::{Action, Game, Turn};
+use passtally_rs::piece::{Piece, PositionedPiece};
+use passtally_rs::rules::Ruleset;
+
+const PIECES: [Piece; 6] = [
+    Piece::Red,
+    Piece::Green,
+    Piece::Yellow,
+    Piece::Blue,
+    Piece::Cyan,
+    Piece::Pink,
+];
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    players: u8,
+    teams: bool,
+    allow_redraw: bool,
+    seed: u64,
+    moves: Vec<Move>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Move {
+    /// One of the legal turns, so games get somewhere.
+    Legal(u16),
+    Turn(FuzzAction, FuzzAction),
+    Action(FuzzAction),
+}
+
+/// An action with every field left as the fuzzer picked it, in range or not.
+#[derive(Arbitrary, Debug)]
+enum FuzzAction {
+    PlacePiece {
+        piece: u8,
+        x: i8,
+        y: i8,
+        rotation: u8,
+    },
+    MovePlayerMarker(u8, u8),
+    RedrawMarket(u8),
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+}
+
+impl From<FuzzAction> for Action {
+    fn from(action: FuzzAction) -> Action {
+        match action {
+            FuzzAction::PlacePiece {
+                piece,
+                x,
+                y,
+                rotation,
+            } => Action::PlacePiece(PositionedPiece {
+                piece: PIECES[piece as usize % PIECES.len()],
+                position: BoardPosition::new(x, y),
+                rotation,
+            }),
+            FuzzAction::MovePlayerMarker(from, to) => Action::MovePlayerMarker(from, to),
+            FuzzAction::RedrawMarket(deck) => Action::RedrawMarket(deck),
+            FuzzAction::Resign => Action::Resign,
+            FuzzAction::OfferDraw => Action::OfferDraw,
+            FuzzAction::AcceptDraw => Action::AcceptDraw,
+        }
+    }
+}
+
+/// What has to hold from one move to the next, whether or not it was legal.
+fn check_invariants(before: &Game, after: &Game) {
+    for pos in BoardPosition::all() {
+        assert!(after.board.height(pos) >= before.board.height(pos));
+    }
+    for player in 0..before.rules().player_count {
+        let markers = |game: &Game| game.player_markers().filter(|&(_, p)| p == player).count();
+        assert_eq!(markers(after), markers(before));
+    }
+    for (after, before) in after.scores().iter().zip(before.scores()) {
+        assert!(after >= before);
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let players = input.players % 4 + 1;
+    let rules = Ruleset {
+        teams: input.teams && players == 4,
+        allow_redraw: input.allow_redraw,
+        ..Ruleset::new(players)
+    };
+    let mut game = Game::new_with_rules(rules, input.seed);
+    for next in input.moves {
+        let before = game.clone();
+        match next {
+            Move::Legal(n) => {
+                let count = game.legal_turns_iter().count();
+                if count > 0 {
+                    let turn = game.legal_turns_iter().nth(n as usize % count).unwrap();
+                    game.play_turn(turn).unwrap();
+                }
+            }
+            Move::Turn(action1, action2) => {
+                let _ = game.play_turn(Turn(action1.into(), action2.into()));
+            }
+            Move::Action(action) => {
+                let _ = game.do_action(action.into());
+            }
+        }
+        check_invariants(&before, &game);
+    }
+});
@@ -0,0 +1,243 @@
+//! The rules engine as a Godot 4 GDExtension, so Godot frontends are built
+//! on the real engine. Adds a `PasstallyGame` class, which plays turns
+//! given in the engine's notation, like `P:Pink@c3r1+M:4>7`, and tells the
+//! frontend what happened through signals to animate.
+
+use godot::prelude::*;
+
+use passtally_rs::game::{Action, Game, Turn};
+use passtally_rs::rules::Ruleset;
+
+struct PasstallyExtension;
+
+#[gdextension]
+unsafe impl ExtensionLibrary for PasstallyExtension {}
+
+/// A game of passtally. Starts as a two player game with seed 0, call
+/// `start` or `load_notation` to set up another.
+#[derive(GodotClass)]
+#[class(base = RefCounted)]
+pub struct PasstallyGame {
+    game: Game,
+    /// Why the last call failed, empty if it didn't.
+    error: GString,
+    base: Base<RefCounted>,
+}
+
+#[godot_api]
+impl IRefCounted for PasstallyGame {
+    fn init(base: Base<RefCounted>) -> Self {
+        PasstallyGame {
+            game: Game::new_with_rules(Ruleset::new(2), 0),
+            error: GString::new(),
+            base,
+        }
+    }
+}
+
+#[godot_api]
+impl PasstallyGame {
+    /// A piece was placed, its first half at the column and row from the top
+    /// left, rotated clockwise 0 to 3 times.
+    #[signal]
+    fn piece_placed(piece: GString, x: i64, y: i64, rotation: i64);
+
+    /// A player marker moved between edge slots, clockwise from the top left.
+    #[signal]
+    fn marker_moved(from: i64, to: i64);
+
+    /// The top piece of a deck was discarded.
+    #[signal]
+    fn market_redrawn(deck: i64);
+
+    /// A line between two of the player's markers scored at the end of their
+    /// turn.
+    #[signal]
+    fn line_scored(player: i64, entry: i64, exit: i64, points: i64);
+
+    /// The player's turn is over, having scored the points.
+    #[signal]
+    fn turn_played(player: i64, points: i64);
+
+    #[signal]
+    fn game_finished();
+
+    /// Starts a new game between 1 to 4 players, with the decks shuffled by
+    /// the seed. Returns false for any other amount of players.
+    #[func]
+    fn start(&mut self, players: i64, seed: i64) -> bool {
+        if !(1..=4).contains(&players) {
+            return self.fail("Games are for 1 to 4 players.");
+        }
+        self.game = Game::new_with_rules(Ruleset::new(players as u8), seed as u64);
+        true
+    }
+
+    /// Sets up the game in the notation. Returns false, leaving the game as
+    /// it was, if it isn't valid.
+    #[func]
+    fn load_notation(&mut self, notation: GString) -> bool {
+        match Game::from_notation(&notation.to_string()) {
+            Ok(game) => {
+                self.game = game;
+                true
+            }
+            Err(error) => self.fail(error),
+        }
+    }
+
+    #[func]
+    fn to_notation(&self) -> GString {
+        self.game.to_notation().into()
+    }
+
+    #[func]
+    fn next_player(&self) -> i64 {
+        self.game.next_player() as i64
+    }
+
+    #[func]
+    fn round(&self) -> i64 {
+        self.game.round() as i64
+    }
+
+    #[func]
+    fn scores(&self) -> PackedInt64Array {
+        self.game
+            .scores()
+            .iter()
+            .map(|&score| score as i64)
+            .collect()
+    }
+
+    /// The pieces on top of the three decks, empty for empty decks.
+    #[func]
+    fn market(&self) -> PackedStringArray {
+        self.game
+            .market()
+            .iter()
+            .map(|piece| piece.map_or_else(GString::new, |piece| piece.to_string().into()))
+            .collect()
+    }
+
+    #[func]
+    fn is_finished(&self) -> bool {
+        self.game.is_finished()
+    }
+
+    /// Every action the player to move could take as half of their turn.
+    #[func]
+    fn legal_actions(&self) -> PackedStringArray {
+        self.game
+            .legal_actions_iter()
+            .map(|action| GString::from(action.to_string()))
+            .collect()
+    }
+
+    /// Every turn the player to move could play.
+    #[func]
+    fn legal_turns(&self) -> PackedStringArray {
+        self.game
+            .legal_turns_iter()
+            .map(|turn| GString::from(turn.to_string()))
+            .collect()
+    }
+
+    /// Plays the turn for the player to move and signals what it did.
+    /// Returns false, playing nothing, if the turn isn't legal.
+    #[func]
+    fn play_turn(&mut self, turn: GString) -> bool {
+        let turn: Turn = match turn.to_string().parse() {
+            Ok(turn) => turn,
+            Err(error) => return self.fail(error),
+        };
+        let events = self.game.score_events().len();
+        let outcome = match self.game.play_turn(turn.clone()) {
+            Ok(outcome) => outcome,
+            Err(error) => return self.fail(error),
+        };
+
+        for action in [&turn.0, &turn.1] {
+            self.signal_action(action);
+        }
+        let lines: Vec<[Variant; 4]> = self.game.score_events()[events..]
+            .iter()
+            .map(|line| {
+                [
+                    (line.player as i64).to_variant(),
+                    (line.entry as i64).to_variant(),
+                    (line.exit as i64).to_variant(),
+                    (line.points as i64).to_variant(),
+                ]
+            })
+            .collect();
+        for line in &lines {
+            self.base_mut().emit_signal("line_scored", line);
+        }
+        let played = [
+            (outcome.player as i64).to_variant(),
+            (outcome.points as i64).to_variant(),
+        ];
+        self.base_mut().emit_signal("turn_played", &played);
+        self.signal_if_finished();
+        true
+    }
+
+    /// Takes a single action, such as `resign` or `offer-draw`. Returns
+    /// false if it isn't legal.
+    #[func]
+    fn do_action(&mut self, action: GString) -> bool {
+        let action: Action = match action.to_string().parse() {
+            Ok(action) => action,
+            Err(error) => return self.fail(error),
+        };
+        if let Err(error) = self.game.do_action(action.clone()) {
+            return self.fail(error);
+        }
+        self.signal_action(&action);
+        self.signal_if_finished();
+        true
+    }
+
+    /// Why the last call returning false failed.
+    #[func]
+    fn last_error(&self) -> GString {
+        self.error.clone()
+    }
+}
+
+impl PasstallyGame {
+    fn fail(&mut self, error: impl ToString) -> bool {
+        self.error = error.to_string().into();
+        false
+    }
+
+    fn signal_action(&mut self, action: &Action) {
+        match action {
+            Action::PlacePiece(piece) => {
+                let placed = [
+                    piece.piece.to_string().to_variant(),
+                    (piece.position.x as i64).to_variant(),
+                    (piece.position.y as i64).to_variant(),
+                    (piece.rotation as i64).to_variant(),
+                ];
+                self.base_mut().emit_signal("piece_placed", &placed);
+            }
+            Action::MovePlayerMarker(from, to) => {
+                let moved = [(*from as i64).to_variant(), (*to as i64).to_variant()];
+                self.base_mut().emit_signal("marker_moved", &moved);
+            }
+            Action::RedrawMarket(deck) => {
+                let redrawn = [(*deck as i64).to_variant()];
+                self.base_mut().emit_signal("market_redrawn", &redrawn);
+            }
+            Action::Resign | Action::OfferDraw | Action::AcceptDraw => {}
+        }
+    }
+
+    fn signal_if_finished(&mut self) {
+        if self.game.is_finished() {
+            self.base_mut().emit_signal("game_finished", &[]);
+        }
+    }
+}
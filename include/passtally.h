@@ -0,0 +1,312 @@
+#ifndef PASSTALLY_H
+#define PASSTALLY_H
+
+/* Generated by cbindgen from src/ffi.rs and src/ffi/handles.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * What happened, returned by every function.
+ */
+enum PasstallyStatus
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : int32_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  PASSTALLY_STATUS_OK = 0,
+  /**
+   * The handle isn't a game, or the game was destroyed.
+   */
+  PASSTALLY_STATUS_INVALID_HANDLE = 1,
+  /**
+   * A pointer was null, a string wasn't UTF-8 or a number was out of range.
+   */
+  PASSTALLY_STATUS_INVALID_ARGUMENT = 2,
+  /**
+   * The notation couldn't be read.
+   */
+  PASSTALLY_STATUS_BAD_NOTATION = 3,
+  /**
+   * The move isn't legal right now. Nothing was played.
+   */
+  PASSTALLY_STATUS_ILLEGAL_MOVE = 4,
+  /**
+   * The buffer is too small, the length needed was written.
+   */
+  PASSTALLY_STATUS_BUFFER_TOO_SMALL = 5,
+  /**
+   * The engine hit a bug. The game may be left in any state.
+   */
+  PASSTALLY_STATUS_PANIC = 6,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum PasstallyStatus PasstallyStatus;
+#else
+typedef int32_t PasstallyStatus;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+/**
+ * A game of passtally.
+ */
+typedef struct PasstallyGame PasstallyGame;
+
+/**
+ * A game, from [`passtally_game_create`] and friends. 0 is never a game.
+ */
+typedef uint64_t PasstallyHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * A new game between 1 to 4 players with the decks shuffled by the seed, or
+ * null for any other amount of players.
+ */
+struct PasstallyGame *passtally_new(uint8_t players, uint64_t seed);
+
+/**
+ * # Safety
+ *
+ * `game` has to be null or a game from this library not freed yet.
+ */
+void passtally_free(struct PasstallyGame *game);
+
+/**
+ * # Safety
+ *
+ * `s` has to be null or a string from this library not freed yet.
+ */
+void passtally_string_free(char *s);
+
+/**
+ * Why the last function to fail on this thread failed. The string is owned
+ * by the library and valid until the next call failing on the thread.
+ */
+const char *passtally_last_error(void);
+
+/**
+ * The game in the notation, or null if it isn't valid.
+ *
+ * # Safety
+ *
+ * `notation` has to be null or a null terminated string.
+ */
+struct PasstallyGame *passtally_from_notation(const char *notation);
+
+/**
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+char *passtally_to_notation(const struct PasstallyGame *game);
+
+/**
+ * The game in JSON, as saved by the serde support, or null if it isn't
+ * valid.
+ *
+ * # Safety
+ *
+ * `json` has to be null or a null terminated string.
+ */
+struct PasstallyGame *passtally_from_json(const char *json);
+
+/**
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+char *passtally_to_json(const struct PasstallyGame *game);
+
+/**
+ * The player to move, or 255 if the engine hit a bug.
+ *
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+uint8_t passtally_next_player(const struct PasstallyGame *game);
+
+/**
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+uint32_t passtally_round(const struct PasstallyGame *game);
+
+/**
+ * The player's score, or -1 if there is no such player.
+ *
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+int64_t passtally_score(const struct PasstallyGame *game, uint8_t player);
+
+/**
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+bool passtally_is_finished(const struct PasstallyGame *game);
+
+/**
+ * Every turn the player to move could play, as a JSON array of turns in
+ * notation.
+ *
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet.
+ */
+char *passtally_legal_turns(const struct PasstallyGame *game);
+
+/**
+ * Plays the turn, in notation, for the player to move. Returns the points
+ * they scored, or -1 if the turn isn't legal, in which case nothing is
+ * played.
+ *
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet, and `turn` null
+ * or a null terminated string.
+ */
+int64_t passtally_play_turn(struct PasstallyGame *game, const char *turn);
+
+/**
+ * Takes a single action in notation, such as `resign`. Returns 0, or -1 if
+ * the action isn't legal.
+ *
+ * # Safety
+ *
+ * `game` has to be a game from this library not freed yet, and `action`
+ * null or a null terminated string.
+ */
+int32_t passtally_do_action(struct PasstallyGame *game, const char *action);
+
+/**
+ * Starts a game between 1 to 4 players with the decks shuffled by the
+ * seed.
+ *
+ * # Safety
+ *
+ * `handle` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_create(uint8_t players, uint64_t seed, PasstallyHandle *handle);
+
+/**
+ * Sets up the game in the notation.
+ *
+ * # Safety
+ *
+ * `notation` has to be a null terminated string and `handle` has to point
+ * to writable memory.
+ */
+PasstallyStatus passtally_game_from_notation(const char *notation, PasstallyHandle *handle);
+
+/**
+ * A new game in the same position as the game, to try moves on.
+ *
+ * # Safety
+ *
+ * `copy` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_clone(PasstallyHandle handle, PasstallyHandle *copy);
+
+/**
+ * Frees the game. The handle is invalid from then on.
+ */
+PasstallyStatus passtally_game_destroy(PasstallyHandle handle);
+
+/**
+ * The game in notation. Pass a null buffer to find the length needed.
+ *
+ * # Safety
+ *
+ * `buffer` has to be null or `length` bytes of writable memory, and
+ * `needed` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_to_notation(PasstallyHandle handle,
+                                           char *buffer,
+                                           size_t length,
+                                           size_t *needed);
+
+/**
+ * Every turn the player to move could play, in notation, one per line.
+ * Pass a null buffer to find the length needed.
+ *
+ * # Safety
+ *
+ * `buffer` has to be null or `length` bytes of writable memory, and
+ * `needed` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_legal_turns(PasstallyHandle handle,
+                                           char *buffer,
+                                           size_t length,
+                                           size_t *needed);
+
+/**
+ * Plays the turn, in notation, for the player to move and writes the
+ * points they scored.
+ *
+ * # Safety
+ *
+ * `turn` has to be a null terminated string and `points` has to point to
+ * writable memory.
+ */
+PasstallyStatus passtally_game_play_turn(PasstallyHandle handle,
+                                         const char *turn,
+                                         uint32_t *points);
+
+/**
+ * Takes a single action in notation, such as `resign`.
+ *
+ * # Safety
+ *
+ * `action` has to be a null terminated string.
+ */
+PasstallyStatus passtally_game_do_action(PasstallyHandle handle, const char *action);
+
+/**
+ * # Safety
+ *
+ * `player` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_next_player(PasstallyHandle handle, uint8_t *player);
+
+/**
+ * # Safety
+ *
+ * `round` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_round(PasstallyHandle handle, uint32_t *round);
+
+/**
+ * # Safety
+ *
+ * `score` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_score(PasstallyHandle handle, uint8_t player, uint32_t *score);
+
+/**
+ * # Safety
+ *
+ * `finished` has to point to writable memory.
+ */
+PasstallyStatus passtally_game_is_finished(PasstallyHandle handle, bool *finished);
+
+/**
+ * What the status means, in English. The string is static.
+ */
+const char *passtally_status_message(PasstallyStatus status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PASSTALLY_H */
@@ -0,0 +1,137 @@
+//! The rules engine as a native Node.js module, for JavaScript backends to
+//! host authoritative games on the real engine without WebAssembly. Build
+//! it with `npm run build`, which also writes the TypeScript definitions.
+//!
+//! Actions and turns go back and forth in the text notation of the engine,
+//! like `P:Pink@c3r1+M:4>7`, and whole games in that notation or as the JSON
+//! the engine saves. Illegal moves and bad notation throw.
+
+use napi::{Error, Result};
+use napi_derive::napi;
+
+use passtally_rs::game::{Action, Turn};
+use passtally_rs::rules::Ruleset;
+
+fn error(error: impl ToString) -> Error {
+    Error::from_reason(error.to_string())
+}
+
+/// What a turn scored.
+#[napi(object)]
+pub struct TurnOutcome {
+    pub player: u32,
+    pub points: u32,
+}
+
+/// A game of passtally.
+#[napi]
+pub struct Game {
+    game: passtally_rs::game::Game,
+}
+
+#[napi]
+impl Game {
+    /// A new game between 1 to 4 players, with the decks shuffled by the
+    /// seed.
+    #[napi(constructor)]
+    pub fn new(players: u32, seed: i64) -> Result<Game> {
+        if !(1..=4).contains(&players) {
+            return Err(error("Games are for 1 to 4 players."));
+        }
+        let rules = Ruleset::new(players as u8);
+        Ok(Game {
+            game: passtally_rs::game::Game::new_with_rules(rules, seed as u64),
+        })
+    }
+
+    #[napi(factory)]
+    pub fn from_notation(notation: String) -> Result<Game> {
+        let game = passtally_rs::game::Game::from_notation(&notation).map_err(error)?;
+        Ok(Game { game })
+    }
+
+    #[napi]
+    pub fn to_notation(&self) -> String {
+        self.game.to_notation()
+    }
+
+    /// The game as saved by the engine, for storing games between requests.
+    #[napi(factory)]
+    pub fn from_json(json: String) -> Result<Game> {
+        let game: passtally_rs::game::Game = serde_json::from_str(&json).map_err(error)?;
+        game.validate().map_err(error)?;
+        Ok(Game { game })
+    }
+
+    #[napi]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.game).map_err(error)
+    }
+
+    #[napi(getter)]
+    pub fn next_player(&self) -> u32 {
+        self.game.next_player() as u32
+    }
+
+    #[napi(getter)]
+    pub fn round(&self) -> u32 {
+        self.game.round()
+    }
+
+    #[napi(getter)]
+    pub fn scores(&self) -> Vec<u32> {
+        self.game.scores().to_vec()
+    }
+
+    /// The pieces on top of the three decks, null for empty decks.
+    #[napi(getter)]
+    pub fn market(&self) -> Vec<Option<String>> {
+        self.game
+            .market()
+            .iter()
+            .map(|piece| piece.map(|piece| piece.to_string()))
+            .collect()
+    }
+
+    #[napi(getter)]
+    pub fn is_finished(&self) -> bool {
+        self.game.is_finished()
+    }
+
+    /// Every action the player to move could take as half of their turn.
+    #[napi]
+    pub fn legal_actions(&self) -> Vec<String> {
+        self.game
+            .legal_actions_iter()
+            .map(|action| action.to_string())
+            .collect()
+    }
+
+    /// Every turn the player to move could play.
+    #[napi]
+    pub fn legal_turns(&self) -> Vec<String> {
+        self.game
+            .legal_turns_iter()
+            .map(|turn| turn.to_string())
+            .collect()
+    }
+
+    /// Plays the turn for the player to move. If either action is illegal
+    /// nothing is played.
+    #[napi]
+    pub fn play_turn(&mut self, turn: String) -> Result<TurnOutcome> {
+        let turn: Turn = turn.parse().map_err(error)?;
+        let outcome = self.game.play_turn(turn).map_err(error)?;
+        Ok(TurnOutcome {
+            player: outcome.player as u32,
+            points: outcome.points,
+        })
+    }
+
+    /// Takes a single action, such as `resign` or `offer-draw`.
+    #[napi]
+    pub fn do_action(&mut self, action: String) -> Result<()> {
+        let action: Action = action.parse().map_err(error)?;
+        self.game.do_action(action).map_err(error)
+    }
+}
@@ -0,0 +1,17 @@
+// Plays a few turns through the built module. Run `npm run build` first.
+const assert = require('assert')
+const { Game } = require('./index.js')
+
+const game = new Game(2, 7)
+for (let i = 0; i < 4; i++) {
+  const outcome = game.playTurn(game.legalTurns()[0])
+  assert.strictEqual(outcome.player, i % 2)
+}
+assert.strictEqual(game.round, 4)
+assert.throws(() => game.playTurn('M:0>30+R:1'))
+assert.strictEqual(Game.fromJson(game.toJson()).toNotation(), game.toNotation())
+assert.strictEqual(Game.fromNotation(game.toNotation()).round, 4)
+const broken = JSON.parse(game.toJson())
+broken.rules.player_count = 0
+assert.throws(() => Game.fromJson(JSON.stringify(broken)), /not 0/)
+console.log('ok')
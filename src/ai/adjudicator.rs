@@ -0,0 +1,188 @@
+//! Games between [external engines](super::external), refereed: the
+//! adjudicator keeps the clocks, tells each engine the position when it's
+//! their turn and plays the turns they answer with. An engine playing an
+//! illegal turn, running out of time or going silent loses, as if it had
+//! resigned, and the others play on if there is more than one left.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::external::{EngineError, ExternalEngine};
+use super::playout::rewards;
+use super::protocol::{Command, Go, Response, Start};
+use super::selfplay::{SelfPlayError, MAX_TURNS};
+use crate::clock::TimeControl;
+use crate::game::{Game, PasstallyError};
+use crate::replay::Replay;
+use crate::rules::Ruleset;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchConfig {
+    pub seed: u64,
+    pub control: TimeControl,
+    /// Extra time an engine gets to answer after its clock has run out,
+    /// for the pipes and the process to catch up, before it loses.
+    pub grace: Duration,
+    pub max_turns: u32,
+}
+
+impl MatchConfig {
+    /// Games on the time control, stopped after [`MAX_TURNS`] turns.
+    pub fn new(control: TimeControl, seed: u64) -> MatchConfig {
+        MatchConfig {
+            seed,
+            control,
+            grace: Duration::from_millis(100),
+            max_turns: MAX_TURNS,
+        }
+    }
+}
+
+/// How an engine lost the game through no fault of the rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fault {
+    IllegalTurn {
+        turn: String,
+        error: String,
+    },
+    Timeout,
+    /// The engine stopped talking or said something that isn't part of the
+    /// protocol.
+    Broken {
+        error: String,
+    },
+}
+
+/// A game played by [`play`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Adjudicated {
+    /// The turns played, the engines' names as the players.
+    pub replay: Replay,
+    /// The engines that lost by a fault, by seat, in the order they did.
+    pub faults: Vec<(u8, Fault)>,
+    /// How well each seat did, as in [`rewards`].
+    pub rewards: Vec<f64>,
+    /// Whether the game ended, rather than being stopped after
+    /// [`max_turns`](MatchConfig::max_turns).
+    pub finished: bool,
+}
+
+impl Adjudicated {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SelfPlayError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Plays a game between the engines, the first engine in the first seat.
+pub fn play(engines: &mut [ExternalEngine], config: &MatchConfig) -> Adjudicated {
+    let players = engines.len() as u8;
+    let rules = Ruleset::new(players);
+    let names = engines
+        .iter()
+        .map(|engine| engine.name().to_string())
+        .collect();
+    let mut replay = Replay::new(names, rules.clone(), config.seed);
+    let mut faults = Vec::new();
+
+    let start = Command::NewGame {
+        players,
+        seed: config.seed,
+    };
+    for engine in engines.iter_mut() {
+        // An engine that can't be told will fail on its turn just the same.
+        let _ = engine.send(&start);
+    }
+    let mut game = Game::new_with_rules(rules, config.seed);
+    game.set_time_control(config.control);
+
+    while !game.is_finished() && replay.turns.len() < config.max_turns as usize {
+        let player = game.next_player();
+        if let Err(fault) = turn(
+            &mut engines[player as usize],
+            &mut game,
+            &mut replay,
+            config,
+        ) {
+            // Running out of time ends the game by itself.
+            if !game.is_finished() {
+                game.resign(player).expect("The player to move can resign");
+            }
+            faults.push((player, fault));
+        }
+    }
+
+    Adjudicated {
+        rewards: rewards(&game),
+        finished: game.is_finished(),
+        replay,
+        faults,
+    }
+}
+
+/// Asks the player to move for their turn and plays it.
+fn turn(
+    engine: &mut ExternalEngine,
+    game: &mut Game,
+    replay: &mut Replay,
+    config: &MatchConfig,
+) -> Result<(), Fault> {
+    let player = game.next_player();
+    let remaining = game.clock().map_or(config.control.base, |clock| {
+        clock.remaining(player).saturating_sub(clock.elapsed())
+    });
+    // From the deal, which the engines were told in `newgame`, since the
+    // second action of a turn can place a piece the first uncovers.
+    let position = Command::Position {
+        start: Start::Dealt {
+            players: replay.rules.player_count,
+            seed: replay.seed,
+        },
+        turns: replay.turns.clone(),
+    };
+    let go = Command::Go(Go {
+        clock: Some((remaining, config.control.increment)),
+        ..Go::default()
+    });
+    engine.send(&position).map_err(broken)?;
+    engine.send(&go).map_err(broken)?;
+
+    let started = Instant::now();
+    let deadline = started + remaining + config.grace;
+    let chosen = loop {
+        match engine.receive(deadline) {
+            Ok(Response::BestTurn(turn)) => break turn,
+            Ok(Response::Error(error)) => return Err(Fault::Broken { error }),
+            Ok(_) => {}
+            Err(EngineError::Timeout) => {
+                let _ = engine.send(&Command::Stop);
+                game.claim_time_forfeit();
+                return Err(Fault::Timeout);
+            }
+            Err(err) => return Err(broken(err)),
+        }
+    };
+
+    match game.play_timed_turn(chosen.clone(), started.elapsed()) {
+        Ok(outcome) => {
+            replay.turns.push(chosen);
+            replay.outcomes.push(outcome);
+            Ok(())
+        }
+        Err(PasstallyError::TimeForfeit(_)) => Err(Fault::Timeout),
+        Err(err) => Err(Fault::IllegalTurn {
+            turn: chosen.to_string(),
+            error: err.to_string(),
+        }),
+    }
+}
+
+fn broken(err: EngineError) -> Fault {
+    Fault::Broken {
+        error: err.to_string(),
+    }
+}
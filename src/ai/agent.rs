@@ -0,0 +1,403 @@
+//! The interface every bot implements, so the GUI, the command line and the
+//! tournament tools can drive any of them the same way.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::game::{Game, Turn};
+
+/// How much a bot may think about one turn. Every bot honors the time
+/// limits and the stop flag. Bots that can't measure their effort in nodes
+/// or depth ignore those limits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Time for this turn.
+    pub max_time: Option<Duration>,
+    /// Positions searched, or playouts for the monte carlo bots.
+    pub max_nodes: Option<u64>,
+    /// Turns searched ahead, for the bots searching to a depth.
+    pub max_depth: Option<u32>,
+    /// The time left on the player's game clock, shared out between the
+    /// turns they have left.
+    pub clock: Option<ClockTime>,
+    /// Stops the search early when raised from another thread.
+    pub stop: Option<StopFlag>,
+    /// Told what the search has found so far, every so often while it runs.
+    pub info: Option<InfoCallback>,
+}
+
+/// A player's side of the game clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockTime {
+    pub remaining: Duration,
+    /// Time added after each turn.
+    pub increment: Duration,
+}
+
+impl Limits {
+    /// No limits, the bot thinks as long as it wants to.
+    pub fn none() -> Limits {
+        Limits::default()
+    }
+
+    pub fn time(max_time: Duration) -> Limits {
+        Limits {
+            max_time: Some(max_time),
+            ..Limits::default()
+        }
+    }
+
+    pub fn nodes(max_nodes: u64) -> Limits {
+        Limits {
+            max_nodes: Some(max_nodes),
+            ..Limits::default()
+        }
+    }
+
+    pub fn depth(max_depth: u32) -> Limits {
+        Limits {
+            max_depth: Some(max_depth),
+            ..Limits::default()
+        }
+    }
+
+    /// Thinking within the time left on the game clock.
+    pub fn clock(remaining: Duration, increment: Duration) -> Limits {
+        Limits {
+            clock: Some(ClockTime {
+                remaining,
+                increment,
+            }),
+            ..Limits::default()
+        }
+    }
+
+    /// The same limits, also within the time the player to move has left on
+    /// the game's clock, if it has one.
+    pub fn with_game_clock(self, game: &Game) -> Limits {
+        match game.clock() {
+            Some(clock) => Limits {
+                clock: Some(ClockTime {
+                    remaining: clock.remaining(game.next_player()),
+                    increment: clock.control().increment,
+                }),
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// The limits for the turn about to be thought about: the time on the
+    /// clock becomes time for this turn. Bots call this before searching.
+    pub fn for_turn(&self, game: &Game) -> Limits {
+        let budget = self.clock.map(|clock| {
+            // Roughly two pieces placed a turn, and a turn or two to spare.
+            let pieces: usize = game.decks_remaining().iter().sum();
+            let players = game.rules().player_count as usize;
+            let turns = pieces / (2 * players) + 2;
+            let share = clock.remaining / turns as u32 + clock.increment;
+            // Never the whole clock, the turn has to be played too.
+            share.min(clock.remaining * 9 / 10)
+        });
+        let max_time = match (self.max_time, budget) {
+            (Some(time), Some(budget)) => Some(time.min(budget)),
+            (time, budget) => time.or(budget),
+        };
+        Limits {
+            max_time,
+            clock: None,
+            ..self.clone()
+        }
+    }
+
+    /// The same limits, also stopping when the flag is raised.
+    pub fn with_stop(self, stop: StopFlag) -> Limits {
+        Limits {
+            stop: Some(stop),
+            ..self
+        }
+    }
+
+    /// The same limits, reporting to the callback as the search goes.
+    pub fn with_info(self, info: InfoCallback) -> Limits {
+        Limits {
+            info: Some(info),
+            ..self
+        }
+    }
+
+    /// Passes what the search has found to the callback, if there is one.
+    /// The info is only put together when someone is listening, the callback
+    /// or, with the `instrument` feature, a tracing subscriber.
+    pub fn report(&self, info: impl FnOnce() -> SearchInfo) {
+        #[cfg(feature = "instrument")]
+        {
+            if tracing::enabled!(tracing::Level::DEBUG) {
+                let info = info();
+                tracing::debug!(
+                    nodes = info.nodes,
+                    depth = info.depth,
+                    elapsed = ?info.elapsed,
+                    score = info.score,
+                    "searched"
+                );
+                if let Some(callback) = &self.info {
+                    callback.report(&info);
+                }
+                return;
+            }
+        }
+        if let Some(callback) = &self.info {
+            callback.report(&info());
+        }
+    }
+
+    /// Whether a search that started at `start` has run out of time or was
+    /// told to stop. Searches check this every so often, so a stopped search
+    /// still plays the best turn it has found.
+    pub fn should_stop(&self, start: Instant) -> bool {
+        matches!(self.max_time, Some(max) if start.elapsed() >= max)
+            || matches!(&self.stop, Some(stop) if stop.is_raised())
+    }
+}
+
+/// A flag shared between a thinking bot and whoever waits for it, to cut the
+/// thinking short. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct StopFlag(Arc<AtomicBool>);
+
+impl StopFlag {
+    pub fn new() -> StopFlag {
+        StopFlag::default()
+    }
+
+    pub fn raise(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Lowers the flag again, to reuse it for the next turn.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_raised(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Flags are equal when they are the same flag.
+impl PartialEq for StopFlag {
+    fn eq(&self, other: &StopFlag) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for StopFlag {}
+
+/// What a search has found so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    /// Positions searched, or playouts for the monte carlo bots.
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Turns searched ahead. The monte carlo bots count the turns along the
+    /// principal variation.
+    pub depth: u32,
+    /// How good the position looks for the bot's player: the evaluation for
+    /// alpha-beta, the average reward for the monte carlo bots.
+    pub score: f32,
+    /// The turns the bot expects to be played, starting with its own. The
+    /// alpha-beta bots average over the pieces to come rather than expect
+    /// one line, so they only give their own turn.
+    pub pv: Vec<Turn>,
+}
+
+impl SearchInfo {
+    pub fn nodes_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.nodes as f64 / seconds
+    }
+}
+
+/// A function told what a search has found so far, to show it while the bot
+/// thinks. It may be called from another thread than the one asking for the
+/// turn. Clones call the same function.
+#[derive(Clone)]
+pub struct InfoCallback(Arc<dyn Fn(&SearchInfo) + Send + Sync>);
+
+impl InfoCallback {
+    pub fn new(callback: impl Fn(&SearchInfo) + Send + Sync + 'static) -> InfoCallback {
+        InfoCallback(Arc::new(callback))
+    }
+
+    pub fn report(&self, info: &SearchInfo) {
+        (self.0)(info)
+    }
+}
+
+impl fmt::Debug for InfoCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("InfoCallback")
+    }
+}
+
+/// Callbacks are equal when they are the same callback.
+impl PartialEq for InfoCallback {
+    fn eq(&self, other: &InfoCallback) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InfoCallback {}
+
+/// Thinking times to offer players, from the quickest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThinkingTime {
+    Instant,
+    #[default]
+    OneSecond,
+    FiveSeconds,
+}
+
+impl ThinkingTime {
+    pub const ALL: [ThinkingTime; 3] = [
+        ThinkingTime::Instant,
+        ThinkingTime::OneSecond,
+        ThinkingTime::FiveSeconds,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThinkingTime::Instant => "instant",
+            ThinkingTime::OneSecond => "1s",
+            ThinkingTime::FiveSeconds => "5s",
+        }
+    }
+
+    pub fn limits(self) -> Limits {
+        let millis = match self {
+            // Not quite nothing, or the searching bots would play at random.
+            ThinkingTime::Instant => 100,
+            ThinkingTime::OneSecond => 1000,
+            ThinkingTime::FiveSeconds => 5000,
+        };
+        Limits::time(Duration::from_millis(millis))
+    }
+}
+
+pub trait Agent {
+    /// A short name to show players.
+    fn name(&self) -> &str;
+
+    /// Picks a turn for the player to move. Only asked when they have a legal
+    /// turn.
+    ///
+    /// Bots built from the same seed choose the same turns in the same
+    /// positions when limited by nodes or depth, so games between them can be
+    /// played again. Time limits, pondering and searches on several threads
+    /// give that up.
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn;
+
+    /// Starts thinking in the background about the position, usually the one
+    /// after the bot's own turn, while the opponents think about theirs.
+    /// Returns straight away. Bots that can't make use of the time do
+    /// nothing.
+    fn ponder(&mut self, _game: &Game) {}
+
+    /// Stops pondering and waits for the background thinking to finish. Does
+    /// nothing when the bot isn't pondering. [`choose_turn`](Agent::choose_turn)
+    /// stops pondering by itself, so this is only needed to stop without
+    /// asking for a turn.
+    fn stop(&mut self) {}
+}
+
+impl<A: Agent + ?Sized> Agent for Box<A> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        (**self).choose_turn(game, limits)
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        (**self).ponder(game)
+    }
+
+    fn stop(&mut self) {
+        (**self).stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::suite::Suite;
+
+    /// Always plays the first legal turn.
+    struct First;
+
+    impl Agent for First {
+        fn name(&self) -> &str {
+            "first"
+        }
+
+        fn choose_turn(&mut self, game: &Game, _: &Limits) -> Turn {
+            game.legal_turns().remove(0)
+        }
+    }
+
+    #[test]
+    fn boxed_agents() {
+        let mut agent: Box<dyn Agent> = Box::new(First);
+        assert_eq!(agent.name(), "first");
+
+        let suite = Suite::standard().with_tag("quick");
+        let report = suite.run_agent(&mut agent, &Limits::nodes(1)).unwrap();
+        assert_eq!(report.results.len(), suite.positions.len());
+        assert!(report.results.iter().all(|result| result.points.is_some()));
+    }
+
+    #[test]
+    fn stop_flags() {
+        let stop = StopFlag::new();
+        let limits = Limits::none().with_stop(stop.clone());
+        assert_ne!(limits.stop, Some(StopFlag::new()));
+        assert!(!limits.should_stop(Instant::now()));
+
+        stop.raise();
+        assert!(limits.should_stop(Instant::now()));
+        stop.reset();
+        assert!(!limits.should_stop(Instant::now()));
+        assert!(Limits::time(Duration::from_secs(0)).should_stop(Instant::now()));
+    }
+
+    #[test]
+    fn clock_budgets() {
+        let game = Game::new_with_rules(crate::rules::Ruleset::new(2), 1);
+        let limits = Limits::clock(Duration::from_secs(60), Duration::from_secs(1));
+        let turn = limits.for_turn(&game);
+        let budget = turn.max_time.unwrap();
+        assert!(budget > Duration::from_secs(1) && budget < Duration::from_secs(10));
+        assert_eq!(turn.clock, None);
+
+        let capped = Limits {
+            max_time: Some(Duration::from_millis(5)),
+            ..limits
+        };
+        assert_eq!(
+            capped.for_turn(&game).max_time,
+            Some(Duration::from_millis(5))
+        );
+        // Never the whole clock, even with a big increment.
+        let short = Limits::clock(Duration::from_secs(1), Duration::from_secs(5));
+        assert!(short.for_turn(&game).max_time < Some(Duration::from_secs(1)));
+        assert_eq!(Limits::depth(2).for_turn(&game), Limits::depth(2));
+    }
+}
@@ -0,0 +1,627 @@
+//! Depth limited minimax search with alpha-beta pruning. The player searching
+//! plays for their team's evaluation and every other team plays against it,
+//! which makes the bot careful rather than clever, but every turn it plays
+//! can be explained by the line it expected.
+//!
+//! The pieces a turn uncovers in the market aren't known when the turn is
+//! chosen, so the search goes one action at a time: after an action takes a
+//! piece from the market, a chance node averages over the pieces that could
+//! come up, weighted by how many of each nobody has seen. Chance nodes prune
+//! with the bounds of the evaluation (Star1), so the search never peeks at
+//! the real deck order.
+//!
+//! The search deepens one turn at a time, so it can be stopped whenever and
+//! still play the turn the deepest finished search found.
+//!
+//! The turns after the bot's own depend on the pieces it uncovers, which the
+//! search averages over rather than following one line. So the principal
+//! variation it reports is only its own turn.
+
+use std::time::{Duration, Instant};
+
+use super::endgame::winning_turn;
+use super::evaluation::{Evaluator, LinearEvaluator, WIN};
+use super::ordering::{order_actions, CutoffStats, HistoryTable, KillerMoves};
+use super::refill::{refills, reveal, uncovers};
+use super::transposition::{Bound, TranspositionTable};
+use super::{Agent, EvaluatorWeights, Limits, Preset, SearchInfo};
+use crate::game::{Action, Game, Turn};
+use crate::zobrist;
+
+/// Turns searched by a bot made from a preset. At one turn every action is
+/// the bot's own, so the move ordering saves nothing.
+pub const DEFAULT_DEPTH: u32 = 1;
+
+/// A bot searching up to a fixed number of turns ahead, see the module
+/// documentation. It judges where it stops with the [`LinearEvaluator`]
+/// unless given another evaluator.
+#[derive(Debug, Clone)]
+pub struct AlphaBetaAgent<E = LinearEvaluator> {
+    evaluator: E,
+    depth: u32,
+    depth_reached: u32,
+    nodes: u64,
+    table: TranspositionTable,
+    /// The player the table's values are for.
+    table_player: Option<u8>,
+    history: HistoryTable<Action>,
+    cutoffs: CutoffStats,
+    /// Whether actions are searched most promising first.
+    ordering: bool,
+}
+
+impl AlphaBetaAgent {
+    /// Searches up to `depth` turns ahead, the bot's own turn included, or as
+    /// far as the limits allow. A depth of 0 counts as 1.
+    pub fn new(weights: EvaluatorWeights, depth: u32) -> AlphaBetaAgent {
+        AlphaBetaAgent::with_evaluator(LinearEvaluator::new(weights), depth)
+    }
+
+    pub fn from_preset(preset: &Preset) -> AlphaBetaAgent {
+        AlphaBetaAgent::new(preset.weights.clone(), DEFAULT_DEPTH)
+    }
+}
+
+impl<E: Evaluator> AlphaBetaAgent<E> {
+    /// Like [`new`](AlphaBetaAgent::new), judging positions with the
+    /// evaluator.
+    pub fn with_evaluator(evaluator: E, depth: u32) -> AlphaBetaAgent<E> {
+        AlphaBetaAgent {
+            evaluator,
+            depth: depth.max(1),
+            depth_reached: 0,
+            nodes: 0,
+            table: TranspositionTable::default(),
+            table_player: None,
+            history: HistoryTable::new(),
+            cutoffs: CutoffStats::default(),
+            ordering: true,
+        }
+    }
+
+    /// The same bot, remembering positions in the table. The table is kept
+    /// from one turn to the next.
+    pub fn with_table(self, table: TranspositionTable) -> AlphaBetaAgent<E> {
+        AlphaBetaAgent { table, ..self }
+    }
+
+    /// The same bot, searching actions in the order they are generated
+    /// instead of with the ordering heuristics, to measure what they save.
+    pub fn without_ordering(self) -> AlphaBetaAgent<E> {
+        AlphaBetaAgent {
+            ordering: false,
+            ..self
+        }
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// How many turns ahead the last search got before it had to stop.
+    pub fn depth_reached(&self) -> u32 {
+        self.depth_reached
+    }
+
+    /// Positions looked at in the last search.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn table(&self) -> &TranspositionTable {
+        &self.table
+    }
+
+    /// How often the last search cut off, and how often by its first move.
+    pub fn cutoffs(&self) -> CutoffStats {
+        self.cutoffs
+    }
+}
+
+impl<E: Evaluator> Agent for AlphaBetaAgent<E> {
+    fn name(&self) -> &str {
+        "alphabeta"
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "debug", name = "search", skip_all, fields(bot = "alphabeta"))
+    )]
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        let limits = &limits.for_turn(game);
+        if let Some(turn) = winning_turn(game, limits) {
+            return turn;
+        }
+        // Values are stored for one player, so playing another seat starts
+        // over.
+        let player = game.next_player();
+        if self.table_player != Some(player) {
+            self.table.clear();
+            self.table_player = Some(player);
+        }
+        self.table.new_search();
+        self.history.age();
+
+        let max_depth = limits
+            .max_depth
+            .map_or(self.depth, |max| max.min(self.depth));
+        let mut search = Search {
+            evaluator: &self.evaluator,
+            table: &mut self.table,
+            killers: KillerMoves::new(),
+            history: &mut self.history,
+            cutoffs: CutoffStats::default(),
+            ordering: self.ordering,
+            player,
+            depth: 0,
+            limits,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+        };
+
+        // Deepening one turn at a time, so running out of time still leaves
+        // the turn found by the deepest finished search.
+        let mut best: Option<Turn> = None;
+        self.depth_reached = 0;
+        for depth in 1..=max_depth {
+            let found = search.root(game, depth, best.as_ref().map(|turn| &turn.0));
+            if !search.stopped {
+                if let Some((value, turn)) = &found {
+                    // Only the bot's own turn, see the module documentation.
+                    limits.report(|| SearchInfo {
+                        nodes: search.nodes,
+                        elapsed: search.start.elapsed(),
+                        depth,
+                        score: *value,
+                        pv: vec![turn.clone()],
+                    });
+                }
+                best = found.map(|(_, turn)| turn);
+                self.depth_reached = depth;
+            } else {
+                // Better than nothing when not even one turn ahead was done.
+                best = best.or(found.map(|(_, turn)| turn));
+                break;
+            }
+        }
+        self.nodes = search.nodes;
+        self.cutoffs = search.cutoffs;
+
+        best.unwrap_or_else(|| {
+            game.legal_turns()
+                .into_iter()
+                .next()
+                .expect("The player to move should have a legal turn")
+        })
+    }
+}
+
+/// The state of one search.
+struct Search<'a, E> {
+    evaluator: &'a E,
+    table: &'a mut TranspositionTable,
+    killers: KillerMoves<Action>,
+    history: &'a mut HistoryTable<Action>,
+    cutoffs: CutoffStats,
+    ordering: bool,
+    /// The player searching, whose team maximizes.
+    player: u8,
+    /// Turns searched from the root in this iteration.
+    depth: u32,
+    limits: &'a Limits,
+    start: Instant,
+    nodes: u64,
+    /// Set once the limits are reached. Values found after that mean nothing.
+    stopped: bool,
+}
+
+impl<E: Evaluator> Search<'_, E> {
+    /// The best turn searching `depth` turns ahead, with its value, trying
+    /// `first` before the other first actions. The first action is chosen
+    /// not knowing what it uncovers, the second one knowing.
+    fn root(&mut self, game: &Game, depth: u32, first: Option<&Action>) -> Option<(f32, Turn)> {
+        self.depth = depth;
+        let stored = self
+            .table
+            .probe(position_key(game))
+            .and_then(|entry| entry.best.clone());
+        let actions = self.ordered(game, 0, first.or(stored.as_ref()));
+        let (value, first) = self.best(true, actions, 0, |search, action, alpha, beta| {
+            search.after_first(game, action, depth, alpha, beta)
+        })?;
+
+        let mut mid = game.clone();
+        mid.do_action(first.clone())
+            .expect("Legal actions should be playable");
+        let actions = self.ordered(&mid, 1, None);
+        let found = self.best(true, actions, 1, |search, action, alpha, beta| {
+            search.after_second(&mid, action, depth, alpha, beta)
+        });
+        let (value, second) = match found {
+            Some(found) => found,
+            // Out of time, so settle for anything.
+            None => (value, mid.legal_actions().into_iter().next()?),
+        };
+        Some((value, Turn(first, second)))
+    }
+
+    /// Counts a position, returning whether the search may go on.
+    fn tick(&mut self) -> bool {
+        self.nodes += 1;
+        let out_of_nodes = matches!(self.limits.max_nodes, Some(max) if self.nodes > max);
+        if out_of_nodes || self.limits.should_stop(self.start) {
+            self.stopped = true;
+        }
+        !self.stopped
+    }
+
+    fn maximizing(&self, game: &Game) -> bool {
+        let rules = game.rules();
+        rules.team_of(game.next_player()) == rules.team_of(self.player)
+    }
+
+    /// The legal actions, most promising first, with `hint` before all
+    /// others. `ply` counts actions from the root.
+    fn ordered(&self, game: &Game, ply: usize, hint: Option<&Action>) -> Vec<Action> {
+        let mut actions = game.legal_actions();
+        if self.ordering {
+            order_actions(game, &mut actions, ply, &self.killers, self.history);
+        }
+        if let Some(hint) = hint {
+            move_to_front(&mut actions, hint);
+        }
+        actions
+    }
+
+    /// Actions from the root to the start of the turn searching `depth`
+    /// turns.
+    fn ply(&self, depth: u32) -> usize {
+        2 * (self.depth - depth) as usize
+    }
+
+    /// The best of the actions by the values `child` gives them, with the
+    /// action itself. `None` if there are none, or the search stopped before
+    /// finishing any.
+    fn best(
+        &mut self,
+        maximizing: bool,
+        actions: Vec<Action>,
+        ply: usize,
+        mut child: impl FnMut(&mut Self, Action, f32, f32) -> f32,
+    ) -> Option<(f32, Action)> {
+        self.best_within(
+            maximizing,
+            actions,
+            ply,
+            f32::NEG_INFINITY,
+            f32::INFINITY,
+            &mut child,
+        )
+    }
+
+    fn best_within(
+        &mut self,
+        maximizing: bool,
+        actions: Vec<Action>,
+        ply: usize,
+        mut alpha: f32,
+        mut beta: f32,
+        child: &mut impl FnMut(&mut Self, Action, f32, f32) -> f32,
+    ) -> Option<(f32, Action)> {
+        if !actions.is_empty() {
+            self.cutoffs.searched += 1;
+        }
+        let mut best: Option<(f32, Action)> = None;
+        for (i, action) in actions.into_iter().enumerate() {
+            let value = child(self, action.clone(), alpha, beta);
+            if self.stopped {
+                break;
+            }
+            let better = match &best {
+                None => true,
+                Some((best, _)) if maximizing => value > *best,
+                Some((best, _)) => value < *best,
+            };
+            if maximizing {
+                alpha = alpha.max(value);
+            } else {
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                self.cutoffs.cutoffs += 1;
+                if i == 0 {
+                    self.cutoffs.first_move_cutoffs += 1;
+                }
+                self.killers.record(ply, &action);
+                let left = (2 * self.depth as usize).saturating_sub(ply);
+                self.history.record(&action, left as u32);
+            }
+            if better {
+                best = Some((value, action));
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// The value of the position at the start of a turn, looking `depth`
+    /// turns ahead.
+    fn turn(&mut self, game: &Game, depth: u32, alpha: f32, beta: f32) -> f32 {
+        if !self.tick() {
+            return 0.0;
+        }
+        if depth == 0 || game.is_finished() {
+            return self.evaluator.evaluate(game, self.player);
+        }
+
+        let key = position_key(game);
+        let entry = self.table.probe(key).cloned();
+        if let Some(entry) = &entry {
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.value,
+                    Bound::Lower if entry.value >= beta => return entry.value,
+                    Bound::Upper if entry.value <= alpha => return entry.value,
+                    _ => {}
+                }
+            }
+        }
+
+        let maximizing = self.maximizing(game);
+        let ply = self.ply(depth);
+        let actions = self.ordered(game, ply, entry.and_then(|entry| entry.best).as_ref());
+        let (value, best) = match self.best_within(
+            maximizing,
+            actions,
+            ply,
+            alpha,
+            beta,
+            &mut |search, action, alpha, beta| search.after_first(game, action, depth, alpha, beta),
+        ) {
+            Some(found) => found,
+            None => return self.evaluator.evaluate(game, self.player),
+        };
+        if !self.stopped {
+            let bound = if value <= alpha {
+                Bound::Upper
+            } else if value >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            self.table.store(key, depth, value, bound, Some(best));
+        }
+        value
+    }
+
+    /// The value of doing the action first in the turn.
+    fn after_first(
+        &mut self,
+        game: &Game,
+        action: Action,
+        depth: u32,
+        alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        let deck = uncovers(game, &action);
+        let mut mid = game.clone();
+        mid.do_action(action)
+            .expect("Legal actions should be playable");
+        self.chance(&mid, deck, alpha, beta, |search, mid, alpha, beta| {
+            search.second_action(mid, depth, alpha, beta)
+        })
+    }
+
+    /// The value of the position between the two actions of a turn.
+    fn second_action(&mut self, mid: &Game, depth: u32, alpha: f32, beta: f32) -> f32 {
+        if !self.tick() {
+            return 0.0;
+        }
+        let maximizing = self.maximizing(mid);
+        let ply = self.ply(depth) + 1;
+        let actions = self.ordered(mid, ply, None);
+        match self.best_within(
+            maximizing,
+            actions,
+            ply,
+            alpha,
+            beta,
+            &mut |search, action, alpha, beta| search.after_second(mid, action, depth, alpha, beta),
+        ) {
+            Some((value, _)) => value,
+            // The first action led nowhere, which is as bad as losing.
+            None if maximizing => -WIN,
+            None => WIN,
+        }
+    }
+
+    /// The value of doing the action second in the turn.
+    fn after_second(
+        &mut self,
+        mid: &Game,
+        action: Action,
+        depth: u32,
+        alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        let player = mid.next_player();
+        let deck = uncovers(mid, &action);
+        let mut after = mid.clone();
+        after
+            .do_action(action)
+            .expect("Legal actions should be playable");
+        let end_turn = |search: &mut Self, after: &Game, alpha, beta| {
+            let mut after = after.clone();
+            after.end_turn(player, Duration::from_secs(0));
+            search.turn(&after, depth - 1, alpha, beta)
+        };
+        if depth == 1 {
+            // Nothing will be played with what this uncovers, so the chance
+            // node would only cost time.
+            end_turn(self, &after, alpha, beta)
+        } else {
+            self.chance(&after, deck, alpha, beta, end_turn)
+        }
+    }
+
+    /// The average value of `child` over the pieces that could be uncovered
+    /// at the top of the deck, pruned with Star1.
+    fn chance(
+        &mut self,
+        game: &Game,
+        deck: Option<usize>,
+        alpha: f32,
+        beta: f32,
+        mut child: impl FnMut(&mut Self, &Game, f32, f32) -> f32,
+    ) -> f32 {
+        let deck = match deck {
+            Some(deck) if !game.decks[deck].is_empty() => deck,
+            _ => return child(self, game, alpha, beta),
+        };
+
+        let mut sum = 0.0;
+        let mut left = 1.0;
+        for (piece, chance) in refills(game, deck) {
+            left -= chance;
+            let mut refilled = game.clone();
+            reveal(&mut refilled, deck, piece);
+            let low = ((alpha - sum - left * WIN) / chance).max(-WIN);
+            let high = ((beta - sum + left * WIN) / chance).min(WIN);
+            let value = child(self, &refilled, low, high);
+            if self.stopped {
+                return 0.0;
+            }
+            sum += chance * value;
+            if sum + left * WIN <= alpha {
+                return sum + left * WIN;
+            }
+            if sum - left * WIN >= beta {
+                return sum - left * WIN;
+            }
+        }
+        sum
+    }
+}
+
+/// The key of the position in the transposition table. Positions only
+/// differing in the scores are valued differently, so the scores are part of
+/// it.
+fn position_key(game: &Game) -> u64 {
+    game.scores()
+        .iter()
+        .enumerate()
+        .fold(game.hash(), |hash, (player, &score)| {
+            hash ^ zobrist::score(player as u8, score)
+        })
+}
+
+fn move_to_front(actions: &mut Vec<Action>, action: &Action) {
+    if let Some(i) = actions.iter().position(|other| other == action) {
+        let action = actions.remove(i);
+        actions.insert(0, action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::suite::{best_scoring_turn, Suite};
+    use crate::ai::StopFlag;
+    use crate::rules::Ruleset;
+
+    fn points_only() -> EvaluatorWeights {
+        EvaluatorWeights {
+            score: 1.0,
+            height_near_markers: 0.0,
+            connectable_pairs: 0.0,
+            market_quality: 0.0,
+        }
+    }
+
+    #[test]
+    fn scores_like_greedy_at_depth_one() {
+        let suite = Suite::standard().with_tag("endgame").with_tag("quick");
+        let mut agent = AlphaBetaAgent::new(points_only(), 1);
+        let report = suite.run_agent(&mut agent, &Limits::none()).unwrap();
+        assert_eq!(agent.depth_reached(), 1);
+        assert!(agent.cutoffs().searched > 0);
+
+        // Unless the best scoring turn needs luck with what its first action
+        // uncovers, nothing scores more.
+        for (position, result) in suite.positions.iter().zip(&report.results) {
+            let game = position.game();
+            let (greedy, points) = best_scoring_turn(&game).unwrap();
+            let market = game.market();
+            let lucky = matches!(
+                &greedy.1,
+                Action::PlacePiece(piece) if !market.contains(&Some(piece.piece))
+            );
+            assert!(result.points.is_some());
+            assert!(lucky || result.points == Some(points));
+        }
+    }
+
+    #[test]
+    fn stops_at_the_node_limit() {
+        let mut game = Game::new_with_rules(Ruleset::new(3), 8);
+        let mut agent = AlphaBetaAgent::new(Preset::default().weights, 2);
+        let turn = agent.choose_turn(&game, &Limits::nodes(300));
+        assert_eq!(agent.nodes(), 301);
+        assert_eq!(agent.depth_reached(), 0);
+        game.play_turn(turn).unwrap();
+
+        let stop = StopFlag::new();
+        stop.raise();
+        let turn = agent.choose_turn(&game, &Limits::none().with_stop(stop));
+        assert_eq!(agent.nodes(), 1);
+        game.play_turn(turn).unwrap();
+
+        // Only as deep as the limit, however deep the agent would go.
+        let game = Suite::standard().with_tag("endgame").positions[0].game();
+        agent.choose_turn(&game, &Limits::depth(1));
+        assert_eq!(agent.depth_reached(), 1);
+    }
+
+    #[test]
+    fn remembers_positions() {
+        let game = Suite::standard().with_tag("endgame").positions[0].game();
+        let evaluator = LinearEvaluator::new(points_only());
+        let limits = Limits::none();
+        let mut table = TranspositionTable::new(1024, Default::default());
+        let mut history = HistoryTable::new();
+        let mut search = Search {
+            evaluator: &evaluator,
+            table: &mut table,
+            killers: KillerMoves::new(),
+            history: &mut history,
+            cutoffs: CutoffStats::default(),
+            ordering: true,
+            player: game.next_player(),
+            depth: 1,
+            limits: &limits,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+        };
+        let value = search.turn(&game, 1, f32::NEG_INFINITY, f32::INFINITY);
+        let nodes = search.nodes;
+
+        // The second time it is looked up rather than searched.
+        assert_eq!(
+            search.turn(&game, 1, f32::NEG_INFINITY, f32::INFINITY),
+            value
+        );
+        assert_eq!(search.nodes, nodes + 1);
+        let entry = table.probe(position_key(&game)).unwrap();
+        assert_eq!(entry.bound, Bound::Exact);
+        assert!(entry.best.is_some());
+        assert_eq!(table.stats().hits, 2);
+    }
+}
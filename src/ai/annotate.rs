@@ -0,0 +1,279 @@
+//! Finding the turns of a game that gave something away: each turn of a
+//! replay is compared with the turn a bot would have played instead, and the
+//! ones that look much worse are marked as inaccuracies, mistakes or
+//! blunders.
+//!
+//! Both turns are judged by the evaluation of the position right after them,
+//! for the player who played. The pieces the turns uncover are the ones the
+//! real deck held, since the second action of the played turn may take what
+//! the first one uncovered.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::evaluation::{Evaluator, LinearEvaluator};
+use super::{Agent, Limits, Preset};
+use crate::game::{Action, Game, Turn};
+use crate::overlay::{Overlay, Tone};
+use crate::replay::{Bookmark, Replay, ReplayError};
+
+/// How bad a turn was, by how much it lost against the best one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// The mark used for the judgement in annotated game records.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Judgement::Inaccuracy => "inaccuracy",
+            Judgement::Mistake => "mistake",
+            Judgement::Blunder => "blunder",
+        })
+    }
+}
+
+/// The smallest loss for each judgement, in units of the evaluation. With
+/// the default weights a unit is about a point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Thresholds {
+    pub inaccuracy: f32,
+    pub mistake: f32,
+    pub blunder: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            inaccuracy: 1.0,
+            mistake: 3.0,
+            blunder: 6.0,
+        }
+    }
+}
+
+impl Thresholds {
+    pub fn judge(&self, loss: f32) -> Option<Judgement> {
+        if loss >= self.blunder {
+            Some(Judgement::Blunder)
+        } else if loss >= self.mistake {
+            Some(Judgement::Mistake)
+        } else if loss >= self.inaccuracy {
+            Some(Judgement::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+/// What the annotator made of one turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// The number of turns played before this one.
+    pub turn: usize,
+    pub player: u8,
+    pub played: Turn,
+    /// The turn the bot would have played.
+    pub best: Turn,
+    pub played_value: f32,
+    pub best_value: f32,
+    /// `None` for turns that were good enough.
+    pub judgement: Option<Judgement>,
+}
+
+impl Annotation {
+    /// How much worse the played turn looks than the bot's, never negative:
+    /// the bot isn't always right.
+    pub fn loss(&self) -> f32 {
+        (self.best_value - self.played_value).max(0.0)
+    }
+
+    /// A bookmark on the position before the turn, drawing the played turn
+    /// in red and the bot's in green.
+    pub fn bookmark(&self) -> Bookmark {
+        let overlay = draw(
+            draw(Overlay::default(), &self.played, Tone::Bad),
+            &self.best,
+            Tone::Good,
+        );
+        Bookmark {
+            turn: self.turn,
+            note: self.to_string(),
+            overlay,
+        }
+    }
+}
+
+/// Marks where the turn places pieces and moves markers.
+fn draw(overlay: Overlay, turn: &Turn, tone: Tone) -> Overlay {
+    [&turn.0, &turn.1]
+        .iter()
+        .fold(overlay, |overlay, action| match action {
+            Action::PlacePiece(piece) => {
+                let (first, second) = piece.positions();
+                overlay.highlight(first, tone).highlight(second, tone)
+            }
+            Action::MovePlayerMarker(from, to) => overlay.arrow(*from, *to, tone),
+            _ => overlay,
+        })
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}. Player {}: {}",
+            self.turn + 1,
+            self.player + 1,
+            self.played
+        )?;
+        if let Some(judgement) = self.judgement {
+            write!(
+                f,
+                " {} {}, loses {:.1}, {} was better",
+                judgement.symbol(),
+                judgement,
+                self.loss(),
+                self.best
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Walks through replays asking a bot for the best turn of every position.
+pub struct Annotator<A, E = LinearEvaluator> {
+    agent: A,
+    limits: Limits,
+    evaluator: E,
+    thresholds: Thresholds,
+}
+
+impl<A: Agent> Annotator<A> {
+    /// Asks the agent within the limits, judging turns with the default
+    /// preset's evaluation and the default thresholds.
+    pub fn new(agent: A, limits: Limits) -> Annotator<A> {
+        Annotator {
+            agent,
+            limits,
+            evaluator: LinearEvaluator::new(Preset::default().weights),
+            thresholds: Thresholds::default(),
+        }
+    }
+}
+
+impl<A: Agent, E: Evaluator> Annotator<A, E> {
+    pub fn with_evaluator<F: Evaluator>(self, evaluator: F) -> Annotator<A, F> {
+        Annotator {
+            agent: self.agent,
+            limits: self.limits,
+            evaluator,
+            thresholds: self.thresholds,
+        }
+    }
+
+    pub fn with_thresholds(self, thresholds: Thresholds) -> Annotator<A, E> {
+        Annotator { thresholds, ..self }
+    }
+
+    /// Annotates every turn of the replay, in order.
+    pub fn annotate(&mut self, replay: &Replay) -> Result<Vec<Annotation>, ReplayError> {
+        let mut game = replay.initial_game();
+        let mut annotations = Vec::new();
+        for (i, played) in replay.turns.iter().enumerate() {
+            annotations.push(self.annotate_turn(&game, i, played)?);
+            game.play_turn(played.clone())
+                .map_err(|err| ReplayError::IllegalTurn(i, err))?;
+        }
+        Ok(annotations)
+    }
+
+    /// Annotates the turn played in the position, the `index`th of its game.
+    pub fn annotate_turn(
+        &mut self,
+        game: &Game,
+        index: usize,
+        played: &Turn,
+    ) -> Result<Annotation, ReplayError> {
+        let player = game.next_player();
+        let best = self.agent.choose_turn(game, &self.limits);
+        let value = |turn: &Turn| {
+            let mut after = game.clone();
+            after
+                .play_turn(turn.clone())
+                .map(|_| self.evaluator.evaluate(&after, player))
+                .map_err(|err| ReplayError::IllegalTurn(index, err))
+        };
+        let played_value = value(played)?;
+        let best_value = value(&best).expect("The bot's turn should be legal");
+
+        let mut annotation = Annotation {
+            turn: index,
+            player,
+            played: played.clone(),
+            best,
+            played_value,
+            best_value,
+            judgement: None,
+        };
+        annotation.judgement = self.thresholds.judge(annotation.loss());
+        Ok(annotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{GreedyAgent, RandomAgent};
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn judgements() {
+        let thresholds = Thresholds::default();
+        assert_eq!(thresholds.judge(0.5), None);
+        assert_eq!(thresholds.judge(1.0), Some(Judgement::Inaccuracy));
+        assert_eq!(thresholds.judge(4.0), Some(Judgement::Mistake));
+        assert_eq!(thresholds.judge(10.0), Some(Judgement::Blunder));
+    }
+
+    #[test]
+    fn annotates_replays() {
+        let mut replay = Replay::new(vec!["A".into(), "B".into()], Ruleset::new(2), 4);
+        let mut game = replay.initial_game();
+        let mut random = RandomAgent::new(4);
+        for _ in 0..6 {
+            let turn = random.choose_turn(&game, &Limits::none());
+            replay.play(&mut game, turn).unwrap();
+        }
+
+        let mut annotator = Annotator::new(GreedyAgent::new(), Limits::none());
+        let annotations = annotator.annotate(&replay).unwrap();
+        assert_eq!(annotations.len(), 6);
+        for (i, annotation) in annotations.iter().enumerate() {
+            assert_eq!(annotation.turn, i);
+            assert_eq!(annotation.player as usize, i % 2);
+            assert!(annotation.loss() >= 0.0);
+            if annotation.played == annotation.best {
+                assert_eq!(annotation.judgement, None);
+            }
+            let bookmark = annotation.bookmark();
+            assert_eq!(bookmark.turn, i);
+            assert!(!bookmark.overlay.is_empty());
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Storage for the nodes of the search trees. The nodes of a tree live in
+//! one `Vec` and point at each other by [`NodeId`], a `u32` index, rather
+//! than each sitting in a box of its own. Growing the tree is a push, and
+//! keeping the part of it below the turns played for the next search moves
+//! the nodes over instead of cloning them.
+
+use std::convert::TryFrom;
+use std::ops::{Index, IndexMut};
+
+/// A node of an [`Arena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct NodeId(u32);
+
+impl NodeId {
+    /// The root, which is always the first node.
+    pub(crate) const ROOT: NodeId = NodeId(0);
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// What the arena has to know of a node to walk the tree.
+pub(crate) trait TreeNode {
+    fn children(&self) -> &[NodeId];
+
+    /// Forgets the children, but not whether the node has been expanded.
+    fn clear_children(&mut self);
+
+    fn push_child(&mut self, child: NodeId);
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Arena<N> {
+    nodes: Vec<N>,
+}
+
+impl<N: TreeNode> Arena<N> {
+    pub(crate) fn new(root: N) -> Arena<N> {
+        Arena { nodes: vec![root] }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Adds a node, not yet anyone's child.
+    pub(crate) fn push(&mut self, node: N) -> NodeId {
+        let id = u32::try_from(self.nodes.len()).expect("A tree holds at most u32::MAX nodes");
+        self.nodes.push(node);
+        NodeId(id)
+    }
+
+    /// The first node within `depth` turns of the root that matches, looking
+    /// a layer at a time.
+    pub(crate) fn find(&self, depth: u32, matches: impl Fn(&N) -> bool) -> Option<NodeId> {
+        let mut layer = vec![NodeId::ROOT];
+        for _ in 0..=depth {
+            if let Some(&found) = layer.iter().find(|&&id| matches(&self[id])) {
+                return Some(found);
+            }
+            layer = layer
+                .iter()
+                .flat_map(|&id| self[id].children().iter().copied())
+                .collect();
+        }
+        None
+    }
+
+    /// The subtree under the node, as a tree of its own with the node as its
+    /// root. The rest of the tree is dropped.
+    pub(crate) fn into_subtree(self, root: NodeId) -> Arena<N> {
+        let mut old: Vec<Option<N>> = self.nodes.into_iter().map(Some).collect();
+        let mut nodes: Vec<N> = Vec::new();
+        let mut stack: Vec<(NodeId, Option<NodeId>)> = vec![(root, None)];
+        while let Some((id, parent)) = stack.pop() {
+            let mut node = old[id.index()].take().expect("Nodes have a single parent");
+            let children = node.children().to_vec();
+            node.clear_children();
+            let new = NodeId(nodes.len() as u32);
+            nodes.push(node);
+            if let Some(parent) = parent {
+                nodes[parent.index()].push_child(new);
+            }
+            for &child in children.iter().rev() {
+                stack.push((child, Some(new)));
+            }
+        }
+        Arena { nodes }
+    }
+}
+
+impl<N> Index<NodeId> for Arena<N> {
+    type Output = N;
+
+    fn index(&self, id: NodeId) -> &N {
+        &self.nodes[id.index()]
+    }
+}
+
+impl<N> IndexMut<NodeId> for Arena<N> {
+    fn index_mut(&mut self, id: NodeId) -> &mut N {
+        &mut self.nodes[id.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Node(u32, Vec<NodeId>);
+
+    impl TreeNode for Node {
+        fn children(&self) -> &[NodeId] {
+            &self.1
+        }
+
+        fn clear_children(&mut self) {
+            self.1.clear();
+        }
+
+        fn push_child(&mut self, child: NodeId) {
+            self.1.push(child);
+        }
+    }
+
+    #[test]
+    fn keeps_subtrees() {
+        // 0 has children 1 and 2, and 2 has 3.
+        let mut tree = Arena::new(Node(0, Vec::new()));
+        for (value, parent) in &[(1, 0), (2, 0), (3, 2)] {
+            let id = tree.push(Node(*value, Vec::new()));
+            tree[NodeId(*parent)].push_child(id);
+        }
+        assert_eq!(tree.find(1, |node| node.0 == 3), None);
+        let found = tree.find(2, |node| node.0 == 2).unwrap();
+
+        let subtree = tree.into_subtree(found);
+        assert_eq!(subtree.len(), 2);
+        assert_eq!(subtree[NodeId::ROOT], Node(2, vec![NodeId(1)]));
+        assert_eq!(subtree[NodeId(1)].0, 3);
+    }
+}
@@ -0,0 +1,341 @@
+//! An opening book: the turns played in early positions by earlier games,
+//! usually games the bots played against themselves, and how they went.
+//! Early positions come up again and again, so the bots can play from the
+//! book instead of searching them every time.
+//!
+//! Positions are looked up by their canonical form, so a position and its
+//! turned or flipped versions share their entry. Turns are stored by what
+//! they leave on the board, also in canonical form, which makes them the
+//! same turn in every version of the position.
+//!
+//! A book file has one line per turn: the position's key and the key of what
+//! the turn leaves, both in hex, how often the turn was played, the sum of
+//! the rewards it got and the turn itself as it was first played, all
+//! separated by spaces. Empty lines and lines starting with `#` are ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use super::{Agent, Limits};
+use crate::game::{Game, Turn};
+
+/// Turns at the start of a game worth adding to a book with
+/// [`OpeningBook::add_game`]. Later positions hardly ever come up again.
+pub const BOOK_TURNS: usize = 8;
+
+/// The key of a position in the book, the same for all its turned and
+/// flipped versions.
+pub fn book_key(game: &Game) -> u64 {
+    game.canonical().hash()
+}
+
+/// What a turn left on the board, without the market it was refilled with.
+fn result_key(after: &Game) -> u64 {
+    let canonical = after.canonical();
+    canonical.board.hash() ^ canonical.markers_hash
+}
+
+/// A turn in the book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookMove {
+    /// What the turn leaves on the board.
+    pub result: u64,
+    pub games: u32,
+    /// The sum of the rewards of the player who played the turn.
+    pub score: f64,
+    /// The turn as it was first played. In a turned or flipped version of
+    /// the position it is another turn, or no turn at all.
+    pub turn: Turn,
+}
+
+impl BookMove {
+    /// The player's average reward after the turn, between 0 and 1.
+    pub fn average(&self) -> f64 {
+        self.score / self.games.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpeningBook {
+    positions: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        OpeningBook::default()
+    }
+
+    /// How many positions are in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The turns in the book for the position.
+    pub fn moves(&self, game: &Game) -> &[BookMove] {
+        self.positions
+            .get(&book_key(game))
+            .map_or(&[], |moves| moves.as_slice())
+    }
+
+    /// Records the turn being played in the position, and the reward its
+    /// player got in the end.
+    pub fn add(&mut self, game: &Game, turn: &Turn, reward: f64) {
+        let mut after = game.clone();
+        if after.play_turn(turn.clone()).is_err() {
+            return;
+        }
+        let result = result_key(&after);
+        let moves = self.positions.entry(book_key(game)).or_default();
+        match moves.iter_mut().find(|found| found.result == result) {
+            Some(found) => {
+                found.games += 1;
+                found.score += reward;
+            }
+            None => moves.push(BookMove {
+                result,
+                games: 1,
+                score: reward,
+                turn: turn.clone(),
+            }),
+        }
+    }
+
+    /// Records the first `turns` turns of a game played from `start`, with
+    /// the rewards each player got in the end.
+    pub fn add_game(&mut self, start: &Game, played: &[Turn], rewards: &[f64], turns: usize) {
+        let mut game = start.clone();
+        for turn in played.iter().take(turns) {
+            let reward = rewards[game.next_player() as usize];
+            self.add(&game, turn, reward);
+            if game.play_turn(turn.clone()).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// The legal turn the book likes best in the position: the one played
+    /// most, then the one with the best average reward. `None` when the
+    /// position isn't in the book.
+    pub fn choose(&self, game: &Game) -> Option<Turn> {
+        let moves = self.positions.get(&book_key(game))?;
+        let mut preferred: Vec<&BookMove> = moves.iter().collect();
+        preferred.sort_by(|a, b| {
+            (b.games, b.average())
+                .partial_cmp(&(a.games, a.average()))
+                .unwrap()
+        });
+        // Usually the position is the way round it was when the turns were
+        // recorded, and they can be played as they are.
+        for found in preferred {
+            let mut after = game.clone();
+            if after.play_turn(found.turn.clone()).is_ok() && result_key(&after) == found.result {
+                return Some(found.turn.clone());
+            }
+        }
+
+        // Otherwise every legal turn is tried to see which the book knows.
+        let mut best: Option<(&BookMove, Turn)> = None;
+        for turn in game.legal_turns() {
+            let mut after = game.clone();
+            after
+                .play_turn(turn.clone())
+                .expect("Legal turns should be playable");
+            let result = result_key(&after);
+            let found = match moves.iter().find(|found| found.result == result) {
+                Some(found) => found,
+                None => continue,
+            };
+            let better = match &best {
+                Some((best, _)) => (found.games, found.average()) > (best.games, best.average()),
+                None => true,
+            };
+            if better {
+                best = Some((found, turn));
+            }
+        }
+        best.map(|(_, turn)| turn)
+    }
+
+    /// Adds the turns of another book to this one.
+    pub fn merge(&mut self, other: &OpeningBook) {
+        for (&key, moves) in &other.positions {
+            let ours = self.positions.entry(key).or_default();
+            for theirs in moves {
+                match ours.iter_mut().find(|found| found.result == theirs.result) {
+                    Some(found) => {
+                        found.games += theirs.games;
+                        found.score += theirs.score;
+                    }
+                    None => ours.push(theirs.clone()),
+                }
+            }
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<OpeningBook, BookError> {
+        let mut book = OpeningBook::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 5 {
+                return Err(BookError::FieldCount(i + 1));
+            }
+            let malformed = |_| BookError::Malformed(i + 1);
+            let key = u64::from_str_radix(fields[0], 16).map_err(malformed)?;
+            let book_move = BookMove {
+                result: u64::from_str_radix(fields[1], 16).map_err(malformed)?,
+                games: fields[2].parse().map_err(|_| BookError::Malformed(i + 1))?,
+                score: fields[3].parse().map_err(|_| BookError::Malformed(i + 1))?,
+                turn: fields[4].parse().map_err(|_| BookError::Malformed(i + 1))?,
+            };
+            book.positions.entry(key).or_default().push(book_move);
+        }
+        Ok(book)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<OpeningBook, BookError> {
+        OpeningBook::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BookError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+/// The book file, sorted so the same book is always written the same way.
+impl fmt::Display for OpeningBook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut keys: Vec<&u64> = self.positions.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let mut moves = self.positions[key].clone();
+            moves.sort_by_key(|found| found.result);
+            for found in moves {
+                writeln!(
+                    f,
+                    "{:016x} {:016x} {} {} {}",
+                    key, found.result, found.games, found.score, found.turn
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BookError {
+    #[error("Couldn't access the book file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Line {0} doesn't have 5 fields separated by spaces.")]
+    FieldCount(usize),
+    #[error("Line {0} has a malformed key, count, score or turn.")]
+    Malformed(usize),
+}
+
+/// A bot playing from the book while it can, and asking another bot once
+/// the game has left it.
+#[derive(Debug, Clone)]
+pub struct BookAgent<A> {
+    book: Arc<OpeningBook>,
+    agent: A,
+}
+
+impl<A: Agent> BookAgent<A> {
+    /// The book is shared, so many bots can play from one copy.
+    pub fn new(book: Arc<OpeningBook>, agent: A) -> BookAgent<A> {
+        BookAgent { book, agent }
+    }
+
+    pub fn book(&self) -> &OpeningBook {
+        &self.book
+    }
+
+    pub fn agent(&self) -> &A {
+        &self.agent
+    }
+}
+
+impl<A: Agent> Agent for BookAgent<A> {
+    fn name(&self) -> &str {
+        self.agent.name()
+    }
+
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        match self.book.choose(game) {
+            Some(turn) => {
+                self.agent.stop();
+                turn
+            }
+            None => self.agent.choose_turn(game, limits),
+        }
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        self.agent.ponder(game)
+    }
+
+    fn stop(&mut self) {
+        self.agent.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::RandomAgent;
+    use crate::board::Symmetry;
+    use crate::rules::Ruleset;
+    use crate::zobrist;
+
+    #[test]
+    fn plays_from_the_book() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 3);
+        let first = game.legal_turns().remove(0);
+        game.play_turn(first).unwrap();
+        let turn = game.legal_turns().pop().unwrap();
+
+        let mut book = OpeningBook::new();
+        book.add(&game, &turn, 1.0);
+        book.add(&game, &turn, 0.0);
+        assert_eq!(book.moves(&game)[0].games, 2);
+        assert_eq!(book.choose(&game), Some(turn));
+
+        // The flipped position is the same one as far as the book is
+        // concerned.
+        let symmetry = Symmetry {
+            mirrored: true,
+            rotation: 1,
+        };
+        let mut flipped = game.clone();
+        flipped.board = game.board.transformed(symmetry);
+        flipped.player_markers = symmetry.markers(&game.player_markers);
+        flipped.markers_hash = zobrist::markers(&flipped.player_markers);
+        assert_eq!(book_key(&flipped), book_key(&game));
+        assert_eq!(book.moves(&flipped).len(), 1);
+
+        let parsed = OpeningBook::parse(&book.to_string()).unwrap();
+        assert_eq!(parsed, book);
+        assert!(matches!(
+            OpeningBook::parse("ab cd 1"),
+            Err(BookError::FieldCount(1))
+        ));
+
+        let expected = book.choose(&game);
+        let mut agent = BookAgent::new(Arc::new(book), RandomAgent::new(0));
+        assert_eq!(Some(agent.choose_turn(&game, &Limits::none())), expected);
+    }
+}
@@ -0,0 +1,245 @@
+//! Opponents graded from [`Beginner`](Difficulty::Beginner) to
+//! [`Expert`](Difficulty::Expert), for players to pick from. The weaker
+//! levels are the usual bots held back: searching less, misjudging positions
+//! by a little noise on the evaluation, and now and then playing any turn.
+
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::evaluation::{Evaluator, LinearEvaluator};
+use super::{Agent, AlphaBetaAgent, GreedyAgent, Limits, MctsAgent, Preset};
+use crate::game::{Game, Turn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+        }
+    }
+
+    /// The most the level's bot searches a turn when given no limits: nodes
+    /// for the alpha-beta levels, playouts for the MCTS ones. `None` for
+    /// beginners, whose greedy bot doesn't search.
+    pub fn max_nodes(self, preset: &Preset) -> Option<u64> {
+        let playouts = preset.mcts.playouts as u64;
+        match self {
+            Difficulty::Beginner => None,
+            Difficulty::Easy => Some(200),
+            Difficulty::Medium => Some(2_000),
+            Difficulty::Hard => Some(playouts / 2),
+            Difficulty::Expert => Some(playouts),
+        }
+    }
+}
+
+/// A new opponent playing at the level.
+pub fn difficulty(level: Difficulty) -> Box<dyn Agent + Send> {
+    difficulty_seeded(level, thread_rng().gen())
+}
+
+/// Like [`difficulty`], with the opponent's random numbers seeded from
+/// `seed`.
+pub fn difficulty_seeded(level: Difficulty, seed: u64) -> Box<dyn Agent + Send> {
+    let preset = Preset::default();
+    let blurred = |blur| {
+        AlphaBetaAgent::with_evaluator(
+            BlurredEvaluator::new(LinearEvaluator::new(preset.weights.clone()), blur, seed),
+            1,
+        )
+    };
+    let weakened = |agent: Box<dyn Agent + Send>, noise| {
+        Weakened::new(agent, level.name(), seed)
+            .with_max_nodes(level.max_nodes(&preset))
+            .with_noise(noise)
+    };
+    match level {
+        Difficulty::Beginner => Box::new(weakened(Box::new(GreedyAgent::new()), 0.5)),
+        Difficulty::Easy => Box::new(weakened(Box::new(blurred(3.0)), 0.15)),
+        Difficulty::Medium => Box::new(weakened(Box::new(blurred(1.0)), 0.05)),
+        // Half the playouts of the expert.
+        Difficulty::Hard => Box::new(weakened(
+            Box::new(MctsAgent::new(preset.mcts.clone(), seed)),
+            0.0,
+        )),
+        Difficulty::Expert => Box::new(MctsAgent::new(preset.mcts, seed)),
+    }
+}
+
+/// A bot held back: it never searches more than a number of nodes, and with
+/// some probability plays a random legal turn instead of its own.
+pub struct Weakened<A> {
+    agent: A,
+    name: &'static str,
+    max_nodes: Option<u64>,
+    noise: f64,
+    rng: ChaCha8Rng,
+}
+
+impl<A: Agent> Weakened<A> {
+    /// The bot as it is, until told how to hold back.
+    pub fn new(agent: A, name: &'static str, seed: u64) -> Weakened<A> {
+        Weakened {
+            agent,
+            name,
+            max_nodes: None,
+            noise: 0.0,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn with_max_nodes(self, max_nodes: Option<u64>) -> Weakened<A> {
+        Weakened { max_nodes, ..self }
+    }
+
+    /// The probability of playing a random turn.
+    pub fn with_noise(self, noise: f64) -> Weakened<A> {
+        Weakened { noise, ..self }
+    }
+}
+
+impl<A: Agent> Agent for Weakened<A> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        if self.noise > 0.0 && self.rng.gen_bool(self.noise) {
+            if let Some(turn) = game.legal_turns().choose(&mut self.rng) {
+                return turn.clone();
+            }
+        }
+        let max_nodes = match (limits.max_nodes, self.max_nodes) {
+            (Some(asked), Some(cap)) => Some(asked.min(cap)),
+            (asked, cap) => asked.or(cap),
+        };
+        let limits = Limits {
+            max_nodes,
+            ..limits.clone()
+        };
+        self.agent.choose_turn(game, &limits)
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        self.agent.ponder(game)
+    }
+
+    fn stop(&mut self) {
+        self.agent.stop()
+    }
+}
+
+/// An evaluation off by up to `blur` either way. The error depends only on
+/// the position and the seed, so a search sees the same value for a
+/// position each time it gets there.
+#[derive(Debug, Clone)]
+pub struct BlurredEvaluator<E> {
+    evaluator: E,
+    blur: f32,
+    seed: u64,
+}
+
+impl<E: Evaluator> BlurredEvaluator<E> {
+    pub fn new(evaluator: E, blur: f32, seed: u64) -> BlurredEvaluator<E> {
+        BlurredEvaluator {
+            evaluator,
+            blur,
+            seed,
+        }
+    }
+}
+
+impl<E: Evaluator> Evaluator for BlurredEvaluator<E> {
+    fn evaluate(&self, game: &Game, player: u8) -> f32 {
+        let value = self.evaluator.evaluate(game, player);
+        if game.is_finished() {
+            return value;
+        }
+        // SplitMix64, to spread the hash evenly over the range.
+        let mut x = (game.hash() ^ self.seed ^ player as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^= x >> 31;
+        let unit = (x >> 11) as f32 / (1u64 << 53) as f32;
+        value + self.blur * (2.0 * unit - 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ai::suite::Suite;
+    use crate::ai::InfoCallback;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn every_level_plays() {
+        let game = Game::new_with_rules(Ruleset::new(2), 5);
+        let turns = game.legal_turns();
+        for &level in &Difficulty::ALL {
+            let mut agent = difficulty_seeded(level, 1);
+            let turn = agent.choose_turn(&game, &Limits::nodes(20));
+            assert!(
+                turns.contains(&turn),
+                "{} played an illegal turn",
+                level.name()
+            );
+        }
+    }
+
+    #[test]
+    fn search_budgets_grow_with_the_level() {
+        let preset = Preset::default();
+        let budget = |level: Difficulty| level.max_nodes(&preset);
+        assert_eq!(budget(Difficulty::Beginner), None);
+        // Easy and Medium search with alpha-beta, Hard and Expert with MCTS.
+        assert!(budget(Difficulty::Easy) < budget(Difficulty::Medium));
+        assert!(budget(Difficulty::Hard) < budget(Difficulty::Expert));
+
+        let game = Suite::standard().with_tag("endgame").positions[0].game();
+        for &level in &[Difficulty::Hard, Difficulty::Expert] {
+            let searched = Arc::new(AtomicU64::new(0));
+            let info = {
+                let searched = searched.clone();
+                InfoCallback::new(move |info| searched.store(info.nodes, Ordering::Relaxed))
+            };
+            difficulty_seeded(level, 1).choose_turn(&game, &Limits::none().with_info(info));
+            assert_eq!(Some(searched.load(Ordering::Relaxed)), budget(level));
+        }
+    }
+
+    #[test]
+    fn blurs_consistently() {
+        let game = Game::new_with_rules(Ruleset::new(2), 5);
+        let exact = LinearEvaluator::new(Preset::default().weights);
+        let blurred = BlurredEvaluator::new(exact.clone(), 2.0, 9);
+        let value = blurred.evaluate(&game, 0);
+        assert_eq!(blurred.evaluate(&game, 0), value);
+        assert!((value - exact.evaluate(&game, 0)).abs() <= 2.0);
+    }
+}
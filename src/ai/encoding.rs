@@ -0,0 +1,130 @@
+//! Positions and actions as numbers, the way neural networks see them. The
+//! training data and the networks playing from it share this encoding, so a
+//! network trained on exported games reads positions the same way in play.
+
+use crate::board::BoardPosition;
+use crate::game::{Action, Game};
+
+/// Board planes: the height of each square, then which of the three routings
+/// lies on top of it.
+const PLANES: [&str; 4] = ["height", "routing_a", "routing_b", "routing_c"];
+
+/// How many numbers [`position_features`] gives.
+pub const FEATURES: usize = PLANES.len() * 36 + 2 * 24 + 3 * 6 + 3;
+
+/// How many actions [`action_index`] tells apart: every placement, every
+/// marker move, every redraw and the three standalone actions.
+pub const ACTION_SPACE: u32 = PLACEMENTS + MOVES + 3 + 3;
+
+const PLACEMENTS: u32 = 6 * 36 * 4;
+const MOVES: u32 = 24 * 24;
+
+/// A number for each action, below [`ACTION_SPACE`], for using actions as
+/// the classes of a policy.
+pub fn action_index(action: &Action) -> u32 {
+    match action {
+        Action::PlacePiece(piece) => {
+            let square = piece.position.y as u32 * 6 + piece.position.x as u32;
+            (piece.piece.index() * 36 + square) * 4 + piece.rotation as u32
+        }
+        Action::MovePlayerMarker(from, to) => PLACEMENTS + *from as u32 * 24 + *to as u32,
+        Action::RedrawMarket(deck) => PLACEMENTS + MOVES + *deck as u32,
+        Action::Resign => PLACEMENTS + MOVES + 3,
+        Action::OfferDraw => PLACEMENTS + MOVES + 4,
+        Action::AcceptDraw => PLACEMENTS + MOVES + 5,
+    }
+}
+
+/// The names of the numbers [`position_features`] gives, in order.
+pub fn feature_names() -> Vec<String> {
+    let mut names = Vec::new();
+    for plane in &PLANES {
+        for pos in BoardPosition::all() {
+            names.push(format!("{}_{}_{}", plane, pos.x, pos.y));
+        }
+    }
+    for owner in &["own", "other"] {
+        for slot in 0..24 {
+            names.push(format!("{}_marker_{}", owner, slot));
+        }
+    }
+    for deck in 0..3 {
+        for piece in 0..6 {
+            names.push(format!("market_{}_{}", deck, piece));
+        }
+    }
+    for name in &["own_score", "best_other_score", "pieces_left"] {
+        names.push(name.to_string());
+    }
+    names
+}
+
+/// The features of the position for the player to move: the board planes,
+/// the markers of their team and everyone else's, the market one-hot by
+/// piece, and the scores.
+pub fn position_features(game: &Game) -> Vec<f32> {
+    let mut features = Vec::with_capacity(FEATURES);
+    features.extend(BoardPosition::all().map(|pos| game.board.height(pos) as f32));
+    for routing in 0..3 {
+        features.extend(BoardPosition::all().map(|pos| {
+            let covered = game.board.height(pos) > 0;
+            let on_top = game.board.top_piece(pos).routing() as u8 == routing;
+            (covered && on_top) as u8 as f32
+        }));
+    }
+
+    let rules = game.rules();
+    let team = rules.team_of(game.next_player());
+    let markers = game.team_markers(&game.player_markers);
+    for own in &[true, false] {
+        features.extend(markers.iter().map(|marker| match marker {
+            Some(owner) => ((*owner == team) == *own) as u8 as f32,
+            None => 0.0,
+        }));
+    }
+    for top in game.market().iter() {
+        features.extend(
+            (0..6).map(|piece| matches!(top, Some(top) if top.index() == piece) as u8 as f32),
+        );
+    }
+
+    let best_other = (0..rules.player_count)
+        .map(|player| rules.team_of(player))
+        .filter(|&other| other != team)
+        .map(|other| game.team_score(other))
+        .max()
+        .unwrap_or(0);
+    features.push(game.team_score(team) as f32);
+    features.push(best_other as f32);
+    features.push(game.decks_remaining().iter().sum::<usize>() as f32);
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::{Piece, PositionedPiece};
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn action_indices() {
+        let last = Action::PlacePiece(PositionedPiece {
+            piece: Piece::Blue,
+            rotation: 3,
+            position: BoardPosition::new(5, 5),
+        });
+        assert_eq!(action_index(&last), PLACEMENTS - 1);
+        assert_eq!(action_index(&Action::AcceptDraw), ACTION_SPACE - 1);
+        assert_eq!(
+            action_index(&Action::MovePlayerMarker(0, 1)),
+            PLACEMENTS + 1
+        );
+    }
+
+    #[test]
+    fn features_match_names() {
+        let game = Game::new_with_rules(Ruleset::new(3), 2);
+        assert_eq!(position_features(&game).len(), FEATURES);
+        assert_eq!(feature_names().len(), FEATURES);
+    }
+}
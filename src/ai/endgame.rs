@@ -0,0 +1,273 @@
+//! Exact play at the end of the game. Once the decks are almost empty, the
+//! game can be over within a turn or two, and it is worth looking at every
+//! way that could go rather than trusting an evaluation.
+//!
+//! Markers can be moved back and forth forever, so the game tree has no end
+//! and the solver can't find the value of every position. What it can do is
+//! prove a turn guarantees a result within a few turns, whatever the other
+//! players do and whatever comes up in the market.
+
+use std::time::{Duration, Instant};
+
+use super::playout::rewards;
+use super::refill::{refills, reveal, uncovers};
+use super::Limits;
+use crate::game::{Action, Game, Turn};
+
+/// Pieces left in the decks, at most, for the search agents to try the
+/// solver before searching.
+pub const ENDGAME_PIECES: usize = 2;
+
+/// Turns the search agents let the solver look ahead.
+pub const ENDGAME_TURNS: u32 = 1;
+
+/// Whether the search agents try the solver in this position.
+pub fn is_endgame(game: &Game) -> bool {
+    !game.is_finished() && game.decks_remaining().iter().sum::<usize>() <= ENDGAME_PIECES
+}
+
+/// A turn and the result it guarantees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proof {
+    pub turn: Turn,
+    /// The player's share of first place once the game is over, as in
+    /// [`rewards`]: 1 for winning alone. The player might do better, but
+    /// never worse.
+    pub reward: f64,
+}
+
+impl Proof {
+    /// Whether the turn wins the game outright, so nothing could be better.
+    pub fn is_win(&self) -> bool {
+        self.reward >= 1.0
+    }
+}
+
+/// The turn guaranteeing the player to move the best result within `turns`
+/// turns, if any guarantees them something. `None` as well if the limits
+/// stopped the search first.
+pub fn solve(game: &Game, turns: u32, limits: &Limits) -> Option<Proof> {
+    if game.is_finished() || turns == 0 {
+        return None;
+    }
+    let mut solver = Solver {
+        player: game.next_player(),
+        limits,
+        start: Instant::now(),
+        nodes: 0,
+        stopped: false,
+    };
+
+    let mut first: Option<(f64, Action)> = None;
+    for action in game.legal_actions() {
+        let value = solver.after_first(game, action.clone(), turns);
+        if solver.stopped {
+            return None;
+        }
+        match &first {
+            Some((best, _)) if value <= *best => {}
+            _ => first = Some((value, action)),
+        }
+        if value >= 1.0 {
+            break;
+        }
+    }
+    let (_, first) = first.filter(|(value, _)| *value > 0.0)?;
+
+    // What the first action uncovers is known by the second.
+    let mut mid = game.clone();
+    mid.do_action(first.clone())
+        .expect("Legal actions should be playable");
+    let mut second: Option<(f64, Action)> = None;
+    for action in mid.legal_actions() {
+        let value = solver.after_second(&mid, action.clone(), turns);
+        if solver.stopped {
+            return None;
+        }
+        match &second {
+            Some((best, _)) if value <= *best => {}
+            _ => second = Some((value, action)),
+        }
+        if value >= 1.0 {
+            break;
+        }
+    }
+    let (reward, second) = second?;
+    Some(Proof {
+        turn: Turn(first, second),
+        reward,
+    })
+}
+
+/// A winning turn for the player to move, if the position is an endgame and
+/// the solver finds one in time.
+pub fn winning_turn(game: &Game, limits: &Limits) -> Option<Turn> {
+    if !is_endgame(game) {
+        return None;
+    }
+    solve(game, ENDGAME_TURNS, limits)
+        .filter(Proof::is_win)
+        .map(|proof| proof.turn)
+}
+
+/// Finds the guaranteed rewards: the player picks the best of their moves,
+/// everyone else and the market the worst.
+struct Solver<'a> {
+    player: u8,
+    limits: &'a Limits,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+}
+
+impl Solver<'_> {
+    /// Counts a position, returning whether the search may go on.
+    fn tick(&mut self) -> bool {
+        self.nodes += 1;
+        let out_of_nodes = matches!(self.limits.max_nodes, Some(max) if self.nodes > max);
+        if out_of_nodes || self.limits.should_stop(self.start) {
+            self.stopped = true;
+        }
+        !self.stopped
+    }
+
+    fn ours(&self, game: &Game) -> bool {
+        let rules = game.rules();
+        rules.team_of(game.next_player()) == rules.team_of(self.player)
+    }
+
+    /// The best of the values for us, the worst for anyone else.
+    fn pick(
+        &mut self,
+        ours: bool,
+        actions: Vec<Action>,
+        mut value: impl FnMut(&mut Self, Action) -> f64,
+    ) -> f64 {
+        let mut picked: Option<f64> = None;
+        for action in actions {
+            let value = value(self, action);
+            if self.stopped {
+                return 0.0;
+            }
+            picked = Some(match picked {
+                Some(picked) if ours => picked.max(value),
+                Some(picked) => picked.min(value),
+                None => value,
+            });
+            if (ours && value >= 1.0) || (!ours && value <= 0.0) {
+                break;
+            }
+        }
+        // No way to go on is no guarantee of anything.
+        picked.unwrap_or(0.0)
+    }
+
+    /// The worst of `value` over what the market could show at the top of
+    /// the deck.
+    fn worst_refill(
+        &mut self,
+        game: &Game,
+        deck: Option<usize>,
+        mut value: impl FnMut(&mut Self, &Game) -> f64,
+    ) -> f64 {
+        let deck = match deck {
+            Some(deck) if !game.decks[deck].is_empty() => deck,
+            _ => return value(self, game),
+        };
+        let mut worst: f64 = 1.0;
+        for (piece, _) in refills(game, deck) {
+            let mut refilled = game.clone();
+            reveal(&mut refilled, deck, piece);
+            worst = worst.min(value(self, &refilled));
+            if self.stopped || worst <= 0.0 {
+                break;
+            }
+        }
+        worst
+    }
+
+    fn turn(&mut self, game: &Game, turns: u32) -> f64 {
+        if !self.tick() {
+            return 0.0;
+        }
+        if game.is_finished() {
+            return rewards(game)[self.player as usize];
+        }
+        if turns == 0 {
+            return 0.0;
+        }
+        let ours = self.ours(game);
+        self.pick(ours, game.legal_actions(), |solver, action| {
+            solver.after_first(game, action, turns)
+        })
+    }
+
+    fn after_first(&mut self, game: &Game, action: Action, turns: u32) -> f64 {
+        let deck = uncovers(game, &action);
+        let mut mid = game.clone();
+        mid.do_action(action)
+            .expect("Legal actions should be playable");
+        self.worst_refill(&mid, deck, |solver, mid| {
+            if !solver.tick() {
+                return 0.0;
+            }
+            let ours = solver.ours(mid);
+            solver.pick(ours, mid.legal_actions(), |solver, action| {
+                solver.after_second(mid, action, turns)
+            })
+        })
+    }
+
+    fn after_second(&mut self, mid: &Game, action: Action, turns: u32) -> f64 {
+        let player = mid.next_player();
+        let deck = uncovers(mid, &action);
+        let mut after = mid.clone();
+        after
+            .do_action(action)
+            .expect("Legal actions should be playable");
+        // Whether the game is stuck after the turn depends on the market.
+        self.worst_refill(&after, deck, |solver, after| {
+            let mut after = after.clone();
+            after.end_turn(player, Duration::from_secs(0));
+            solver.turn(&after, turns - 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+    use crate::piece::Piece;
+    use crate::rules::Ruleset;
+
+    /// Only a red piece left, so whoever places it ends the game.
+    fn last_piece(scores: Vec<u32>) -> Game {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 5);
+        game.decks = [Deck::from_slice(&[Piece::Red]), Deck::new(), Deck::new()];
+        game.scores = scores;
+        game
+    }
+
+    #[test]
+    fn ends_won_games() {
+        let game = last_piece(vec![10, 0]);
+        assert!(is_endgame(&game));
+        let turn = winning_turn(&game, &Limits::none()).unwrap();
+        let mut played = game.clone();
+        played.play_turn(turn).unwrap();
+        assert!(played.is_finished());
+        assert_eq!(played.final_ranking()[0], vec![0]);
+    }
+
+    #[test]
+    fn proves_what_it_can() {
+        // Level, but the last piece can be part of a line.
+        let proof = solve(&last_piece(vec![4, 4]), 1, &Limits::none()).unwrap();
+        assert!(proof.is_win());
+        assert!(last_piece(vec![4, 4]).play_turn(proof.turn).unwrap().points > 0);
+
+        assert_eq!(solve(&last_piece(vec![0, 10]), 1, &Limits::none()), None);
+        assert_eq!(solve(&last_piece(vec![10, 0]), 1, &Limits::nodes(0)), None);
+    }
+}
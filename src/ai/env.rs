@@ -0,0 +1,224 @@
+//! The game as a reinforcement learning environment, in the style of OpenAI
+//! Gym: the learner takes one action per step, the other seats are played by
+//! a bot, and the reward comes at the end of the game.
+//!
+//! Observations are the features of [`position_features`] followed by one
+//! number telling whether the action is the second of the turn. Actions are
+//! numbered as in [`action_index`], below [`ACTION_SPACE`], and
+//! [`PasstallyEnv::action_mask`] tells which of them are legal.
+
+use thiserror::Error;
+
+use super::encoding::{action_index, position_features, ACTION_SPACE, FEATURES};
+use super::playout::rewards;
+use super::{Agent, Limits};
+use crate::game::{Action, Game, Turn};
+use crate::rules::Ruleset;
+
+/// How many numbers an observation holds.
+pub const OBSERVATION: usize = FEATURES + 1;
+
+/// Turns after which an episode is stopped when not told otherwise, as
+/// markers can be moved back and forth forever.
+const MAX_TURNS: u32 = 200;
+
+pub struct PasstallyEnv {
+    rules: Ruleset,
+    /// The seat of the learner.
+    seat: u8,
+    opponent: Box<dyn Agent + Send>,
+    limits: Limits,
+    seed: u64,
+    episodes: u64,
+    max_turns: u32,
+    game: Game,
+    /// The position before the learner's first action and that action,
+    /// while waiting for the second.
+    first: Option<(Game, Action)>,
+    turns: u32,
+}
+
+impl PasstallyEnv {
+    /// An environment where the learner plays the first seat and `opponent`
+    /// plays every other one. Episode `i` is dealt with `seed + i`.
+    pub fn new(rules: Ruleset, opponent: Box<dyn Agent + Send>, seed: u64) -> PasstallyEnv {
+        PasstallyEnv {
+            game: Game::new_with_rules(rules.clone(), seed),
+            rules,
+            seat: 0,
+            opponent,
+            limits: Limits::none(),
+            seed,
+            episodes: 0,
+            max_turns: MAX_TURNS,
+            first: None,
+            turns: 0,
+        }
+    }
+
+    pub fn with_seat(mut self, seat: u8) -> Self {
+        self.seat = seat;
+        self
+    }
+
+    /// How long the opponent thinks about each turn.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Turns after which an episode is stopped and judged on its standings.
+    pub fn with_max_turns(mut self, max_turns: u32) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+
+    /// The position as it stands, halfway through the learner's turn after
+    /// their first action.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Starts a new episode and gives the first observation.
+    pub fn reset(&mut self) -> Vec<f32> {
+        let seed = self.seed.wrapping_add(self.episodes);
+        self.episodes += 1;
+        self.game = Game::new_with_rules(self.rules.clone(), seed);
+        self.first = None;
+        self.turns = 0;
+        self.play_opponents();
+        self.observation()
+    }
+
+    /// Takes the learner's action, lets the opponents play if that ends the
+    /// turn, and gives the observation, the learner's reward and whether the
+    /// episode is over. The reward is zero until the end, then as in
+    /// [`rewards`].
+    pub fn step(&mut self, action: u32) -> Result<(Vec<f32>, f32, bool), EnvError> {
+        if self.is_done() {
+            return Err(EnvError::Done);
+        }
+        let action = self
+            .legal_actions()
+            .into_iter()
+            .find(|legal| action_index(legal) == action)
+            .ok_or(EnvError::IllegalAction(action))?;
+
+        match self.first.take() {
+            None => {
+                let start = self.game.clone();
+                self.game
+                    .do_action(action.clone())
+                    .expect("Legal actions should be playable");
+                self.first = Some((start, action));
+            }
+            Some((mut start, first)) => {
+                start
+                    .play_turn(Turn(first, action))
+                    .expect("Legal turns should be playable");
+                self.game = start;
+                self.turns += 1;
+                self.play_opponents();
+            }
+        }
+
+        let done = self.is_done();
+        let reward = if done {
+            rewards(&self.game)[self.seat as usize] as f32
+        } else {
+            0.0
+        };
+        Ok((self.observation(), reward, done))
+    }
+
+    /// Which actions [`step`](PasstallyEnv::step) takes, indexed by action
+    /// number.
+    pub fn action_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; ACTION_SPACE as usize];
+        for action in self.legal_actions() {
+            mask[action_index(&action) as usize] = true;
+        }
+        mask
+    }
+
+    /// The actions the learner can take, leaving out first actions after
+    /// which there is no second one.
+    fn legal_actions(&self) -> Vec<Action> {
+        if self.is_done() {
+            return Vec::new();
+        }
+        let mut actions = self.game.legal_actions();
+        if self.first.is_none() {
+            actions.retain(|action| {
+                let mut game = self.game.clone();
+                game.do_action(action.clone())
+                    .expect("Legal actions should be playable");
+                !game.legal_actions().is_empty()
+            });
+        }
+        actions
+    }
+
+    fn is_done(&self) -> bool {
+        self.game.is_finished() || self.turns >= self.max_turns
+    }
+
+    /// Plays the other seats until it's the learner's turn again.
+    fn play_opponents(&mut self) {
+        while !self.is_done() && self.game.next_player() != self.seat {
+            let turn = self.opponent.choose_turn(&self.game, &self.limits);
+            self.game
+                .play_turn(turn)
+                .expect("Bots should only play legal turns");
+            self.turns += 1;
+        }
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        let mut observation = position_features(&self.game);
+        observation.push(self.first.is_some() as u8 as f32);
+        observation
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EnvError {
+    #[error("Action {0} isn't legal here.")]
+    IllegalAction(u32),
+    #[error("The episode is over, it needs a reset.")]
+    Done,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::RandomAgent;
+
+    #[test]
+    fn steps_through_an_episode() {
+        let opponent = Box::new(RandomAgent::new(1));
+        let mut env = PasstallyEnv::new(Ruleset::new(2), opponent, 3)
+            .with_seat(1)
+            .with_max_turns(4);
+        let observation = env.reset();
+        assert_eq!(observation.len(), OBSERVATION);
+        assert_eq!(env.game().next_player(), 1);
+
+        assert_eq!(
+            env.step(ACTION_SPACE - 1),
+            Err(EnvError::IllegalAction(ACTION_SPACE - 1))
+        );
+        let mut done = false;
+        let mut steps = 0;
+        while !done {
+            let mask = env.action_mask();
+            let action = mask.iter().position(|&legal| legal).unwrap() as u32;
+            let (observation, _, over) = env.step(action).unwrap();
+            assert_eq!(observation[FEATURES], (steps % 2 == 0) as u8 as f32);
+            done = over;
+            steps += 1;
+        }
+        assert_eq!(steps, 4);
+        assert_eq!(env.step(0), Err(EnvError::Done));
+    }
+}
@@ -0,0 +1,68 @@
+//! How likely each player is to win from a position, for graphs of a game
+//! and broadcast overlays. Estimates are for each player, add up to 1 and
+//! share a win between the players sharing first place.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::mcts::PLAYOUT_TURNS;
+use super::network::{value_rewards, Network};
+use super::playout::{playout, rewards};
+use crate::game::Game;
+
+/// Estimates from the average result of `samples` short random games, as
+/// the monte carlo bots judge positions. The playouts are seeded from the
+/// position, so the same position always gets the same estimate.
+pub fn estimate_win_probability(game: &Game, samples: u32) -> Vec<f64> {
+    if game.is_finished() || samples == 0 {
+        return rewards(game);
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(game.hash());
+    let mut totals = vec![0.0; game.rules().player_count as usize];
+    for _ in 0..samples {
+        let mut game = game.clone();
+        playout(&mut game, &mut rng, PLAYOUT_TURNS);
+        for (total, reward) in totals.iter_mut().zip(rewards(&game)) {
+            *total += reward;
+        }
+    }
+    normalized(totals)
+}
+
+/// Estimates from what the network makes of the position.
+pub fn estimate_with_network(game: &Game, network: &impl Network) -> Vec<f64> {
+    if game.is_finished() {
+        return rewards(game);
+    }
+    normalized(value_rewards(game, network.predict(game).value))
+}
+
+fn normalized(mut values: Vec<f64>) -> Vec<f64> {
+    let sum: f64 = values.iter().sum();
+    if sum > 0.0 {
+        values.iter_mut().for_each(|value| *value /= sum);
+    } else {
+        let even = 1.0 / values.len() as f64;
+        values.iter_mut().for_each(|value| *value = even);
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::network::UniformNetwork;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn estimates_add_up() {
+        let game = Game::new_with_rules(Ruleset::new(3), 5);
+        let estimate = estimate_win_probability(&game, 4);
+        assert_eq!(estimate.len(), 3);
+        assert!((estimate.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(estimate_win_probability(&game, 4), estimate);
+
+        let network = estimate_with_network(&game, &UniformNetwork);
+        assert!(network.iter().all(|p| (p - 1.0 / 3.0).abs() < 1e-6));
+    }
+}
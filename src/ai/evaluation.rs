@@ -0,0 +1,262 @@
+//! The static evaluation: how good a position looks for a player without
+//! searching any further. The bots use a weighted sum of named features, and
+//! anything implementing [`Evaluator`] can take its place.
+
+use super::playout::rewards;
+use super::EvaluatorWeights;
+use crate::board::edge_square;
+use crate::game::Game;
+
+/// The value of a won game. Every other position is worth less, and a lost
+/// game is worth `-WIN`.
+pub const WIN: f32 = 10_000.0;
+
+pub trait Evaluator {
+    /// How good the position is for the player's team, between `-WIN` and
+    /// `WIN`.
+    fn evaluate(&self, game: &Game, player: u8) -> f32;
+}
+
+impl<F: Fn(&Game, u8) -> f32> Evaluator for F {
+    fn evaluate(&self, game: &Game, player: u8) -> f32 {
+        self(game, player)
+    }
+}
+
+/// Something about a position that tends to go with winning it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Points already scored.
+    Score,
+    /// Sum of the heights of the squares next to the team's markers.
+    HeightNearMarkers,
+    /// Markers that a single placement could connect to another.
+    ConnectablePairs,
+    /// Pieces on offer that fit next to the team's markers.
+    MarketQuality,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 4] = [
+        Feature::Score,
+        Feature::HeightNearMarkers,
+        Feature::ConnectablePairs,
+        Feature::MarketQuality,
+    ];
+
+    /// The name of the feature's weight in presets.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Score => "score",
+            Feature::HeightNearMarkers => "height_near_markers",
+            Feature::ConnectablePairs => "connectable_pairs",
+            Feature::MarketQuality => "market_quality",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Feature> {
+        Feature::ALL
+            .iter()
+            .copied()
+            .find(|feature| feature.name() == name)
+    }
+
+    /// The unweighted value of the feature for the team.
+    pub fn extract(self, game: &Game, team: u8) -> f32 {
+        let value = match self {
+            Feature::Score => game.team_score(team),
+            Feature::HeightNearMarkers => height_near_markers(game, team),
+            Feature::ConnectablePairs => connectable_pairs(game, team),
+            Feature::MarketQuality => market_quality(game, team),
+        };
+        value as f32
+    }
+}
+
+impl EvaluatorWeights {
+    pub fn get(&self, feature: Feature) -> f32 {
+        match feature {
+            Feature::Score => self.score,
+            Feature::HeightNearMarkers => self.height_near_markers,
+            Feature::ConnectablePairs => self.connectable_pairs,
+            Feature::MarketQuality => self.market_quality,
+        }
+    }
+
+    pub fn set(&mut self, feature: Feature, weight: f32) {
+        match feature {
+            Feature::Score => self.score = weight,
+            Feature::HeightNearMarkers => self.height_near_markers = weight,
+            Feature::ConnectablePairs => self.connectable_pairs = weight,
+            Feature::MarketQuality => self.market_quality = weight,
+        }
+    }
+
+    /// The weights in the order of [`Feature::ALL`], for tuning them as a
+    /// vector.
+    pub fn to_vec(&self) -> Vec<f32> {
+        Feature::ALL
+            .iter()
+            .map(|&feature| self.get(feature))
+            .collect()
+    }
+
+    /// Weights from a vector in the order of [`Feature::ALL`]. Missing
+    /// weights are 0, extra ones are ignored.
+    pub fn from_slice(weights: &[f32]) -> EvaluatorWeights {
+        let mut result = EvaluatorWeights {
+            score: 0.0,
+            height_near_markers: 0.0,
+            connectable_pairs: 0.0,
+            market_quality: 0.0,
+        };
+        for (&feature, &weight) in Feature::ALL.iter().zip(weights) {
+            result.set(feature, weight);
+        }
+        result
+    }
+}
+
+/// Every feature of the team, in the order of [`Feature::ALL`].
+pub fn features(game: &Game, team: u8) -> Vec<f32> {
+    Feature::ALL
+        .iter()
+        .map(|feature| feature.extract(game, team))
+        .collect()
+}
+
+/// The evaluation the bots use: the weighted sum of the features, each
+/// counted relative to the best of the other teams. Finished games are won
+/// or lost outright.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearEvaluator {
+    pub weights: EvaluatorWeights,
+}
+
+impl LinearEvaluator {
+    pub fn new(weights: EvaluatorWeights) -> LinearEvaluator {
+        LinearEvaluator { weights }
+    }
+
+    fn weighted(&self, game: &Game, team: u8) -> f32 {
+        features(game, team)
+            .iter()
+            .zip(self.weights.to_vec())
+            .map(|(value, weight)| value * weight)
+            .sum()
+    }
+}
+
+impl Evaluator for LinearEvaluator {
+    fn evaluate(&self, game: &Game, player: u8) -> f32 {
+        if game.is_finished() {
+            return WIN * (2.0 * rewards(game)[player as usize] as f32 - 1.0);
+        }
+
+        let team = game.rules().team_of(player);
+        let mut teams: Vec<u8> = (0..game.rules().player_count)
+            .map(|player| game.rules().team_of(player))
+            .collect();
+        teams.sort_unstable();
+        teams.dedup();
+        let best_other = teams
+            .iter()
+            .filter(|&&other| other != team)
+            .map(|&other| self.weighted(game, other))
+            .fold(None, |best: Option<f32>, value| {
+                Some(best.map_or(value, |best| best.max(value)))
+            });
+        // Alone, as in a puzzle, the features count as they are.
+        let value = self.weighted(game, team) - best_other.unwrap_or(0.0);
+        value.clamp(-WIN + 1.0, WIN - 1.0)
+    }
+}
+
+/// The edge slots with a marker of the team on them.
+fn team_slots(game: &Game, team: u8) -> Vec<u8> {
+    let markers = game.team_markers(&game.player_markers);
+    (0..24)
+        .filter(|&slot| markers[slot as usize] == Some(team))
+        .collect()
+}
+
+fn height_near_markers(game: &Game, team: u8) -> u32 {
+    team_slots(game, team)
+        .into_iter()
+        .map(|slot| game.board.height(edge_square(slot).0))
+        .sum()
+}
+
+/// Markers whose line leaves the board on, or next to, the square of
+/// another of the team's markers, so one placement could join them.
+fn connectable_pairs(game: &Game, team: u8) -> u32 {
+    let slots = team_slots(game, team);
+    slots
+        .iter()
+        .filter(|&&slot| {
+            let exit = game.board.exit(slot);
+            let (near, _) = edge_square(exit);
+            !slots.contains(&exit)
+                && slots.iter().any(|&other| {
+                    let (pos, _) = edge_square(other);
+                    other != slot && (pos.x - near.x).abs() + (pos.y - near.y).abs() <= 1
+                })
+        })
+        .count() as u32
+}
+
+/// Pieces on offer that fit on the square next to one of the team's
+/// markers, counted once per marker.
+fn market_quality(game: &Game, team: u8) -> u32 {
+    let slots = team_slots(game, team);
+    let mut count = 0;
+    for piece in game.market().iter().flatten() {
+        for &slot in &slots {
+            let (pos, _) = edge_square(slot);
+            if game
+                .board
+                .placeable_rotations_at(pos, *piece)
+                .next()
+                .is_some()
+            {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Preset;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn symmetric_start() {
+        let evaluator = LinearEvaluator::new(Preset::default().weights);
+        let game = Game::new_with_rules(Ruleset::new(2), 4);
+        assert_eq!(evaluator.evaluate(&game, 0), -evaluator.evaluate(&game, 1));
+
+        let mut won = game.clone();
+        won.resign(1).unwrap();
+        assert_eq!(evaluator.evaluate(&won, 0), WIN);
+        assert_eq!(evaluator.evaluate(&won, 1), -WIN);
+    }
+
+    #[test]
+    fn weight_vectors() {
+        let weights = Preset::default().weights;
+        assert_eq!(EvaluatorWeights::from_slice(&weights.to_vec()), weights);
+        for &feature in &Feature::ALL {
+            assert_eq!(Feature::from_name(feature.name()), Some(feature));
+        }
+
+        // Only the score counts, and nobody has scored yet.
+        let game = Game::new_with_rules(Ruleset::new(2), 4);
+        let evaluator = LinearEvaluator::new(EvaluatorWeights::from_slice(&[1.0]));
+        assert_eq!(evaluator.evaluate(&game, 0), 0.0);
+        let custom = |game: &Game, player| game.scores()[player as usize] as f32;
+        assert_eq!(custom.evaluate(&game, 1), 0.0);
+    }
+}
@@ -0,0 +1,121 @@
+//! Engines running as their own process and speaking the
+//! [protocol](super::protocol) over their stdin and stdout.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command as Process, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use super::protocol::{Command, ProtocolError, Response};
+
+/// How long an engine has to introduce itself after starting.
+pub const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct ExternalEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    /// The engine's stdout, line by line, read on a thread of its own so
+    /// that waiting for it can time out.
+    lines: Receiver<String>,
+}
+
+impl ExternalEngine {
+    /// Starts the program and waits for it to introduce itself.
+    pub fn spawn(program: &str, args: &[String]) -> Result<ExternalEngine, EngineError> {
+        let mut child = Process::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(EngineError::Spawn)?;
+        let stdin = child.stdin.take().expect("The engine's stdin is piped");
+        let stdout = child.stdout.take().expect("The engine's stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = ExternalEngine {
+            name: program.to_string(),
+            child,
+            stdin,
+            lines,
+        };
+        engine.send(&Command::Hello)?;
+        let deadline = Instant::now() + HELLO_TIMEOUT;
+        loop {
+            match engine.receive(deadline)? {
+                Response::Id { name } => engine.name = name,
+                Response::PasstallyOk => return Ok(engine),
+                _ => {}
+            }
+        }
+    }
+
+    /// The name the engine gave, or the program's if it gave none.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn send(&mut self, command: &Command) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(EngineError::Io)
+    }
+
+    /// The next response, waiting for it until the deadline. Lines that
+    /// aren't responses are skipped.
+    pub fn receive(&mut self, deadline: Instant) -> Result<Response, EngineError> {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(timeout) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err(EngineError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => return Err(EngineError::Closed),
+            };
+            match line.parse() {
+                Ok(response) => return Ok(response),
+                Err(ProtocolError::Unknown(_)) => {}
+                Err(err) => return Err(EngineError::Protocol(err)),
+            }
+        }
+    }
+}
+
+/// Asks the engine to quit, and stops it if it doesn't.
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send(&Command::Quit);
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum EngineError {
+    #[error("Couldn't start the engine: {0}")]
+    Spawn(io::Error),
+    #[error("Couldn't talk to the engine: {0}")]
+    Io(io::Error),
+    #[error("The engine didn't answer in time.")]
+    Timeout,
+    #[error("The engine closed its output.")]
+    Closed,
+    #[error("The engine said something wrong: {0}")]
+    Protocol(ProtocolError),
+}
@@ -0,0 +1,82 @@
+//! An opponent that only looks at the turn in front of it. Easy to beat once
+//! you plan ahead, but it never misses points lying on the table.
+
+use super::ordering::turn_points;
+use super::{Agent, Limits};
+use crate::board::BoardPosition;
+use crate::game::{Action, Game, Turn};
+
+/// Plays the turn scoring the most points right away. Between turns scoring
+/// the same, it prefers building on taller stacks and then nearer the middle
+/// of the board, which tends to set up longer lines.
+#[derive(Debug, Clone, Default)]
+pub struct GreedyAgent;
+
+impl GreedyAgent {
+    pub fn new() -> GreedyAgent {
+        GreedyAgent
+    }
+}
+
+/// How well a turn builds up the board, for breaking ties.
+fn placement_key(game: &Game, turn: &Turn) -> (u32, i32) {
+    let mut height = 0;
+    let mut centrality = 0;
+    for action in &[&turn.0, &turn.1] {
+        if let Action::PlacePiece(piece) = action {
+            let (pos1, pos2) = piece.positions();
+            height += game.board.height(pos1) + game.board.height(pos2);
+            centrality -= distance_from_centre(pos1) + distance_from_centre(pos2);
+        }
+    }
+    (height, centrality)
+}
+
+/// Manhattan distance to the middle of the board, doubled to stay whole.
+fn distance_from_centre(pos: BoardPosition) -> i32 {
+    (2 * pos.x as i32 - 5).abs() + (2 * pos.y as i32 - 5).abs()
+}
+
+impl Agent for GreedyAgent {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn choose_turn(&mut self, game: &Game, _: &Limits) -> Turn {
+        // Reversed, so the first of equally good turns is picked.
+        game.legal_turns()
+            .into_iter()
+            .rev()
+            .max_by_key(|turn| (turn_points(game, turn), placement_key(game, turn)))
+            .expect("The player to move should have a legal turn")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::suite::{best_scoring_turn, Suite};
+
+    #[test]
+    fn scores_the_most_right_away() {
+        let suite = Suite::standard().with_tag("quick");
+        let report = suite
+            .run_agent(&mut GreedyAgent::new(), &Limits::none())
+            .unwrap();
+        for (position, result) in suite.positions.iter().zip(&report.results) {
+            let (_, points) = best_scoring_turn(&position.game()).unwrap();
+            assert_eq!(result.points, Some(points));
+        }
+        // The suite's best turns look at the reply too, which greedy
+        // doesn't.
+        assert!(report.solved() < suite.positions.len());
+    }
+
+    #[test]
+    fn prefers_the_middle() {
+        assert!(
+            distance_from_centre(BoardPosition::new(2, 3))
+                < distance_from_centre(BoardPosition::new(0, 5))
+        );
+    }
+}
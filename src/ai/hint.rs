@@ -0,0 +1,83 @@
+//! Hints for new players: a turn suggested by a bot, with enough of its
+//! reasoning for the GUI to explain it.
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use super::evaluation::{Evaluator, LinearEvaluator};
+use super::{Agent, InfoCallback, Limits, MctsAgent, Preset, SearchInfo};
+use crate::game::{Game, Turn};
+
+/// A suggested turn and why it was suggested.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hint {
+    pub turn: Turn,
+    /// Points the turn scores right away.
+    pub points: u32,
+    /// How much better the position looks for the player after the turn
+    /// than before it, in units of the evaluation, about a point each with
+    /// the default weights.
+    pub gain: f32,
+    /// The turns the bot expects to follow, starting with the hint.
+    pub line: Vec<Turn>,
+}
+
+impl Game {
+    /// A good turn for the player to move, as the default bot sees it within
+    /// the limits.
+    pub fn hint(&self, limits: &Limits) -> Turn {
+        self.explained_hint(limits).turn
+    }
+
+    /// Like [`hint`](Game::hint), with the reasons for the turn.
+    pub fn explained_hint(&self, limits: &Limits) -> Hint {
+        let last: Arc<Mutex<Option<SearchInfo>>> = Arc::default();
+        let sink = last.clone();
+        let forward = limits.info.clone();
+        let limits = limits.clone().with_info(InfoCallback::new(move |info| {
+            if let Some(forward) = &forward {
+                forward.report(info);
+            }
+            *sink.lock().unwrap() = Some(info.clone());
+        }));
+
+        let preset = Preset::default();
+        let turn = MctsAgent::new(preset.mcts, self.hash()).choose_turn(self, &limits);
+        // A winning turn is played without searching, so without a line.
+        let line = match last.lock().unwrap().take() {
+            Some(info) if info.pv.first() == Some(&turn) => info.pv,
+            _ => vec![turn.clone()],
+        };
+
+        let player = self.next_player();
+        let evaluator = LinearEvaluator::new(preset.weights);
+        let mut after = self.clone();
+        let points = after
+            .play_turn(turn.clone())
+            .expect("The bot's turn should be legal")
+            .points;
+        Hint {
+            gain: evaluator.evaluate(&after, player) - evaluator.evaluate(self, player),
+            points,
+            line,
+            turn,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::Limits;
+    use crate::game::Game;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn explains_hints() {
+        let game = Game::new_with_rules(Ruleset::new(2), 8);
+        let hint = game.explained_hint(&Limits::nodes(30));
+        assert!(game.legal_turns().contains(&hint.turn));
+        assert_eq!(hint.line[0], hint.turn);
+        assert_eq!(game.hint(&Limits::nodes(30)), hint.turn);
+    }
+}
@@ -0,0 +1,283 @@
+//! Information set monte carlo tree search. Plain [`MctsAgent`] searches the
+//! real game, so it knows which pieces are coming. This search deals the
+//! unseen pieces again before every playout with [`Game::determinize`], so
+//! it only knows what the player it plays for knows.
+//!
+//! Different deals make different turns legal further down the tree, so each
+//! playout only walks through the turns legal in its deal, and a turn is
+//! judged against how often it was available rather than against how often
+//! its parent was visited.
+//!
+//! [`MctsAgent`]: super::MctsAgent
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::arena::{Arena, NodeId, TreeNode};
+use super::endgame::winning_turn;
+use super::mcts::REPORT_EVERY;
+use super::playout::{playout, rewards};
+use super::{Agent, Limits, MctsParams, Preset, SearchInfo};
+use crate::game::{Game, Turn};
+use crate::piece::Piece;
+
+/// Turns played at random after leaving the tree before the game is judged on
+/// its standings.
+const PLAYOUT_TURNS: u32 = 12;
+
+/// What decides the legal turns in a position: everything the hash covers,
+/// and the pieces under the market, which the second action of a turn can
+/// place once the first uncovers them.
+type DealKey = (u64, [Option<Piece>; 3]);
+
+fn deal_key(game: &Game) -> DealKey {
+    let mut below = [None; 3];
+    for (below, deck) in below.iter_mut().zip(&game.decks) {
+        *below = deck.iter().rev().nth(1).copied();
+    }
+    (game.hash(), below)
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// The turn leading here, `None` for the root.
+    turn: Option<Turn>,
+    /// The player who played `turn`.
+    player: u8,
+    children: Vec<NodeId>,
+    visits: u32,
+    /// Playouts in which `turn` could have been played.
+    availability: u32,
+    /// Sum of the rewards of `player` over the playouts through here.
+    value: f64,
+    /// The legal turns in the last deal, with the deal they were found in.
+    /// Deals agreeing on the pieces a turn can reach have the same turns.
+    legal: Option<(DealKey, Vec<Turn>)>,
+}
+
+impl Node {
+    fn new(turn: Option<Turn>, player: u8) -> Node {
+        Node {
+            turn,
+            player,
+            children: Vec::new(),
+            visits: 0,
+            availability: 0,
+            value: 0.0,
+            legal: None,
+        }
+    }
+}
+
+impl TreeNode for Node {
+    fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    fn clear_children(&mut self) {
+        self.children.clear();
+    }
+
+    fn push_child(&mut self, child: NodeId) {
+        self.children.push(child);
+    }
+}
+
+/// A bot searching with information set monte carlo tree search, see the
+/// module documentation.
+#[derive(Debug, Clone)]
+pub struct IsmctsAgent {
+    params: MctsParams,
+    rng: ChaCha8Rng,
+}
+
+impl IsmctsAgent {
+    pub fn new(params: MctsParams, seed: u64) -> IsmctsAgent {
+        IsmctsAgent {
+            params,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_preset(preset: &Preset) -> IsmctsAgent {
+        IsmctsAgent::new(preset.mcts.clone(), thread_rng().gen())
+    }
+
+    /// One playout from the root, in a fresh deal of the unseen pieces.
+    fn playout(&mut self, nodes: &mut Arena<Node>, root: &Game) {
+        let mut game = root.determinize(&mut self.rng);
+        let mut path = vec![NodeId::ROOT];
+        let mut node = NodeId::ROOT;
+
+        while !game.is_finished() {
+            let key = deal_key(&game);
+            let legal = match nodes[node].legal.take() {
+                Some((cached, legal)) if cached == key => legal,
+                _ => game.legal_turns(),
+            };
+            let available: Vec<NodeId> = {
+                let legal: HashSet<&Turn> = legal.iter().collect();
+                nodes[node]
+                    .children
+                    .iter()
+                    .copied()
+                    .filter(|&child| legal.contains(nodes[child].turn.as_ref().unwrap()))
+                    .collect()
+            };
+            for &child in &available {
+                nodes[child].availability += 1;
+            }
+
+            let tried: HashSet<&Turn> = nodes[node]
+                .children
+                .iter()
+                .map(|&child| nodes[child].turn.as_ref().unwrap())
+                .collect();
+            let untried: Vec<&Turn> = legal.iter().filter(|turn| !tried.contains(turn)).collect();
+
+            let expand = untried.choose(&mut self.rng).map(|&turn| turn.clone());
+            drop(tried);
+            nodes[node].legal = Some((key, legal));
+
+            if let Some(turn) = expand {
+                let player = game.next_player();
+                game.play_turn(turn.clone())
+                    .expect("Legal turns should be playable");
+                let mut child = Node::new(Some(turn), player);
+                child.availability = 1;
+                let child = nodes.push(child);
+                nodes[node].children.push(child);
+                path.push(child);
+                break;
+            }
+            if available.is_empty() {
+                break;
+            }
+
+            node = self.select(nodes, &available);
+            game.play_turn(nodes[node].turn.clone().unwrap())
+                .expect("Available turns should be legal");
+            path.push(node);
+        }
+
+        playout(&mut game, &mut self.rng, PLAYOUT_TURNS);
+        let rewards = rewards(&game);
+        for id in path {
+            let node = &mut nodes[id];
+            node.visits += 1;
+            node.value += rewards[node.player as usize];
+        }
+    }
+
+    /// The available child with the best UCT score, counting availability
+    /// in place of the parent's visits.
+    fn select(&mut self, nodes: &Arena<Node>, available: &[NodeId]) -> NodeId {
+        let exploration = self.params.exploration as f64;
+        let uct = |id: NodeId| {
+            let child = &nodes[id];
+            let visits = child.visits.max(1) as f64;
+            let availability = (child.availability.max(1) as f64).ln();
+            child.value / visits + exploration * (availability / visits).sqrt()
+        };
+        let mut available = available.to_vec();
+        // Shuffled so ties don't always go the same way.
+        available.shuffle(&mut self.rng);
+        available
+            .into_iter()
+            .max_by(|&a, &b| uct(a).partial_cmp(&uct(b)).unwrap())
+            .unwrap()
+    }
+}
+
+impl Agent for IsmctsAgent {
+    fn name(&self) -> &str {
+        "ismcts"
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "debug", name = "search", skip_all, fields(bot = "ismcts"))
+    )]
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        let limits = &limits.for_turn(game);
+        if let Some(turn) = winning_turn(game, limits) {
+            return turn;
+        }
+        let mut nodes = Arena::new(Node::new(None, game.next_player()));
+
+        let start = Instant::now();
+        let playouts = match limits.max_nodes {
+            Some(nodes) => nodes,
+            // Searches until told to stop.
+            None if limits.max_time.is_some() || limits.stop.is_some() => u64::MAX,
+            None => self.params.playouts as u64,
+        };
+        let best = |nodes: &Arena<Node>| {
+            nodes[NodeId::ROOT]
+                .children
+                .iter()
+                .copied()
+                .max_by_key(|&child| nodes[child].visits)
+        };
+        // Only the first turn, deeper ones depend on the deal.
+        let info = |nodes: &Arena<Node>, played: u64| {
+            let best = best(nodes).map(|child| &nodes[child]);
+            SearchInfo {
+                nodes: played,
+                elapsed: start.elapsed(),
+                depth: best.is_some() as u32,
+                score: best.map_or(0.0, |best| (best.value / best.visits.max(1) as f64) as f32),
+                pv: best.iter().map(|best| best.turn.clone().unwrap()).collect(),
+            }
+        };
+        let mut played = 0;
+        let mut reported = start;
+        while played < playouts && !limits.should_stop(start) {
+            self.playout(&mut nodes, game);
+            played += 1;
+            if limits.info.is_some() && reported.elapsed() >= REPORT_EVERY {
+                limits.report(|| info(&nodes, played));
+                reported = Instant::now();
+            }
+        }
+        limits.report(|| info(&nodes, played));
+
+        match best(&nodes) {
+            Some(child) => nodes[child].turn.clone().unwrap(),
+            // Not even one playout, so take whatever is legal.
+            None => game
+                .legal_turns()
+                .into_iter()
+                .next()
+                .expect("The player to move should have a legal turn"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn ignores_the_hidden_order() {
+        let params = MctsParams {
+            exploration: 1.41,
+            playouts: 10,
+        };
+        let mut game = Game::new_with_rules(Ruleset::new(2), 9);
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        let reshuffled = game.fork_reshuffled(3);
+        assert_ne!(game, reshuffled);
+
+        // The same seed sees the same thing in both games, so plays the same.
+        let turn = IsmctsAgent::new(params.clone(), 5).choose_turn(&game, &Limits::none());
+        let other = IsmctsAgent::new(params, 5).choose_turn(&reshuffled, &Limits::none());
+        assert_eq!(turn, other);
+        game.play_turn(turn).unwrap();
+    }
+}
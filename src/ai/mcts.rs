@@ -0,0 +1,473 @@
+//! Monte carlo tree search. Each playout walks down the tree of turns picking
+//! children by UCT, adds one new turn to the tree, plays the game out at
+//! random and credits the result to every turn on the way.
+//!
+//! The search sees the real order of the decks, so it knows which pieces come
+//! next when nobody else does.
+//!
+//! While pondering, the search grows the tree of the position after the
+//! bot's turn on another thread, and the next search starts from the part of
+//! it below the turn the opponent played.
+
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::arena::{Arena, NodeId, TreeNode};
+use super::endgame::winning_turn;
+use super::playout::{playout_with, rewards, PlayoutPolicy, UniformPolicy};
+use super::{Agent, Limits, MctsParams, Preset, SearchInfo, StopFlag};
+use crate::game::{Game, Turn};
+
+/// Turns played by the playout policy after leaving the tree before the game
+/// is judged on its standings.
+pub(crate) const PLAYOUT_TURNS: u32 = 12;
+
+/// How often the search reports what it has found so far.
+pub(crate) const REPORT_EVERY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// The turn leading here, `None` for the root.
+    turn: Option<Turn>,
+    /// The player who played `turn`.
+    player: u8,
+    /// The position's hash, to find it again when the tree is reused.
+    hash: u64,
+    round: u32,
+    children: Vec<NodeId>,
+    /// Turns not in the tree yet, found the first time the node is expanded.
+    untried: Option<Vec<Turn>>,
+    visits: u32,
+    /// Sum of the rewards of `player` over the playouts through here.
+    value: f64,
+}
+
+impl Node {
+    fn new(turn: Option<Turn>, player: u8, game: &Game) -> Node {
+        Node {
+            turn,
+            player,
+            hash: game.hash(),
+            round: game.round(),
+            children: Vec::new(),
+            untried: None,
+            visits: 0,
+            value: 0.0,
+        }
+    }
+}
+
+impl TreeNode for Node {
+    fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    fn clear_children(&mut self) {
+        self.children.clear();
+    }
+
+    fn push_child(&mut self, child: NodeId) {
+        self.children.push(child);
+    }
+}
+
+/// The search tree, kept between turns so the part of it that is still
+/// relevant doesn't have to be searched again.
+type Tree = Arena<Node>;
+
+impl Tree {
+    fn start(game: &Game) -> Tree {
+        Arena::new(Node::new(None, game.next_player(), game))
+    }
+
+    /// The most visited child of the node.
+    fn best_child(&self, node: NodeId) -> Option<NodeId> {
+        self[node]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&child| self[child].visits)
+    }
+
+    /// What the search has found, following the most visited children from
+    /// the root.
+    fn info(&self, playouts: u64, start: Instant) -> SearchInfo {
+        let mut pv = Vec::new();
+        let mut score = 0.0;
+        let mut node = NodeId::ROOT;
+        while let Some(child) = self.best_child(node) {
+            let found = &self[child];
+            if pv.is_empty() {
+                score = found.value / found.visits.max(1) as f64;
+            }
+            pv.push(found.turn.clone().unwrap());
+            node = child;
+        }
+        SearchInfo {
+            nodes: playouts,
+            elapsed: start.elapsed(),
+            depth: pv.len() as u32,
+            score: score as f32,
+            pv,
+        }
+    }
+
+    /// The node for the game's position within a few turns of the root.
+    fn position(&self, game: &Game) -> Option<NodeId> {
+        self.find(game.rules().player_count as u32, |node| {
+            node.hash == game.hash() && node.round == game.round()
+        })
+    }
+}
+
+/// A search running on another thread, handing the agent back once stopped.
+#[derive(Debug)]
+struct Pondering {
+    stop: StopFlag,
+    handle: Option<JoinHandle<MctsAgent>>,
+}
+
+/// Dropping the agent stops the search rather than leave it running.
+impl Drop for Pondering {
+    fn drop(&mut self) {
+        self.stop.raise();
+    }
+}
+
+/// A bot searching with monte carlo tree search, see the module
+/// documentation.
+#[derive(Debug)]
+pub struct MctsAgent {
+    params: MctsParams,
+    rng: ChaCha8Rng,
+    policy: Arc<dyn PlayoutPolicy + Send + Sync>,
+    tree: Option<Tree>,
+    playouts: u64,
+    pondering: Option<Pondering>,
+}
+
+/// Clones don't ponder, even when the original is.
+impl Clone for MctsAgent {
+    fn clone(&self) -> MctsAgent {
+        MctsAgent {
+            params: self.params.clone(),
+            rng: self.rng.clone(),
+            policy: self.policy.clone(),
+            tree: self.tree.clone(),
+            playouts: self.playouts,
+            pondering: None,
+        }
+    }
+}
+
+impl MctsAgent {
+    pub fn new(params: MctsParams, seed: u64) -> MctsAgent {
+        MctsAgent {
+            params,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            policy: Arc::new(UniformPolicy),
+            tree: None,
+            playouts: 0,
+            pondering: None,
+        }
+    }
+
+    /// The same bot, picking the turns of its playouts with the policy
+    /// rather than at random.
+    pub fn with_policy(self, policy: impl PlayoutPolicy + Send + Sync + 'static) -> MctsAgent {
+        MctsAgent {
+            policy: Arc::new(policy),
+            ..self
+        }
+    }
+
+    pub fn from_preset(preset: &Preset) -> MctsAgent {
+        MctsAgent::new(preset.mcts.clone(), thread_rng().gen())
+    }
+
+    pub fn params(&self) -> &MctsParams {
+        &self.params
+    }
+
+    /// Playouts run in the last search.
+    pub fn playouts(&self) -> u64 {
+        self.playouts
+    }
+
+    /// How many positions are in the search tree kept from the last turn.
+    pub fn tree_size(&self) -> usize {
+        self.tree.as_ref().map_or(0, Tree::len)
+    }
+
+    /// Searches the position, reusing what is left of the last tree, and
+    /// returns the turns tried from it with how often each was visited and
+    /// the sum of the rewards through them.
+    pub(crate) fn root_visits(&mut self, game: &Game, limits: &Limits) -> Vec<(Turn, u32, f64)> {
+        self.stop();
+        let mut tree = match self.tree.take() {
+            Some(tree) => match tree.position(game) {
+                Some(node) => tree.into_subtree(node),
+                None => Tree::start(game),
+            },
+            None => Tree::start(game),
+        };
+
+        let start = Instant::now();
+        let playouts = match limits.max_nodes {
+            Some(nodes) => nodes,
+            // Searches until told to stop.
+            None if limits.max_time.is_some() || limits.stop.is_some() => u64::MAX,
+            None => self.params.playouts as u64,
+        };
+        self.playouts = 0;
+        let mut reported = start;
+        while self.playouts < playouts && !limits.should_stop(start) {
+            self.playout(&mut tree, game);
+            self.playouts += 1;
+            if limits.info.is_some() && reported.elapsed() >= REPORT_EVERY {
+                limits.report(|| tree.info(self.playouts, start));
+                reported = Instant::now();
+            }
+        }
+        limits.report(|| tree.info(self.playouts, start));
+
+        let visits = tree[NodeId::ROOT]
+            .children
+            .iter()
+            .map(|&child| {
+                let child = &tree[child];
+                (child.turn.clone().unwrap(), child.visits, child.value)
+            })
+            .collect();
+        self.tree = Some(tree);
+        visits
+    }
+
+    /// One playout from the root.
+    fn playout(&mut self, tree: &mut Tree, root: &Game) {
+        let mut game = root.clone();
+        let mut path = vec![NodeId::ROOT];
+        let mut node = NodeId::ROOT;
+
+        // Select
+        loop {
+            if tree[node].untried.is_none() {
+                tree[node].untried = Some(game.legal_turns());
+            }
+            let fully_expanded = tree[node].untried.as_ref().unwrap().is_empty();
+            if !fully_expanded || tree[node].children.is_empty() {
+                break;
+            }
+            node = self.select(tree, node);
+            let turn = tree[node].turn.clone().unwrap();
+            game.play_turn(turn)
+                .expect("Turns in the tree should be legal");
+            path.push(node);
+        }
+
+        // Expand
+        let untried = tree[node].untried.as_mut().unwrap();
+        if !untried.is_empty() {
+            let i = self.rng.gen_range(0..untried.len());
+            let turn = untried.swap_remove(i);
+            let player = game.next_player();
+            game.play_turn(turn.clone())
+                .expect("Legal turns should be playable");
+            let child = tree.push(Node::new(Some(turn), player, &game));
+            tree[node].children.push(child);
+            path.push(child);
+        }
+
+        // Simulate
+        playout_with(
+            &mut game,
+            &mut self.rng,
+            PLAYOUT_TURNS,
+            self.policy.as_ref(),
+        );
+        let rewards = rewards(&game);
+
+        // Back up
+        for id in path {
+            let node = &mut tree[id];
+            node.visits += 1;
+            node.value += rewards[node.player as usize];
+        }
+    }
+
+    /// The child with the best UCT score.
+    fn select(&mut self, tree: &Tree, node: NodeId) -> NodeId {
+        let parent_visits = (tree[node].visits.max(1) as f64).ln();
+        let exploration = self.params.exploration as f64;
+        let uct = |id: NodeId| {
+            let child = &tree[id];
+            let visits = child.visits.max(1) as f64;
+            child.value / visits + exploration * (parent_visits / visits).sqrt()
+        };
+        let mut children = tree[node].children.clone();
+        // Shuffled so ties don't always go the same way.
+        children.shuffle(&mut self.rng);
+        children
+            .into_iter()
+            .max_by(|&a, &b| uct(a).partial_cmp(&uct(b)).unwrap())
+            .unwrap()
+    }
+}
+
+impl Agent for MctsAgent {
+    fn name(&self) -> &str {
+        "mcts"
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "debug", name = "search", skip_all, fields(bot = "mcts"))
+    )]
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        let limits = &limits.for_turn(game);
+        if let Some(turn) = winning_turn(game, limits) {
+            return turn;
+        }
+        let best = self
+            .root_visits(game, limits)
+            .into_iter()
+            .max_by_key(|(_, visits, _)| *visits);
+        match best {
+            Some((turn, _, _)) => turn,
+            // Not even one playout, so take whatever is legal.
+            None => game
+                .legal_turns()
+                .into_iter()
+                .next()
+                .expect("The player to move should have a legal turn"),
+        }
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        self.stop();
+        if game.is_finished() {
+            return;
+        }
+        let stop = StopFlag::new();
+        let limits = Limits::none().with_stop(stop.clone());
+        // The search carries on with the tree, and hands it back when stopped.
+        let tree = self.tree.take();
+        let mut agent = self.clone();
+        agent.tree = tree;
+        let game = game.clone();
+        let handle = thread::spawn(move || {
+            agent.root_visits(&game, &limits);
+            agent
+        });
+        self.pondering = Some(Pondering {
+            stop,
+            handle: Some(handle),
+        });
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut pondering) = self.pondering.take() {
+            pondering.stop.raise();
+            let agent = pondering
+                .handle
+                .take()
+                .unwrap()
+                .join()
+                .expect("The pondering search shouldn't panic");
+            self.tree = agent.tree;
+            self.rng = agent.rng;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    fn params() -> MctsParams {
+        MctsParams {
+            exploration: 1.41,
+            playouts: 30,
+        }
+    }
+
+    #[test]
+    fn plays_legal_turns() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 6);
+        let mut agent = MctsAgent::new(params(), 0);
+        let turn = agent.choose_turn(&game, &Limits::none());
+        assert!(agent.tree_size() > 30);
+        game.play_turn(turn).unwrap();
+
+        let turn = agent.choose_turn(&game, &Limits::nodes(5));
+        assert_eq!(agent.playouts(), 5);
+        game.play_turn(turn).unwrap();
+    }
+
+    #[test]
+    fn reuses_the_tree() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 6);
+        let mut agent = MctsAgent::new(params(), 1);
+        agent.choose_turn(&game, &Limits::none());
+        agent.choose_turn(&game, &Limits::nodes(10));
+        assert_eq!(agent.tree_size(), 41);
+
+        // After our turn, only what is below it is kept.
+        let turn = agent.choose_turn(&game, &Limits::nodes(0));
+        game.play_turn(turn).unwrap();
+        let tree = agent.tree.as_ref().unwrap();
+        let ours = tree.position(&game).unwrap();
+        let visits = tree[ours].visits;
+        assert_eq!(tree.clone().into_subtree(ours)[NodeId::ROOT].visits, visits);
+        agent.choose_turn(&game, &Limits::nodes(3));
+        assert_eq!(agent.tree_size(), 4);
+    }
+
+    #[test]
+    fn reports_the_principal_variation() {
+        use crate::ai::InfoCallback;
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let limits = Limits::nodes(20).with_info(InfoCallback::new(move |info| {
+            sink.lock().unwrap().push(info.clone());
+        }));
+        let game = Game::new_with_rules(Ruleset::new(2), 6);
+        let turn = MctsAgent::new(params(), 3).choose_turn(&game, &limits);
+
+        let reports = reports.lock().unwrap();
+        let last = reports.last().unwrap();
+        assert_eq!(last.nodes, 20);
+        assert_eq!(last.pv[0], turn);
+        assert_eq!(last.depth as usize, last.pv.len());
+    }
+
+    #[test]
+    fn ponders_on_the_opponents_turn() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 6);
+        let mut agent = MctsAgent::new(params(), 2);
+        let turn = agent.choose_turn(&game, &Limits::nodes(0));
+        game.play_turn(turn).unwrap();
+
+        agent.ponder(&game);
+        assert_eq!(agent.tree_size(), 0);
+        thread::sleep(std::time::Duration::from_millis(20));
+        agent.stop();
+        let pondered = agent.tree_size();
+        assert!(pondered > 1);
+        agent.stop();
+
+        // Thinking on our turn picks up where pondering left off.
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        agent.ponder(&game);
+        agent.choose_turn(&game, &Limits::nodes(1));
+        assert!(agent.pondering.is_none());
+    }
+}
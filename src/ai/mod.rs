@@ -0,0 +1,76 @@
+//! Computer opponents and the tooling around them.
+
+#[cfg(feature = "json")]
+pub mod adjudicator;
+pub mod agent;
+pub mod alphabeta;
+pub mod annotate;
+mod arena;
+pub mod book;
+pub mod difficulty;
+pub mod encoding;
+pub mod endgame;
+pub mod env;
+pub mod estimate;
+pub mod evaluation;
+pub mod external;
+pub mod greedy;
+pub mod hint;
+pub mod ismcts;
+pub mod mcts;
+pub mod network;
+#[cfg(feature = "nn")]
+pub mod onnx;
+pub mod ordering;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod playout;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod presets;
+pub mod protocol;
+pub mod puzzles;
+pub mod random;
+#[cfg(feature = "json")]
+pub mod ratings;
+mod refill;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "json")]
+pub mod selfplay;
+#[cfg(feature = "json")]
+pub mod simulate;
+#[cfg(feature = "json")]
+pub mod sprt;
+pub mod suite;
+#[cfg(feature = "json")]
+pub mod tournament;
+#[cfg(feature = "json")]
+pub mod training;
+pub mod transposition;
+#[cfg(feature = "json")]
+pub mod tuning;
+
+pub use agent::{Agent, ClockTime, InfoCallback, Limits, SearchInfo, StopFlag, ThinkingTime};
+pub use alphabeta::AlphaBetaAgent;
+pub use book::{BookAgent, BookError, OpeningBook};
+pub use difficulty::{difficulty, Difficulty};
+pub use env::{EnvError, PasstallyEnv};
+pub use estimate::estimate_win_probability;
+pub use evaluation::{Evaluator, Feature, LinearEvaluator};
+pub use greedy::GreedyAgent;
+pub use hint::Hint;
+pub use ismcts::IsmctsAgent;
+pub use mcts::MctsAgent;
+pub use network::{Network, NetworkAgent};
+#[cfg(feature = "nn")]
+pub use onnx::OnnxNetwork;
+#[cfg(feature = "parallel")]
+pub use parallel::ParallelMctsAgent;
+#[cfg(feature = "plugins")]
+pub use plugin::WasmBot;
+pub use presets::{AgentSpec, EvaluatorWeights, MctsParams, Preset, PresetError};
+pub use random::RandomAgent;
+#[cfg(feature = "scripting")]
+pub use script::ScriptAgent;
+pub use transposition::TranspositionTable;
@@ -0,0 +1,337 @@
+//! Tree search guided by a network, as in AlphaZero. The network gives each
+//! position a policy, how likely each action is to be the one to play, and a
+//! value, how well the player to move is expected to do. The policy steers
+//! the search towards promising turns through PUCT, and the value takes the
+//! place of random playouts when the search reaches a new position.
+//!
+//! Networks read positions and actions as in [`encoding`](super::encoding).
+//! With the `nn` feature, [`OnnxNetwork`](super::onnx::OnnxNetwork) runs
+//! one trained outside Rust.
+
+use std::time::Instant;
+
+use super::arena::{Arena, NodeId, TreeNode};
+use super::encoding::{action_index, ACTION_SPACE};
+use super::endgame::winning_turn;
+use super::playout::rewards;
+use super::{Agent, Limits, MctsParams, SearchInfo};
+use crate::game::{Game, Turn};
+
+/// What a network makes of a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    /// A logit for every action, indexed by
+    /// [`action_index`](super::encoding::action_index).
+    pub policy: Vec<f32>,
+    /// The expected reward of the player to move, between 0 and 1.
+    pub value: f32,
+}
+
+pub trait Network {
+    fn predict(&self, game: &Game) -> Prediction;
+}
+
+/// A network knowing nothing: every action is as likely, and every position
+/// is even. The search is then plain monte carlo tree search without
+/// playouts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformNetwork;
+
+impl Network for UniformNetwork {
+    fn predict(&self, game: &Game) -> Prediction {
+        Prediction {
+            policy: vec![0.0; ACTION_SPACE as usize],
+            value: 1.0 / game.rules().player_count as f32,
+        }
+    }
+}
+
+/// Every player's reward from a network's value for the player to move:
+/// their team gets the value, the others share what is left.
+pub fn value_rewards(game: &Game, value: f32) -> Vec<f64> {
+    let rules = game.rules();
+    let team = rules.team_of(game.next_player());
+    let others = (0..rules.player_count)
+        .filter(|&player| rules.team_of(player) != team)
+        .count()
+        .max(1);
+    (0..rules.player_count)
+        .map(|player| {
+            if rules.team_of(player) == team {
+                value as f64
+            } else {
+                (1.0 - value as f64) / others as f64
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// The turn leading here, `None` for the root.
+    turn: Option<Turn>,
+    /// The player who played `turn`.
+    player: u8,
+    /// How likely the network thought `turn` was to be played.
+    prior: f32,
+    /// Found the first time the node is reached.
+    children: Option<Vec<NodeId>>,
+    /// The position's hash and round, also found the first time the node is
+    /// reached, to find it again when the tree is reused.
+    position: Option<(u64, u32)>,
+    visits: u32,
+    /// Sum of the rewards of `player` over the visits.
+    value: f64,
+}
+
+impl TreeNode for Node {
+    fn children(&self) -> &[NodeId] {
+        self.children.as_deref().unwrap_or(&[])
+    }
+
+    fn clear_children(&mut self) {
+        if let Some(children) = &mut self.children {
+            children.clear();
+        }
+    }
+
+    fn push_child(&mut self, child: NodeId) {
+        self.children.get_or_insert_with(Vec::new).push(child);
+    }
+}
+
+/// The node for the game's position within a few turns of the root.
+fn find(nodes: &Arena<Node>, game: &Game) -> Option<NodeId> {
+    let position = Some((game.hash(), game.round()));
+    nodes.find(game.rules().player_count as u32, |node| {
+        node.position == position
+    })
+}
+
+/// A bot searching with PUCT and a network, see the module documentation.
+/// `playouts` in the parameters counts positions given to the network.
+///
+/// The tree is kept from one turn to the next, and the search starts again
+/// from the part of it below the turns played since, so positions the
+/// network has seen aren't given to it again.
+#[derive(Debug, Clone)]
+pub struct NetworkAgent<N> {
+    network: N,
+    params: MctsParams,
+    tree: Option<Arena<Node>>,
+}
+
+impl<N: Network> NetworkAgent<N> {
+    pub fn new(network: N, params: MctsParams) -> NetworkAgent<N> {
+        NetworkAgent {
+            network,
+            params,
+            tree: None,
+        }
+    }
+
+    pub fn network(&self) -> &N {
+        &self.network
+    }
+
+    /// How many positions are in the search tree kept from the last turn.
+    pub fn tree_size(&self) -> usize {
+        self.tree.as_ref().map_or(0, Arena::len)
+    }
+
+    /// Priors for the turns, from the logits of their two actions.
+    fn priors(prediction: &Prediction, turns: &[Turn]) -> Vec<f32> {
+        let logit = |turn: &Turn| {
+            let policy = &prediction.policy;
+            let of = |index: u32| policy.get(index as usize).copied().unwrap_or(0.0);
+            of(action_index(&turn.0)) + of(action_index(&turn.1))
+        };
+        let logits: Vec<f32> = turns.iter().map(logit).collect();
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exps: Vec<f32> = logits.iter().map(|logit| (logit - max).exp()).collect();
+        let sum: f32 = exps.iter().sum();
+        exps.into_iter().map(|exp| exp / sum).collect()
+    }
+
+    /// One descent from the root to a position the network hasn't seen yet.
+    fn visit(&self, nodes: &mut Arena<Node>, root: &Game) {
+        let mut game = root.clone();
+        let mut path = vec![NodeId::ROOT];
+        let mut node = NodeId::ROOT;
+
+        let rewards = loop {
+            if game.is_finished() {
+                break rewards(&game);
+            }
+            let children = match &nodes[node].children {
+                Some(children) => children.clone(),
+                None => {
+                    let prediction = self.network.predict(&game);
+                    let turns = game.legal_turns();
+                    let priors = Self::priors(&prediction, &turns);
+                    let player = game.next_player();
+                    let mut children = Vec::new();
+                    for (turn, prior) in turns.into_iter().zip(priors) {
+                        children.push(nodes.push(Node {
+                            turn: Some(turn),
+                            player,
+                            prior,
+                            children: None,
+                            position: None,
+                            visits: 0,
+                            value: 0.0,
+                        }));
+                    }
+                    nodes[node].children = Some(children);
+                    nodes[node].position = Some((game.hash(), game.round()));
+                    break value_rewards(&game, prediction.value);
+                }
+            };
+            if children.is_empty() {
+                break rewards(&game);
+            }
+
+            node = self.select(nodes, node, &children);
+            game.play_turn(nodes[node].turn.clone().unwrap())
+                .expect("Legal turns should be playable");
+            path.push(node);
+        };
+
+        for id in path {
+            let node = &mut nodes[id];
+            node.visits += 1;
+            node.value += rewards[node.player as usize];
+        }
+    }
+
+    /// The child with the best PUCT score.
+    fn select(&self, nodes: &Arena<Node>, parent: NodeId, children: &[NodeId]) -> NodeId {
+        let exploration = self.params.exploration as f64;
+        let parent_visits = (nodes[parent].visits.max(1) as f64).sqrt();
+        let puct = |id: NodeId| {
+            let child = &nodes[id];
+            let mean = if child.visits == 0 {
+                0.0
+            } else {
+                child.value / child.visits as f64
+            };
+            mean + exploration * child.prior as f64 * parent_visits / (1.0 + child.visits as f64)
+        };
+        children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| puct(a).partial_cmp(&puct(b)).unwrap())
+            .unwrap()
+    }
+}
+
+impl<N: Network> Agent for NetworkAgent<N> {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "debug", name = "search", skip_all, fields(bot = "network"))
+    )]
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        let limits = &limits.for_turn(game);
+        if let Some(turn) = winning_turn(game, limits) {
+            return turn;
+        }
+        let reused = self
+            .tree
+            .take()
+            .and_then(|nodes| find(&nodes, game).map(|root| nodes.into_subtree(root)));
+        let mut nodes = reused.unwrap_or_else(|| {
+            Arena::new(Node {
+                turn: None,
+                player: game.next_player(),
+                prior: 1.0,
+                children: None,
+                position: None,
+                visits: 0,
+                value: 0.0,
+            })
+        });
+
+        let start = Instant::now();
+        let visits = match limits.max_nodes {
+            Some(nodes) => nodes,
+            // Searches until told to stop.
+            None if limits.max_time.is_some() || limits.stop.is_some() => u64::MAX,
+            None => self.params.playouts as u64,
+        };
+        let mut visited = 0;
+        while visited < visits && !limits.should_stop(start) {
+            self.visit(&mut nodes, game);
+            visited += 1;
+        }
+
+        let best = nodes[NodeId::ROOT]
+            .children
+            .iter()
+            .flatten()
+            .copied()
+            .max_by_key(|&child| nodes[child].visits)
+            .filter(|&child| nodes[child].visits > 0);
+        let best = best.map(|child| nodes[child].clone());
+        self.tree = Some(nodes);
+        match best {
+            Some(child) => {
+                let turn = child.turn.clone().unwrap();
+                limits.report(|| SearchInfo {
+                    nodes: visited,
+                    elapsed: start.elapsed(),
+                    depth: 1,
+                    score: (child.value / child.visits as f64) as f32,
+                    pv: vec![turn.clone()],
+                });
+                turn
+            }
+            // Not even one visit below the root, so take whatever is legal.
+            None => game
+                .legal_turns()
+                .into_iter()
+                .next()
+                .expect("The player to move should have a legal turn"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Preset;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn follows_the_network() {
+        let game = Game::new_with_rules(Ruleset::new(2), 4);
+        let mut agent = NetworkAgent::new(UniformNetwork, Preset::default().mcts);
+        let turn = agent.choose_turn(&game, &Limits::nodes(2));
+        assert!(game.clone().play_turn(turn).is_ok());
+
+        let turns = game.legal_turns();
+        let priors = NetworkAgent::<UniformNetwork>::priors(&UniformNetwork.predict(&game), &turns);
+        assert!((priors.iter().sum::<f32>() - 1.0).abs() < 1e-3);
+        assert_eq!(value_rewards(&game, 0.75), vec![0.75, 0.25]);
+    }
+
+    #[test]
+    fn keeps_the_tree_between_turns() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 4);
+        let limits = Limits::nodes(4);
+        let mut agent = NetworkAgent::new(UniformNetwork, Preset::default().mcts);
+        let turn = agent.choose_turn(&game, &limits);
+        let size = agent.tree_size();
+        agent.choose_turn(&game, &limits);
+        assert!(agent.tree_size() > size);
+
+        game.play_turn(turn).unwrap();
+        let mut fresh = NetworkAgent::new(UniformNetwork, Preset::default().mcts);
+        fresh.choose_turn(&game, &limits);
+        agent.choose_turn(&game, &limits);
+        assert!(agent.tree_size() > fresh.tree_size());
+    }
+}
@@ -0,0 +1,60 @@
+//! Networks trained outside Rust, saved as ONNX and run with tract.
+//!
+//! The network takes one input of shape `[1, FEATURES]`, the features of
+//! [`position_features`], and gives two outputs: the policy logits of shape
+//! `[1, ACTION_SPACE]` and the value of shape `[1, 1]`, in that order.
+
+use std::path::Path;
+
+use thiserror::Error;
+use tract_onnx::prelude::*;
+
+use super::encoding::{position_features, ACTION_SPACE, FEATURES};
+use super::network::{Network, Prediction};
+use crate::game::Game;
+
+/// A policy and value network loaded from an ONNX file.
+#[derive(Debug, Clone)]
+pub struct OnnxNetwork {
+    model: TypedRunnableModel<TypedModel>,
+}
+
+impl OnnxNetwork {
+    pub fn load(path: impl AsRef<Path>) -> Result<OnnxNetwork, NetworkError> {
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .map_err(NetworkError::Load)?
+            .with_input_fact(0, f32::fact([1, FEATURES]).into())
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(NetworkError::Load)?;
+        Ok(OnnxNetwork { model })
+    }
+
+    fn run(&self, game: &Game) -> TractResult<Prediction> {
+        let input = tract_ndarray::Array2::from_shape_vec((1, FEATURES), position_features(game))?;
+        let outputs = self.model.run(tvec!(Tensor::from(input).into()))?;
+        let policy = outputs[0].to_array_view::<f32>()?;
+        let value = outputs[1].to_array_view::<f32>()?;
+        Ok(Prediction {
+            policy: policy.iter().copied().collect(),
+            value: value.iter().copied().next().unwrap_or(0.5),
+        })
+    }
+}
+
+impl Network for OnnxNetwork {
+    /// A network failing to run on a position knows nothing about it.
+    fn predict(&self, game: &Game) -> Prediction {
+        self.run(game).unwrap_or_else(|_| Prediction {
+            policy: vec![0.0; ACTION_SPACE as usize],
+            value: 1.0 / game.rules().player_count as f32,
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum NetworkError {
+    #[error("Couldn't load the network: {0}")]
+    Load(TractError),
+}
@@ -0,0 +1,295 @@
+//! Move ordering for the search agents. Searching the most promising turns
+//! first lets alpha-beta cut off far more of the tree.
+//!
+//! Only searches of two turns or more have anything to cut off. The bots
+//! made from presets search [`DEFAULT_DEPTH`](super::alphabeta::DEFAULT_DEPTH),
+//! one turn, so as shipped they get no benefit from the ordering.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::game::{Action, Game, Turn};
+
+/// Remembers the last two moves that caused a cutoff at each ply. Sibling
+/// positions often share the same refutation.
+#[derive(Debug, Clone)]
+pub struct KillerMoves<M> {
+    killers: Vec<[Option<M>; 2]>,
+}
+
+impl<M: Clone + PartialEq> KillerMoves<M> {
+    pub fn new() -> Self {
+        KillerMoves {
+            killers: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, ply: usize, m: &M) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let slots = &mut self.killers[ply];
+        if slots[0].as_ref() != Some(m) {
+            slots[1] = slots[0].take();
+            slots[0] = Some(m.clone());
+        }
+    }
+
+    pub fn is_killer(&self, ply: usize, m: &M) -> bool {
+        matches!(self.killers.get(ply), Some(slots) if slots.iter().any(|k| k.as_ref() == Some(m)))
+    }
+
+    pub fn clear(&mut self) {
+        self.killers.clear();
+    }
+}
+
+impl<M: Clone + PartialEq> Default for KillerMoves<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How often each move has caused a cutoff anywhere in the tree, weighted by
+/// the depth of the cutoff.
+#[derive(Debug, Clone)]
+pub struct HistoryTable<M: Hash + Eq> {
+    scores: HashMap<M, u32>,
+}
+
+impl<M: Hash + Eq + Clone> HistoryTable<M> {
+    pub fn new() -> Self {
+        HistoryTable {
+            scores: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, m: &M, depth: u32) {
+        *self.scores.entry(m.clone()).or_insert(0) += depth * depth;
+    }
+
+    pub fn score(&self, m: &M) -> u32 {
+        self.scores.get(m).copied().unwrap_or(0)
+    }
+
+    /// Halves every score, so old cutoffs matter less than recent ones.
+    pub fn age(&mut self) {
+        for score in self.scores.values_mut() {
+            *score /= 2;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.scores.clear();
+    }
+}
+
+impl<M: Hash + Eq + Clone> Default for HistoryTable<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How often the searches cut off, to measure how well their moves are
+/// ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CutoffStats {
+    /// Positions whose moves were searched.
+    pub searched: u64,
+    /// Positions where a move was good enough to skip the rest.
+    pub cutoffs: u64,
+    /// Cutoffs by the first move tried.
+    pub first_move_cutoffs: u64,
+}
+
+impl CutoffStats {
+    /// The share of searched positions that cut off, between 0 and 1.
+    pub fn cutoff_rate(&self) -> f64 {
+        ratio(self.cutoffs, self.searched)
+    }
+
+    /// The share of cutoffs made by the first move, between 0 and 1. The
+    /// closer to 1, the better the ordering.
+    pub fn first_move_rate(&self) -> f64 {
+        ratio(self.first_move_cutoffs, self.cutoffs)
+    }
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 / whole as f64
+}
+
+/// How many points the player to move scores by playing the turn, which is
+/// what all their connected lines are worth after it. Assumes the turn is
+/// legal.
+pub fn turn_points(game: &Game, turn: &Turn) -> u32 {
+    points_after(game, &[&turn.0, &turn.1])
+}
+
+/// What the connected lines of the player to move are worth after the
+/// actions.
+fn points_after(game: &Game, actions: &[&Action]) -> u32 {
+    let player = game.next_player();
+    let mut board = game.board;
+    let mut player_markers = game.player_markers;
+
+    for action in actions {
+        match action {
+            Action::PlacePiece(piece) => {
+                if board.place_piece(piece.clone()).is_err() {
+                    return 0;
+                }
+            }
+            Action::MovePlayerMarker(from, to) => {
+                player_markers[*to as usize] = player_markers[*from as usize].take();
+            }
+            Action::RedrawMarket(_) | Action::Resign | Action::OfferDraw | Action::AcceptDraw => {}
+        }
+    }
+
+    board.connected_points(
+        &game.team_markers(&player_markers),
+        game.rules().team_of(player),
+    )
+}
+
+/// How many points the player to move gains on their connected lines by
+/// playing the turn. Assumes the turn is legal.
+pub fn turn_gain(game: &Game, turn: &Turn) -> u32 {
+    turn_points(game, turn).saturating_sub(game.line_points(game.next_player()))
+}
+
+/// How many points the lines of the player to move gain by the action alone.
+/// Assumes the action is legal.
+pub fn action_gain(game: &Game, action: &Action) -> u32 {
+    points_after(game, &[action]).saturating_sub(game.line_points(game.next_player()))
+}
+
+/// Sorts the actions best first, like [`order_turns`] does turns.
+pub fn order_actions(
+    game: &Game,
+    actions: &mut [Action],
+    ply: usize,
+    killers: &KillerMoves<Action>,
+    history: &HistoryTable<Action>,
+) {
+    actions.sort_by_cached_key(|action| {
+        std::cmp::Reverse((
+            action_gain(game, action),
+            killers.is_killer(ply, action),
+            history.score(action),
+        ))
+    });
+}
+
+/// Sorts the turns best first: turns that connect lines, then killer moves,
+/// then by history score.
+pub fn order_turns(
+    game: &Game,
+    turns: &mut [Turn],
+    ply: usize,
+    killers: &KillerMoves<Turn>,
+    history: &HistoryTable<Turn>,
+) {
+    turns.sort_by_cached_key(|turn| {
+        std::cmp::Reverse((
+            turn_gain(game, turn),
+            killers.is_killer(ply, turn),
+            history.score(turn),
+        ))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardPosition;
+    use crate::piece::{Piece, PositionedPiece};
+
+    fn red(x: i8, y: i8, rotation: u8) -> PositionedPiece {
+        PositionedPiece {
+            piece: Piece::Red,
+            position: BoardPosition::new(x, y),
+            rotation,
+        }
+    }
+
+    fn place(x: i8, y: i8, rotation: u8) -> Action {
+        Action::PlacePiece(red(x, y, rotation))
+    }
+
+    #[test]
+    fn killers_keep_the_last_two() {
+        let mut killers = KillerMoves::new();
+        killers.record(3, &1);
+        killers.record(3, &2);
+        killers.record(3, &2);
+        assert!(killers.is_killer(3, &1));
+        assert!(killers.is_killer(3, &2));
+        assert!(!killers.is_killer(2, &1));
+
+        killers.record(3, &3);
+        assert!(!killers.is_killer(3, &1));
+        assert!(killers.is_killer(3, &3));
+    }
+
+    #[test]
+    fn history_prefers_deep_cutoffs() {
+        let mut history = HistoryTable::new();
+        history.record(&"shallow", 1);
+        history.record(&"shallow", 1);
+        history.record(&"deep", 3);
+        assert!(history.score(&"deep") > history.score(&"shallow"));
+
+        history.age();
+        assert_eq!(history.score(&"shallow"), 1);
+        assert_eq!(history.score(&"unseen"), 0);
+    }
+
+    #[test]
+    fn connecting_turns_come_first() {
+        let mut game = Game::new(2);
+        // Player 0 has markers at 0 and 18. Moving 18 to 17 connects it to 0
+        // down column a, which is worth points once pieces lie there.
+        game.board.place_piece(red(0, 0, 1)).unwrap();
+
+        let quiet = Turn(place(3, 3, 0), place(3, 4, 0));
+        let connecting = Turn(place(3, 3, 0), Action::MovePlayerMarker(18, 17));
+        let mut turns = vec![quiet.clone(), connecting.clone()];
+
+        let mut killers = KillerMoves::new();
+        killers.record(0, &quiet);
+        let mut history = HistoryTable::new();
+        history.record(&quiet, 10);
+
+        order_turns(&game, &mut turns, 0, &killers, &history);
+        assert_eq!(turn_gain(&game, &connecting), 2);
+        assert_eq!(turns, vec![connecting, quiet]);
+    }
+
+    #[test]
+    fn ordering_actions() {
+        let mut game = Game::new(2);
+        game.board.place_piece(red(0, 0, 1)).unwrap();
+
+        let connecting = Action::MovePlayerMarker(18, 17);
+        let killer = place(3, 3, 0);
+        let mut actions = vec![place(3, 4, 0), killer.clone(), connecting.clone()];
+        let mut killers = KillerMoves::new();
+        killers.record(1, &killer);
+
+        order_actions(&game, &mut actions, 1, &killers, &HistoryTable::new());
+        assert_eq!(actions, vec![connecting, killer, place(3, 4, 0)]);
+
+        let stats = CutoffStats {
+            searched: 4,
+            cutoffs: 2,
+            first_move_cutoffs: 1,
+        };
+        assert_eq!(stats.cutoff_rate(), 0.5);
+        assert_eq!(stats.first_move_rate(), 0.5);
+    }
+}
@@ -0,0 +1,185 @@
+//! Monte carlo tree search on every core. Each thread grows a tree of its own
+//! with its own random numbers, and the visits of the turns at the roots are
+//! summed to pick the turn. The trees share nothing, so there are no locks to
+//! wait on, and each one is kept for the next turn like [`MctsAgent`]'s.
+
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+
+use super::endgame::winning_turn;
+use std::time::Instant;
+
+use super::playout::PlayoutPolicy;
+use super::{Agent, Limits, MctsAgent, MctsParams, Preset, SearchInfo};
+use crate::game::{Game, Turn};
+
+/// A bot running [`MctsAgent`]s side by side, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct ParallelMctsAgent {
+    params: MctsParams,
+    workers: Vec<MctsAgent>,
+}
+
+impl ParallelMctsAgent {
+    /// A bot searching with `threads` trees, at least one. The trees run on
+    /// rayon's current thread pool.
+    pub fn new(params: MctsParams, threads: usize, seed: u64) -> ParallelMctsAgent {
+        let workers = (0..threads.max(1) as u64)
+            .map(|i| MctsAgent::new(params.clone(), seed.wrapping_add(i)))
+            .collect();
+        ParallelMctsAgent { params, workers }
+    }
+
+    /// A bot with a tree for every thread of rayon's pool.
+    pub fn from_preset(preset: &Preset) -> ParallelMctsAgent {
+        ParallelMctsAgent::new(
+            preset.mcts.clone(),
+            rayon::current_num_threads(),
+            thread_rng().gen(),
+        )
+    }
+
+    /// The same bot, every tree's playouts picking turns with the policy.
+    pub fn with_policy(
+        self,
+        policy: impl PlayoutPolicy + Clone + Send + Sync + 'static,
+    ) -> ParallelMctsAgent {
+        ParallelMctsAgent {
+            workers: self
+                .workers
+                .into_iter()
+                .map(|worker| worker.with_policy(policy.clone()))
+                .collect(),
+            ..self
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Playouts run in the last search, over all the trees.
+    pub fn playouts(&self) -> u64 {
+        self.workers.iter().map(MctsAgent::playouts).sum()
+    }
+}
+
+impl Agent for ParallelMctsAgent {
+    fn name(&self) -> &str {
+        "parallel-mcts"
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(
+            level = "debug",
+            name = "search",
+            skip_all,
+            fields(bot = "parallel_mcts")
+        )
+    )]
+    fn choose_turn(&mut self, game: &Game, limits: &Limits) -> Turn {
+        let limits = &limits.for_turn(game);
+        if let Some(turn) = winning_turn(game, limits) {
+            return turn;
+        }
+
+        // The playouts are shared out, so a limit holds for the whole search.
+        let threads = self.workers.len() as u64;
+        let playouts = match limits.max_nodes {
+            Some(nodes) => Some(nodes),
+            None if limits.max_time.is_some() || limits.stop.is_some() => None,
+            None => Some(self.params.playouts as u64),
+        };
+        let start = Instant::now();
+        let visits: Vec<Vec<(Turn, u32, f64)>> = self
+            .workers
+            .par_iter_mut()
+            .enumerate()
+            .map(|(i, worker)| {
+                // Reported once for all the trees, below.
+                let mut limits = Limits {
+                    info: None,
+                    ..limits.clone()
+                };
+                if let Some(playouts) = playouts {
+                    let extra = (i as u64) < playouts % threads;
+                    limits.max_nodes = Some(playouts / threads + extra as u64);
+                }
+                worker.root_visits(game, &limits)
+            })
+            .collect();
+
+        // Summed in the order the turns were first seen, so ties are broken
+        // the same way every time.
+        let mut summed: Vec<(Turn, u32, f64)> = Vec::new();
+        for (turn, visits, value) in visits.into_iter().flatten() {
+            match summed.iter_mut().find(|(seen, _, _)| *seen == turn) {
+                Some((_, total, total_value)) => {
+                    *total += visits;
+                    *total_value += value;
+                }
+                None => summed.push((turn, visits, value)),
+            }
+        }
+        let best = summed
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, visits, _)| *visits);
+        match best {
+            Some((turn, visits, value)) => {
+                limits.report(|| SearchInfo {
+                    nodes: self.playouts(),
+                    elapsed: start.elapsed(),
+                    depth: 1,
+                    score: (value / visits.max(1) as f64) as f32,
+                    pv: vec![turn.clone()],
+                });
+                turn
+            }
+            // Not even one playout, so take whatever is legal.
+            None => game
+                .legal_turns()
+                .into_iter()
+                .next()
+                .expect("The player to move should have a legal turn"),
+        }
+    }
+
+    /// Every tree ponders on a thread of its own, outside rayon's pool.
+    fn ponder(&mut self, game: &Game) {
+        for worker in &mut self.workers {
+            worker.ponder(game);
+        }
+    }
+
+    fn stop(&mut self) {
+        for worker in &mut self.workers {
+            worker.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn shares_out_the_playouts() {
+        let params = MctsParams {
+            exploration: 1.41,
+            playouts: 10,
+        };
+        let mut agent = ParallelMctsAgent::new(params, 3, 7);
+        assert_eq!(agent.threads(), 3);
+
+        let mut game = Game::new_with_rules(Ruleset::new(2), 4);
+        let turn = agent.choose_turn(&game, &Limits::nodes(8));
+        assert_eq!(agent.playouts(), 8);
+        game.play_turn(turn).unwrap();
+        let turn = agent.choose_turn(&game, &Limits::none());
+        assert_eq!(agent.playouts(), 10);
+        game.play_turn(turn).unwrap();
+    }
+}
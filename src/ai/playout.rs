@@ -0,0 +1,171 @@
+//! Quick random games, for the monte carlo agents to estimate how good a
+//! position is. How the turns are picked is up to a [`PlayoutPolicy`]: at
+//! small playout budgets a policy playing a little better than at random
+//! makes for a stronger bot.
+
+use std::fmt;
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+use super::evaluation::{Evaluator, LinearEvaluator};
+use super::ordering::turn_points;
+use crate::game::{Game, Turn};
+
+/// Picks the turns of playouts.
+pub trait PlayoutPolicy: fmt::Debug {
+    /// A legal turn for the player to move, or `None` if they have none.
+    fn turn(&self, game: &Game, rng: &mut dyn RngCore) -> Option<Turn>;
+}
+
+/// Any turn, as [`random_turn`] picks them. The default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformPolicy;
+
+impl PlayoutPolicy for UniformPolicy {
+    fn turn(&self, game: &Game, rng: &mut dyn RngCore) -> Option<Turn> {
+        random_turn(game, rng)
+    }
+}
+
+/// The turn scoring the most points right away out of a few random ones.
+#[derive(Debug, Clone, Copy)]
+pub struct GreedyPolicy {
+    /// Random turns to pick from. 1 plays at random.
+    pub candidates: u32,
+}
+
+impl PlayoutPolicy for GreedyPolicy {
+    fn turn(&self, game: &Game, rng: &mut dyn RngCore) -> Option<Turn> {
+        (0..self.candidates.max(1))
+            .filter_map(|_| random_turn(game, rng))
+            .max_by_key(|turn| turn_points(game, turn))
+    }
+}
+
+/// With probability `epsilon` any turn, otherwise the turn the evaluation
+/// likes best out of a few random ones. Trying every legal turn would cost
+/// more than the better turns are worth.
+#[derive(Debug, Clone)]
+pub struct EpsilonGreedyPolicy {
+    pub epsilon: f64,
+    pub candidates: u32,
+    pub evaluator: LinearEvaluator,
+}
+
+impl PlayoutPolicy for EpsilonGreedyPolicy {
+    fn turn(&self, game: &Game, rng: &mut dyn RngCore) -> Option<Turn> {
+        if rng.gen_bool(self.epsilon) {
+            return random_turn(game, rng);
+        }
+        let player = game.next_player();
+        (0..self.candidates.max(1))
+            .filter_map(|_| random_turn(game, rng))
+            .map(|turn| {
+                let mut after = game.clone();
+                after
+                    .play_turn(turn.clone())
+                    .expect("Random turns should be legal");
+                (self.evaluator.evaluate(&after, player), turn)
+            })
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+            .map(|(_, turn)| turn)
+    }
+}
+
+/// A random legal turn for the player to move, or `None` if they have none.
+/// Much cheaper than picking from [`Game::legal_turns`], at the cost of turns
+/// with fewer follow ups being a bit more likely.
+pub fn random_turn(game: &Game, rng: &mut (impl Rng + ?Sized)) -> Option<Turn> {
+    if game.is_finished() {
+        return None;
+    }
+    let mut firsts = game.legal_actions();
+    firsts.shuffle(rng);
+    for first in firsts {
+        let mut after = game.clone();
+        after
+            .do_action(first.clone())
+            .expect("Legal actions should be playable");
+        if let Some(second) = after.legal_actions().choose(rng) {
+            return Some(Turn(first, second.clone()));
+        }
+    }
+    None
+}
+
+/// Plays random turns until the game ends or `max_turns` have been played.
+pub fn playout(game: &mut Game, rng: &mut impl Rng, max_turns: u32) {
+    playout_with(game, rng, max_turns, &UniformPolicy)
+}
+
+/// Like [`playout`], with the turns picked by the policy.
+pub fn playout_with(
+    game: &mut Game,
+    rng: &mut impl Rng,
+    max_turns: u32,
+    policy: &(impl PlayoutPolicy + ?Sized),
+) {
+    for _ in 0..max_turns {
+        match policy.turn(game, rng) {
+            Some(turn) => {
+                game.play_turn(turn).expect("Playout turns should be legal");
+            }
+            None => break,
+        }
+    }
+}
+
+/// How well each player did, between 0 and 1: first place shares 1 between
+/// the players in it, everyone else gets nothing. Unfinished games count
+/// their current standings.
+pub fn rewards(game: &Game) -> Vec<f64> {
+    let mut rewards = vec![0.0; game.rules().player_count as usize];
+    if let Some(first) = game.final_ranking().first() {
+        for &player in first {
+            rewards[player as usize] = 1.0 / first.len() as f64;
+        }
+    }
+    rewards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn random_games() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut game = Game::new_with_rules(Ruleset::new(3), 2);
+        assert_eq!(rewards(&game), vec![1.0 / 3.0; 3]);
+
+        playout(&mut game, &mut rng, 5);
+        assert_eq!(game.round(), 5);
+        playout(&mut game, &mut rng, 100);
+        assert!(game.is_finished());
+        assert!(random_turn(&game, &mut rng).is_none());
+        assert_eq!(rewards(&game).iter().sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn policies() {
+        let game = Game::new_with_rules(Ruleset::new(2), 7);
+        let policies: Vec<Box<dyn PlayoutPolicy>> = vec![
+            Box::new(UniformPolicy),
+            Box::new(GreedyPolicy { candidates: 4 }),
+            Box::new(EpsilonGreedyPolicy {
+                epsilon: 0.1,
+                candidates: 4,
+                evaluator: LinearEvaluator::new(crate::ai::Preset::default().weights),
+            }),
+        ];
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for policy in &policies {
+            let mut game = game.clone();
+            playout_with(&mut game, &mut rng, 3, policy.as_ref());
+            assert_eq!(game.round(), 3);
+        }
+    }
+}
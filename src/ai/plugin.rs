@@ -0,0 +1,260 @@
+//! Community bots compiled to WebAssembly and run with wasmtime, so bots
+//! nobody has reviewed can join tournaments without being able to harm the
+//! machine running them.
+//!
+//! A plugin is a module exporting:
+//!
+//! - `memory`, its linear memory.
+//! - `alloc(len: i32) -> i32`, returning where the host may write `len`
+//!   bytes.
+//! - `choose_turn(ptr: i32, len: i32) -> i64`, given the position in the
+//!   state notation, UTF-8 at `ptr`, and returning the turn in the turn
+//!   notation as `ptr << 32 | len`.
+//! - Optionally `name() -> i64`, its name the same way.
+//!
+//! Plugins can't import anything, so they have no way to reach files, the
+//! network or the clock. Each turn runs on a fresh instance with a budget of
+//! fuel and a cap on memory. A plugin running out of either, crashing or
+//! answering with anything but a legal turn plays the first legal turn
+//! instead, and the fault is kept for the tournament to report.
+
+use std::path::Path;
+
+use thiserror::Error;
+use wasmtime::{Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use super::{Agent, Limits};
+use crate::game::{Game, Turn};
+
+/// Fuel a plugin may burn per turn, roughly one unit per instruction.
+pub const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+/// Bytes of memory a plugin may grow to.
+pub const MAX_MEMORY: usize = 64 << 20;
+
+pub struct WasmBot {
+    engine: Engine,
+    module: Module,
+    name: String,
+    fuel: u64,
+    faults: Vec<PluginError>,
+}
+
+impl WasmBot {
+    /// Compiles the module in the file, a binary or text module.
+    pub fn load(path: impl AsRef<Path>) -> Result<WasmBot, PluginError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(PluginError::Io)?;
+        let name = path
+            .file_stem()
+            .map_or("plugin".into(), |stem| stem.to_string_lossy().into_owned());
+        WasmBot::from_bytes(&bytes, &name)
+    }
+
+    /// Compiles the module, called `name` unless it names itself.
+    pub fn from_bytes(bytes: &[u8], name: &str) -> Result<WasmBot, PluginError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(PluginError::Load)?;
+        let module = Module::new(&engine, bytes).map_err(PluginError::Load)?;
+        if let Some(import) = module.imports().next() {
+            return Err(PluginError::Import(format!(
+                "{}::{}",
+                import.module(),
+                import.name()
+            )));
+        }
+        let mut bot = WasmBot {
+            engine,
+            module,
+            name: name.to_string(),
+            fuel: DEFAULT_FUEL,
+            faults: Vec::new(),
+        };
+        let (mut store, instance) = bot.instantiate()?;
+        if let Ok(own_name) = instance.get_typed_func::<(), i64>(&mut store, "name") {
+            let packed = own_name.call(&mut store, ()).map_err(PluginError::Trap)?;
+            bot.name = read_string(&mut store, &instance, packed)?;
+        }
+        Ok(bot)
+    }
+
+    pub fn with_fuel(self, fuel: u64) -> WasmBot {
+        WasmBot { fuel, ..self }
+    }
+
+    /// Everything that went wrong in the turns so far.
+    pub fn faults(&self) -> &[PluginError] {
+        &self.faults
+    }
+
+    fn instantiate(&self) -> Result<(Store<StoreLimits>, Instance), PluginError> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(PluginError::Load)?;
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .map_err(PluginError::Load)?;
+        Ok((store, instance))
+    }
+
+    /// The plugin's answer in the position, as it gave it.
+    fn ask(&self, game: &Game) -> Result<Turn, PluginError> {
+        let (mut store, instance) = self.instantiate()?;
+        let notation = game.to_notation();
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(PluginError::Load)?;
+        let choose_turn = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "choose_turn")
+            .map_err(PluginError::Load)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or(PluginError::Memory)?;
+
+        let len = notation.len() as i32;
+        let ptr = alloc.call(&mut store, len).map_err(PluginError::Trap)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, notation.as_bytes())
+            .map_err(|_| PluginError::Memory)?;
+        let packed = choose_turn
+            .call(&mut store, (ptr, len))
+            .map_err(PluginError::Trap)?;
+        let answer = read_string(&mut store, &instance, packed)?;
+        answer
+            .parse()
+            .map_err(|_| PluginError::IllegalTurn(answer.clone()))
+    }
+}
+
+/// The UTF-8 string at `packed >> 32` of length `packed & 0xffff_ffff`.
+fn read_string(
+    store: &mut Store<StoreLimits>,
+    instance: &Instance,
+    packed: i64,
+) -> Result<String, PluginError> {
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or(PluginError::Memory)?;
+    let (ptr, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+    let bytes = memory
+        .data(&*store)
+        .get(ptr..ptr.saturating_add(len))
+        .ok_or(PluginError::Memory)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| PluginError::Memory)
+}
+
+impl Agent for WasmBot {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The plugin has its fuel rather than the limits to go by.
+    fn choose_turn(&mut self, game: &Game, _limits: &Limits) -> Turn {
+        let legal = game.legal_turns();
+        match self.ask(game) {
+            Ok(turn) if legal.contains(&turn) => return turn,
+            Ok(turn) => self.faults.push(PluginError::IllegalTurn(turn.to_string())),
+            Err(err) => self.faults.push(err),
+        }
+        legal
+            .into_iter()
+            .next()
+            .expect("The player to move should have a legal turn")
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PluginError {
+    #[error("Couldn't read the plugin: {0}")]
+    Io(std::io::Error),
+    #[error("Couldn't load the plugin: {0}")]
+    Load(wasmtime::Error),
+    #[error("The plugin imports {0}, plugins can't import anything.")]
+    Import(String),
+    #[error("The plugin crashed or ran out of fuel: {0}")]
+    Trap(wasmtime::Error),
+    #[error("The plugin's memory is missing or was read out of bounds.")]
+    Memory,
+    #[error("The plugin played {0}, which isn't a legal turn.")]
+    IllegalTurn(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AgentSpec;
+    use crate::rules::Ruleset;
+
+    /// A plugin answering `answer` whatever the position, after spinning
+    /// `spins` times.
+    fn plugin(answer: &str, spins: u32) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{answer}")
+                (data (i32.const 512) "echo")
+                (func (export "name") (result i64) (i64.const 0x200_0000_0004))
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "choose_turn") (param i32 i32) (result i64) (local i32)
+                    (loop
+                        (local.set 2 (i32.add (local.get 2) (i32.const 1)))
+                        (br_if 0 (i32.lt_u (local.get 2) (i32.const {spins}))))
+                    (i64.const {len})))"#,
+            answer = answer,
+            spins = spins,
+            len = answer.len()
+        )
+    }
+
+    #[test]
+    fn plays_the_plugins_turn() {
+        let game = Game::new_with_rules(Ruleset::new(2), 3);
+        let turn = game.legal_turns().pop().unwrap();
+        let mut bot = WasmBot::from_bytes(plugin(&turn.to_string(), 1).as_bytes(), "x").unwrap();
+        assert_eq!(bot.name(), "echo");
+        assert_eq!(bot.choose_turn(&game, &Limits::none()), turn);
+        assert!(bot.faults().is_empty());
+    }
+
+    #[test]
+    fn faults_fall_back_to_a_legal_turn() {
+        let game = Game::new_with_rules(Ruleset::new(2), 3);
+        let turn = game.legal_turns().pop().unwrap().to_string();
+        let mut bots = vec![
+            WasmBot::from_bytes(plugin("nonsense", 1).as_bytes(), "x").unwrap(),
+            WasmBot::from_bytes(plugin(&turn, 1_000_000).as_bytes(), "x")
+                .unwrap()
+                .with_fuel(10_000),
+        ];
+        for bot in &mut bots {
+            let played = bot.choose_turn(&game, &Limits::none());
+            assert_eq!(played, game.legal_turns()[0]);
+            assert_eq!(bot.faults().len(), 1);
+        }
+
+        let wasi = r#"(module (import "wasi_snapshot_preview1" "fd_write" (func)))"#;
+        assert!(matches!(
+            WasmBot::from_bytes(wasi.as_bytes(), "x"),
+            Err(PluginError::Import(_))
+        ));
+    }
+
+    #[test]
+    fn plugins_join_as_specs() {
+        let game = Game::new_with_rules(Ruleset::new(2), 3);
+        let turn = game.legal_turns().pop().unwrap();
+        let path =
+            std::env::temp_dir().join(format!("passtally-plugin-{}.wat", std::process::id()));
+        std::fs::write(&path, plugin(&turn.to_string(), 1)).unwrap();
+
+        let spec: AgentSpec = format!("plugin:{}", path.display()).parse().unwrap();
+        let mut bot = spec.build(0).unwrap();
+        assert_eq!(bot.name(), "echo");
+        assert_eq!(bot.choose_turn(&game, &Limits::none()), turn);
+        std::fs::remove_file(&path).unwrap();
+        assert!(spec.build(0).is_err());
+        assert!("plugin:missing.wasm".parse::<AgentSpec>().is_err());
+    }
+}
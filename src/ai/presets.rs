@@ -0,0 +1,291 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::alphabeta::DEFAULT_DEPTH;
+#[cfg(feature = "plugins")]
+use super::plugin::{PluginError, WasmBot};
+#[cfg(feature = "scripting")]
+use super::script::{ScriptAgent, ScriptError};
+use super::{Agent, AlphaBetaAgent, GreedyAgent, IsmctsAgent, MctsAgent, RandomAgent};
+
+/// Weights for the features of the static evaluation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluatorWeights {
+    /// Points already scored, relative to the opponents.
+    pub score: f32,
+    /// Sum of the heights of the squares next to your own markers.
+    pub height_near_markers: f32,
+    /// Pairs of your markers that a single placement could connect.
+    pub connectable_pairs: f32,
+    /// How useful the pieces currently on offer are to you.
+    pub market_quality: f32,
+}
+
+/// Parameters for the monte carlo tree search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MctsParams {
+    /// The UCT exploration constant.
+    pub exploration: f32,
+    /// Playouts per move when no other limit is given.
+    pub playouts: u32,
+}
+
+/// A named set of bot parameters, stored as TOML.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub weights: EvaluatorWeights,
+    pub mcts: MctsParams,
+}
+
+/// The presets shipped with the game. The first one is the default.
+const BUILTIN: [&str; 3] = [
+    include_str!("../../assets/presets/default.toml"),
+    include_str!("../../assets/presets/builder.toml"),
+    include_str!("../../assets/presets/opportunist.toml"),
+];
+
+impl Preset {
+    pub fn from_toml(s: &str) -> Result<Self, PresetError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, PresetError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PresetError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PresetError> {
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// All presets shipped with the game.
+    pub fn builtin() -> Vec<Preset> {
+        BUILTIN
+            .iter()
+            .map(|s| Self::from_toml(s).expect("Builtin presets should be valid"))
+            .collect()
+    }
+
+    /// Look up a shipped preset by name.
+    pub fn named(name: &str) -> Option<Preset> {
+        Self::builtin()
+            .into_iter()
+            .find(|preset| preset.name == name)
+    }
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self::from_toml(BUILTIN[0]).expect("Builtin presets should be valid")
+    }
+}
+
+/// Which bot to build, and with what parameters, for tools setting up many
+/// games between bots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AgentSpec {
+    Random,
+    Greedy,
+    Mcts {
+        preset: Preset,
+    },
+    Ismcts {
+        preset: Preset,
+    },
+    AlphaBeta {
+        preset: Preset,
+        depth: u32,
+    },
+    /// A community bot compiled to WebAssembly.
+    #[cfg(feature = "plugins")]
+    Plugin {
+        path: std::path::PathBuf,
+    },
+    /// A bot written as a Lua script.
+    #[cfg(feature = "scripting")]
+    Script {
+        path: std::path::PathBuf,
+    },
+}
+
+impl AgentSpec {
+    /// A new bot, with its random numbers seeded from `seed`. Plugins and
+    /// scripts are loaded from their files each time, so this fails if a
+    /// file has gone or changed into something that doesn't load since the
+    /// spec was made.
+    pub fn build(&self, seed: u64) -> Result<Box<dyn Agent + Send>, PresetError> {
+        Ok(match self {
+            AgentSpec::Random => Box::new(RandomAgent::new(seed)),
+            AgentSpec::Greedy => Box::new(GreedyAgent::new()),
+            AgentSpec::Mcts { preset } => Box::new(MctsAgent::new(preset.mcts.clone(), seed)),
+            AgentSpec::Ismcts { preset } => Box::new(IsmctsAgent::new(preset.mcts.clone(), seed)),
+            AgentSpec::AlphaBeta { preset, depth } => {
+                Box::new(AlphaBetaAgent::new(preset.weights.clone(), *depth))
+            }
+            #[cfg(feature = "plugins")]
+            AgentSpec::Plugin { path } => Box::new(WasmBot::load(path)?),
+            #[cfg(feature = "scripting")]
+            AgentSpec::Script { path } => Box::new(ScriptAgent::load(path)?),
+        })
+    }
+}
+
+/// Bots as written on the command line: `random`, `greedy`, the search bots
+/// followed by the name of a preset or a path to one, as in `mcts:builder`,
+/// and for alpha-beta also a depth, as in `alphabeta:default:2`. The preset
+/// and the depth can be left out. With plugins, `plugin:` followed by the
+/// path to a WebAssembly bot, and with scripting `script:` followed by the
+/// path to a Lua script.
+impl FromStr for AgentSpec {
+    type Err = PresetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().unwrap_or_default();
+        let rest = parts.next();
+        let preset = |name: Option<&str>| match name {
+            None => Ok(Preset::default()),
+            Some(name) => match Preset::named(name) {
+                Some(preset) => Ok(preset),
+                None => Preset::load(name),
+            },
+        };
+        match (kind, rest) {
+            ("random", None) => Ok(AgentSpec::Random),
+            ("greedy", None) => Ok(AgentSpec::Greedy),
+            ("mcts", rest) => Ok(AgentSpec::Mcts {
+                preset: preset(rest)?,
+            }),
+            ("ismcts", rest) => Ok(AgentSpec::Ismcts {
+                preset: preset(rest)?,
+            }),
+            ("alphabeta", rest) => {
+                let (name, depth) = match rest.map(|rest| rest.rsplitn(2, ':')) {
+                    None => (None, DEFAULT_DEPTH),
+                    Some(mut parts) => {
+                        let last = parts.next().unwrap_or_default();
+                        match (last.parse(), parts.next()) {
+                            (Ok(depth), name) => (name, depth),
+                            (Err(_), _) => (rest, DEFAULT_DEPTH),
+                        }
+                    }
+                };
+                Ok(AgentSpec::AlphaBeta {
+                    preset: preset(name)?,
+                    depth,
+                })
+            }
+            #[cfg(feature = "plugins")]
+            ("plugin", Some(path)) => {
+                WasmBot::load(path)?;
+                Ok(AgentSpec::Plugin { path: path.into() })
+            }
+            #[cfg(feature = "scripting")]
+            ("script", Some(path)) => {
+                ScriptAgent::load(path)?;
+                Ok(AgentSpec::Script { path: path.into() })
+            }
+            _ => Err(PresetError::UnknownAgent(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for AgentSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentSpec::Random => write!(f, "random"),
+            AgentSpec::Greedy => write!(f, "greedy"),
+            AgentSpec::Mcts { preset } => write!(f, "mcts:{}", preset.name),
+            AgentSpec::Ismcts { preset } => write!(f, "ismcts:{}", preset.name),
+            AgentSpec::AlphaBeta { preset, depth } => {
+                write!(f, "alphabeta:{}:{}", preset.name, depth)
+            }
+            #[cfg(feature = "plugins")]
+            AgentSpec::Plugin { path } => write!(f, "plugin:{}", path.display()),
+            #[cfg(feature = "scripting")]
+            AgentSpec::Script { path } => write!(f, "script:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PresetError {
+    #[error("Couldn't access the preset file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse the preset: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Couldn't write the preset: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("Unknown bot {0}, expected random, greedy, mcts, ismcts or alphabeta.")]
+    UnknownAgent(String),
+    #[cfg(feature = "plugins")]
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+    #[cfg(feature = "scripting")]
+    #[error(transparent)]
+    Script(#[from] ScriptError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_presets_parse() {
+        let presets = Preset::builtin();
+        assert_eq!(presets.len(), BUILTIN.len());
+        assert_eq!(presets[0], Preset::default());
+        assert!(Preset::named("builder").is_some());
+        assert!(Preset::named("nonexistent").is_none());
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        for preset in Preset::builtin() {
+            let s = preset.to_toml().unwrap();
+            assert_eq!(Preset::from_toml(&s).unwrap(), preset);
+        }
+    }
+
+    #[test]
+    fn agent_specs() {
+        let spec = AgentSpec::AlphaBeta {
+            preset: Preset::default(),
+            depth: 2,
+        };
+        assert_eq!(spec.build(0).unwrap().name(), "alphabeta");
+        assert_eq!(AgentSpec::Greedy.build(0).unwrap().name(), "greedy");
+
+        assert_eq!("alphabeta:default:2".parse::<AgentSpec>().unwrap(), spec);
+        assert_eq!(spec.to_string(), "alphabeta:default:2");
+        assert_eq!(
+            "mcts:builder".parse::<AgentSpec>().unwrap(),
+            AgentSpec::Mcts {
+                preset: Preset::named("builder").unwrap()
+            }
+        );
+        assert!(matches!(
+            "minimax".parse::<AgentSpec>(),
+            Err(PresetError::UnknownAgent(_))
+        ));
+    }
+
+    #[test]
+    fn missing_weights_fail() {
+        let err = Preset::from_toml("name = \"broken\"").unwrap_err();
+        assert!(matches!(err, PresetError::Parse(_)));
+    }
+}
@@ -0,0 +1,612 @@
+//! A line based text protocol between engines and the programs using them,
+//! in the spirit of UCI in chess. Programs write commands to the engine's
+//! stdin and read responses from its stdout, one per line:
+//!
+//! ```text
+//! > passtally
+//! < id name mcts
+//! < passtallyok
+//! > newgame 2 7
+//! > position startpos 2 7 moves P:Pink@c3r1+M:4>7
+//! > isready
+//! < readyok
+//! > go movetime 1000
+//! < info depth 2 score 0.54 nodes 1830 time 250 pv P:Red@a1r0+M:2>3 ...
+//! < bestturn P:Red@a1r0+M:2>3
+//! > position startpos 2 7 moves P:Pink@c3r1+M:4>7 P:Red@a1r0+M:2>3
+//! > ponder
+//! > stop
+//! > quit
+//! ```
+//!
+//! Positions are given from the start of a game, `startpos PLAYERS SEED`, or
+//! in [notation](crate::notation), followed by the turns played since. The
+//! notation leaves out the order of the decks, so an engine given one deals
+//! the pieces under the market in an order of its own and may not know what
+//! a turn uncovers.
+//! `go` takes any of `movetime MS`, `nodes N`, `depth N`, `time MS inc MS`
+//! for a clock, and `infinite` to search until `stop`. `ponder` has the
+//! engine think about the position, usually the one after its own turn,
+//! while the opponents think about theirs, until `stop` or the next `go`.
+//! Anything the engine can't make sense of is answered with `error` and a
+//! message.
+//!
+//! [`Engine`] speaks the protocol for any [`Agent`].
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use thiserror::Error;
+
+use super::{Agent, InfoCallback, Limits, SearchInfo, StopFlag};
+use crate::game::{Game, Turn};
+use crate::notation::NotationError;
+use crate::rules::Ruleset;
+
+/// What a program tells an engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Asks the engine to introduce itself, answered by `id` and
+    /// `passtallyok`.
+    Hello,
+    IsReady,
+    NewGame {
+        players: u8,
+        seed: u64,
+    },
+    Position {
+        start: Start,
+        turns: Vec<Turn>,
+    },
+    Go(Go),
+    /// Thinks about the position in the background, see [`Agent::ponder`].
+    Ponder,
+    Stop,
+    Quit,
+}
+
+/// Where a position starts from, before the turns played since.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Start {
+    /// A new game dealt from the seed.
+    Dealt { players: u8, seed: u64 },
+    /// A game in notation, which tells the engine what the players know.
+    Notation(Box<Game>),
+}
+
+impl Start {
+    pub fn game(&self) -> Game {
+        match self {
+            Start::Dealt { players, seed } => Game::new_with_rules(Ruleset::new(*players), *seed),
+            Start::Notation(game) => (**game).clone(),
+        }
+    }
+}
+
+/// How long to search for, as given to `go`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Go {
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub depth: Option<u32>,
+    /// Time left on the engine's clock and the increment per turn.
+    pub clock: Option<(Duration, Duration)>,
+    pub infinite: bool,
+}
+
+impl Go {
+    pub fn limits(&self) -> Limits {
+        let mut limits = match self.clock {
+            Some((remaining, increment)) => Limits::clock(remaining, increment),
+            None => Limits::none(),
+        };
+        if !self.infinite {
+            limits.max_time = self.movetime;
+            limits.max_nodes = self.nodes;
+            limits.max_depth = self.depth;
+        }
+        limits
+    }
+}
+
+/// What an engine tells the program using it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    Id { name: String },
+    PasstallyOk,
+    ReadyOk,
+    Info(SearchInfo),
+    BestTurn(Turn),
+    Error(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("Unknown command {0:?}.")]
+    Unknown(String),
+    #[error("Couldn't understand {0:?}.")]
+    Malformed(String),
+    #[error("Couldn't read the position: {0}")]
+    Notation(#[from] NotationError),
+}
+
+fn number<T: FromStr>(word: Option<&str>, line: &str) -> Result<T, ProtocolError> {
+    word.and_then(|word| word.parse().ok())
+        .ok_or_else(|| ProtocolError::Malformed(line.to_string()))
+}
+
+impl FromStr for Command {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let malformed = || ProtocolError::Malformed(line.to_string());
+        match words.next() {
+            Some("passtally") => Ok(Command::Hello),
+            Some("isready") => Ok(Command::IsReady),
+            Some("newgame") => {
+                let players: u8 = words.next().map_or(Ok(2), |w| number(Some(w), line))?;
+                let seed = words.next().map_or(Ok(0), |w| number(Some(w), line))?;
+                if !(1..=4).contains(&players) {
+                    return Err(malformed());
+                }
+                Ok(Command::NewGame { players, seed })
+            }
+            Some("position") => {
+                let rest: Vec<&str> = words.collect();
+                let moves = rest.iter().position(|&word| word == "moves");
+                let (start, turns) = rest.split_at(moves.unwrap_or(rest.len()));
+                let start = match start {
+                    ["startpos", players, seed] => {
+                        let players: u8 = number(Some(players), line)?;
+                        if !(1..=4).contains(&players) {
+                            return Err(malformed());
+                        }
+                        Start::Dealt {
+                            players,
+                            seed: number(Some(seed), line)?,
+                        }
+                    }
+                    _ => Start::Notation(Box::new(Game::from_notation(&start.join(" "))?)),
+                };
+                let turns = turns
+                    .iter()
+                    .skip(1)
+                    .map(|turn| turn.parse())
+                    .collect::<Result<_, _>>()?;
+                Ok(Command::Position { start, turns })
+            }
+            Some("go") => {
+                let mut go = Go::default();
+                while let Some(word) = words.next() {
+                    match word {
+                        "movetime" => {
+                            go.movetime = Some(Duration::from_millis(number(words.next(), line)?))
+                        }
+                        "nodes" => go.nodes = Some(number(words.next(), line)?),
+                        "depth" => go.depth = Some(number(words.next(), line)?),
+                        "time" => {
+                            let remaining = number(words.next(), line)?;
+                            if words.next() != Some("inc") {
+                                return Err(malformed());
+                            }
+                            let increment = number(words.next(), line)?;
+                            go.clock = Some((
+                                Duration::from_millis(remaining),
+                                Duration::from_millis(increment),
+                            ));
+                        }
+                        "infinite" => go.infinite = true,
+                        _ => return Err(malformed()),
+                    }
+                }
+                Ok(Command::Go(go))
+            }
+            Some("ponder") => Ok(Command::Ponder),
+            Some("stop") => Ok(Command::Stop),
+            Some("quit") => Ok(Command::Quit),
+            _ => Err(ProtocolError::Unknown(line.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Hello => write!(f, "passtally"),
+            Command::IsReady => write!(f, "isready"),
+            Command::NewGame { players, seed } => write!(f, "newgame {} {}", players, seed),
+            Command::Position { start, turns } => {
+                match start {
+                    Start::Dealt { players, seed } => {
+                        write!(f, "position startpos {} {}", players, seed)?
+                    }
+                    Start::Notation(game) => write!(f, "position {}", game.to_notation())?,
+                }
+                if !turns.is_empty() {
+                    write!(f, " moves")?;
+                    for turn in turns {
+                        write!(f, " {}", turn)?;
+                    }
+                }
+                Ok(())
+            }
+            Command::Go(go) => {
+                write!(f, "go")?;
+                if let Some(movetime) = go.movetime {
+                    write!(f, " movetime {}", movetime.as_millis())?;
+                }
+                if let Some(nodes) = go.nodes {
+                    write!(f, " nodes {}", nodes)?;
+                }
+                if let Some(depth) = go.depth {
+                    write!(f, " depth {}", depth)?;
+                }
+                if let Some((remaining, increment)) = go.clock {
+                    write!(
+                        f,
+                        " time {} inc {}",
+                        remaining.as_millis(),
+                        increment.as_millis()
+                    )?;
+                }
+                if go.infinite {
+                    write!(f, " infinite")?;
+                }
+                Ok(())
+            }
+            Command::Ponder => write!(f, "ponder"),
+            Command::Stop => write!(f, "stop"),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl FromStr for Response {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let malformed = || ProtocolError::Malformed(line.to_string());
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("id") if words.next() == Some("name") => Ok(Response::Id {
+                name: words.collect::<Vec<_>>().join(" "),
+            }),
+            Some("passtallyok") => Ok(Response::PasstallyOk),
+            Some("readyok") => Ok(Response::ReadyOk),
+            Some("bestturn") => Ok(Response::BestTurn(
+                words.next().ok_or_else(malformed)?.parse()?,
+            )),
+            Some("error") => Ok(Response::Error(words.collect::<Vec<_>>().join(" "))),
+            Some("info") => {
+                let mut info = SearchInfo {
+                    nodes: 0,
+                    elapsed: Duration::from_secs(0),
+                    depth: 0,
+                    score: 0.0,
+                    pv: Vec::new(),
+                };
+                while let Some(word) = words.next() {
+                    match word {
+                        "depth" => info.depth = number(words.next(), line)?,
+                        "score" => info.score = number(words.next(), line)?,
+                        "nodes" => info.nodes = number(words.next(), line)?,
+                        "time" => info.elapsed = Duration::from_millis(number(words.next(), line)?),
+                        "pv" => {
+                            info.pv = words
+                                .by_ref()
+                                .map(|turn| turn.parse())
+                                .collect::<Result<_, _>>()?
+                        }
+                        _ => return Err(malformed()),
+                    }
+                }
+                Ok(Response::Info(info))
+            }
+            _ => Err(ProtocolError::Unknown(line.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Response::Id { name } => write!(f, "id name {}", name),
+            Response::PasstallyOk => write!(f, "passtallyok"),
+            Response::ReadyOk => write!(f, "readyok"),
+            Response::Info(info) => {
+                write!(
+                    f,
+                    "info depth {} score {} nodes {} time {}",
+                    info.depth,
+                    info.score,
+                    info.nodes,
+                    info.elapsed.as_millis()
+                )?;
+                if !info.pv.is_empty() {
+                    write!(f, " pv")?;
+                    for turn in &info.pv {
+                        write!(f, " {}", turn)?;
+                    }
+                }
+                Ok(())
+            }
+            Response::BestTurn(turn) => write!(f, "bestturn {}", turn),
+            Response::Error(message) => write!(f, "error {}", message),
+        }
+    }
+}
+
+type Output = Arc<dyn Fn(&Response) + Send + Sync>;
+
+/// A search running on its own thread, giving the bot back when it's done.
+struct Search {
+    stop: StopFlag,
+    handle: JoinHandle<Box<dyn Agent + Send>>,
+}
+
+/// Speaks the protocol for a bot: takes commands one line at a time and
+/// sends the responses to `output`, from the thread searching for those
+/// coming out of `go`.
+pub struct Engine {
+    /// `None` while a search has it.
+    agent: Option<Box<dyn Agent + Send>>,
+    name: String,
+    game: Game,
+    search: Option<Search>,
+    output: Output,
+}
+
+impl Engine {
+    pub fn new(
+        agent: Box<dyn Agent + Send>,
+        output: impl Fn(&Response) + Send + Sync + 'static,
+    ) -> Engine {
+        Engine {
+            name: agent.name().to_string(),
+            agent: Some(agent),
+            game: Game::new_with_rules(Ruleset::new(2), 0),
+            search: None,
+            output: Arc::new(output),
+        }
+    }
+
+    /// Handles a line from the program. Returns false once told to quit.
+    pub fn handle(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+        let command = match line.parse::<Command>() {
+            Ok(command) => command,
+            Err(err) => {
+                (self.output)(&Response::Error(err.to_string()));
+                return true;
+            }
+        };
+        match command {
+            Command::Hello => {
+                (self.output)(&Response::Id {
+                    name: self.name.clone(),
+                });
+                (self.output)(&Response::PasstallyOk);
+            }
+            Command::IsReady => (self.output)(&Response::ReadyOk),
+            Command::NewGame { players, seed } => {
+                self.wait();
+                self.game = Game::new_with_rules(Ruleset::new(players), seed);
+            }
+            Command::Position { start, turns } => {
+                self.wait();
+                let mut game = start.game();
+                for turn in turns {
+                    if let Err(err) = game.play_turn(turn.clone()) {
+                        let message = format!("Couldn't play {}: {}", turn, err);
+                        (self.output)(&Response::Error(message));
+                        return true;
+                    }
+                }
+                self.game = game;
+            }
+            Command::Go(go) => {
+                self.wait();
+                self.go(&go);
+            }
+            Command::Ponder => {
+                self.wait();
+                self.agent
+                    .as_mut()
+                    .expect("The bot should be back")
+                    .ponder(&self.game);
+            }
+            Command::Stop => {
+                if let Some(search) = &self.search {
+                    search.stop.raise();
+                }
+                self.wait();
+                // Ends pondering, bots that aren't do nothing.
+                self.agent.as_mut().expect("The bot should be back").stop();
+            }
+            Command::Quit => {
+                self.handle("stop");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Waits for the running search, if any, to send its turn.
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.agent = Some(search.handle.join().expect("The search shouldn't panic"));
+        }
+    }
+
+    fn go(&mut self, go: &Go) {
+        if !self.game.has_legal_turn() {
+            let message = "There is no legal turn to play.".to_string();
+            (self.output)(&Response::Error(message));
+            return;
+        }
+        let mut agent = self.agent.take().expect("The bot should be back");
+        let game = self.game.clone();
+        let stop = StopFlag::new();
+        let output = self.output.clone();
+        let mut limits = go.limits().with_info({
+            let output = output.clone();
+            InfoCallback::new(move |info| output(&Response::Info(info.clone())))
+        });
+        // A bare `go` leaves the bot to its own limits, which a stop flag
+        // would turn into searching until stopped for some of them.
+        if go != &Go::default() {
+            limits = limits.with_stop(stop.clone());
+        }
+        let handle = thread::spawn(move || {
+            let turn = agent.choose_turn(&game, &limits);
+            output(&Response::BestTurn(turn));
+            agent
+        });
+        self.search = Some(Search { stop, handle });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::RandomAgent;
+    use std::sync::Mutex;
+
+    #[test]
+    fn commands_round_trip() {
+        let game = Game::new_with_rules(Ruleset::new(2), 7);
+        let turn = game.legal_turns().remove(0);
+        let line = format!("position startpos 2 7 moves {}", turn);
+        let command: Command = line.parse().unwrap();
+        assert_eq!(
+            command,
+            Command::Position {
+                start: Start::Dealt {
+                    players: 2,
+                    seed: 7
+                },
+                turns: vec![turn.clone()]
+            }
+        );
+        assert_eq!(command.to_string(), line);
+
+        // Engines given a position in notation don't learn the deck order.
+        let command = Command::Position {
+            start: Start::Notation(Box::new(game.clone())),
+            turns: Vec::new(),
+        };
+        assert_eq!(
+            command.to_string(),
+            format!("position {}", game.to_notation())
+        );
+        match command.to_string().parse::<Command>().unwrap() {
+            Command::Position {
+                start: Start::Notation(read),
+                ..
+            } => assert_eq!(read.to_notation(), game.to_notation()),
+            command => panic!("Unexpected command {:?}", command),
+        }
+
+        let go: Command = "go movetime 500 time 60000 inc 1000".parse().unwrap();
+        assert_eq!(go.to_string(), "go movetime 500 time 60000 inc 1000");
+        assert!(matches!(
+            "go sideways".parse::<Command>(),
+            Err(ProtocolError::Malformed(_))
+        ));
+
+        let info = Response::Info(SearchInfo {
+            nodes: 12,
+            elapsed: Duration::from_millis(30),
+            depth: 2,
+            score: 0.5,
+            pv: vec![turn.clone()],
+        });
+        assert_eq!(info.to_string().parse::<Response>().unwrap(), info);
+        let best = Response::BestTurn(turn);
+        assert_eq!(best.to_string().parse::<Response>().unwrap(), best);
+    }
+
+    #[test]
+    fn engine_answers() {
+        let responses = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new(Box::new(RandomAgent::new(3)), {
+            let responses = responses.clone();
+            move |response: &Response| responses.lock().unwrap().push(response.clone())
+        });
+        for line in &[
+            "passtally",
+            "newgame 2 5",
+            "go nodes 10",
+            "isready",
+            "bogus",
+        ] {
+            assert!(engine.handle(line));
+        }
+        assert!(!engine.handle("quit"));
+
+        let responses = responses.lock().unwrap();
+        assert_eq!(
+            responses[..2],
+            [
+                Response::Id {
+                    name: "random".to_string()
+                },
+                Response::PasstallyOk
+            ]
+        );
+        let game = Game::new_with_rules(Ruleset::new(2), 5);
+        assert!(responses.iter().any(
+            |response| matches!(response, Response::BestTurn(turn) if game.clone().play_turn(turn.clone()).is_ok())
+        ));
+        assert!(responses
+            .iter()
+            .any(|response| matches!(response, Response::Error(_))));
+    }
+
+    /// Notes when it's told to ponder and to stop.
+    struct Ponderer(Arc<Mutex<Vec<String>>>);
+
+    impl Agent for Ponderer {
+        fn name(&self) -> &str {
+            "ponderer"
+        }
+
+        fn choose_turn(&mut self, game: &Game, _: &Limits) -> Turn {
+            game.legal_turns_iter().next().unwrap()
+        }
+
+        fn ponder(&mut self, game: &Game) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("ponder {}", game.round()));
+        }
+
+        fn stop(&mut self) {
+            self.0.lock().unwrap().push("stop".to_string());
+        }
+    }
+
+    #[test]
+    fn engine_ponders_until_stopped() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new(Box::new(Ponderer(calls.clone())), |_: &Response| {});
+        let game = Game::new_with_rules(Ruleset::new(2), 5);
+        let turn = game.legal_turns_iter().next().unwrap();
+        assert_eq!("ponder".parse::<Command>().unwrap(), Command::Ponder);
+        for line in &[
+            format!("position startpos 2 5 moves {}", turn),
+            "ponder".to_string(),
+            "stop".to_string(),
+        ] {
+            assert!(engine.handle(line));
+        }
+        assert_eq!(*calls.lock().unwrap(), ["ponder 1", "stop"]);
+    }
+}
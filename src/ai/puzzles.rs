@@ -0,0 +1,238 @@
+//! "Score X in one turn" puzzles, mined from games between bots: a position
+//! makes a puzzle when a single turn scores the most points, every other turn
+//! scoring less or only getting there in a different way.
+//!
+//! Puzzles are written one per line as the position in the state notation,
+//! the points to score and the solution, separated by `|`. The solver is
+//! player 0 and moves first, the markers of the other players are only in the
+//! way. Empty lines and lines starting with `#` are ignored.
+
+use std::fmt;
+
+use thiserror::Error;
+
+use super::{Agent, GreedyAgent, Limits};
+use crate::game::{Game, Turn};
+use crate::notation::NotationError;
+use crate::rules::Ruleset;
+use crate::zobrist;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub notation: String,
+    /// Points the solution scores.
+    pub target: u32,
+    pub solution: Turn,
+}
+
+impl Puzzle {
+    /// The puzzle for the player to move, if one turn scores the most
+    /// points, at least `min_points` of them.
+    pub fn from_position(game: &Game, min_points: u32) -> Option<Puzzle> {
+        if game.is_finished() {
+            return None;
+        }
+        let notation = solver_to_move(game).to_notation();
+        // The target doesn't change which turns score the most.
+        let (target, mut solutions) = solutions(&puzzle_game(&notation, 0));
+        if target < min_points || solutions.len() != 1 {
+            return None;
+        }
+        Some(Puzzle {
+            notation,
+            target,
+            solution: solutions.remove(0),
+        })
+    }
+
+    /// The puzzle ready to be played: a solo game with one turn to score the
+    /// target.
+    pub fn game(&self) -> Game {
+        puzzle_game(&self.notation, self.target)
+    }
+
+    /// Reads a list of puzzles in the format described in the module
+    /// documentation.
+    pub fn parse_list(s: &str) -> Result<Vec<Puzzle>, PuzzleError> {
+        let mut puzzles = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+            if fields.len() != 3 {
+                return Err(PuzzleError::FieldCount(i + 1));
+            }
+            let bad_notation = |err| PuzzleError::Notation(i + 1, err);
+
+            Game::from_notation(fields[0]).map_err(bad_notation)?;
+            puzzles.push(Puzzle {
+                notation: fields[0].to_string(),
+                target: fields[1]
+                    .parse()
+                    .map_err(|_| PuzzleError::BadTarget(i + 1))?,
+                solution: fields[2].parse().map_err(bad_notation)?,
+            });
+        }
+        Ok(puzzles)
+    }
+}
+
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | {} | {}", self.notation, self.target, self.solution)
+    }
+}
+
+/// The position with the player to move as player 0, the others after them
+/// in seat order, and nobody having scored yet.
+fn solver_to_move(game: &Game) -> Game {
+    let players = game.rules().player_count;
+    let solver = game.next_player();
+    let mut position = game.clone();
+    for marker in position.player_markers.iter_mut().flatten() {
+        *marker = (*marker + players - solver) % players;
+    }
+    position.markers_hash = zobrist::markers(&position.player_markers);
+    position.scores = vec![0; players as usize];
+    position.round = 0;
+    position
+}
+
+fn puzzle_game(notation: &str, target: u32) -> Game {
+    let position = Game::from_notation(notation).expect("Puzzle positions are checked when read");
+    Game::new_puzzle(
+        position.board,
+        position.player_markers,
+        position.decks,
+        1,
+        target,
+    )
+}
+
+/// The most points the player to move can score this turn, and a turn
+/// scoring them for each position they can be scored in.
+pub fn solutions(game: &Game) -> (u32, Vec<Turn>) {
+    let mut best = 0;
+    let mut found: Vec<(u64, Turn)> = Vec::new();
+    for turn in game.legal_turns() {
+        let mut after = game.clone();
+        let points = after
+            .play_turn(turn.clone())
+            .expect("Legal turns should be playable")
+            .points;
+        if points > best {
+            best = points;
+            found.clear();
+        }
+        let hash = after.hash();
+        if points == best && found.iter().all(|(other, _)| *other != hash) {
+            found.push((hash, turn));
+        }
+    }
+    (best, found.into_iter().map(|(_, turn)| turn).collect())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningConfig {
+    pub games: u32,
+    pub players: u8,
+    pub seed: u64,
+    /// Points a puzzle has to be worth at least.
+    pub min_points: u32,
+    pub max_turns: u32,
+}
+
+impl MiningConfig {
+    /// Two player games, for puzzles worth at least 5 points.
+    pub fn new(games: u32, seed: u64) -> MiningConfig {
+        MiningConfig {
+            games,
+            players: 2,
+            seed,
+            min_points: 5,
+            max_turns: 200,
+        }
+    }
+}
+
+/// Plays games between greedy bots, which reach positions worth scoring
+/// in, and looks for a puzzle before every turn. Each puzzle is passed to
+/// `found` as it turns up.
+pub fn mine(config: &MiningConfig, mut found: impl FnMut(&Puzzle)) -> Vec<Puzzle> {
+    let mut puzzles = Vec::new();
+    let mut agent = GreedyAgent::new();
+    for index in 0..config.games {
+        let rules = Ruleset::new(config.players);
+        let mut game = Game::new_with_rules(rules, config.seed + index as u64);
+        let mut turns = 0;
+        while !game.is_finished() && turns < config.max_turns {
+            if let Some(puzzle) = Puzzle::from_position(&game, config.min_points) {
+                found(&puzzle);
+                puzzles.push(puzzle);
+            }
+            let turn = agent.choose_turn(&game, &Limits::none());
+            game.play_turn(turn)
+                .expect("The bot's turn should be legal");
+            turns += 1;
+        }
+    }
+    puzzles
+}
+
+#[derive(Error, Debug)]
+pub enum PuzzleError {
+    #[error("Line {0} doesn't have 3 fields separated by '|'.")]
+    FieldCount(usize),
+    #[error("Line {0} has a malformed position or turn: {1}")]
+    Notation(usize, NotationError),
+    #[error("Line {0} has a malformed target.")]
+    BadTarget(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mined_puzzles_have_one_solution() {
+        let config = MiningConfig {
+            min_points: 1,
+            max_turns: 6,
+            ..MiningConfig::new(1, 3)
+        };
+        let puzzles = mine(&config, |_| {});
+        assert!(!puzzles.is_empty());
+
+        let written: Vec<String> = puzzles.iter().map(Puzzle::to_string).collect();
+        let read = Puzzle::parse_list(&written.join("\n")).unwrap();
+        assert_eq!(read, puzzles);
+        for puzzle in &read {
+            let mut game = puzzle.game();
+            assert_eq!(game.puzzle_solved(), Some(false));
+            game.play_turn(puzzle.solution.clone()).unwrap();
+            assert_eq!(game.puzzle_solved(), Some(true));
+        }
+    }
+
+    #[test]
+    fn mined_puzzles_validate_and_round_trip() {
+        let config = MiningConfig {
+            min_points: 1,
+            max_turns: 6,
+            players: 3,
+            ..MiningConfig::new(1, 5)
+        };
+        let puzzles = mine(&config, |_| {});
+        assert!(!puzzles.is_empty());
+        for puzzle in &puzzles {
+            let game = puzzle.game();
+            assert_eq!(game.validate(), Ok(()));
+            let restored = Game::from_notation(&game.to_full_notation()).unwrap();
+            assert_eq!(restored.validate(), Ok(()));
+            assert_eq!(restored, game);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! The weakest possible opponent, a reference point for testing and
+//! benchmarking the others.
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::{Agent, Limits};
+use crate::game::{Game, Turn};
+
+/// Picks any legal turn, each as likely as the others.
+#[derive(Debug, Clone)]
+pub struct RandomAgent {
+    rng: ChaCha8Rng,
+}
+
+impl RandomAgent {
+    /// The same seed always picks the same turns in the same positions.
+    pub fn new(seed: u64) -> RandomAgent {
+        RandomAgent {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for RandomAgent {
+    fn default() -> Self {
+        RandomAgent::new(thread_rng().gen())
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose_turn(&mut self, game: &Game, _: &Limits) -> Turn {
+        // Counting the turns is cheap, so only the one picked is found. The
+        // index is drawn the way choosing from a slice of them would.
+        let count = game.legal_turns_iter().count();
+        assert!(count > 0, "The player to move should have a legal turn");
+        let index = self.rng.gen_range(0..count as u32) as usize;
+        game.legal_turns_iter().nth(index).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn seeded_games_repeat() {
+        let play = |seed| {
+            let mut game = Game::new_with_rules(Ruleset::new(2), 1);
+            let mut agent = RandomAgent::new(seed);
+            let mut turns = Vec::new();
+            for _ in 0..3 {
+                let turn = agent.choose_turn(&game, &Limits::none());
+                game.play_turn(turn.clone()).unwrap();
+                turns.push(turn);
+            }
+            turns
+        };
+        assert_eq!(play(7), play(7));
+        assert_ne!(play(7), play(8));
+    }
+}
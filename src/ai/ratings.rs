@@ -0,0 +1,275 @@
+//! Ratings of bots kept across tournaments, updated with Glicko-2 after each
+//! one, so progress can be followed over time. Every tournament counts as a
+//! single rating period.
+//!
+//! Bots are told apart by their name as an [`AgentSpec`](super::AgentSpec)
+//! writes it, and the ratings are stored as JSON.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::tournament::Standings;
+
+/// Converts between Glicko and Glicko-2 scales.
+const SCALE: f64 = 173.7178;
+/// How much the volatility may change over a period.
+const TAU: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    /// How unsure the rating is, a standard deviation.
+    pub deviation: f64,
+    /// How erratic the bot's results are.
+    pub volatility: f64,
+    pub games: u32,
+}
+
+impl Default for Rating {
+    /// The rating of a bot that hasn't played yet.
+    fn default() -> Self {
+        Rating {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+            games: 0,
+        }
+    }
+}
+
+impl Rating {
+    /// The 95% confidence interval of the rating.
+    pub fn interval(&self) -> (f64, f64) {
+        (
+            self.rating - 1.96 * self.deviation,
+            self.rating + 1.96 * self.deviation,
+        )
+    }
+
+    /// The rating after a period with these results, each an opponent's
+    /// rating from before the period and the points scored against them.
+    pub fn updated(&self, results: &[(Rating, f64)]) -> Rating {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+        if results.is_empty() {
+            return Rating {
+                deviation: (phi.powi(2) + self.volatility.powi(2)).sqrt() * SCALE,
+                ..*self
+            };
+        }
+
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi.powi(2) / std::f64::consts::PI.powi(2)).sqrt();
+        let mut v_inverse = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in results {
+            let mu_j = (opponent.rating - 1500.0) / SCALE;
+            let g_j = g(opponent.deviation / SCALE);
+            let expected = 1.0 / (1.0 + (-g_j * (mu - mu_j)).exp());
+            v_inverse += g_j.powi(2) * expected * (1.0 - expected);
+            improvement += g_j * (score - expected);
+        }
+        let v = 1.0 / v_inverse;
+        let delta = v * improvement;
+
+        let volatility = self.new_volatility(phi, v, delta);
+        let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+        let phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / v).sqrt();
+        Rating {
+            rating: (mu + phi.powi(2) * improvement) * SCALE + 1500.0,
+            deviation: phi * SCALE,
+            volatility,
+            games: self.games + results.len() as u32,
+        }
+    }
+
+    /// The new volatility, found with the Illinois algorithm as in
+    /// Glickman's description of Glicko-2.
+    fn new_volatility(&self, phi: f64, v: f64, delta: f64) -> f64 {
+        let a = self.volatility.powi(2).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta.powi(2) - phi.powi(2) - v - ex) / (2.0 * (phi.powi(2) + v + ex).powi(2))
+                - (x - a) / TAU.powi(2)
+        };
+
+        let mut low = a;
+        let mut high = if delta.powi(2) > phi.powi(2) + v {
+            (delta.powi(2) - phi.powi(2) - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+        let (mut f_low, mut f_high) = (f(low), f(high));
+        while (high - low).abs() > 1e-6 {
+            let c = low + (low - high) * f_low / (f_high - f_low);
+            let f_c = f(c);
+            if f_c * f_high <= 0.0 {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.0;
+            }
+            high = c;
+            f_high = f_c;
+        }
+        (low / 2.0).exp()
+    }
+}
+
+/// The ratings of every bot seen so far, by name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ratings {
+    pub bots: BTreeMap<String, Rating>,
+}
+
+impl Ratings {
+    /// Reads the ratings, or starts afresh if the file doesn't exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Ratings, RatingsError> {
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(serde_json::from_str(&s)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Ratings::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RatingsError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Rating {
+        self.bots.get(name).copied().unwrap_or_default()
+    }
+
+    /// Updates the ratings of the bots in the tournament from its games.
+    /// Bots that didn't take part keep their ratings.
+    pub fn update(&mut self, standings: &Standings) {
+        let names: Vec<String> = standings
+            .entrants
+            .iter()
+            .map(|spec| spec.to_string())
+            .collect();
+        let mut results: BTreeMap<&str, Vec<(Rating, f64)>> = BTreeMap::new();
+        for &(first, second, result) in &standings.matches {
+            let pairs = [(first, second, result), (second, first, result.reversed())];
+            for &(bot, opponent, result) in &pairs {
+                let opponent = self.get(&names[opponent]);
+                let games = results.entry(&names[bot]).or_default();
+                games.extend((0..result.wins).map(|_| (opponent, 1.0)));
+                games.extend((0..result.draws).map(|_| (opponent, 0.5)));
+                games.extend((0..result.losses).map(|_| (opponent, 0.0)));
+            }
+        }
+        let updated: Vec<(String, Rating)> = results
+            .iter()
+            .map(|(name, results)| (name.to_string(), self.get(name).updated(results)))
+            .collect();
+        self.bots.extend(updated);
+    }
+}
+
+impl fmt::Display for Ratings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bots: Vec<(&String, &Rating)> = self.bots.iter().collect();
+        bots.sort_by(|a, b| b.1.rating.partial_cmp(&a.1.rating).unwrap());
+        let width = bots
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        writeln!(
+            f,
+            "{:width$}  {:>6}  {:>13}  {:>5}",
+            "Bot",
+            "Rating",
+            "95%",
+            "Games",
+            width = width
+        )?;
+        for (name, rating) in bots {
+            let (low, high) = rating.interval();
+            writeln!(
+                f,
+                "{:width$}  {:>6.0}  {:>6.0}-{:<6.0}  {:>5}",
+                name,
+                rating.rating,
+                low,
+                high,
+                rating.games,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RatingsError {
+    #[error("Couldn't access the ratings file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't read or write the ratings: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::tournament::MatchResult;
+    use crate::ai::AgentSpec;
+
+    #[test]
+    fn glickmans_example() {
+        let player = Rating {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+            games: 0,
+        };
+        let opponent = |rating, deviation| Rating {
+            rating,
+            deviation,
+            ..Rating::default()
+        };
+        let updated = player.updated(&[
+            (opponent(1400.0, 30.0), 1.0),
+            (opponent(1550.0, 100.0), 0.0),
+            (opponent(1700.0, 300.0), 0.0),
+        ]);
+        assert!((updated.rating - 1464.06).abs() < 0.01);
+        assert!((updated.deviation - 151.52).abs() < 0.01);
+        assert!((updated.volatility - 0.05999).abs() < 1e-5);
+        assert_eq!(updated.games, 3);
+    }
+
+    #[test]
+    fn rates_tournaments() {
+        let standings = Standings {
+            entrants: vec![AgentSpec::Greedy, AgentSpec::Random],
+            matches: vec![(
+                0,
+                1,
+                MatchResult {
+                    wins: 8,
+                    draws: 1,
+                    losses: 1,
+                },
+            )],
+        };
+        let mut ratings = Ratings::default();
+        ratings.update(&standings);
+        let (greedy, random) = (ratings.get("greedy"), ratings.get("random"));
+        assert!(greedy.rating > 1500.0 && random.rating < 1500.0);
+        assert!(greedy.deviation < 350.0);
+        assert_eq!(greedy.games, 10);
+        assert_eq!(ratings.to_string().lines().count(), 3);
+    }
+}
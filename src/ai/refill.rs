@@ -0,0 +1,84 @@
+//! What the market could show after a piece is taken from it. Searches that
+//! shouldn't peek at the deck order deal the uncovered piece themselves, once
+//! for each kind of piece nobody has seen.
+
+use crate::game::{Action, Game};
+use crate::piece::Piece;
+
+/// The deck whose top piece the action takes, uncovering the one below.
+pub(crate) fn uncovers(game: &Game, action: &Action) -> Option<usize> {
+    match action {
+        Action::PlacePiece(piece) => game
+            .decks
+            .iter()
+            .position(|deck| deck.last() == Some(&piece.piece)),
+        Action::RedrawMarket(deck) => Some(*deck as usize),
+        _ => None,
+    }
+}
+
+/// Each piece that could be at the top of the deck, which was just
+/// uncovered, with how likely it is. Anything not on offer in another deck
+/// could be there.
+pub(crate) fn refills(game: &Game, deck: usize) -> Vec<(Piece, f32)> {
+    let mut counts: Vec<(Piece, u32)> = Vec::new();
+    for piece in unseen(game, deck).map(|(_, _, piece)| piece) {
+        match counts.iter_mut().find(|(counted, _)| *counted == piece) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((piece, 1)),
+        }
+    }
+    let total: u32 = counts.iter().map(|(_, count)| count).sum();
+    counts
+        .into_iter()
+        .map(|(piece, count)| (piece, count as f32 / total as f32))
+        .collect()
+}
+
+/// Where the pieces nobody has seen are, as `(deck, index, piece)`, counting
+/// the top of `deck`.
+fn unseen(game: &Game, deck: usize) -> impl Iterator<Item = (usize, usize, Piece)> + '_ {
+    game.decks.iter().enumerate().flat_map(move |(i, pieces)| {
+        let hidden = if i == deck {
+            pieces.len()
+        } else {
+            pieces.len().saturating_sub(1)
+        };
+        pieces[..hidden]
+            .iter()
+            .enumerate()
+            .map(move |(j, &piece)| (i, j, piece))
+    })
+}
+
+/// Deals the piece to the top of the deck, swapping it with an unseen piece
+/// of that kind.
+pub(crate) fn reveal(game: &mut Game, deck: usize, piece: Piece) {
+    let (i, j, _) = unseen(game, deck)
+        .find(|&(_, _, unseen)| unseen == piece)
+        .expect("Only unseen pieces should be revealed");
+    let top = game.decks[deck].len() - 1;
+    game.decks[i][j] = game.decks[deck][top];
+    game.decks[deck][top] = piece;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn refills_count_unseen_pieces() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 3);
+        let seen = game.market();
+        game.decks[0].pop();
+        let refills = refills(&game, 0);
+        let total: f32 = refills.iter().map(|(_, chance)| chance).sum();
+        assert!((total - 1.0).abs() < 1e-5);
+
+        let (piece, _) = refills[0];
+        reveal(&mut game, 0, piece);
+        assert_eq!(game.market()[0], Some(piece));
+        assert_eq!(game.market()[1..], seen[1..]);
+    }
+}
@@ -0,0 +1,259 @@
+//! Bots written as Lua scripts, for trying out a heuristic without building
+//! anything.
+//!
+//! A script defines a global function `choose_turn(game)` returning one of
+//! `game:legal_turns()`, or any turn in the turn notation. `game` has these
+//! methods:
+//!
+//! - `notation()`, the position in the state notation.
+//! - `player()` and `players()`, the player to move and how many there are,
+//!   counting from 0.
+//! - `scores()`, everyone's score.
+//! - `round()`, turns played by each player so far.
+//! - `height(x, y)`, the height of the stack at a cell, from 0 to 5.
+//! - `legal_turns()`, the turns the player to move can play.
+//! - `preview(turn)`, the points the player to move would score with the
+//!   turn, or nil if it isn't legal.
+//! - `after(turn)`, the game once the turn is played, or nil if it isn't
+//!   legal.
+//!
+//! ```lua
+//! function choose_turn(game)
+//!     local best, most = nil, -1
+//!     for _, turn in ipairs(game:legal_turns()) do
+//!         if game:preview(turn) > most then
+//!             best, most = turn, game:preview(turn)
+//!         end
+//!     end
+//!     return best
+//! end
+//! ```
+//!
+//! A script failing or answering with anything but a legal turn plays the
+//! first legal turn instead, and the fault is kept to be shown.
+
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::path::Path;
+
+use mlua::{Function, Lua, UserData, UserDataMethods};
+use thiserror::Error;
+
+use super::ordering::turn_points;
+use super::{Agent, Limits};
+use crate::board::BoardPosition;
+use crate::game::{Game, Turn};
+
+pub struct ScriptAgent {
+    lua: Lua,
+    name: String,
+    faults: Vec<ScriptError>,
+}
+
+impl ScriptAgent {
+    /// Runs the script in the file, named after the file.
+    pub fn load(path: impl AsRef<Path>) -> Result<ScriptAgent, ScriptError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(ScriptError::Io)?;
+        let name = path
+            .file_stem()
+            .map_or("script".into(), |stem| stem.to_string_lossy().into_owned());
+        ScriptAgent::from_source(&source, &name)
+    }
+
+    /// Runs the script, which has to define `choose_turn`.
+    pub fn from_source(source: &str, name: &str) -> Result<ScriptAgent, ScriptError> {
+        let lua = Lua::new();
+        lua.load(source)
+            .set_name(name)
+            .exec()
+            .map_err(ScriptError::Lua)?;
+        lua.globals()
+            .get::<_, Function>("choose_turn")
+            .map_err(|_| ScriptError::NoChooseTurn)?;
+        Ok(ScriptAgent {
+            lua,
+            name: name.to_string(),
+            faults: Vec::new(),
+        })
+    }
+
+    /// Everything that went wrong in the turns so far.
+    pub fn faults(&self) -> &[ScriptError] {
+        &self.faults
+    }
+
+    /// The script's answer in the position, as it gave it.
+    fn ask(&self, game: &Game) -> Result<Turn, ScriptError> {
+        let choose_turn: Function = self
+            .lua
+            .globals()
+            .get("choose_turn")
+            .map_err(ScriptError::Lua)?;
+        let answer: String = choose_turn
+            .call(ScriptGame::new(game.clone()))
+            .map_err(ScriptError::Lua)?;
+        answer
+            .parse()
+            .map_err(|_| ScriptError::IllegalTurn(answer.clone()))
+    }
+}
+
+impl Agent for ScriptAgent {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Scripts run until they answer, whatever the limits.
+    fn choose_turn(&mut self, game: &Game, _limits: &Limits) -> Turn {
+        let legal = game.legal_turns();
+        match self.ask(game) {
+            Ok(turn) if legal.contains(&turn) => return turn,
+            Ok(turn) => self.faults.push(ScriptError::IllegalTurn(turn.to_string())),
+            Err(err) => self.faults.push(err),
+        }
+        legal
+            .into_iter()
+            .next()
+            .expect("The player to move should have a legal turn")
+    }
+}
+
+/// The game as the scripts see it, with its legal turns worked out the
+/// first time they are needed.
+struct ScriptGame {
+    game: Game,
+    legal: OnceCell<Vec<Turn>>,
+    legal_set: OnceCell<HashSet<Turn>>,
+}
+
+impl ScriptGame {
+    fn new(game: Game) -> ScriptGame {
+        ScriptGame {
+            game,
+            legal: OnceCell::new(),
+            legal_set: OnceCell::new(),
+        }
+    }
+
+    fn legal_turns(&self) -> &[Turn] {
+        self.legal.get_or_init(|| self.game.legal_turns())
+    }
+
+    fn legal(&self, turn: &str) -> Option<Turn> {
+        let turn: Turn = turn.parse().ok()?;
+        let legal = self
+            .legal_set
+            .get_or_init(|| self.legal_turns().iter().cloned().collect());
+        Some(turn).filter(|turn| legal.contains(turn))
+    }
+}
+
+impl UserData for ScriptGame {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("notation", |_, game, ()| Ok(game.game.to_notation()));
+        methods.add_method("player", |_, game, ()| Ok(game.game.next_player()));
+        methods.add_method("players", |_, game, ()| Ok(game.game.rules().player_count));
+        methods.add_method("scores", |_, game, ()| Ok(game.game.scores().to_vec()));
+        methods.add_method("round", |_, game, ()| Ok(game.game.round()));
+        methods.add_method("height", |_, game, (x, y): (i8, i8)| {
+            if !(0..6).contains(&x) || !(0..6).contains(&y) {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "({}, {}) isn't on the board",
+                    x, y
+                )));
+            }
+            Ok(game.game.board.height(BoardPosition::new(x, y)))
+        });
+        methods.add_method("legal_turns", |_, game, ()| {
+            Ok(game
+                .legal_turns()
+                .iter()
+                .map(Turn::to_string)
+                .collect::<Vec<_>>())
+        });
+        methods.add_method("preview", |_, game, turn: String| {
+            Ok(game.legal(&turn).map(|turn| turn_points(&game.game, &turn)))
+        });
+        methods.add_method("after", |_, game, turn: String| {
+            Ok(game.legal(&turn).map(|turn| {
+                let mut after = game.game.clone();
+                after
+                    .play_turn(turn)
+                    .expect("Legal turns should be playable");
+                ScriptGame::new(after)
+            }))
+        });
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ScriptError {
+    #[error("Couldn't read the script: {0}")]
+    Io(std::io::Error),
+    #[error("The script failed: {0}")]
+    Lua(mlua::Error),
+    #[error("The script doesn't define choose_turn.")]
+    NoChooseTurn,
+    #[error("The script played {0}, which isn't a legal turn.")]
+    IllegalTurn(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::GreedyAgent;
+    use crate::rules::Ruleset;
+
+    const GREEDY: &str = r#"
+        function choose_turn(game)
+            local best, most = nil, -1
+            for _, turn in ipairs(game:legal_turns()) do
+                if game:preview(turn) > most then
+                    best, most = turn, game:preview(turn)
+                end
+            end
+            assert(game:after(best):player() ~= game:player())
+            assert(#game:scores() == game:players() and game:preview("nonsense") == nil)
+            return best
+        end
+    "#;
+
+    #[test]
+    fn scripts_see_the_game() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 4);
+        let mut script = ScriptAgent::from_source(GREEDY, "greedy").unwrap();
+        for _ in 0..4 {
+            let turn = script.choose_turn(&game, &Limits::none());
+            assert_eq!(
+                turn_points(&game, &turn),
+                turn_points(
+                    &game,
+                    &GreedyAgent::new().choose_turn(&game, &Limits::none())
+                )
+            );
+            game.play_turn(turn).unwrap();
+        }
+        assert!(script.faults().is_empty());
+    }
+
+    #[test]
+    fn faults_fall_back_to_a_legal_turn() {
+        let game = Game::new_with_rules(Ruleset::new(2), 4);
+        for source in &[
+            "function choose_turn(game) return 'nonsense' end",
+            "function choose_turn(game) error('oops') end",
+        ] {
+            let mut script = ScriptAgent::from_source(source, "broken").unwrap();
+            assert_eq!(
+                script.choose_turn(&game, &Limits::none()),
+                game.legal_turns()[0]
+            );
+            assert_eq!(script.faults().len(), 1);
+        }
+        assert!(matches!(
+            ScriptAgent::from_source("x = 1", "empty"),
+            Err(ScriptError::NoChooseTurn)
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+//! Games between bots, played several at a time and written to disk, as the
+//! raw material for tuning the bots and training models.
+//!
+//! Each game is saved as `game-NNNNN.json` in the output directory, holding
+//! the replay, what the bot to move made of each position and how every
+//! player did in the end.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::playout::rewards;
+use super::{Agent, AgentSpec, InfoCallback, Limits, PresetError};
+use crate::game::Game;
+use crate::replay::Replay;
+use crate::rules::Ruleset;
+
+/// Turns after which a game is stopped when not told otherwise. Markers can
+/// be moved back and forth forever, so not every game ends by itself.
+pub const MAX_TURNS: u32 = 200;
+
+#[derive(Debug, Clone)]
+pub struct SelfPlayConfig {
+    pub games: usize,
+    pub rules: Ruleset,
+    /// The bots, one per seat. They move one seat along from each game to
+    /// the next, so every bot gets to play from every seat.
+    pub seats: Vec<AgentSpec>,
+    /// How long the bots think about each turn. Any info callback is
+    /// replaced to record the evaluations.
+    pub limits: Limits,
+    /// Games played at once, at least one.
+    pub threads: usize,
+    /// Game `i` is dealt with `seed + i`, and its bots are seeded from that.
+    pub seed: u64,
+    /// Turns after which a game is stopped and judged on its standings.
+    pub max_turns: u32,
+    /// Where to save the games, if anywhere.
+    pub output: Option<PathBuf>,
+}
+
+impl SelfPlayConfig {
+    /// `games` games between the bots, with the standard rules for that many
+    /// players, on every core and without saving them.
+    pub fn new(seats: Vec<AgentSpec>, games: usize) -> SelfPlayConfig {
+        SelfPlayConfig {
+            games,
+            rules: Ruleset::new(seats.len() as u8),
+            seats,
+            limits: Limits::none(),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: 0,
+            max_turns: MAX_TURNS,
+            output: None,
+        }
+    }
+}
+
+/// One game played by [`run`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfPlayGame {
+    pub replay: Replay,
+    /// The bot in each seat, as an index into [`SelfPlayConfig::seats`].
+    pub seats: Vec<usize>,
+    /// What the bot to move thought of the position before each turn, for
+    /// its own side and in its own units, if it said.
+    pub evaluations: Vec<Option<f32>>,
+    /// How well each seat did, as in [`rewards`].
+    pub rewards: Vec<f64>,
+    /// Whether the game ended, rather than being stopped after
+    /// [`max_turns`](SelfPlayConfig::max_turns).
+    pub finished: bool,
+}
+
+impl SelfPlayGame {
+    pub fn load(path: impl AsRef<Path>) -> Result<SelfPlayGame, SelfPlayError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SelfPlayError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Plays the games and saves them if the config says where to. Returns the
+/// games in the order of their seeds.
+pub fn run(config: &SelfPlayConfig) -> Result<Vec<SelfPlayGame>, SelfPlayError> {
+    if config.seats.len() != config.rules.player_count as usize {
+        return Err(SelfPlayError::SeatCount {
+            rules: config.rules.player_count,
+            seats: config.seats.len(),
+        });
+    }
+    if let Some(output) = &config.output {
+        fs::create_dir_all(output)?;
+    }
+
+    // Each worker keeps the games it plays to itself until the end, so the
+    // only thing the workers share is the counter handing out the games.
+    let next = AtomicUsize::new(0);
+    let mut played = thread::scope(|scope| {
+        let workers: Vec<_> = (0..config.threads.max(1))
+            .map(|_| {
+                scope.spawn(|| -> Result<Vec<(usize, SelfPlayGame)>, SelfPlayError> {
+                    let mut played = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= config.games {
+                            return Ok(played);
+                        }
+                        let game = play(config, index)?;
+                        if let Some(output) = &config.output {
+                            game.save(output.join(format!("game-{:05}.json", index)))?;
+                        }
+                        played.push((index, game));
+                    }
+                })
+            })
+            .collect();
+        workers.into_iter().try_fold(Vec::new(), |mut all, worker| {
+            all.extend(worker.join().expect("Self-play games shouldn't panic")?);
+            Ok::<_, SelfPlayError>(all)
+        })
+    })?;
+    played.sort_by_key(|(index, _)| *index);
+    Ok(played.into_iter().map(|(_, game)| game).collect())
+}
+
+/// The sum of the rewards of each bot in [`SelfPlayConfig::seats`] over the
+/// games.
+pub fn scores(games: &[SelfPlayGame], bots: usize) -> Vec<f64> {
+    let mut scores = vec![0.0; bots];
+    for game in games {
+        for (&bot, reward) in game.seats.iter().zip(&game.rewards) {
+            scores[bot] += reward;
+        }
+    }
+    scores
+}
+
+fn play(config: &SelfPlayConfig, index: usize) -> Result<SelfPlayGame, SelfPlayError> {
+    let seed = config.seed.wrapping_add(index as u64);
+    let bots = config.seats.len();
+    let seats: Vec<usize> = (0..bots).map(|seat| (seat + index) % bots).collect();
+    let mut agents: Vec<Box<dyn Agent + Send>> = seats
+        .iter()
+        .enumerate()
+        .map(|(seat, &bot)| {
+            config.seats[bot].build(seed.wrapping_mul(bots as u64).wrapping_add(seat as u64))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let evaluation = Arc::new(Mutex::new(None));
+    let limits = {
+        let evaluation = evaluation.clone();
+        config
+            .limits
+            .clone()
+            .with_info(InfoCallback::new(move |info| {
+                *evaluation.lock().unwrap() = Some(info.score);
+            }))
+    };
+
+    let names = agents
+        .iter()
+        .map(|agent| agent.name().to_string())
+        .collect();
+    let mut replay = Replay::new(names, config.rules.clone(), seed);
+    let mut game = Game::new_with_rules(config.rules.clone(), seed);
+    let mut evaluations = Vec::new();
+    while !game.is_finished() && replay.turns.len() < config.max_turns as usize {
+        let player = game.next_player() as usize;
+        *evaluation.lock().unwrap() = None;
+        let turn = agents[player].choose_turn(&game, &limits);
+        evaluations.push(evaluation.lock().unwrap().take());
+        replay
+            .play(&mut game, turn)
+            .expect("Bots should only play legal turns");
+    }
+
+    Ok(SelfPlayGame {
+        rewards: rewards(&game),
+        finished: game.is_finished(),
+        replay,
+        seats,
+        evaluations,
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum SelfPlayError {
+    #[error("The rules are for {rules} players but {seats} bots were given.")]
+    SeatCount { rules: u8, seats: usize },
+    #[error("Couldn't write the games: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't save or read a game: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Couldn't set up a bot: {0}")]
+    Bot(#[from] PresetError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_and_saves_games() {
+        let output =
+            std::env::temp_dir().join(format!("passtally-selfplay-{}", std::process::id()));
+        let config = SelfPlayConfig {
+            threads: 2,
+            max_turns: 6,
+            seed: 4,
+            output: Some(output.clone()),
+            ..SelfPlayConfig::new(vec![AgentSpec::Random, AgentSpec::Random], 3)
+        };
+        let games = run(&config).unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].seats, vec![0, 1]);
+        assert_eq!(games[1].seats, vec![1, 0]);
+        assert_eq!(games[1].replay.players, vec!["random", "random"]);
+        for game in &games {
+            assert_eq!(game.evaluations.len(), game.replay.turns.len());
+            assert!(game.replay.play_back().is_ok());
+        }
+        assert_eq!(
+            SelfPlayGame::load(output.join("game-00002.json")).unwrap(),
+            games[2]
+        );
+        assert!(scores(&games, 2).iter().sum::<f64>() <= 3.0);
+        fs::remove_dir_all(output).unwrap();
+
+        let config = SelfPlayConfig {
+            rules: Ruleset::new(3),
+            ..config
+        };
+        assert!(matches!(run(&config), Err(SelfPlayError::SeatCount { .. })));
+    }
+}
@@ -0,0 +1,185 @@
+//! Many games between bots boiled down to statistics: how the bots score,
+//! how long games last and how much moving first is worth. For balancing
+//! the rules as much as for comparing bots.
+
+use std::fmt;
+
+use super::selfplay::{self, SelfPlayConfig, SelfPlayError, SelfPlayGame};
+use super::AgentSpec;
+
+/// Plays `games` games between the bots, one per seat, moving them one seat
+/// along from each game to the next. Game `i` is dealt with `seed + i`.
+pub fn run_many(
+    games: usize,
+    agents: &[AgentSpec],
+    seed: u64,
+) -> Result<SimulationReport, SelfPlayError> {
+    let config = SelfPlayConfig {
+        seed,
+        ..SelfPlayConfig::new(agents.to_vec(), games)
+    };
+    run_config(&config)
+}
+
+/// Like [`run_many`], with every detail of the games up to the config.
+pub fn run_config(config: &SelfPlayConfig) -> Result<SimulationReport, SelfPlayError> {
+    let games = selfplay::run(config)?;
+    Ok(SimulationReport::new(&config.seats, &games))
+}
+
+/// The count, spread and extremes of some numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn new(values: &[f64]) -> Distribution {
+        if values.is_empty() {
+            return Distribution::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let variance = sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count as f64;
+        let median = if count % 2 == 1 {
+            sorted[count / 2]
+        } else {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        };
+        Distribution {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+            min: sorted[0],
+            median,
+            max: sorted[count - 1],
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} ± {:.1} (min {}, median {}, max {})",
+            self.mean, self.std_dev, self.min, self.median, self.max
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport {
+    pub bots: Vec<AgentSpec>,
+    pub games: usize,
+    /// Games that ended by themselves rather than being stopped.
+    pub finished: usize,
+    /// Each bot's final scores.
+    pub scores: Vec<Distribution>,
+    /// Turns played per game.
+    pub lengths: Distribution,
+    /// The average reward of each bot, as in
+    /// [`rewards`](super::playout::rewards).
+    pub bot_rewards: Vec<f64>,
+    /// The average reward of whoever sat in each seat, the first to move
+    /// first.
+    pub seat_rewards: Vec<f64>,
+}
+
+impl SimulationReport {
+    pub fn new(bots: &[AgentSpec], games: &[SelfPlayGame]) -> SimulationReport {
+        let mut scores = vec![Vec::new(); bots.len()];
+        let mut bot_rewards = vec![0.0; bots.len()];
+        let mut seat_rewards = vec![0.0; bots.len()];
+        for game in games {
+            let final_scores = game
+                .replay
+                .final_scores()
+                .expect("Self-play only records legal turns");
+            for (seat, &bot) in game.seats.iter().enumerate() {
+                scores[bot].push(final_scores[seat] as f64);
+                bot_rewards[bot] += game.rewards[seat];
+                seat_rewards[seat] += game.rewards[seat];
+            }
+        }
+        let average = |total: f64| total / games.len().max(1) as f64;
+        let lengths: Vec<f64> = games
+            .iter()
+            .map(|game| game.replay.turns.len() as f64)
+            .collect();
+        SimulationReport {
+            bots: bots.to_vec(),
+            games: games.len(),
+            finished: games.iter().filter(|game| game.finished).count(),
+            scores: scores
+                .iter()
+                .map(|scores| Distribution::new(scores))
+                .collect(),
+            lengths: Distribution::new(&lengths),
+            bot_rewards: bot_rewards.into_iter().map(average).collect(),
+            seat_rewards: seat_rewards.into_iter().map(average).collect(),
+        }
+    }
+
+    /// How much more than an even share of the rewards the first seat gets.
+    pub fn first_player_advantage(&self) -> f64 {
+        match self.seat_rewards.first() {
+            Some(first) => first - 1.0 / self.seat_rewards.len() as f64,
+            None => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} games, {} finished", self.games, self.finished)?;
+        writeln!(f, "Turns per game: {}", self.lengths)?;
+        for ((bot, scores), reward) in self.bots.iter().zip(&self.scores).zip(&self.bot_rewards) {
+            writeln!(f, "{}: reward {:.3}, score {}", bot, reward, scores)?;
+        }
+        for (seat, reward) in self.seat_rewards.iter().enumerate() {
+            writeln!(f, "Seat {}: reward {:.3}", seat + 1, reward)?;
+        }
+        writeln!(
+            f,
+            "First player advantage: {:+.3}",
+            self.first_player_advantage()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distributions() {
+        let distribution = Distribution::new(&[1.0, 3.0, 2.0, 6.0]);
+        assert_eq!(distribution.count, 4);
+        assert_eq!(distribution.mean, 3.0);
+        assert_eq!(distribution.median, 2.5);
+        assert_eq!((distribution.min, distribution.max), (1.0, 6.0));
+        assert_eq!(Distribution::new(&[]), Distribution::default());
+    }
+
+    #[test]
+    fn simulates_games() {
+        let config = SelfPlayConfig {
+            max_turns: 10,
+            ..SelfPlayConfig::new(vec![AgentSpec::Random, AgentSpec::Greedy], 4)
+        };
+        let report = run_config(&config).unwrap();
+        assert_eq!(report.games, 4);
+        assert_eq!(report.scores[0].count, 4);
+        assert!(report.lengths.max <= 10.0);
+        let total: f64 = report.seat_rewards.iter().sum();
+        assert!((total - report.bot_rewards.iter().sum::<f64>()).abs() < 1e-9);
+        assert!(report.to_string().contains("First player advantage"));
+    }
+}
@@ -0,0 +1,169 @@
+//! Sequential probability ratio tests between two bots: rather than playing
+//! a fixed number of games, play until the results say clearly enough
+//! whether the first bot is at least `elo1` stronger than the second or at
+//! most `elo0`.
+//!
+//! The log likelihood ratio is the usual normal approximation over wins,
+//! draws and losses, as used by engine testing frameworks.
+
+use super::selfplay::{self, SelfPlayError};
+use super::tournament::{MatchResult, TournamentConfig};
+
+/// The hypotheses of the test, in Elo, and how often it may be wrong.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// How often the test may pass a bot that isn't `elo1` stronger.
+    pub alpha: f64,
+    /// How often the test may fail a bot that is `elo1` stronger.
+    pub beta: f64,
+}
+
+/// Where a test stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtOutcome {
+    /// The first bot is stronger by `elo1` or more.
+    Passed,
+    /// The first bot is stronger by `elo0` at most.
+    Failed,
+    /// More games are needed.
+    Continue,
+}
+
+impl Sprt {
+    /// A test between `elo0` and `elo1`, wrong 5% of the time each way.
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    /// The lower and upper bounds of the log likelihood ratio.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The log likelihood ratio of the results, from the first bot's side.
+    /// Half a win and half a loss are added to the results, so that a run
+    /// of the same result every game still moves the ratio.
+    pub fn llr(&self, result: &MatchResult) -> f64 {
+        let wins = result.wins as f64 + 0.5;
+        let draws = result.draws as f64;
+        let losses = result.losses as f64 + 0.5;
+        let games = wins + draws + losses;
+        let score = (wins + draws / 2.0) / games;
+        let variance =
+            (wins * (1.0 - score).powi(2) + draws * (0.5 - score).powi(2) + losses * score.powi(2))
+                / games;
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        games * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn outcome(&self, result: &MatchResult) -> SprtOutcome {
+        let llr = self.llr(result);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtOutcome::Passed
+        } else if llr <= lower {
+            SprtOutcome::Failed
+        } else {
+            SprtOutcome::Continue
+        }
+    }
+}
+
+/// The share of the points expected by a bot that much stronger.
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The difference in Elo the results point to, from the first bot's side.
+/// Infinite after winning or losing every game.
+pub fn elo(result: &MatchResult) -> f64 {
+    let score = result.points() / result.games().max(1) as f64;
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Plays the first two bots of the config against each other, `games` at a
+/// time, until the test decides or `max_games` have been played. Calls
+/// `progress` after every batch. With an output directory, each batch is
+/// saved in its own directory, `batch-NNN`.
+pub fn run(
+    config: &TournamentConfig,
+    sprt: &Sprt,
+    max_games: usize,
+    mut progress: impl FnMut(&MatchResult, f64),
+) -> Result<(MatchResult, SprtOutcome), SelfPlayError> {
+    let mut result = MatchResult::default();
+    let mut batch = 0;
+    while (result.games() as usize) < max_games {
+        let games = config.games.max(1).min(max_games - result.games() as usize);
+        let mut selfplay = config.selfplay(0, 1, games);
+        selfplay.seed = config.seed.wrapping_add(result.games() as u64);
+        selfplay.output = config
+            .output
+            .as_ref()
+            .map(|output| output.join(format!("batch-{:03}", batch)));
+        let played = MatchResult::from_games(&selfplay::run(&selfplay)?);
+        result.add(played);
+        batch += 1;
+
+        progress(&result, sprt.llr(&result));
+        let outcome = sprt.outcome(&result);
+        if outcome != SprtOutcome::Continue {
+            return Ok((result, outcome));
+        }
+    }
+    Ok((result, SprtOutcome::Continue))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AgentSpec;
+
+    #[test]
+    fn decides_clear_results() {
+        let sprt = Sprt::new(0.0, 10.0);
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3);
+
+        let even = MatchResult {
+            wins: 10,
+            draws: 0,
+            losses: 10,
+        };
+        assert_eq!(sprt.outcome(&even), SprtOutcome::Continue);
+        assert_eq!(elo(&even), 0.0);
+        let crushing = MatchResult {
+            wins: 900,
+            draws: 0,
+            losses: 100,
+        };
+        assert_eq!(sprt.outcome(&crushing), SprtOutcome::Passed);
+        assert_eq!(sprt.outcome(&crushing.reversed()), SprtOutcome::Failed);
+        let flawless = MatchResult {
+            wins: 40,
+            draws: 0,
+            losses: 0,
+        };
+        assert_eq!(sprt.outcome(&flawless), SprtOutcome::Passed);
+
+        let config = TournamentConfig {
+            threads: 2,
+            max_turns: 4,
+            ..TournamentConfig::new(vec![AgentSpec::Random, AgentSpec::Random], 2)
+        };
+        let mut batches = 0;
+        let (result, _) = run(&config, &sprt, 4, |_, _| batches += 1).unwrap();
+        assert!(result.games() <= 4);
+        assert_eq!(batches, result.games() / 2);
+    }
+}
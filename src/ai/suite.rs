@@ -0,0 +1,374 @@
+//! A suite of test positions with known best turns, for a quick read on a
+//! bot's strength without playing whole tournaments.
+//!
+//! Each line of a suite file is a position in the state notation, with the
+//! order of the decks as [`Game::to_full_notation`] writes it, the best
+//! turn, the points the best turn scores, and a comma separated list of tags,
+//! all separated by `|`. Empty lines and lines starting with `#` are ignored.
+//!
+//! The best turns of the standard suite come from [`best_turn_two_deep`],
+//! which knows the decks as dealt. Scoring the most points right away isn't
+//! always best, so a bot solves a position with a turn worth as much as the
+//! best turn two turns deep, not just one scoring as much.
+
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use super::{Agent, Limits};
+use crate::game::{Game, Turn};
+use crate::notation::NotationError;
+
+const STANDARD: &str = include_str!("../../assets/positions/standard.txt");
+
+#[derive(Debug, Clone)]
+pub struct TestPosition {
+    pub notation: String,
+    pub best: Turn,
+    /// Points scored by the best turn.
+    pub points: u32,
+    pub tags: Vec<String>,
+}
+
+impl TestPosition {
+    pub fn game(&self) -> Game {
+        Game::from_notation(&self.notation).expect("Suite positions are checked when parsed")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Suite {
+    pub positions: Vec<TestPosition>,
+}
+
+impl Suite {
+    /// The positions shipped with the game.
+    pub fn standard() -> Suite {
+        Suite::parse(STANDARD).expect("The standard suite should be valid")
+    }
+
+    pub fn parse(s: &str) -> Result<Suite, SuiteError> {
+        let mut positions = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+            if fields.len() != 4 {
+                return Err(SuiteError::FieldCount(i + 1));
+            }
+            let bad_notation = |err| SuiteError::Notation(i + 1, err);
+
+            let mut game = Game::from_notation(fields[0]).map_err(bad_notation)?;
+            let best: Turn = fields[1].parse().map_err(bad_notation)?;
+            game.play_turn(best.clone())
+                .map_err(|_| SuiteError::IllegalTurn(i + 1))?;
+            positions.push(TestPosition {
+                notation: fields[0].to_string(),
+                best,
+                points: fields[2]
+                    .parse()
+                    .map_err(|_| SuiteError::BadPoints(i + 1))?,
+                tags: fields[3]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect(),
+            });
+        }
+        Ok(Suite { positions })
+    }
+
+    /// Only the positions with the tag.
+    pub fn with_tag(&self, tag: &str) -> Suite {
+        Suite {
+            positions: self
+                .positions
+                .iter()
+                .filter(|position| position.tags.iter().any(|t| t == tag))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Asks the bot for a turn in every position. A position counts as solved
+    /// when the bot's turn leaves the same position as the best turn, or is
+    /// worth as much by [`two_turn_value`]. Fails on positions built by hand
+    /// that don't parse or whose best turn can't be played.
+    pub fn run(&self, mut bot: impl FnMut(&Game) -> Turn) -> Result<SuiteReport, SuiteError> {
+        let mut results = Vec::with_capacity(self.positions.len());
+        for (i, position) in self.positions.iter().enumerate() {
+            let game = Game::from_notation(&position.notation)
+                .map_err(|err| SuiteError::BadPosition(i, err))?;
+            let mut best = game.clone();
+            best.play_turn(position.best.clone())
+                .map_err(|_| SuiteError::BadBestTurn(i))?;
+
+            let start = Instant::now();
+            let turn = bot(&game);
+            let time = start.elapsed();
+
+            let mut after = game.clone();
+            let points = after
+                .play_turn(turn.clone())
+                .ok()
+                .map(|outcome| outcome.points);
+            let solved = points.is_some()
+                && (after == best
+                    || two_turn_value(&game, turn.clone())
+                        >= two_turn_value(&game, position.best.clone()));
+            results.push(PositionResult {
+                solved,
+                turn,
+                points,
+                time,
+            });
+        }
+        Ok(SuiteReport { results })
+    }
+
+    /// Like [`run`](Suite::run), asking the agent with the same limits in
+    /// every position.
+    pub fn run_agent(
+        &self,
+        agent: &mut impl Agent,
+        limits: &Limits,
+    ) -> Result<SuiteReport, SuiteError> {
+        self.run(|game| agent.choose_turn(game, limits))
+    }
+}
+
+/// The best turn for the player to move, judged only by the points it scores
+/// right away, found by trying every legal turn.
+pub fn best_scoring_turn(game: &Game) -> Option<(Turn, u32)> {
+    let mut best: Option<(Turn, u32)> = None;
+    for turn in game.legal_turns() {
+        let points = game
+            .clone()
+            .play_turn(turn.clone())
+            .expect("Legal turns should be playable")
+            .points;
+        match &best {
+            Some((_, best_points)) if *best_points >= points => {}
+            _ => best = Some((turn, points)),
+        }
+    }
+    best
+}
+
+/// What the turn is worth looking two turns deep: the points it scores less
+/// the most the next player can score in reply, with the decks as dealt.
+/// `None` if the turn can't be played.
+pub fn two_turn_value(game: &Game, turn: Turn) -> Option<i64> {
+    let mut after = game.clone();
+    let points = after.play_turn(turn).ok()?.points;
+    Some(points as i64 - reply_points(&after, u32::MAX) as i64)
+}
+
+/// The best turn for the player to move by [`two_turn_value`], with the
+/// points it scores right away. Of equally good turns, the one scoring more
+/// right away is preferred. Slow: it tries every reply to each turn that
+/// could still be the best, so it is for building suites, not for bots.
+pub fn best_turn_two_deep(game: &Game) -> Option<(Turn, u32)> {
+    let mut turns: Vec<(Turn, u32)> = game
+        .legal_turns_iter()
+        .map(|turn| {
+            let points = game
+                .clone()
+                .play_turn(turn.clone())
+                .expect("Legal turns should be playable")
+                .points;
+            (turn, points)
+        })
+        .collect();
+    turns.sort_by_key(|&(_, points)| Reverse(points));
+
+    let mut best: Option<(Turn, u32, i64)> = None;
+    for (turn, points) in turns {
+        // A reply scores nothing at worst, so a turn is worth at most its
+        // own points, and only a reply scoring less than `enough` makes
+        // this turn the best so far.
+        let enough = match &best {
+            Some((_, _, value)) if points as i64 <= *value => break,
+            Some((_, _, value)) => (points as i64 - value) as u32,
+            None => u32::MAX,
+        };
+        let mut after = game.clone();
+        after
+            .play_turn(turn.clone())
+            .expect("Legal turns should be playable");
+        let value = points as i64 - reply_points(&after, enough) as i64;
+        match &best {
+            Some((_, _, best)) if *best >= value => {}
+            _ => best = Some((turn, points, value)),
+        }
+    }
+    best.map(|(turn, points, _)| (turn, points))
+}
+
+/// The most points the player to move can score this turn, looking no
+/// further once `enough` is found.
+fn reply_points(game: &Game, enough: u32) -> u32 {
+    if game.is_finished() {
+        return 0;
+    }
+    let mut best = 0;
+    for turn in game.legal_turns_iter() {
+        let points = game
+            .clone()
+            .play_turn(turn)
+            .expect("Legal turns should be playable")
+            .points;
+        best = best.max(points);
+        if best >= enough {
+            break;
+        }
+    }
+    best
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionResult {
+    pub turn: Turn,
+    /// Points the bot's turn scored, or `None` if it was illegal.
+    pub points: Option<u32>,
+    pub solved: bool,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct SuiteReport {
+    /// One result per position, in suite order.
+    pub results: Vec<PositionResult>,
+}
+
+impl SuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    /// The fraction of positions solved.
+    pub fn accuracy(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        self.solved() as f64 / self.results.len() as f64
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.results.iter().map(|result| result.time).sum()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SuiteError {
+    #[error("Line {0} doesn't have 4 fields separated by '|'.")]
+    FieldCount(usize),
+    #[error("Line {0} has a malformed position or turn: {1}")]
+    Notation(usize, NotationError),
+    #[error("Line {0} has malformed points.")]
+    BadPoints(usize),
+    #[error("Line {0} has a best turn that can't be played in its position.")]
+    IllegalTurn(usize),
+    #[error("Position {0} isn't a valid state: {1}")]
+    BadPosition(usize, NotationError),
+    #[error("Position {0} has a best turn that can't be played in it.")]
+    BadBestTurn(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_suite_is_consistent() {
+        let suite = Suite::standard();
+        assert!(suite.positions.len() >= 10);
+
+        // The reference answers solve every position.
+        let mut positions = suite.positions.iter();
+        let report = suite
+            .run(|_| positions.next().unwrap().best.clone())
+            .unwrap();
+        assert_eq!(report.solved(), suite.positions.len());
+        for (position, result) in suite.positions.iter().zip(&report.results) {
+            assert_eq!(result.points, Some(position.points));
+        }
+    }
+
+    #[test]
+    fn best_turns_are_found_two_turns_deep() {
+        let suite = Suite::standard().with_tag("quick");
+        assert!(!suite.positions.is_empty());
+        let mut greedy_misses = 0;
+        for position in &suite.positions {
+            let game = position.game();
+            let best = two_turn_value(&game, position.best.clone());
+            let (turn, points) = best_turn_two_deep(&game).unwrap();
+            assert_eq!(two_turn_value(&game, turn), best, "{}", position.notation);
+            assert_eq!(points, position.points, "{}", position.notation);
+
+            // Scoring the most points right away is never better.
+            let (greedy, _) = best_scoring_turn(&game).unwrap();
+            let greedy = two_turn_value(&game, greedy);
+            assert!(greedy <= best, "{}", position.notation);
+            if greedy < best {
+                greedy_misses += 1;
+            }
+        }
+        assert!(greedy_misses > 0);
+    }
+
+    #[test]
+    fn illegal_turns_are_unsolved() {
+        let suite = Suite::standard();
+        let report = suite.run(|_| "M:0>1+M:0>1".parse().unwrap()).unwrap();
+        assert_eq!(report.solved(), 0);
+        assert!(report.results.iter().all(|result| result.points.is_none()));
+        assert_eq!(report.accuracy(), 0.0);
+    }
+
+    #[test]
+    fn malformed_suites() {
+        assert!(matches!(
+            Suite::parse("6/6/6/6/6/6 | M:0>1+M:0>1 | 0"),
+            Err(SuiteError::FieldCount(1))
+        ));
+        assert!(matches!(
+            Suite::parse("# comment\n\n6/6/6/6/6/6 | M:0>1+M:0>1 | 0 | opening"),
+            Err(SuiteError::Notation(3, _))
+        ));
+        let position = Suite::standard().positions[0].clone();
+        let illegal = format!("{} | M:0>1+M:0>1 | 0 | opening", position.notation);
+        assert!(matches!(
+            Suite::parse(&illegal),
+            Err(SuiteError::IllegalTurn(1))
+        ));
+
+        // Positions built by hand are only checked when run.
+        let first = |game: &Game| game.legal_turns_iter().next().unwrap();
+        let bad_turn = TestPosition {
+            best: "M:0>1+M:0>1".parse().unwrap(),
+            ..position.clone()
+        };
+        let suite = Suite {
+            positions: vec![position.clone(), bad_turn],
+        };
+        assert!(matches!(suite.run(first), Err(SuiteError::BadBestTurn(1))));
+        let bad_position = TestPosition {
+            notation: "6/6/6/6/6/6".to_string(),
+            ..position
+        };
+        let suite = Suite {
+            positions: vec![bad_position],
+        };
+        assert!(matches!(
+            suite.run(first),
+            Err(SuiteError::BadPosition(0, _))
+        ));
+    }
+}
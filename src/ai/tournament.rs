@@ -0,0 +1,254 @@
+//! Tournaments between bots: every pairing plays a match of two player
+//! games through [`selfplay`](super::selfplay), the bots taking turns at
+//! moving first, and the results are gathered into a table.
+//!
+//! With an output directory, the games of each pairing are saved in their
+//! own directory, `pairing-AA-BB` for the bots at indices `AA` and `BB`.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use super::selfplay::{self, SelfPlayConfig, SelfPlayError, SelfPlayGame};
+use super::{AgentSpec, Limits};
+use crate::rules::Ruleset;
+
+/// Who plays whom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Everyone against everyone.
+    RoundRobin,
+    /// The first bot against each of the others.
+    Gauntlet,
+}
+
+impl Format {
+    /// The pairings for that many bots, as indices, the earlier bot first.
+    pub fn pairings(self, bots: usize) -> Vec<(usize, usize)> {
+        match self {
+            Format::RoundRobin => (0..bots)
+                .flat_map(|first| (first + 1..bots).map(move |second| (first, second)))
+                .collect(),
+            Format::Gauntlet => (1..bots).map(|second| (0, second)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentConfig {
+    pub entrants: Vec<AgentSpec>,
+    pub format: Format,
+    /// Games per pairing. The bots swap seats from one game to the next, so
+    /// this should be even.
+    pub games: usize,
+    pub limits: Limits,
+    pub threads: usize,
+    /// Pairing `i` plays its games with seeds from `seed + i * games` on.
+    pub seed: u64,
+    pub max_turns: u32,
+    pub output: Option<PathBuf>,
+}
+
+impl TournamentConfig {
+    /// A round robin of `games` games per pairing, on every core and
+    /// without saving the games.
+    pub fn new(entrants: Vec<AgentSpec>, games: usize) -> TournamentConfig {
+        let selfplay = SelfPlayConfig::new(vec![AgentSpec::Random, AgentSpec::Random], games);
+        TournamentConfig {
+            entrants,
+            format: Format::RoundRobin,
+            games,
+            limits: selfplay.limits,
+            threads: selfplay.threads,
+            seed: selfplay.seed,
+            max_turns: selfplay.max_turns,
+            output: None,
+        }
+    }
+
+    /// The self-play config for a match between two of the bots.
+    pub fn selfplay(&self, first: usize, second: usize, games: usize) -> SelfPlayConfig {
+        let index = self
+            .format
+            .pairings(self.entrants.len())
+            .iter()
+            .position(|&pairing| pairing == (first, second))
+            .unwrap_or(0);
+        SelfPlayConfig {
+            rules: Ruleset::new(2),
+            limits: self.limits.clone(),
+            threads: self.threads,
+            seed: self.seed.wrapping_add(index as u64 * self.games as u64),
+            max_turns: self.max_turns,
+            output: self
+                .output
+                .as_ref()
+                .map(|output| output.join(format!("pairing-{:02}-{:02}", first, second))),
+            ..SelfPlayConfig::new(
+                vec![self.entrants[first].clone(), self.entrants[second].clone()],
+                games,
+            )
+        }
+    }
+}
+
+/// How a match between two bots went, from the first bot's side.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchResult {
+    /// Tallies games played by a self-play config with the first bot in
+    /// seat 0.
+    pub fn from_games(games: &[SelfPlayGame]) -> MatchResult {
+        let mut result = MatchResult::default();
+        for game in games {
+            let seat = game.seats.iter().position(|&bot| bot == 0).unwrap_or(0);
+            let reward = game.rewards[seat];
+            if reward >= 1.0 {
+                result.wins += 1;
+            } else if reward <= 0.0 {
+                result.losses += 1;
+            } else {
+                result.draws += 1;
+            }
+        }
+        result
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Wins plus half the draws.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    /// The same match from the second bot's side.
+    pub fn reversed(self) -> MatchResult {
+        MatchResult {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    pub fn add(&mut self, other: MatchResult) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+/// The results of [`run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standings {
+    pub entrants: Vec<AgentSpec>,
+    /// Every pairing played, with its result from the first bot's side.
+    pub matches: Vec<(usize, usize, MatchResult)>,
+}
+
+impl Standings {
+    /// Everything a bot played, added up.
+    pub fn total(&self, bot: usize) -> MatchResult {
+        let mut total = MatchResult::default();
+        for &(first, second, result) in &self.matches {
+            if first == bot {
+                total.add(result);
+            } else if second == bot {
+                total.add(result.reversed());
+            }
+        }
+        total
+    }
+
+    /// The bots from most to fewest points.
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.entrants.len()).collect();
+        ranking.sort_by(|&a, &b| {
+            self.total(b)
+                .points()
+                .partial_cmp(&self.total(a).points())
+                .unwrap()
+        });
+        ranking
+    }
+}
+
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .entrants
+            .iter()
+            .map(|spec| spec.to_string().len())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        writeln!(
+            f,
+            "{:>2}  {:width$}  {:>5}  {:>5}  {:>5}  {:>6}  {:>6}",
+            "#",
+            "Bot",
+            "Games",
+            "Wins",
+            "Draws",
+            "Losses",
+            "Score",
+            width = width
+        )?;
+        for (place, bot) in self.ranking().into_iter().enumerate() {
+            let total = self.total(bot);
+            let score = total.points() / total.games().max(1) as f64;
+            writeln!(
+                f,
+                "{:>2}  {:width$}  {:>5}  {:>5}  {:>5}  {:>6}  {:>5.1}%",
+                place + 1,
+                self.entrants[bot].to_string(),
+                total.games(),
+                total.wins,
+                total.draws,
+                total.losses,
+                score * 100.0,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays every pairing of the tournament, one after the other.
+pub fn run(config: &TournamentConfig) -> Result<Standings, SelfPlayError> {
+    let mut matches = Vec::new();
+    for (first, second) in config.format.pairings(config.entrants.len()) {
+        let games = selfplay::run(&config.selfplay(first, second, config.games))?;
+        matches.push((first, second, MatchResult::from_games(&games)));
+    }
+    Ok(Standings {
+        entrants: config.entrants.clone(),
+        matches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        assert_eq!(Format::RoundRobin.pairings(3), vec![(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(Format::Gauntlet.pairings(3), vec![(0, 1), (0, 2)]);
+
+        let config = TournamentConfig {
+            threads: 2,
+            max_turns: 4,
+            ..TournamentConfig::new(vec![AgentSpec::Random; 3], 2)
+        };
+        let standings = run(&config).unwrap();
+        assert_eq!(standings.matches.len(), 3);
+        assert!((0..3).all(|bot| standings.total(bot).games() == 4));
+        assert_eq!(standings.to_string().lines().count(), 4);
+    }
+}
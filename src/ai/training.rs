@@ -0,0 +1,106 @@
+//! Self-play games as flat tables of numbers, for training policy and value
+//! models outside Rust.
+//!
+//! Every position before a turn becomes a row: the features of the position
+//! as seen by the player to move, as in [`position_features`], the turn they
+//! chose as two action indices, the evaluation of the bot that chose it and
+//! how the player did in the end. [`columns`] names the columns, in order.
+//!
+//! The rows can be written as CSV, for `numpy.loadtxt` or pandas, or as raw
+//! little endian `f32`s, row after row, for `numpy.fromfile` followed by a
+//! reshape to `(-1, len(columns))`.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::encoding::{action_index, feature_names, position_features};
+use super::selfplay::SelfPlayGame;
+use crate::replay::ReplayError;
+
+/// The names of the columns of a row, the features first.
+pub fn columns() -> Vec<String> {
+    let mut columns = feature_names();
+    for name in &["first_action", "second_action", "evaluation", "result"] {
+        columns.push(name.to_string());
+    }
+    columns
+}
+
+/// The rows of a game, one per turn played. Evaluations the bot didn't give
+/// are NaN.
+pub fn game_rows(game: &SelfPlayGame) -> Result<Vec<Vec<f32>>, ReplayError> {
+    let mut position = game.replay.initial_game();
+    let mut rows = Vec::new();
+    for (i, turn) in game.replay.turns.iter().enumerate() {
+        let player = position.next_player() as usize;
+        let mut row = position_features(&position);
+        row.push(action_index(&turn.0) as f32);
+        row.push(action_index(&turn.1) as f32);
+        row.push(
+            game.evaluations
+                .get(i)
+                .copied()
+                .flatten()
+                .unwrap_or(f32::NAN),
+        );
+        row.push(game.rewards[player] as f32);
+        rows.push(row);
+
+        position
+            .play_turn(turn.clone())
+            .map_err(|err| ReplayError::IllegalTurn(i, err))?;
+    }
+    Ok(rows)
+}
+
+/// Writes the rows of the games as CSV, with the column names first.
+pub fn write_csv(games: &[SelfPlayGame], mut out: impl Write) -> Result<(), ReplayError> {
+    writeln!(out, "{}", columns().join(","))?;
+    for game in games {
+        for row in game_rows(game)? {
+            let row: Vec<String> = row.iter().map(f32::to_string).collect();
+            writeln!(out, "{}", row.join(","))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the rows of the games as raw little endian `f32`s, with nothing
+/// else in the file.
+pub fn write_raw(games: &[SelfPlayGame], path: impl AsRef<Path>) -> Result<(), ReplayError> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    for game in games {
+        for row in game_rows(game)? {
+            for value in row {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::selfplay::{run, SelfPlayConfig};
+    use crate::ai::AgentSpec;
+
+    #[test]
+    fn rows_match_columns() {
+        let config = SelfPlayConfig {
+            threads: 1,
+            max_turns: 3,
+            ..SelfPlayConfig::new(vec![AgentSpec::Random, AgentSpec::Random], 1)
+        };
+        let games = run(&config).unwrap();
+        let rows = game_rows(&games[0]).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == columns().len()));
+
+        let mut csv = Vec::new();
+        write_csv(&games, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 4);
+    }
+}
@@ -0,0 +1,216 @@
+//! A transposition table, remembering what the search found out about
+//! positions it has seen, so reaching them again by another order of turns,
+//! or in the next search, costs a lookup rather than a search.
+
+use crate::game::Action;
+
+/// Entries in a table made with [`TranspositionTable::default`].
+pub const DEFAULT_ENTRIES: usize = 1 << 16;
+
+/// What the stored value says about the real value of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The real value is at least this, the search stopped at a cutoff.
+    Lower,
+    /// The real value is at most this, no turn reached the window.
+    Upper,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: u64,
+    /// Turns searched below the position.
+    pub depth: u32,
+    pub value: f32,
+    pub bound: Bound,
+    /// The first action of the best turn found. The second depends on what
+    /// the first uncovers, so it isn't stored.
+    pub best: Option<Action>,
+    /// The search that stored the entry, see
+    /// [`new_search`](TranspositionTable::new_search).
+    generation: u32,
+}
+
+/// Which entry stays when two positions want the same slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Replacement {
+    /// The newest entry always wins.
+    Always,
+    /// The entry searched deeper wins, unless it is left from an earlier
+    /// search.
+    #[default]
+    DepthPreferred,
+}
+
+/// How the table has been used since it was made or last cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableStats {
+    pub probes: u64,
+    /// Probes finding the position they looked for.
+    pub hits: u64,
+    pub stores: u64,
+    /// Stores replacing an entry for another position.
+    pub overwrites: u64,
+    /// Stores dropped to keep a deeper entry.
+    pub rejected: u64,
+}
+
+impl TableStats {
+    /// The share of probes that were hits, between 0 and 1.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.probes as f64
+    }
+}
+
+/// A fixed size table of search results, indexed by position hash.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    replacement: Replacement,
+    generation: u32,
+    stats: TableStats,
+}
+
+impl TranspositionTable {
+    /// A table of `entries` slots, at least one.
+    pub fn new(entries: usize, replacement: Replacement) -> TranspositionTable {
+        TranspositionTable {
+            entries: vec![None; entries.max(1)],
+            replacement,
+            generation: 0,
+            stats: TableStats::default(),
+        }
+    }
+
+    /// A table taking up about `megabytes` of memory.
+    pub fn with_megabytes(megabytes: usize, replacement: Replacement) -> TranspositionTable {
+        let entry = std::mem::size_of::<Option<Entry>>();
+        TranspositionTable::new(megabytes * 1024 * 1024 / entry, replacement)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn replacement(&self) -> Replacement {
+        self.replacement
+    }
+
+    pub fn stats(&self) -> TableStats {
+        self.stats
+    }
+
+    /// How many slots hold an entry.
+    pub fn occupied(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.is_some()).count()
+    }
+
+    /// Marks the start of a new search. Deeper entries from earlier searches
+    /// no longer keep new ones out.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            *entry = None;
+        }
+        self.stats = TableStats::default();
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    pub fn probe(&mut self, key: u64) -> Option<&Entry> {
+        self.stats.probes += 1;
+        let slot = self.slot(key);
+        match &self.entries[slot] {
+            Some(entry) if entry.key == key => {
+                self.stats.hits += 1;
+                Some(entry)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn store(&mut self, key: u64, depth: u32, value: f32, bound: Bound, best: Option<Action>) {
+        let slot = self.slot(key);
+        let generation = self.generation;
+        let keep_old = match &self.entries[slot] {
+            Some(old) if old.key != key => {
+                self.replacement == Replacement::DepthPreferred
+                    && old.generation == generation
+                    && old.depth > depth
+            }
+            _ => false,
+        };
+        if keep_old {
+            self.stats.rejected += 1;
+            return;
+        }
+        if matches!(&self.entries[slot], Some(old) if old.key != key) {
+            self.stats.overwrites += 1;
+        }
+        self.stats.stores += 1;
+        self.entries[slot] = Some(Entry {
+            key,
+            depth,
+            value,
+            bound,
+            best,
+            generation,
+        });
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_ENTRIES, Replacement::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacement() {
+        let mut table = TranspositionTable::new(4, Replacement::DepthPreferred);
+        table.store(1, 3, 1.0, Bound::Exact, None);
+        // 5 wants the same slot as 1, but was searched shallower.
+        table.store(5, 2, 2.0, Bound::Lower, None);
+        assert!(table.probe(5).is_none());
+        assert_eq!(table.probe(1).unwrap().value, 1.0);
+
+        table.new_search();
+        table.store(5, 2, 2.0, Bound::Lower, None);
+        assert_eq!(table.probe(5).unwrap().bound, Bound::Lower);
+        assert_eq!(
+            table.stats(),
+            TableStats {
+                probes: 3,
+                hits: 2,
+                stores: 2,
+                overwrites: 1,
+                rejected: 1,
+            }
+        );
+
+        let mut table = TranspositionTable::new(4, Replacement::Always);
+        table.store(1, 3, 1.0, Bound::Exact, None);
+        table.store(5, 2, 2.0, Bound::Upper, None);
+        assert!(table.probe(1).is_none());
+        assert_eq!(table.occupied(), 1);
+        table.clear();
+        assert_eq!(table.occupied(), 0);
+    }
+}
@@ -0,0 +1,171 @@
+//! Tuning the evaluation weights by playing, with SPSA: each iteration nudges
+//! every weight at random, up for one bot and down for the other, plays them
+//! against each other and moves the weights towards whichever side won more.
+//!
+//! Weights are nudged in proportion to their starting size, so weights of
+//! very different scales move at a similar pace.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::selfplay::{run, scores, SelfPlayConfig, SelfPlayError};
+use super::{AgentSpec, EvaluatorWeights, Limits, Preset};
+use crate::rules::Ruleset;
+
+#[derive(Debug, Clone)]
+pub struct TuningConfig {
+    pub iterations: u32,
+    /// Games per iteration. The two bots swap seats from one game to the
+    /// next, so this should be even.
+    pub games: usize,
+    /// Depth of the alpha-beta bots playing the games.
+    pub depth: u32,
+    pub limits: Limits,
+    pub rules: Ruleset,
+    /// The size of the first step, relative to the starting weights.
+    pub step: f32,
+    /// The size of the first nudge, relative to the starting weights.
+    pub nudge: f32,
+    pub threads: usize,
+    pub seed: u64,
+    pub max_turns: u32,
+}
+
+impl TuningConfig {
+    /// Two player games between depth 2 bots, with the usual SPSA schedule.
+    pub fn new(iterations: u32, games: usize) -> TuningConfig {
+        let selfplay = SelfPlayConfig::new(vec![AgentSpec::Random, AgentSpec::Random], games);
+        TuningConfig {
+            iterations,
+            games,
+            depth: 2,
+            limits: selfplay.limits,
+            rules: selfplay.rules,
+            step: 0.1,
+            nudge: 0.1,
+            threads: selfplay.threads,
+            seed: 0,
+            max_turns: selfplay.max_turns,
+        }
+    }
+}
+
+/// How an iteration of [`tune`] went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Iteration {
+    pub index: u32,
+    /// The share of the games won by the weights nudged up.
+    pub score: f64,
+    /// The weights after the iteration.
+    pub weights: EvaluatorWeights,
+}
+
+/// Tunes the weights of the preset, calling `progress` after every
+/// iteration, and gives the preset with the tuned weights.
+pub fn tune(
+    start: &Preset,
+    config: &TuningConfig,
+    mut progress: impl FnMut(&Iteration),
+) -> Result<Preset, SelfPlayError> {
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let scales: Vec<f32> = start
+        .weights
+        .to_vec()
+        .iter()
+        .map(|weight| weight.abs().max(1.0))
+        .collect();
+    // Tuned in units of the scales, see the module documentation.
+    let mut theta: Vec<f32> = start
+        .weights
+        .to_vec()
+        .iter()
+        .zip(&scales)
+        .map(|(weight, scale)| weight / scale)
+        .collect();
+    let weights = |theta: &[f32]| {
+        let unscaled: Vec<f32> = theta.iter().zip(&scales).map(|(x, s)| x * s).collect();
+        EvaluatorWeights::from_slice(&unscaled)
+    };
+    let stability = config.iterations as f32 / 10.0;
+
+    for index in 0..config.iterations {
+        let k = index as f32 + 1.0;
+        let step = config.step * (1.0 + stability).powf(0.602) / (k + stability).powf(0.602);
+        let nudge = config.nudge / k.powf(0.101);
+        let signs: Vec<f32> = theta
+            .iter()
+            .map(|_| if rng.gen() { 1.0 } else { -1.0 })
+            .collect();
+        let nudged = |sign: f32| {
+            let nudged: Vec<f32> = theta
+                .iter()
+                .zip(&signs)
+                .map(|(x, delta)| x + sign * nudge * delta)
+                .collect();
+            AgentSpec::AlphaBeta {
+                preset: Preset {
+                    weights: weights(&nudged),
+                    ..start.clone()
+                },
+                depth: config.depth,
+            }
+        };
+
+        let selfplay = SelfPlayConfig {
+            rules: config.rules.clone(),
+            limits: config.limits.clone(),
+            threads: config.threads,
+            seed: config.seed.wrapping_add(index as u64 * config.games as u64),
+            max_turns: config.max_turns,
+            ..SelfPlayConfig::new(vec![nudged(1.0), nudged(-1.0)], config.games)
+        };
+        let games = run(&selfplay)?;
+        let totals = scores(&games, 2);
+        let score = totals[0] / (totals[0] + totals[1]).max(1.0);
+        let difference = ((totals[0] - totals[1]) / games.len().max(1) as f64) as f32;
+        for (x, delta) in theta.iter_mut().zip(&signs) {
+            *x += step * difference / (2.0 * nudge * delta);
+        }
+
+        progress(&Iteration {
+            index,
+            score,
+            weights: weights(&theta),
+        });
+    }
+
+    Ok(Preset {
+        name: format!("{}-tuned", start.name),
+        description: format!(
+            "{} tuned over {} iterations of {} games.",
+            start.name, config.iterations, config.games
+        ),
+        weights: weights(&theta),
+        ..start.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tunes_the_weights() {
+        let config = TuningConfig {
+            depth: 1,
+            limits: Limits::nodes(1),
+            threads: 2,
+            max_turns: 2,
+            ..TuningConfig::new(2, 2)
+        };
+        let mut iterations = Vec::new();
+        let tuned = tune(&Preset::default(), &config, |iteration| {
+            iterations.push(iteration.clone())
+        })
+        .unwrap();
+        assert_eq!(iterations.len(), 2);
+        assert_eq!(iterations[1].weights, tuned.weights);
+        assert_eq!(tuned.name, "default-tuned");
+        assert_eq!(tuned.mcts, Preset::default().mcts);
+    }
+}
@@ -0,0 +1,217 @@
+//! Variation trees: a main line of turns with side branches and comments, the
+//! data behind an analysis board and annotated replays.
+
+use crate::game::{Game, PasstallyError, Turn};
+
+/// A node of a [`VariationTree`]. Only valid for the tree it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Node {
+    /// The turn leading to this node. Only the root has none.
+    turn: Option<Turn>,
+    parent: Option<NodeId>,
+    /// The first child continues the line, the others are alternatives to it.
+    children: Vec<NodeId>,
+    comment: String,
+}
+
+/// Turns played from a starting position, with every alternative explored.
+/// Each node is the position after the turns on the path to it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VariationTree {
+    nodes: Vec<Node>,
+}
+
+impl Default for VariationTree {
+    fn default() -> Self {
+        VariationTree {
+            nodes: vec![Node {
+                turn: None,
+                parent: None,
+                children: Vec::new(),
+                comment: String::new(),
+            }],
+        }
+    }
+}
+
+impl VariationTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A tree with just the turns as its main line.
+    pub fn from_turns(turns: impl IntoIterator<Item = Turn>) -> Self {
+        let mut tree = Self::new();
+        let mut node = tree.root();
+        for turn in turns {
+            node = tree.add(node, turn);
+        }
+        tree
+    }
+
+    /// The starting position.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Adds the turn as a continuation of the node. If the node already has
+    /// that continuation, that node is returned instead of adding a new one.
+    pub fn add(&mut self, parent: NodeId, turn: Turn) -> NodeId {
+        if let Some(&existing) = self.nodes[parent.0]
+            .children
+            .iter()
+            .find(|child| self.nodes[child.0].turn.as_ref() == Some(&turn))
+        {
+            return existing;
+        }
+
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            turn: Some(turn),
+            parent: Some(parent),
+            children: Vec::new(),
+            comment: String::new(),
+        });
+        self.nodes[parent.0].children.push(id);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Never true, there is always the root.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The turn leading to the node, `None` for the root.
+    pub fn turn(&self, node: NodeId) -> Option<&Turn> {
+        self.nodes[node.0].turn.as_ref()
+    }
+
+    pub fn comment(&self, node: NodeId) -> &str {
+        &self.nodes[node.0].comment
+    }
+
+    pub fn set_comment(&mut self, node: NodeId, comment: impl Into<String>) {
+        self.nodes[node.0].comment = comment.into();
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node.0].parent
+    }
+
+    /// The continuations of the node, main one first.
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.nodes[node.0].children
+    }
+
+    /// The main continuation of the node.
+    pub fn next(&self, node: NodeId) -> Option<NodeId> {
+        self.children(node).first().copied()
+    }
+
+    /// The next alternative to the node's turn.
+    pub fn next_sibling(&self, node: NodeId) -> Option<NodeId> {
+        let (siblings, i) = self.siblings(node)?;
+        siblings.get(i + 1).copied()
+    }
+
+    /// The previous alternative to the node's turn.
+    pub fn previous_sibling(&self, node: NodeId) -> Option<NodeId> {
+        let (siblings, i) = self.siblings(node)?;
+        i.checked_sub(1).map(|i| siblings[i])
+    }
+
+    /// The node's parent's children, and where the node is among them.
+    fn siblings(&self, node: NodeId) -> Option<(&[NodeId], usize)> {
+        let siblings = self.children(self.parent(node)?);
+        let i = siblings.iter().position(|&id| id == node).unwrap();
+        Some((siblings, i))
+    }
+
+    /// Makes the node's turn the main continuation of its parent.
+    pub fn promote(&mut self, node: NodeId) {
+        if let Some(parent) = self.parent(node) {
+            let children = &mut self.nodes[parent.0].children;
+            let i = children.iter().position(|&id| id == node).unwrap();
+            let node = children.remove(i);
+            children.insert(0, node);
+        }
+    }
+
+    /// The nodes of the main line, from the root to its end.
+    pub fn main_line(&self) -> Vec<NodeId> {
+        let mut line = vec![self.root()];
+        while let Some(next) = self.next(*line.last().unwrap()) {
+            line.push(next);
+        }
+        line
+    }
+
+    /// The turns leading from the root to the node.
+    pub fn turns_to(&self, node: NodeId) -> Vec<Turn> {
+        let mut turns = Vec::new();
+        let mut node = node;
+        while let Some(parent) = self.parent(node) {
+            turns.push(self.turn(node).unwrap().clone());
+            node = parent;
+        }
+        turns.reverse();
+        turns
+    }
+
+    /// The position at the node, when the tree starts at `start`.
+    pub fn game_at(&self, start: &Game, node: NodeId) -> Result<Game, PasstallyError> {
+        let mut game = start.clone();
+        for turn in self.turns_to(node) {
+            game.play_turn(turn)?;
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn navigation() {
+        let start = Game::new_with_rules(Ruleset::new(2), 3);
+        let turns = start.legal_turns();
+
+        let mut tree = VariationTree::from_turns(vec![turns[0].clone()]);
+        let root = tree.root();
+        let main = tree.next(root).unwrap();
+        let after = tree.game_at(&start, main).unwrap();
+        let reply = tree.add(main, after.legal_turns().remove(0));
+        let alternative = tree.add(root, turns[1].clone());
+        tree.set_comment(alternative, "Also fine.");
+
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.add(root, turns[1].clone()), alternative);
+        assert_eq!(tree.main_line(), vec![root, main, reply]);
+        assert_eq!(tree.children(root), &[main, alternative][..]);
+        assert_eq!(tree.next_sibling(main), Some(alternative));
+        assert_eq!(tree.previous_sibling(alternative), Some(main));
+        assert_eq!(tree.previous_sibling(main), None);
+        assert_eq!(tree.next_sibling(root), None);
+        assert_eq!(tree.parent(reply), Some(main));
+        assert_eq!(tree.comment(alternative), "Also fine.");
+        assert_eq!(tree.turns_to(reply).len(), 2);
+        assert_eq!(tree.game_at(&start, reply).unwrap().round(), 2);
+
+        tree.promote(alternative);
+        assert_eq!(tree.main_line(), vec![root, alternative]);
+        assert_eq!(tree.turn(alternative), Some(&turns[1]));
+        assert_eq!(tree.turn(root), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Proptest strategies for the engine's types, so properties can be checked
+//! against pieces, placements and positions proptest makes up and shrinks.
+//!
+//! Positions and placements stay on the board and games are reached by
+//! playing legal turns, so a failing case is always one that could come up
+//! in play. Actions are the exception: they are anything a player could
+//! send, legal or not.
+
+use proptest::prelude::*;
+
+use crate::board::BoardPosition;
+use crate::game::{Action, Game};
+use crate::piece::{PartialPiece, Piece, PositionedPiece, RotatedPartialPiece, Side};
+use crate::rules::Ruleset;
+
+/// Turns played at most to reach a game, about a third of a two player game.
+const MAX_TURNS: usize = 16;
+
+impl Arbitrary for Side {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Side>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(Side::Top),
+            Just(Side::Right),
+            Just(Side::Bottom),
+            Just(Side::Left),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for PartialPiece {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<PartialPiece>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(PartialPiece::TopBottom_LeftRight),
+            Just(PartialPiece::TopLeft_BottomRight),
+            Just(PartialPiece::TopRight_BottomLeft),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for RotatedPartialPiece {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<RotatedPartialPiece>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<PartialPiece>(), 0..4u8)
+            .prop_map(|(piece, rotation)| RotatedPartialPiece::new(piece, rotation))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Piece {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Piece>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(Piece::Red),
+            Just(Piece::Green),
+            Just(Piece::Yellow),
+            Just(Piece::Blue),
+            Just(Piece::Cyan),
+            Just(Piece::Pink),
+        ]
+        .boxed()
+    }
+}
+
+/// A square on the board.
+impl Arbitrary for BoardPosition {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<BoardPosition>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (0..6i8, 0..6i8)
+            .prop_map(|(x, y)| BoardPosition::new(x, y))
+            .boxed()
+    }
+}
+
+/// A piece lying entirely on the board, though maybe not somewhere it fits.
+impl Arbitrary for PositionedPiece {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<PositionedPiece>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<Piece>(), any::<BoardPosition>(), 0..4u8)
+            .prop_map(|(piece, position, rotation)| PositionedPiece {
+                piece,
+                position,
+                rotation,
+            })
+            .prop_filter("The piece has to lie on the board", |piece| {
+                piece.positions().1.valid()
+            })
+            .boxed()
+    }
+}
+
+/// Any action, with marker slots and decks in range but otherwise legal or not.
+impl Arbitrary for Action {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Action>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            4 => any::<PositionedPiece>().prop_map(Action::PlacePiece),
+            4 => (0..24u8, 0..24u8).prop_map(|(from, to)| Action::MovePlayerMarker(from, to)),
+            1 => (0..3u8).prop_map(Action::RedrawMarket),
+            1 => Just(Action::Resign),
+            1 => Just(Action::OfferDraw),
+            1 => Just(Action::AcceptDraw),
+        ]
+        .boxed()
+    }
+}
+
+/// A game between one to four players some legal turns in, each turn picked
+/// by proptest so games shrink towards the opening and the first legal turns.
+impl Arbitrary for Game {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Game>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            1..=4u8,
+            any::<u64>(),
+            prop::collection::vec(any::<prop::sample::Index>(), 0..MAX_TURNS),
+        )
+            .prop_map(|(players, seed, turns)| {
+                let mut game = Game::new_with_rules(Ruleset::new(players), seed);
+                for turn in turns {
+                    let count = game.legal_turns_iter().count();
+                    if count == 0 {
+                        break;
+                    }
+                    let turn = game.legal_turns_iter().nth(turn.index(count)).unwrap();
+                    game.play_turn(turn).unwrap();
+                }
+                game
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,85 @@
+//! Zero-copy snapshots of games. An archive is laid out the way the game is
+//! in memory, so one read back or memory-mapped from disk is used where it
+//! lies rather than decoded first.
+//!
+//! Archives are checked before they are used, since they come off disk,
+//! and decoded games are checked with [`Game::validate`] too. They are only
+//! meant to be read by the version of the engine that wrote them. Use the
+//! `binary` snapshots for anything kept longer.
+
+use rkyv::{AlignedVec, Deserialize, Infallible};
+use thiserror::Error;
+
+use crate::game::{ArchivedGame, Game, InvalidGame};
+
+impl Game {
+    /// The game as an archive, for writing to disk.
+    pub fn to_archive(&self) -> AlignedVec {
+        rkyv::to_bytes::<_, 1024>(self).expect("Games should always archive")
+    }
+
+    /// The game in the archive, used in place. The bytes have to be aligned
+    /// to 16, as they are when memory-mapped or read into an [`AlignedVec`].
+    pub fn archived(bytes: &[u8]) -> Result<&ArchivedGame, ArchiveError> {
+        rkyv::check_archived_root::<Game>(bytes)
+            .map_err(|error| ArchiveError::Invalid(error.to_string()))
+    }
+
+    /// The game in the archive, decoded to be played on.
+    pub fn from_archive(bytes: &[u8]) -> Result<Game, ArchiveError> {
+        let archived = Game::archived(bytes)?;
+        let game: Game = archived
+            .deserialize(&mut Infallible)
+            .expect("Checked archives should always decode");
+        game.validate()?;
+        Ok(game)
+    }
+}
+
+impl ArchivedGame {
+    /// Amount of rounds played.
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Points scored so far by each player.
+    pub fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("The data isn't a valid game archive: {0}")]
+    Invalid(String),
+    #[error("The archive holds a game that can't be played: {0}")]
+    InvalidGame(#[from] InvalidGame),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn games_archive_and_come_back() {
+        let mut game = Game::new_with_rules(Ruleset::new(3), 6);
+        for _ in 0..8 {
+            let turn = game.legal_turns_iter().next().unwrap();
+            game.play_turn(turn).unwrap();
+        }
+        let archive = game.to_archive();
+        let archived = Game::archived(&archive).unwrap();
+        assert_eq!(archived.round(), game.round());
+        assert_eq!(archived.scores(), &game.scores[..]);
+        assert_eq!(Game::from_archive(&archive).unwrap(), game);
+
+        assert!(Game::archived(&archive[..archive.len() / 2]).is_err());
+
+        game.scores.pop();
+        assert!(matches!(
+            Game::from_archive(&game.to_archive()),
+            Err(ArchiveError::InvalidGame(InvalidGame::Scores(2, 3)))
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+//! Plays engines speaking the [protocol](passtally_rs::ai::protocol)
+//! against each other, keeping their clocks and judging their turns, and
+//! saves the games.
+//!
+//! ```text
+//! passtally-adjudicate ENGINE ENGINE... [--games N] [--base MS] [--inc MS] [--seed N] [--output DIR]
+//! ```
+//!
+//! Each `ENGINE` is the command starting it, quoted if it takes arguments,
+//! like `"passtally-engine mcts:default"`. The engines move one seat along
+//! from each game to the next. Games are saved as `game-NNNNN.json` in the
+//! output directory.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use passtally_rs::ai::adjudicator::{play, MatchConfig};
+use passtally_rs::ai::external::ExternalEngine;
+use passtally_rs::clock::TimeControl;
+
+const USAGE: &str = "Usage: passtally-adjudicate ENGINE ENGINE... [--games N] [--base MS] [--inc MS] [--seed N] [--output DIR]";
+
+fn main() {
+    if let Err(message) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut commands = Vec::new();
+    let mut games = 2;
+    let mut base = 10_000;
+    let mut increment = 100;
+    let mut seed = 0;
+    let mut output = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("{} needs a value.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--games" => games = number(&value()?)?,
+            "--base" => base = number(&value()?)?,
+            "--inc" => increment = number(&value()?)?,
+            "--seed" => seed = number(&value()?)?,
+            "--output" => output = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if !arg.starts_with('-') => commands.push(arg),
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+    if !(2..=4).contains(&commands.len()) {
+        return Err(format!("Two to four engines are needed.\n{}", USAGE));
+    }
+    if let Some(output) = &output {
+        fs::create_dir_all(output).map_err(|err| err.to_string())?;
+    }
+
+    let control = TimeControl::new(
+        Duration::from_millis(base),
+        Duration::from_millis(increment),
+    );
+    let mut scores = vec![0.0; commands.len()];
+    for index in 0..games {
+        // The engine in seat `s` is `(s + index) % engines`.
+        let seats: Vec<usize> = (0..commands.len())
+            .map(|seat| (seat + index) % commands.len())
+            .collect();
+        let mut engines = seats
+            .iter()
+            .map(|&engine| spawn(&commands[engine]))
+            .collect::<Result<Vec<_>, _>>()?;
+        let config = MatchConfig::new(control, seed + index as u64);
+        let game = play(&mut engines, &config);
+
+        for (&engine, reward) in seats.iter().zip(&game.rewards) {
+            scores[engine] += reward;
+        }
+        let faults: Vec<String> = game
+            .faults
+            .iter()
+            .map(|(seat, fault)| format!("{} {:?}", game.replay.players[*seat as usize], fault))
+            .collect();
+        println!(
+            "Game {}: {} turns, rewards {:?}{}{}",
+            index + 1,
+            game.replay.turns.len(),
+            game.rewards,
+            if game.finished { "" } else { ", stopped" },
+            if faults.is_empty() {
+                String::new()
+            } else {
+                format!(", faults: {}", faults.join("; "))
+            }
+        );
+        if let Some(output) = &output {
+            game.save(output.join(format!("game-{:05}.json", index)))
+                .map_err(|err| err.to_string())?;
+        }
+    }
+
+    for (command, score) in commands.iter().zip(scores) {
+        println!("{}: {} of {}", command, score, games);
+    }
+    Ok(())
+}
+
+fn spawn(command: &str) -> Result<ExternalEngine, String> {
+    let mut words = command.split_whitespace().map(str::to_string);
+    let program = words.next().ok_or("An engine command is empty.")?;
+    let args: Vec<String> = words.collect();
+    ExternalEngine::spawn(&program, &args).map_err(|err| format!("{}: {}", command, err))
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number.\n{}", value, USAGE))
+}
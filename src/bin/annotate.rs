@@ -0,0 +1,106 @@
+//! Goes through a saved replay with a bot and prints the turns it thinks
+//! were inaccuracies, mistakes or blunders.
+//!
+//! ```text
+//! passtally-annotate REPLAY [--bot BOT] [--movetime MS] [--all] [--json] [--output FILE]
+//! ```
+//!
+//! `BOT` is written as for the tournament runner, `alphabeta:default` if
+//! not given. `--all` prints every turn rather than only the bad ones, and
+//! `--json` prints the annotations as JSON. `--output` saves the replay with
+//! a bookmark on each bad turn, for the GUI's replay viewer.
+
+use std::env;
+use std::process;
+use std::time::Duration;
+
+use passtally_rs::ai::alphabeta::DEFAULT_DEPTH;
+use passtally_rs::ai::annotate::Annotator;
+use passtally_rs::ai::{AgentSpec, Limits, Preset, PresetError};
+use passtally_rs::replay::Replay;
+
+const USAGE: &str =
+    "Usage: passtally-annotate REPLAY [--bot BOT] [--movetime MS] [--all] [--json] [--output FILE]";
+
+fn main() {
+    if let Err(message) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut path = None;
+    let mut bot = AgentSpec::AlphaBeta {
+        preset: Preset::default(),
+        depth: DEFAULT_DEPTH,
+    };
+    let mut movetime = 1000;
+    let mut all = false;
+    let mut json = false;
+    let mut output = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("{} needs a value.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--bot" => {
+                bot = value()?
+                    .parse()
+                    .map_err(|err: PresetError| err.to_string())?
+            }
+            "--movetime" => movetime = number(&value()?)?,
+            "--all" => all = true,
+            "--json" => json = true,
+            "--output" => output = Some(value()?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+    let path = path.ok_or_else(|| USAGE.to_string())?;
+
+    let mut replay = Replay::load(&path).map_err(|err| err.to_string())?;
+    let limits = Limits::time(Duration::from_millis(movetime));
+    let agent = bot.build(replay.seed).map_err(|err| err.to_string())?;
+    let mut annotator = Annotator::new(agent, limits);
+    let annotations: Vec<_> = annotator
+        .annotate(&replay)
+        .map_err(|err| err.to_string())?
+        .into_iter()
+        .filter(|annotation| all || annotation.judgement.is_some())
+        .collect();
+
+    if json {
+        let json = serde_json::to_string_pretty(&annotations).map_err(|err| err.to_string())?;
+        println!("{}", json);
+    } else {
+        for annotation in &annotations {
+            println!("{}", annotation);
+        }
+    }
+    if let Some(output) = output {
+        replay.bookmarks.extend(
+            annotations
+                .iter()
+                .filter(|annotation| annotation.judgement.is_some())
+                .map(|annotation| annotation.bookmark()),
+        );
+        replay.bookmarks.sort_by_key(|bookmark| bookmark.turn);
+        replay.save(&output).map_err(|err| err.to_string())?;
+        println!("Wrote {}", output);
+    }
+    Ok(())
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number.\n{}", value, USAGE))
+}
@@ -0,0 +1,78 @@
+//! A bot speaking the engine protocol of
+//! [`protocol`](passtally_rs::ai::protocol) on stdin and stdout, for GUIs
+//! and tools driving engines.
+//!
+//! ```text
+//! passtally-engine [BOT]
+//! passtally-engine --json-rpc
+//! ```
+//!
+//! `BOT` is written as in [`AgentSpec`]'s `FromStr`, `mcts:default` if left
+//! out. With `--json-rpc` it instead serves a game over JSON-RPC 2.0, see
+//! [`rpc`](passtally_rs::rpc), for programs in any language to play
+//! through.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use passtally_rs::ai::protocol::Engine;
+use passtally_rs::ai::AgentSpec;
+use passtally_rs::rpc::RpcServer;
+
+fn main() {
+    let arg = env::args().nth(1);
+    if arg.as_deref() == Some("--json-rpc") {
+        return json_rpc();
+    }
+    let agent = match arg
+        .as_deref()
+        .unwrap_or("mcts:default")
+        .parse::<AgentSpec>()
+        .and_then(|spec| spec.build(rand::random()))
+    {
+        Ok(agent) => agent,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let mut engine = Engine::new(agent, |response| {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        // Nothing to be done about a closed stdout, the program will notice
+        // the engine has gone quiet.
+        let _ = writeln!(stdout, "{}", response).and_then(|_| stdout.flush());
+    });
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) if engine.handle(&line) => {}
+            _ => break,
+        }
+    }
+    engine.handle("quit");
+}
+
+fn json_rpc() {
+    let mut server = RpcServer::new();
+    let stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_line(&line) {
+            let mut stdout = stdout.lock();
+            if writeln!(stdout, "{}", response)
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Mines games between bots for "score X in one turn" puzzles and writes
+//! them in the [puzzle format](passtally_rs::ai::puzzles).
+//!
+//! ```text
+//! passtally-puzzles OUTPUT [--games N] [--players N] [--min-points N] [--seed N]
+//! ```
+
+use std::env;
+use std::fs;
+use std::process;
+
+use passtally_rs::ai::puzzles::{mine, MiningConfig};
+
+const USAGE: &str =
+    "Usage: passtally-puzzles OUTPUT [--games N] [--players N] [--min-points N] [--seed N]";
+
+fn main() {
+    if let Err(message) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut output = None;
+    let mut config = MiningConfig::new(10, 0);
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("{} needs a value.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--games" => config.games = number(&value()?)?,
+            "--players" => config.players = number(&value()?)?,
+            "--min-points" => config.min_points = number(&value()?)?,
+            "--seed" => config.seed = number(&value()?)?,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if output.is_none() && !arg.starts_with('-') => output = Some(arg),
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+    let output = output.ok_or_else(|| USAGE.to_string())?;
+    if !(2..=4).contains(&config.players) {
+        return Err(format!("Games have two to four players.\n{}", USAGE));
+    }
+
+    let puzzles = mine(&config, |puzzle| {
+        println!("Score {}: {}", puzzle.target, puzzle.notation)
+    });
+    let mut file =
+        String::from("# Score the points in one turn.\n#\n# position | points | solution\n");
+    for puzzle in &puzzles {
+        file.push_str(&format!("{}\n", puzzle));
+    }
+    fs::write(&output, file).map_err(|err| err.to_string())?;
+    println!("Wrote {} puzzles to {}", puzzles.len(), output);
+    Ok(())
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number.\n{}", value, USAGE))
+}
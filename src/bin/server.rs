@@ -0,0 +1,79 @@
+//! Serves games as REST resources, see
+//! [`rest`](passtally_rs::net::rest) for the routes, live over
+//! WebSockets, see [`live`](passtally_rs::net::live), and the replays of
+//! finished games, see [`protocol`](passtally_rs::net::protocol).
+//!
+//! ```text
+//! passtally-server [--bind ADDRESS] [--replays DIRECTORY]
+//! ```
+//!
+//! Listens on `127.0.0.1:8080` unless told otherwise. Games being played
+//! are kept in memory and lost when the server stops; finished ones are
+//! kept in the replays directory, the usual one unless told otherwise.
+
+use std::env;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use passtally_rs::net::live::{self, Lobby};
+use passtally_rs::net::replays::{self, SharedStore};
+use passtally_rs::net::rest::{self, GameTable};
+use passtally_rs::net::{ReplayStore, RetentionPolicy};
+
+const USAGE: &str = "Usage: passtally-server [--bind ADDRESS] [--replays DIRECTORY]";
+
+#[tokio::main]
+async fn main() {
+    if let Err(message) = run(env::args().skip(1).collect()).await {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+async fn run(args: Vec<String>) -> Result<(), String> {
+    let mut address = "127.0.0.1:8080".to_string();
+    let mut replays_dir = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => {
+                address = args
+                    .next()
+                    .ok_or(format!("{} needs a value.\n{}", arg, USAGE))?
+            }
+            "--replays" => {
+                replays_dir = Some(
+                    args.next()
+                        .ok_or(format!("{} needs a value.\n{}", arg, USAGE))?,
+                )
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+
+    let policy = RetentionPolicy::default();
+    let store = match replays_dir {
+        Some(dir) => ReplayStore::open(dir, policy),
+        None => ReplayStore::open_default(policy),
+    }
+    .map_err(|err| format!("Can't open the replays: {}", err))?;
+    let store = SharedStore::new(Mutex::new(store));
+
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .map_err(|err| format!("Can't listen on {}: {}", address, err))?;
+    println!("Listening on {}", address);
+    let games = Arc::new(Mutex::new(GameTable::with_store(store.clone())));
+    let lobby = Arc::new(Lobby::with_store(store.clone()));
+    let app = rest::router(games)
+        .merge(live::router(lobby))
+        .merge(replays::router(store));
+    axum::serve(listener, app)
+        .await
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,76 @@
+//! Plays many games between bots and prints how they went: the scores, how
+//! long the games lasted and how much moving first was worth.
+//!
+//! ```text
+//! passtally-simulate BOT BOT... [--games N] [--seed N] [--threads N] [--max-turns N]
+//! ```
+//!
+//! Bots are written as for the tournament runner, one per seat.
+
+use std::env;
+use std::process;
+
+use passtally_rs::ai::selfplay::SelfPlayConfig;
+use passtally_rs::ai::simulate::run_config;
+use passtally_rs::ai::AgentSpec;
+
+const USAGE: &str =
+    "Usage: passtally-simulate BOT BOT... [--games N] [--seed N] [--threads N] [--max-turns N]";
+
+fn main() {
+    if let Err(message) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut bots = Vec::new();
+    let mut games = 1000;
+    let mut seed = 0;
+    let mut threads = None;
+    let mut max_turns = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("{} needs a value.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--games" => games = number(&value()?)?,
+            "--seed" => seed = number(&value()?)?,
+            "--threads" => threads = Some(number(&value()?)?),
+            "--max-turns" => max_turns = Some(number(&value()?)?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if !arg.starts_with('-') => {
+                bots.push(arg.parse::<AgentSpec>().map_err(|err| err.to_string())?)
+            }
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+    if !(2..=4).contains(&bots.len()) {
+        return Err(format!("Two to four bots are needed.\n{}", USAGE));
+    }
+
+    let mut config = SelfPlayConfig::new(bots, games);
+    config.seed = seed;
+    if let Some(threads) = threads {
+        config.threads = threads;
+    }
+    if let Some(max_turns) = max_turns {
+        config.max_turns = max_turns;
+    }
+    let report = run_config(&config).map_err(|err| err.to_string())?;
+    print!("{}", report);
+    Ok(())
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number.\n{}", value, USAGE))
+}
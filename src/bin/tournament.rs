@@ -0,0 +1,129 @@
+//! Plays a tournament between bots and prints the standings.
+//!
+//! ```text
+//! passtally-tournament BOT BOT... [--gauntlet] [--games N] [--nodes N] [--millis N] [--seed N] [--output DIR]
+//! passtally-tournament NEW OLD --sprt ELO0 ELO1 [--max-games N] [--games N] ...
+//! ```
+//!
+//! Bots are written as in [`AgentSpec`]'s `FromStr`, for example `random`,
+//! `mcts:builder` or `alphabeta:default:2`. With `--gauntlet` the first bot
+//! plays each of the others, otherwise everyone plays everyone.
+//!
+//! With `--sprt` the two bots play `--games` at a time until a sequential
+//! probability ratio test decides whether the first is at least `ELO1`
+//! stronger or at most `ELO0`, or `--max-games` have been played.
+//!
+//! With `--ratings FILE` the results of a tournament update the Glicko-2
+//! ratings kept in the file, which is created if it doesn't exist.
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use passtally_rs::ai::ratings::Ratings;
+use passtally_rs::ai::sprt::{self, Sprt, SprtOutcome};
+use passtally_rs::ai::tournament::{run, Format, TournamentConfig};
+use passtally_rs::ai::{AgentSpec, Limits};
+
+const USAGE: &str = "Usage: passtally-tournament BOT BOT... [--gauntlet] [--sprt ELO0 ELO1] [--max-games N] [--games N] [--nodes N] [--millis N] [--seed N] [--output DIR] [--ratings FILE]";
+
+fn main() {
+    if let Err(message) = run_tournament(env::args().skip(1).collect()) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run_tournament(args: Vec<String>) -> Result<(), String> {
+    let mut config = TournamentConfig::new(Vec::new(), 20);
+    let mut test = None;
+    let mut max_games = 20_000;
+    let mut ratings = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("{} needs a value.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--gauntlet" => config.format = Format::Gauntlet,
+            "--sprt" => test = Some(Sprt::new(number(&value()?)?, number(&value()?)?)),
+            "--max-games" => max_games = number(&value()?)?,
+            "--games" => config.games = number(&value()?)?,
+            "--nodes" => config.limits = Limits::nodes(number(&value()?)?),
+            "--millis" => config.limits = Limits::time(Duration::from_millis(number(&value()?)?)),
+            "--seed" => config.seed = number(&value()?)?,
+            "--output" => config.output = Some(PathBuf::from(value()?)),
+            "--ratings" => ratings = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if !arg.starts_with('-') => config
+                .entrants
+                .push(arg.parse::<AgentSpec>().map_err(|err| err.to_string())?),
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+    if config.entrants.len() < 2 {
+        return Err(format!("At least two bots are needed.\n{}", USAGE));
+    }
+
+    if let Some(test) = test {
+        return run_sprt(&config, &test, max_games);
+    }
+
+    let standings = run(&config).map_err(|err| err.to_string())?;
+    print!("{}", standings);
+
+    if let Some(path) = ratings {
+        let mut ratings = Ratings::load(&path).map_err(|err| err.to_string())?;
+        ratings.update(&standings);
+        ratings.save(&path).map_err(|err| err.to_string())?;
+        print!("\n{}", ratings);
+    }
+    Ok(())
+}
+
+fn run_sprt(config: &TournamentConfig, test: &Sprt, max_games: usize) -> Result<(), String> {
+    if config.entrants.len() != 2 {
+        return Err(format!("An SPRT needs exactly two bots.\n{}", USAGE));
+    }
+    let (lower, upper) = test.bounds();
+    let (result, outcome) = sprt::run(config, test, max_games, |result, llr| {
+        println!(
+            "{} games: +{} ={} -{}, {:+.1} Elo, LLR {:.2} ({:.2}, {:.2})",
+            result.games(),
+            result.wins,
+            result.draws,
+            result.losses,
+            sprt::elo(result),
+            llr,
+            lower,
+            upper
+        );
+    })
+    .map_err(|err| err.to_string())?;
+
+    let verdict = match outcome {
+        SprtOutcome::Passed => "passed",
+        SprtOutcome::Failed => "failed",
+        SprtOutcome::Continue => "undecided",
+    };
+    println!(
+        "{} vs {}: {} after {} games",
+        config.entrants[0],
+        config.entrants[1],
+        verdict,
+        result.games()
+    );
+    Ok(())
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number.\n{}", value, USAGE))
+}
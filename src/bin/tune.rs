@@ -0,0 +1,87 @@
+//! Tunes the evaluation weights of a preset by self-play and writes the
+//! tuned preset, which the bots load like any other.
+//!
+//! ```text
+//! passtally-tune OUTPUT [--from PRESET] [--iterations N] [--games N] [--depth N] [--seed N]
+//! ```
+//!
+//! `PRESET` is the name of a shipped preset or a path to a preset file. The
+//! output is rewritten after every iteration, so stopping early keeps the
+//! progress made.
+
+use std::env;
+use std::process;
+
+use passtally_rs::ai::tuning::{tune, TuningConfig};
+use passtally_rs::ai::Preset;
+
+const USAGE: &str = "Usage: passtally-tune OUTPUT [--from PRESET] [--iterations N] [--games N] [--depth N] [--seed N]";
+
+fn main() {
+    if let Err(message) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut output = None;
+    let mut start = Preset::default();
+    let mut config = TuningConfig::new(100, 32);
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("{} needs a value.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--from" => {
+                let from = value()?;
+                start = match Preset::named(&from) {
+                    Some(preset) => preset,
+                    None => Preset::load(&from).map_err(|err| err.to_string())?,
+                };
+            }
+            "--iterations" => config.iterations = number(&value()?)?,
+            "--games" => config.games = number(&value()?)?,
+            "--depth" => config.depth = number(&value()?)?,
+            "--seed" => config.seed = number(&value()?)?,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if output.is_none() && !arg.starts_with('-') => output = Some(arg),
+            _ => return Err(format!("Unexpected argument {}.\n{}", arg, USAGE)),
+        }
+    }
+    let output = output.ok_or_else(|| USAGE.to_string())?;
+
+    let mut saved = Ok(());
+    let tuned = tune(&start, &config, |iteration| {
+        println!(
+            "Iteration {}: {:.1}% for the nudged up weights, now {:?}",
+            iteration.index + 1,
+            iteration.score * 100.0,
+            iteration.weights.to_vec()
+        );
+        let preset = Preset {
+            weights: iteration.weights.clone(),
+            ..start.clone()
+        };
+        if saved.is_ok() {
+            saved = preset.save(&output);
+        }
+    })
+    .map_err(|err| err.to_string())?;
+    saved.map_err(|err| err.to_string())?;
+    tuned.save(&output).map_err(|err| err.to_string())?;
+    println!("Wrote {}", output);
+    Ok(())
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number.\n{}", value, USAGE))
+}
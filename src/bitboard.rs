@@ -0,0 +1,131 @@
+//! An experiment in packing the routing of the board into bits. Lines only
+//! care which way each square's top piece routes them, one of three ways, so
+//! two bits a square and two `u64`s hold the whole board. Tracing is a table
+//! lookup per hop, from the square and side a line enters by and the routing
+//! there, to the square and side it enters next or the slot it leaves by.
+//!
+//! It only knows where lines go, not what they are worth. Compare it against
+//! the [`Board`] with `cargo bench --features bitboard --bench bitboard`.
+
+use crate::board::{Board, BoardPosition};
+use crate::piece::{PartialPiece, Side};
+
+/// A line entering `square * 4 + side` is at a state below this, anything
+/// from here on is `EXIT + slot`, the edge slot it left by.
+const EXIT: u8 = 144;
+
+/// The state after each state, by the routing of its square.
+const NEXT: [[u8; 3]; 144] = {
+    const ROUTINGS: [PartialPiece; 3] = [
+        PartialPiece::TopBottom_LeftRight,
+        PartialPiece::TopLeft_BottomRight,
+        PartialPiece::TopRight_BottomLeft,
+    ];
+    let mut table = [[0; 3]; 144];
+    let mut state = 0;
+    while state < 144 {
+        let (x, y) = ((state / 4) % 6, (state / 4) / 6);
+        let mut routing = 0;
+        while routing < 3 {
+            let entry = Side::Top.rotate((state % 4) as u8);
+            let exit = ROUTINGS[routing].pass(entry);
+            // Where the side leads, the slots going clockwise from the top left.
+            table[state][routing] = match exit as u8 {
+                0 if y == 0 => EXIT + x as u8,
+                1 if x == 5 => EXIT + 6 + y as u8,
+                2 if y == 5 => EXIT + 12 + (5 - x) as u8,
+                3 if x == 0 => EXIT + 18 + (5 - y) as u8,
+                0 => (state - 24) as u8 / 4 * 4 + 2,
+                1 => (state + 4) as u8 / 4 * 4 + 3,
+                2 => (state + 24) as u8 / 4 * 4,
+                _ => (state - 4) as u8 / 4 * 4 + 1,
+            };
+            routing += 1;
+        }
+        state += 1;
+    }
+    table
+};
+
+/// The routing of every square, two bits each, squares row by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedRouting {
+    words: [u64; 2],
+}
+
+impl PackedRouting {
+    /// The routing of the top pieces of the board.
+    pub fn from_board(board: &Board) -> PackedRouting {
+        let mut packed = PackedRouting::default();
+        for pos in BoardPosition::all() {
+            packed.set(pos, board.top_piece(pos).routing());
+        }
+        packed
+    }
+
+    pub fn routing(&self, pos: BoardPosition) -> PartialPiece {
+        match self.bits(pos.index()) {
+            0 => PartialPiece::TopBottom_LeftRight,
+            1 => PartialPiece::TopLeft_BottomRight,
+            _ => PartialPiece::TopRight_BottomLeft,
+        }
+    }
+
+    pub fn set(&mut self, pos: BoardPosition, routing: PartialPiece) {
+        let square = pos.index();
+        let shift = square % 32 * 2;
+        let word = &mut self.words[square / 32];
+        *word = (*word & !(3 << shift)) | ((routing as u64) << shift);
+    }
+
+    /// Where the line entering at the edge slot leaves the board.
+    pub fn exit(&self, entry: u8) -> u8 {
+        let mut state = match entry {
+            0..=5 => entry * 4,
+            6..=11 => ((entry - 6) * 6 + 5) * 4 + 1,
+            12..=17 => (30 + 17 - entry) * 4 + 2,
+            _ => (23 - entry) * 6 * 4 + 3,
+        };
+        while state < EXIT {
+            let routing = self.bits(state as usize / 4);
+            state = NEXT[state as usize][routing as usize];
+        }
+        state - EXIT
+    }
+
+    /// Where the line entering at each edge slot leaves the board.
+    pub fn exits(&self) -> [u8; 24] {
+        let mut exits = [0; 24];
+        for entry in 0..24 {
+            exits[entry as usize] = self.exit(entry);
+        }
+        exits
+    }
+
+    fn bits(&self, square: usize) -> u64 {
+        (self.words[square / 32] >> (square % 32 * 2)) & 3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn agrees_with_the_board() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 3);
+        for _ in 0..10 {
+            let packed = PackedRouting::from_board(&game.board);
+            for pos in BoardPosition::all() {
+                assert_eq!(packed.routing(pos), game.board.top_piece(pos).routing());
+            }
+            for entry in 0..24 {
+                assert_eq!(packed.exit(entry), game.board.exit(entry));
+            }
+            let turn = game.legal_turns_iter().next().unwrap();
+            game.play_turn(turn).unwrap();
+        }
+    }
+}
@@ -1,28 +1,325 @@
-use array_macro::array;
-use std::ops::Add;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::ops::{Add, Index};
+
+use smallvec::SmallVec;
 
 use crate::game::PasstallyError;
 use crate::piece::{Side::*, *};
+use crate::zobrist;
 
-#[derive(Clone)]
+/// The squares are kept in small flat arrays, indexed by
+/// [`BoardPosition::index`], so the whole board is a few hundred bytes and
+/// `Copy`, for searches to keep boards on the stack.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Board {
-    top_pieces: [[RotatedPartialPiece; 6]; 6], // Used to direct lines
-    tile_id: [[u32; 6]; 6], // Used to tell when you are moving from a one piece to another
-    pub next_id: u32,       // Id of the next piece, assured to be unique
-    height: [[u32; 6]; 6],  // Height of specific partial piece, used to calculate score
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    top_pieces: [RotatedPartialPiece; 36], // Used to direct lines
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    tile_id: [u16; 36], // Used to tell when you are moving from a one piece to another
+    pub next_id: u16, // Id of the next piece, assured to be unique
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    height: [u8; 36], // Height of specific partial piece, used to calculate score
+    hash: u64,        // Zobrist hash of the squares, kept up to date as pieces are placed
+    /// Where the line entering at each edge slot leaves the board, and what
+    /// it is worth, kept up to date as pieces are placed.
+    exits: [u8; 24],
+    line_points: [u16; 24],
+    /// The entries of the lines running through each square, a bit per slot,
+    /// so a placement only retraces the lines through the squares it covers.
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    lines_through: [u32; 36],
 }
 
-impl Board {
-    pub fn default() -> Self {
-        Board {
-            top_pieces: array![array![RotatedPartialPiece::new(PartialPiece::TopBottom_LeftRight, 0); 6]; 6],
-            tile_id: [[0; 6]; 6],
+/// Every edge slot, a bit each.
+const ALL_ENTRIES: u32 = (1 << 24) - 1;
+
+impl Default for Board {
+    fn default() -> Self {
+        let mut board = Board {
+            top_pieces: [RotatedPartialPiece::new(PartialPiece::TopBottom_LeftRight, 0); 36],
+            tile_id: [0; 36],
             next_id: 1,
-            height: [[0; 6]; 6],
+            height: [0; 36],
+            hash: 0,
+            exits: [0; 24],
+            line_points: [0; 24],
+            lines_through: [0; 36],
+        };
+        board.retrace(ALL_ENTRIES);
+        board
+    }
+}
+
+/// What placing a piece covered up, to take the piece off again with
+/// [`Board::undo_placement`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlacementUndo {
+    squares: [(BoardPosition, RotatedPartialPiece, u16, u32); 2],
+    next_id: u16,
+    hash: u64,
+    /// The entries of the lines through the two squares, retraced again on
+    /// undoing.
+    lines: u32,
+}
+
+/// What placing a piece changed, for frontends and the network layer to
+/// update just that rather than the whole board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChangeSet {
+    /// The two squares covered, with the piece now on top and the new height.
+    pub placed: [(BoardPosition, RotatedPartialPiece, u32); 2],
+    /// The edge slots whose lines were retraced, a bit each.
+    pub rerouted_edges: u32,
+}
+
+impl ChangeSet {
+    /// The edge slots whose lines were retraced.
+    pub fn rerouted(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..24).filter(move |slot| self.rerouted_edges & 1 << slot != 0)
+    }
+}
+
+impl Board {
+    /// Whether the pieces are valid and the lines traced through them are
+    /// the ones they make, as they are unless the board was read through
+    /// serde from somewhere untrusted.
+    pub(crate) fn is_consistent(&self) -> bool {
+        if !self.top_pieces.iter().all(RotatedPartialPiece::is_valid) {
+            return false;
         }
+        let mut retraced = *self;
+        retraced.retrace(ALL_ENTRIES);
+        retraced.exits == self.exits
+            && retraced.line_points == self.line_points
+            && retraced.lines_through == self.lines_through
+    }
+
+    /// How many pieces are stacked on the board, going by the heights of its
+    /// squares, or `None` if no stacks of whole pieces are that tall. Every
+    /// piece covers two different squares, so the heights add up to an even
+    /// number and no square is taller than all the others together.
+    pub(crate) fn pieces_stacked(&self) -> Option<u32> {
+        let squares: u32 = self.height.iter().map(|&height| height as u32).sum();
+        let tallest = self.height.iter().copied().max().unwrap_or(0) as u32;
+        Some(squares / 2).filter(|&pieces| squares.is_multiple_of(2) && tallest <= pieces)
     }
 
     pub fn place_piece(&mut self, piece: PositionedPiece) -> Result<(), PasstallyError> {
+        self.place_piece_undoably(piece).map(|_| ())
+    }
+
+    /// Like [`place_piece`](Board::place_piece), returning what changed.
+    pub fn place_piece_with_changes(
+        &mut self,
+        piece: PositionedPiece,
+    ) -> Result<ChangeSet, PasstallyError> {
+        let undo = self.place_piece_undoably(piece)?;
+        let [(pos1, ..), (pos2, ..)] = undo.squares;
+        Ok(ChangeSet {
+            placed: [
+                (pos1, self.top_piece(pos1), self.height(pos1)),
+                (pos2, self.top_piece(pos2), self.height(pos2)),
+            ],
+            rerouted_edges: undo.lines,
+        })
+    }
+
+    /// Like [`place_piece`](Board::place_piece), returning what it takes to
+    /// undo the placement. Nothing changes when the piece can't be placed.
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "trace", skip(self))
+    )]
+    pub(crate) fn place_piece_undoably(
+        &mut self,
+        piece: PositionedPiece,
+    ) -> Result<PlacementUndo, PasstallyError> {
+        self.can_place(&piece)?;
+        let (pos1, pos2) = piece.positions();
+        let square = |pos| {
+            (
+                pos,
+                self.top_piece(pos),
+                self.tile_id(pos),
+                self.height(pos),
+            )
+        };
+        let undo = PlacementUndo {
+            squares: [square(pos1), square(pos2)],
+            next_id: self.next_id,
+            hash: self.hash,
+            lines: self.lines_through(pos1) | self.lines_through(pos2),
+        };
+
+        // The squares whose hash changes: the two we cover, and the halves
+        // they were paired with, which are left without a partner.
+        let partner = |pos: BoardPosition| self.partner(pos).map(|side| pos.step(side));
+        let changed = [Some(pos1), Some(pos2), partner(pos1), partner(pos2)];
+        for &pos in changed.iter().flatten() {
+            self.hash ^= self.square_hash(pos);
+        }
+
+        // This is a valid move, so we do it
+        self.set_height(pos1, self.height(pos1) + 1);
+        self.set_height(pos2, self.height(pos2) + 1);
+
+        self.set_tile_id(pos1, self.next_id);
+        self.set_tile_id(pos2, self.next_id);
+        self.next_id += 1;
+
+        let (piece1, piece2) = piece.rotated_partial_pieces();
+        self.set_top_piece(pos1, piece1);
+        self.set_top_piece(pos2, piece2);
+
+        for &pos in changed.iter().flatten() {
+            self.hash ^= self.square_hash(pos);
+        }
+        self.retrace(undo.lines);
+
+        Ok(undo)
+    }
+
+    /// Takes the last piece placed back off the board.
+    pub(crate) fn undo_placement(&mut self, undo: PlacementUndo) {
+        // The same lines run through the two squares before and after.
+        for &(pos, top_piece, tile_id, height) in &undo.squares {
+            self.set_top_piece(pos, top_piece);
+            self.set_tile_id(pos, tile_id);
+            self.set_height(pos, height);
+        }
+        self.next_id = undo.next_id;
+        self.hash = undo.hash;
+        self.retrace(undo.lines);
+    }
+
+    /// The board as seen through the symmetry.
+    pub fn transformed(&self, symmetry: Symmetry) -> Board {
+        let mut board = Board::default();
+        for pos in BoardPosition::all() {
+            let to = symmetry.position(pos);
+            let routing = symmetry.routing(self.top_piece(pos).routing());
+            board.set_top_piece(to, RotatedPartialPiece::new(routing, 0));
+            board.set_tile_id(to, self.tile_id(pos));
+            board.set_height(to, self.height(pos));
+        }
+        board.next_id = self.next_id;
+        board.rehash();
+        board
+    }
+
+    /// The position under whichever of the 8 rotations and reflections of the
+    /// board sorts first, with the markers moved along with it. Positions that
+    /// are symmetric to each other get the same canonical form, down to the hash.
+    /// The symmetry that was used is returned to map moves back and forth.
+    pub fn canonicalize(
+        &self,
+        player_markers: &[Option<u8>; 24],
+    ) -> (Board, [Option<u8>; 24], Symmetry) {
+        Symmetry::all()
+            .map(|symmetry| {
+                (
+                    self.transformed(symmetry),
+                    symmetry.markers(player_markers),
+                    symmetry,
+                )
+            })
+            .min_by_key(|(board, markers, _)| (board.squares(), *markers))
+            .unwrap()
+    }
+
+    /// What each square looks like, row by row, for comparing positions.
+    fn squares(&self) -> Vec<(u32, u8, Option<u8>)> {
+        BoardPosition::all()
+            .map(|pos| {
+                (
+                    self.height(pos),
+                    self.top_piece(pos).routing() as u8,
+                    self.partner(pos).map(|side| side as u8),
+                )
+            })
+            .collect()
+    }
+
+    /// Zobrist hash of what lies on the board. Boards with the same pieces on
+    /// top, at the same heights, hash the same whatever order they were placed in.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Recomputes the hash and the lines from scratch, for when the squares
+    /// were set directly.
+    pub(crate) fn rehash(&mut self) {
+        self.hash = BoardPosition::all()
+            .map(|pos| self.square_hash(pos))
+            .fold(0, |hash, key| hash ^ key);
+        self.retrace(ALL_ENTRIES);
+    }
+
+    /// Follows the lines entering at the slots in `entries` again, updating
+    /// where they leave, what they are worth and the squares they run
+    /// through. Lines not passing through a changed square stay as they are.
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "trace", skip(self))
+    )]
+    fn retrace(&mut self, entries: u32) {
+        for lines in self.lines_through.iter_mut() {
+            *lines &= !entries;
+        }
+        for entry in (0..24).filter(|entry| entries & 1 << entry != 0) {
+            let mut squares = [false; 36];
+            let (exit, points) = self.walk(entry, |pos| squares[pos.index()] = true);
+            self.exits[entry as usize] = exit;
+            self.line_points[entry as usize] = points as u16;
+            for (lines, _) in self
+                .lines_through
+                .iter_mut()
+                .zip(squares)
+                .filter(|(_, on)| *on)
+            {
+                *lines |= 1 << entry;
+            }
+        }
+    }
+
+    fn lines_through(&self, pos: BoardPosition) -> u32 {
+        self.lines_through[pos.index()]
+    }
+
+    fn square_hash(&self, pos: BoardPosition) -> u64 {
+        zobrist::square(
+            pos,
+            self.height(pos),
+            self.top_piece(pos).routing(),
+            self.partner(pos),
+        )
+    }
+
+    /// The side of the square the other half of its top piece lies on, if that
+    /// half hasn't been covered.
+    pub(crate) fn partner(&self, pos: BoardPosition) -> Option<Side> {
+        if self.tile_id(pos) == 0 {
+            return None;
+        }
+        [Top, Right, Bottom, Left].iter().copied().find(|&side| {
+            let neighbour = pos.step(side);
+            neighbour.valid() && self.tile_id(neighbour) == self.tile_id(pos)
+        })
+    }
+
+    /// Checks whether the piece could be placed, without placing it.
+    pub fn can_place(&self, piece: &PositionedPiece) -> Result<(), PasstallyError> {
+        if piece.rotation > 3 {
+            return Err(PasstallyError::BadRotation(piece.rotation));
+        }
         let (pos1, pos2) = piece.positions();
 
         // Assert position is within board
@@ -46,72 +343,237 @@ impl Board {
             return Err(PasstallyError::BadPiece);
         }
 
-        // This is a valid move, so we do it
-        *self.height_mut(pos1) += 1;
-        *self.height_mut(pos2) += 1;
+        Ok(())
+    }
 
-        *self.tile_id_mut(pos1) = self.next_id;
-        *self.tile_id_mut(pos2) = self.next_id;
-        self.next_id += 1;
+    /// The squares a piece can lie across with its other half on the square
+    /// to the right, and with it on the square below, a bit each by
+    /// [`BoardPosition::index`]. What [`can_place`](Board::can_place) says
+    /// for every pair of neighbouring squares, read straight off the flat
+    /// arrays without branching, for move generation.
+    pub(crate) fn placeable_pairs(&self) -> (u64, u64) {
+        // Pairs of squares at the same height, not both under the same
+        // piece.
+        let pairs = |step: usize| {
+            let mut fits = 0u64;
+            for square in 0..36 - step {
+                let (id, other) = (self.tile_id[square], self.tile_id[square + step]);
+                let level = self.height[square] == self.height[square + step];
+                fits |= ((level & (id == 0 || id != other)) as u64) << square;
+            }
+            fits
+        };
+        // The right neighbour of the last column is the next row's first.
+        const NOT_LAST_COLUMN: u64 = 0x7df7df7df;
+        (pairs(1) & NOT_LAST_COLUMN, pairs(6))
+    }
 
-        let (piece1, piece2) = piece.rotated_partial_pieces();
-        *self.top_piece_mut(pos1) = piece1;
-        *self.top_piece_mut(pos2) = piece2;
+    /// Squares no piece has been placed on yet.
+    pub fn empty_cells(&self) -> impl Iterator<Item = BoardPosition> + '_ {
+        self.cells_at_height(0)
+    }
 
-        Ok(())
+    /// Squares whose stack is exactly `height` pieces tall.
+    pub fn cells_at_height(&self, height: u32) -> impl Iterator<Item = BoardPosition> + '_ {
+        BoardPosition::all().filter(move |&pos| self.height(pos) == height)
+    }
+
+    /// The rotations the piece can be placed with when its first half is at `position`.
+    pub fn placeable_rotations_at(
+        &self,
+        position: BoardPosition,
+        piece: Piece,
+    ) -> impl Iterator<Item = u8> + '_ {
+        (0..4).filter(move |&rotation| {
+            self.can_place(&PositionedPiece {
+                piece,
+                rotation,
+                position,
+            })
+            .is_ok()
+        })
     }
 
-    // TODO: calulate points
-    fn enter(&self, entry: BoardPosition, mut side: Side) -> BoardPosition {
-        let mut pos = entry;
-        while pos == entry || !pos.on_edge() {
+    /// Follows the line entering the board at the edge slot until it leaves the board again.
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "trace", skip(self))
+    )]
+    pub fn trace(&self, entry: u8) -> LineTrace {
+        let mut path = Path::new();
+        let (exit, points) = self.walk(entry, |pos| path.push(pos));
+        LineTrace {
+            entry,
+            exit,
+            path,
+            points,
+        }
+    }
+
+    /// Where the line entering at the edge slot leaves the board, without
+    /// tracing it again.
+    pub fn exit(&self, entry: u8) -> u8 {
+        self.exits[entry as usize]
+    }
+
+    /// The sum of the heights of the squares the line entering at the edge
+    /// slot passes through, without tracing it again.
+    pub fn line_points(&self, entry: u8) -> u32 {
+        self.line_points[entry as usize] as u32
+    }
+
+    /// Follows the line coming into the square from the side, piece by
+    /// piece, until it leaves the board. Returns the last square it passes
+    /// through and the side it leaves that square by.
+    pub fn enter(&self, entry: BoardPosition, side: Side) -> (BoardPosition, Side) {
+        self.follow(entry, side, |_| {})
+    }
+
+    /// Walks the line from the edge slot, calling `visit` on each square
+    /// passed through, and returns where it leaves and what it is worth.
+    fn walk(&self, entry: u8, mut visit: impl FnMut(BoardPosition)) -> (u8, u32) {
+        let (pos, side) = edge_square(entry);
+        let mut points = 0;
+        let (last, exit_side) = self.follow(pos, side, |pos| {
+            visit(pos);
+            points += self.height(pos);
+        });
+        (edge_slot(last, exit_side), points)
+    }
+
+    fn follow(
+        &self,
+        mut pos: BoardPosition,
+        mut side: Side,
+        mut visit: impl FnMut(BoardPosition),
+    ) -> (BoardPosition, Side) {
+        loop {
+            visit(pos);
+
             // Where does this piece take us?
             let exit_side = self.top_piece(pos).pass(side);
-            println!("{:?} {:?}", pos, exit_side);
-            // Calculate delta_position
-            let delta_position = match exit_side {
-                Top => (0, -1),
-                Bottom => (0, 1),
-                Left => (-1, 0),
-                Right => (1, 0),
-            };
-            pos.x += delta_position.0;
-            pos.y += delta_position.1;
+            let next = pos.step(exit_side);
+            if !next.valid() {
+                return (pos, exit_side);
+            }
 
             // Next enter side is the opposite of exit side
+            pos = next;
             side = exit_side.opposite();
         }
-        pos
     }
 
-    fn top_piece(&self, i: BoardPosition) -> &RotatedPartialPiece {
-        &self.top_pieces[i.x as usize][i.y as usize]
+    /// Every line on the board, indexed by the edge slot it enters at, for
+    /// drawing all of them at once. Each line is followed once and reversed
+    /// for its other end, so it takes half the work of 24 traces.
+    pub fn trace_all_edges(&self) -> Vec<LineTrace> {
+        let mut lines: Vec<Option<LineTrace>> = vec![None; 24];
+        for entry in 0..24 {
+            if lines[entry as usize].is_some() {
+                continue;
+            }
+            let line = self.trace(entry);
+            lines[line.exit as usize] = Some(LineTrace {
+                entry: line.exit,
+                exit: entry,
+                path: line.path.iter().rev().copied().collect(),
+                points: line.points,
+            });
+            lines[entry as usize] = Some(line);
+        }
+        lines
+            .into_iter()
+            .map(|line| line.expect("Every slot is the end of a line"))
+            .collect()
+    }
+
+    /// Lines connecting two of the player's markers, each reported once.
+    pub fn connected_lines<'a>(
+        &'a self,
+        player_markers: &'a [Option<u8>; 24],
+        player: u8,
+    ) -> impl Iterator<Item = LineTrace> + 'a {
+        self.connected_entries(player_markers, player)
+            .map(move |slot| self.trace(slot))
+    }
+
+    /// What the lines connecting two of the player's markers are worth, from
+    /// the lines kept on the board rather than tracing them.
+    pub fn connected_points(&self, player_markers: &[Option<u8>; 24], player: u8) -> u32 {
+        self.connected_entries(player_markers, player)
+            .map(|slot| self.line_points(slot))
+            .sum()
+    }
+
+    /// The lower slot of each line connecting two of the player's markers.
+    fn connected_entries<'a>(
+        &'a self,
+        player_markers: &'a [Option<u8>; 24],
+        player: u8,
+    ) -> impl Iterator<Item = u8> + 'a {
+        (0..24).filter(move |&slot| {
+            let exit = self.exit(slot);
+            player_markers[slot as usize] == Some(player)
+                && exit > slot
+                && player_markers[exit as usize] == Some(player)
+        })
     }
 
-    fn tile_id(&self, i: BoardPosition) -> u32 {
-        self.tile_id[i.x as usize][i.y as usize]
+    pub(crate) fn top_piece(&self, i: BoardPosition) -> RotatedPartialPiece {
+        self.top_pieces[i.index()]
     }
 
-    fn height(&self, i: BoardPosition) -> u32 {
-        self.height[i.x as usize][i.y as usize]
+    pub(crate) fn tile_id(&self, i: BoardPosition) -> u16 {
+        self.tile_id[i.index()]
     }
 
-    fn top_piece_mut(&mut self, i: BoardPosition) -> &mut RotatedPartialPiece {
-        &mut self.top_pieces[i.x as usize][i.y as usize]
+    pub fn height(&self, i: BoardPosition) -> u32 {
+        self.height[i.index()] as u32
     }
 
-    fn tile_id_mut(&mut self, i: BoardPosition) -> &mut u32 {
-        &mut self.tile_id[i.x as usize][i.y as usize]
+    pub(crate) fn set_top_piece(&mut self, i: BoardPosition, piece: RotatedPartialPiece) {
+        self.top_pieces[i.index()] = piece;
+    }
+
+    pub(crate) fn set_tile_id(&mut self, i: BoardPosition, id: u16) {
+        self.tile_id[i.index()] = id;
+    }
+
+    /// Stacks are at most 42 pieces tall, which a byte holds. A taller one
+    /// panics rather than wrapping round to an empty square.
+    pub(crate) fn set_height(&mut self, i: BoardPosition, height: u32) {
+        self.height[i.index()] = u8::try_from(height).expect("Stacks are at most 42 pieces tall");
+    }
+}
+
+/// The piece lying on top of the square.
+impl Index<BoardPosition> for Board {
+    type Output = RotatedPartialPiece;
+
+    fn index(&self, pos: BoardPosition) -> &RotatedPartialPiece {
+        &self.top_pieces[pos.index()]
     }
+}
 
-    fn height_mut(&mut self, i: BoardPosition) -> &mut u32 {
-        &mut self.height[i.x as usize][i.y as usize]
+/// Boards are equal when the same routings lie on top at the same heights,
+/// paired up the same way, no matter which order the pieces were placed in.
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.squares() == other.squares()
     }
 }
 
+impl Eq for Board {}
+
 /// Position on board. x and y value are 0..=5 when on the board
 /// 0,0 is at the top left. x is horizontal and y is vertical
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct BoardPosition {
     pub x: i8,
     pub y: i8,
@@ -122,21 +584,167 @@ impl BoardPosition {
         BoardPosition { x, y }
     }
 
-    fn on_edge(&self) -> bool {
-        self.x == 0 || self.y == 0 || self.x == 5 || self.y == 5
+    /// Every position on the board, row by row.
+    pub fn all() -> impl Iterator<Item = BoardPosition> {
+        (0..6).flat_map(|y| (0..6).map(move |x| BoardPosition::new(x, y)))
+    }
+
+    /// Where the square is kept in the board's arrays, counting row by row
+    /// from the top left, in the order of [`BoardPosition::all`].
+    ///
+    /// Panics for positions off the board, which would otherwise share an
+    /// index with one on it.
+    pub fn index(self) -> usize {
+        assert!(self.valid(), "{:?} is off the board", self);
+        self.y as usize * 6 + self.x as usize
     }
 
-    fn valid(&self) -> bool {
+    /// The neighbouring position in the direction of the side.
+    pub fn step(self, side: Side) -> Self {
+        match side {
+            Top => self + BoardPosition::new(0, -1),
+            Bottom => self + BoardPosition::new(0, 1),
+            Left => self + BoardPosition::new(-1, 0),
+            Right => self + BoardPosition::new(1, 0),
+        }
+    }
+
+    pub(crate) fn valid(&self) -> bool {
         self.x <= 5 && self.x >= 0 && self.y <= 5 && self.y >= 0
     }
 }
 
+/// The squares a line passes through, kept inline up to 36 so tracing
+/// doesn't allocate. Only a line passing most squares twice spills onto the
+/// heap.
+pub type Path = SmallVec<[BoardPosition; 36]>;
+
+/// A line followed across the board between two edge slots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineTrace {
+    pub entry: u8,
+    pub exit: u8,
+    /// The squares passed through in order. A square can be passed twice.
+    pub path: Path,
+    /// The sum of the heights of the squares passed through.
+    pub points: u32,
+}
+
+/// One of the 8 ways to turn or flip the board onto itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symmetry {
+    /// Mirror left to right.
+    pub mirrored: bool,
+    /// Quarter turns clockwise, done after mirroring.
+    pub rotation: u8,
+}
+
+impl Symmetry {
+    pub const IDENTITY: Symmetry = Symmetry {
+        mirrored: false,
+        rotation: 0,
+    };
+
+    pub fn all() -> impl Iterator<Item = Symmetry> {
+        [false, true]
+            .iter()
+            .flat_map(|&mirrored| (0..4).map(move |rotation| Symmetry { mirrored, rotation }))
+    }
+
+    /// The symmetry that undoes this one.
+    pub fn inverse(self) -> Symmetry {
+        if self.mirrored {
+            // Mirroring and then turning is its own inverse.
+            self
+        } else {
+            Symmetry {
+                mirrored: false,
+                rotation: (4 - self.rotation) % 4,
+            }
+        }
+    }
+
+    pub fn position(self, pos: BoardPosition) -> BoardPosition {
+        let mut pos = pos;
+        if self.mirrored {
+            pos = BoardPosition::new(5 - pos.x, pos.y);
+        }
+        for _ in 0..self.rotation {
+            pos = BoardPosition::new(5 - pos.y, pos.x);
+        }
+        pos
+    }
+
+    pub fn side(self, side: Side) -> Side {
+        let side = match (self.mirrored, side) {
+            (true, Left) => Right,
+            (true, Right) => Left,
+            (_, side) => side,
+        };
+        side.rotate(self.rotation)
+    }
+
+    /// Where the edge slot ends up.
+    pub fn slot(self, slot: u8) -> u8 {
+        let (pos, side) = edge_square(slot);
+        edge_slot(self.position(pos), self.side(side))
+    }
+
+    /// The markers moved to where their slots end up.
+    pub fn markers(self, player_markers: &[Option<u8>; 24]) -> [Option<u8>; 24] {
+        let mut markers = [None; 24];
+        for slot in 0..24 {
+            markers[self.slot(slot) as usize] = player_markers[slot as usize];
+        }
+        markers
+    }
+
+    fn routing(self, routing: PartialPiece) -> PartialPiece {
+        use PartialPiece::*;
+
+        // Both mirroring and a quarter turn swap the two curved routings.
+        let swaps = self.mirrored as u8 + self.rotation;
+        match routing {
+            TopLeft_BottomRight if swaps % 2 == 1 => TopRight_BottomLeft,
+            TopRight_BottomLeft if swaps % 2 == 1 => TopLeft_BottomRight,
+            routing => routing,
+        }
+    }
+}
+
+/// The square and side an edge slot (0..=23) lies next to. Slots go clockwise
+/// from the top left corner, six per side.
+pub fn edge_square(slot: u8) -> (BoardPosition, Side) {
+    let i = (slot % 6) as i8;
+    match slot {
+        0..=5 => (BoardPosition::new(i, 0), Top),
+        6..=11 => (BoardPosition::new(5, i), Right),
+        12..=17 => (BoardPosition::new(5 - i, 5), Bottom),
+        18..=23 => (BoardPosition::new(0, 5 - i), Left),
+        _ => unreachable!("Edge slots should only be 0-23"),
+    }
+}
+
+/// The edge slot next to a side of a square on the edge of the board.
+pub fn edge_slot(pos: BoardPosition, side: Side) -> u8 {
+    let slot = match side {
+        Top => pos.x,
+        Right => 6 + pos.y,
+        Bottom => 12 + (5 - pos.x),
+        Left => 18 + (5 - pos.y),
+    };
+    debug_assert_eq!(edge_square(slot as u8), (pos, side));
+    slot as u8
+}
+
+/// Wraps around rather than overflowing, as positions that far out are off
+/// the board either way.
 impl Add for BoardPosition {
     type Output = Self;
     fn add(self, rhs: Self) -> Self {
         BoardPosition {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
+            x: self.x.wrapping_add(rhs.x),
+            y: self.y.wrapping_add(rhs.y),
         }
     }
 }
@@ -160,15 +768,95 @@ mod tests {
         }
     }
 
+    #[test]
+    #[should_panic(expected = "off the board")]
+    fn off_board_positions_have_no_index() {
+        // (6, 0) would otherwise be taken for (0, 1), in release builds too.
+        Board::default().height(BoardPosition::new(6, 0));
+    }
+
     #[test]
     fn simple_board() {
         let board = Board::default();
 
-        let a = board.enter(BoardPosition::new(2, 0), Side::Top);
-        assert_eq!(a, BoardPosition::new(2, 5));
+        let a = board.trace(edge_slot(BoardPosition::new(2, 0), Side::Top));
+        assert_eq!(
+            edge_square(a.exit),
+            (BoardPosition::new(2, 5), Side::Bottom)
+        );
+        assert_eq!(a.path.len(), 6);
+        assert!(!a.path.spilled());
+        assert_eq!(a.points, 0);
+
+        let b = board.trace(edge_slot(BoardPosition::new(0, 2), Side::Left));
+        assert_eq!(edge_square(b.exit), (BoardPosition::new(5, 2), Side::Right));
+    }
+
+    #[test]
+    fn enter_follows_lines_off_the_board() {
+        let board = Board::default();
+        assert_eq!(
+            board.enter(BoardPosition::new(2, 0), Top),
+            (BoardPosition::new(2, 5), Bottom)
+        );
+        assert_eq!(
+            board.enter(BoardPosition::new(3, 4), Right),
+            (BoardPosition::new(0, 4), Left)
+        );
+    }
+
+    #[test]
+    fn edge_slots() {
+        for slot in 0..24 {
+            let (pos, side) = edge_square(slot);
+            assert!(!pos.step(side).valid());
+            assert_eq!(edge_slot(pos, side), slot);
+        }
+    }
+
+    #[test]
+    fn traces_are_reversible() {
+        let mut board = Board::default();
+        for (x, y, rotation) in &[(0, 0, 0), (1, 1, 2), (0, 0, 1), (3, 4, 3), (2, 2, 0)] {
+            board
+                .place_piece(PositionedPiece {
+                    piece: Piece::Pink,
+                    position: BoardPosition::new(*x, *y),
+                    rotation: *rotation,
+                })
+                .unwrap();
+        }
 
-        let b = board.enter(BoardPosition::new(0, 2), Side::Left);
-        assert_eq!(b, BoardPosition::new(5, 2));
+        for slot in 0..24 {
+            let line = board.trace(slot);
+            let back = board.trace(line.exit);
+            assert_eq!(back.exit, slot);
+            assert_eq!(back.points, line.points);
+        }
+    }
+
+    #[test]
+    fn connected_lines() {
+        let mut board = Board::default();
+        // The straight line down column a now passes over a piece.
+        board
+            .place_piece(PositionedPiece {
+                piece: Piece::Red,
+                position: BoardPosition::new(0, 0),
+                rotation: 0,
+            })
+            .unwrap();
+
+        let mut player_markers = [None; 24];
+        player_markers[0] = Some(0);
+        player_markers[17] = Some(0);
+        player_markers[1] = Some(1);
+
+        let lines: Vec<LineTrace> = board.connected_lines(&player_markers, 0).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!((lines[0].entry, lines[0].exit), (0, 17));
+        assert_eq!(lines[0].points, 1);
+        assert_eq!(board.connected_lines(&player_markers, 1).count(), 0);
     }
 
     #[test]
@@ -189,11 +877,39 @@ mod tests {
                         rotated_partial_piece.pass(rotated_partial_piece.pass(*side)),
                         *side
                     );
+                    // The lookup table agrees with rotating into the piece and back.
+                    let local = side.rotate(4 - rotation);
+                    assert_eq!(
+                        rotated_partial_piece.pass(*side),
+                        partial_piece.pass(local).rotate(rotation)
+                    );
                 }
             }
         }
     }
 
+    #[test]
+    fn boards_are_packed() {
+        assert!(std::mem::size_of::<Board>() <= 384);
+        assert_eq!(std::mem::size_of::<RotatedPartialPiece>(), 1);
+        let piece = RotatedPartialPiece::new(PartialPiece::TopRight_BottomLeft, 7);
+        assert_eq!(piece.partial_piece(), PartialPiece::TopRight_BottomLeft);
+        assert_eq!(piece.rotation(), 3);
+    }
+
+    #[test]
+    fn squares_are_indexed_row_by_row() {
+        for (i, pos) in BoardPosition::all().enumerate() {
+            assert_eq!(pos.index(), i);
+        }
+        let mut board = Board::default();
+        let pos = BoardPosition::new(4, 1);
+        let piece = RotatedPartialPiece::new(PartialPiece::TopLeft_BottomRight, 2);
+        board.set_top_piece(pos, piece);
+        assert_eq!(board[pos], piece);
+        assert_eq!(board[BoardPosition::new(1, 4)], Board::default()[pos]);
+    }
+
     #[test]
     fn place_pieces() {
         let mut board = Board::default();
@@ -246,11 +962,17 @@ mod tests {
         };
         board.place_piece(piece).unwrap();
 
+        assert_eq!(board.empty_cells().count(), 32);
+        assert_eq!(board.cells_at_height(2).count(), 2);
+        assert_eq!(board.cells_at_height(1).count(), 2);
+
+        // Row by row, the way the board is drawn above.
+        let rows: Vec<_> = board.height.chunks(6).collect();
         assert_eq!(
-            board.height,
+            rows,
             [
-                [2, 2, 0, 0, 0, 0], // The x and y axes are swapped.
-                [1, 1, 0, 0, 0, 0], // So just transpose this in your mind
+                [2, 1, 0, 0, 0, 0],
+                [2, 1, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0],
@@ -258,4 +980,204 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn placeable_rotations() {
+        let mut board = Board::default();
+
+        // Corners only allow the two rotations pointing into the board.
+        let corner: Vec<u8> = board
+            .placeable_rotations_at(BoardPosition::new(0, 0), Piece::Red)
+            .collect();
+        assert_eq!(corner, vec![0, 1]);
+
+        for y in 2..4 {
+            board
+                .place_piece(PositionedPiece {
+                    piece: Piece::Red,
+                    position: BoardPosition::new(2, y),
+                    rotation: 0,
+                })
+                .unwrap();
+        }
+
+        // Only pointing down bridges the two pieces. Pointing left would sit directly on top.
+        let rotations: Vec<u8> = board
+            .placeable_rotations_at(BoardPosition::new(3, 2), Piece::Red)
+            .collect();
+        assert_eq!(rotations, vec![1]);
+
+        // Next to them, only pointing left is uneven.
+        let rotations: Vec<u8> = board
+            .placeable_rotations_at(BoardPosition::new(4, 2), Piece::Red)
+            .collect();
+        assert_eq!(rotations, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn hash() {
+        let place = |board: &mut Board, x, y, rotation| {
+            board
+                .place_piece(PositionedPiece {
+                    piece: Piece::Cyan,
+                    position: BoardPosition::new(x, y),
+                    rotation,
+                })
+                .unwrap();
+            let mut rehashed = *board;
+            rehashed.rehash();
+            assert_eq!(board.hash(), rehashed.hash());
+        };
+
+        let mut board = Board::default();
+        assert_eq!(board.hash(), 0);
+        place(&mut board, 0, 0, 0);
+        place(&mut board, 0, 1, 0);
+        let before = board.hash();
+        // Covers one half of each, leaving the other halves unpaired.
+        place(&mut board, 0, 0, 1);
+        assert_ne!(board.hash(), before);
+
+        // The order pieces were placed in doesn't matter.
+        let mut other = Board::default();
+        place(&mut other, 0, 1, 0);
+        place(&mut other, 0, 0, 0);
+        place(&mut other, 0, 0, 1);
+        assert_eq!(board.hash(), other.hash());
+    }
+
+    #[test]
+    fn reports_changes() {
+        let mut board = Board::default();
+        let before = board;
+        let piece = PositionedPiece {
+            piece: Piece::Pink,
+            position: BoardPosition::new(2, 3),
+            rotation: 1,
+        };
+        let changes = board.place_piece_with_changes(piece.clone()).unwrap();
+        let (piece1, piece2) = piece.rotated_partial_pieces();
+        assert_eq!(
+            changes.placed,
+            [
+                (BoardPosition::new(2, 3), piece1, 1),
+                (BoardPosition::new(2, 4), piece2, 1)
+            ]
+        );
+
+        // The lines through the two squares are reported, and only those.
+        for slot in 0..24 {
+            let covered = |pos: &BoardPosition| pos.x == 2 && (pos.y == 3 || pos.y == 4);
+            let rerouted = changes.rerouted().any(|rerouted| rerouted == slot);
+            assert_eq!(rerouted, before.trace(slot).path.iter().any(covered));
+            assert!(rerouted || before.trace(slot) == board.trace(slot));
+        }
+    }
+
+    #[test]
+    fn lines_are_kept_up_to_date() {
+        let check = |board: &Board| {
+            for entry in 0..24 {
+                let line = board.trace(entry);
+                assert_eq!(board.exit(entry), line.exit);
+                assert_eq!(board.line_points(entry), line.points);
+            }
+        };
+        let pieces = [
+            Piece::Red,
+            Piece::Pink,
+            Piece::Cyan,
+            Piece::Green,
+            Piece::Yellow,
+            Piece::Blue,
+        ];
+
+        let mut board = Board::default();
+        let mut undos = Vec::new();
+        for i in 0..30 {
+            let placement = (0..36 * 4)
+                .map(|n| (i * 7 + n) % (36 * 4))
+                .map(|n| PositionedPiece {
+                    piece: pieces[i % 6],
+                    position: BoardPosition::new((n / 4 % 6) as i8, (n / 24) as i8),
+                    rotation: (n % 4) as u8,
+                })
+                .find(|piece| board.can_place(piece).is_ok())
+                .unwrap();
+            undos.push(board.place_piece_undoably(placement).unwrap());
+            check(&board);
+        }
+        while let Some(undo) = undos.pop() {
+            board.undo_placement(undo);
+            check(&board);
+        }
+        assert_eq!(board, Board::default());
+    }
+
+    #[test]
+    fn traces_all_edges() {
+        let mut board = Board::default();
+        for &(x, y, rotation, piece) in &[
+            (1, 1, 0, Piece::Pink),
+            (2, 2, 1, Piece::Cyan),
+            (3, 1, 0, Piece::Blue),
+        ] {
+            board
+                .place_piece(PositionedPiece {
+                    piece,
+                    position: BoardPosition::new(x, y),
+                    rotation,
+                })
+                .unwrap();
+        }
+        let lines = board.trace_all_edges();
+        assert_eq!(lines.len(), 24);
+        for (entry, line) in lines.iter().enumerate() {
+            assert_eq!(*line, board.trace(entry as u8));
+        }
+    }
+
+    #[test]
+    fn symmetries() {
+        let mut board = Board::default();
+        for &(x, y, rotation, piece) in &[
+            (0, 0, 0, Piece::Pink),
+            (0, 1, 0, Piece::Green),
+            (0, 0, 1, Piece::Cyan),
+            (3, 4, 1, Piece::Blue),
+        ] {
+            board
+                .place_piece(PositionedPiece {
+                    piece,
+                    rotation,
+                    position: BoardPosition::new(x, y),
+                })
+                .unwrap();
+        }
+        let mut player_markers = [None; 24];
+        player_markers[0] = Some(0);
+        player_markers[9] = Some(1);
+        player_markers[14] = Some(0);
+
+        let (canonical, canonical_markers, _) = board.canonicalize(&player_markers);
+        for symmetry in Symmetry::all() {
+            assert_eq!(symmetry.inverse().slot(symmetry.slot(5)), 5);
+
+            // Lines go the same way through the transformed board.
+            let transformed = board.transformed(symmetry);
+            for slot in 0..24 {
+                let line = board.trace(slot);
+                let moved = transformed.trace(symmetry.slot(slot));
+                assert_eq!(moved.exit, symmetry.slot(line.exit));
+                assert_eq!(moved.points, line.points);
+            }
+
+            // And every symmetric position has the same canonical form.
+            let markers = symmetry.markers(&player_markers);
+            let (other, other_markers, applied) = transformed.canonicalize(&markers);
+            assert_eq!(other.hash(), canonical.hash());
+            assert_eq!(other_markers, canonical_markers);
+            assert_eq!(transformed.transformed(applied).hash(), canonical.hash());
+        }
+    }
 }
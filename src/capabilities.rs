@@ -0,0 +1,51 @@
+//! What this build can do, for frontends to hide what was compiled out rather
+//! than fail when it's picked, and for the server to advertise its bot seats.
+
+/// A computer opponent that can take a seat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BotInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// The bots built into this build, none without the `ai` feature.
+pub fn available_bots() -> Vec<BotInfo> {
+    #[cfg(feature = "ai")]
+    {
+        crate::ai::Preset::builtin()
+            .into_iter()
+            .map(|preset| BotInfo {
+                name: preset.name,
+                description: preset.description,
+            })
+            .collect()
+    }
+    #[cfg(not(feature = "ai"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Whether positions can be analysed, which needs the `ai` feature.
+pub fn supports_analysis() -> bool {
+    cfg!(feature = "ai")
+}
+
+/// Whether bots can evaluate positions with a neural network, which needs the
+/// `nn` feature.
+pub fn supports_neural_evaluation() -> bool {
+    cfg!(feature = "nn")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_features() {
+        assert_eq!(available_bots().is_empty(), !cfg!(feature = "ai"));
+        assert_eq!(supports_analysis(), cfg!(feature = "ai"));
+        assert!(!supports_neural_evaluation() || supports_analysis());
+    }
+}
@@ -0,0 +1,135 @@
+//! Time controls. Each player starts with the same amount of time, which runs
+//! down while it is their turn, and gets an increment after each turn they
+//! finish. A player whose time runs out loses on time.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct TimeControl {
+    /// Time each player starts with.
+    pub base: Duration,
+    /// Time added after each turn.
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    pub fn new(base: Duration, increment: Duration) -> TimeControl {
+        TimeControl { base, increment }
+    }
+}
+
+/// The players' remaining time. Two clocks are equal when they have the same
+/// time control and times left, whenever the current turn started.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: Vec<Duration>,
+    /// When the current turn started. Not saved: a loaded game starts the turn
+    /// over. Without `std` there is no time to read, and turns take as long
+    /// as whoever plays them says.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    turn_started: Option<Instant>,
+}
+
+impl Clock {
+    /// A clock with the full base time for each player, already running.
+    pub fn new(control: TimeControl, player_count: u8) -> Clock {
+        Clock {
+            control,
+            remaining: vec![control.base; player_count as usize],
+            #[cfg(feature = "std")]
+            turn_started: Some(Instant::now()),
+        }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// Amount of players the clock keeps time for.
+    pub(crate) fn player_count(&self) -> usize {
+        self.remaining.len()
+    }
+
+    /// The player's time left, not counting the turn in progress.
+    pub fn remaining(&self, player: u8) -> Duration {
+        self.remaining[player as usize]
+    }
+
+    /// How long the current turn has taken so far.
+    #[cfg(feature = "std")]
+    pub fn elapsed(&self) -> Duration {
+        self.turn_started
+            .map_or(Duration::from_secs(0), |started| started.elapsed())
+    }
+
+    /// How long the current turn has taken so far, which is no time at all
+    /// without `std` to tell.
+    #[cfg(not(feature = "std"))]
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+
+    /// Whether the player would run out of time by taking `elapsed` for their
+    /// turn.
+    pub fn is_out_of_time(&self, player: u8, elapsed: Duration) -> bool {
+        elapsed > self.remaining(player)
+    }
+
+    /// Takes the time of a finished turn off the player's clock, adds the
+    /// increment and starts the next turn.
+    pub(crate) fn charge(&mut self, player: u8, elapsed: Duration) {
+        let remaining = &mut self.remaining[player as usize];
+        *remaining = remaining.saturating_sub(elapsed) + self.control.increment;
+        #[cfg(feature = "std")]
+        {
+            self.turn_started = Some(Instant::now());
+        }
+    }
+}
+
+impl PartialEq for Clock {
+    fn eq(&self, other: &Self) -> bool {
+        self.control == other.control && self.remaining == other.remaining
+    }
+}
+
+impl Eq for Clock {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charge() {
+        let control = TimeControl::new(Duration::from_secs(60), Duration::from_secs(2));
+        let mut clock = Clock::new(control, 2);
+        clock.charge(1, Duration::from_secs(10));
+        assert_eq!(clock.remaining(0), Duration::from_secs(60));
+        assert_eq!(clock.remaining(1), Duration::from_secs(52));
+        assert!(clock.is_out_of_time(1, Duration::from_secs(53)));
+        assert!(!clock.is_out_of_time(1, Duration::from_secs(52)));
+
+        let mut other = Clock::new(control, 2);
+        other.charge(1, Duration::from_secs(10));
+        assert_eq!(clock, other);
+    }
+}
@@ -0,0 +1,205 @@
+//! The daily challenge: a solo puzzle that is the same for everyone on the
+//! same day, its decks and markers derived from the date alone. Players
+//! compare their results by sharing the final position in the state
+//! notation, which [`DailyChallenge::check`] turns back into a score.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use thiserror::Error;
+
+use crate::board::Board;
+use crate::game::Game;
+use crate::notation::NotationError;
+use crate::rules::Ruleset;
+
+/// Turns in a daily challenge.
+pub const DAILY_TURNS: u32 = 10;
+
+/// Points that count as beating the challenge. Players compare their scores
+/// whatever they are.
+pub const DAILY_TARGET: u32 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyChallenge {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl DailyChallenge {
+    /// The challenge of a date written as `YYYY-MM-DD`.
+    pub fn new(date: &str) -> Result<DailyChallenge, DailyError> {
+        let bad_date = || DailyError::BadDate(date.to_string());
+        let fields: Vec<&str> = date.trim().split('-').collect();
+        let lengths: Vec<usize> = fields.iter().map(|field| field.len()).collect();
+        if lengths != [4, 2, 2] {
+            return Err(bad_date());
+        }
+        let numbers = fields
+            .iter()
+            .map(|field| field.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| bad_date())?;
+        let (year, month, day) = (numbers[0], numbers[1], numbers[2]);
+        if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+            return Err(bad_date());
+        }
+        Ok(DailyChallenge { year, month, day })
+    }
+
+    /// Today's challenge. Days start at midnight UTC, so the challenge
+    /// changes at the same moment for everyone.
+    pub fn today() -> DailyChallenge {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("The clock should be after 1970")
+            .as_secs();
+        let (year, month, day) = civil_from_days(seconds / 86_400);
+        DailyChallenge { year, month, day }
+    }
+
+    /// The seed everything about the challenge comes from, a hash of the
+    /// date that is the same on every platform.
+    pub fn seed(&self) -> u64 {
+        // FNV-1a.
+        format!("passtally daily {}", self)
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// The challenge's starting position: player 0 has [`DAILY_TURNS`]
+    /// turns with a marker somewhere on each side of the board.
+    pub fn game(&self) -> Game {
+        let seed = self.seed();
+        let decks = Game::new_with_rules(Ruleset::new(1), seed).decks;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut player_markers = [None; 24];
+        for side in 0..4 {
+            player_markers[side * 6 + rng.gen_range(0..6)] = Some(0);
+        }
+        Game::new_puzzle(
+            Board::default(),
+            player_markers,
+            decks,
+            DAILY_TURNS,
+            DAILY_TARGET,
+        )
+    }
+
+    /// The score of a position shared in the state notation, if it can have
+    /// been reached in this challenge: the decks are what is left of the
+    /// challenge's and no more turns have been played than it allows. The
+    /// notation doesn't give the order of the pieces under the market, so
+    /// only which pieces they are is checked.
+    pub fn check(&self, notation: &str) -> Result<u32, DailyError> {
+        let game = Game::from_notation(notation)?;
+        let mut left = self.game();
+        let mut from_today = true;
+        for (deck, remaining) in left.decks.iter_mut().zip(game.decks_remaining()) {
+            from_today &= remaining <= deck.len();
+            deck.truncate(remaining);
+        }
+        let seen = |game: &Game| {
+            let seen = game.information_set(0);
+            (seen.market, seen.unseen)
+        };
+        from_today &= seen(&left) == seen(&game);
+        if game.scores().len() != 1 || game.round() > DAILY_TURNS || !from_today {
+            return Err(DailyError::OtherGame(self.to_string()));
+        }
+        Ok(game.scores()[0])
+    }
+}
+
+impl fmt::Display for DailyChallenge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The date of a day counted from 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: u64) -> (u32, u32, u32) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year as u32, month as u32, day as u32)
+}
+
+#[derive(Error, Debug)]
+pub enum DailyError {
+    #[error("{0} isn't a date written as YYYY-MM-DD.")]
+    BadDate(String),
+    #[error("The position is malformed: {0}")]
+    Notation(#[from] NotationError),
+    #[error("The position isn't from the challenge of {0}.")]
+    OtherGame(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            DailyChallenge::new("2024-02-29").unwrap().to_string(),
+            "2024-02-29"
+        );
+        assert!(DailyChallenge::new("2023-02-29").is_err());
+        assert!(DailyChallenge::new("2024-13-01").is_err());
+        assert!(DailyChallenge::new("24-1-1").is_err());
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn same_challenge_for_everyone() {
+        let challenge = DailyChallenge::new("2024-05-17").unwrap();
+        let game = challenge.game();
+        assert_eq!(
+            game.to_notation(),
+            DailyChallenge::new("2024-05-17")
+                .unwrap()
+                .game()
+                .to_notation()
+        );
+        assert_ne!(
+            game.to_notation(),
+            DailyChallenge::new("2024-05-18")
+                .unwrap()
+                .game()
+                .to_notation()
+        );
+        assert_eq!(game.player_markers().count(), 4);
+
+        let mut played = game.clone();
+        let turn = played.legal_turns().remove(0);
+        let points = played.play_turn(turn).unwrap().points;
+        assert_eq!(challenge.check(&played.to_notation()).unwrap(), points);
+        let other = DailyChallenge::new("2024-05-18").unwrap();
+        assert!(other.check(&played.to_notation()).is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! Explanations of why an action can't be played. Where
+//! [`Game::do_action`] stops at the first problem, [`Game::explain`] lists all
+//! of them, with the squares and markers involved, for teaching modes that
+//! want to point at what is wrong.
+
+use std::fmt;
+
+use crate::board::BoardPosition;
+use crate::game::{Action, Game};
+use crate::piece::{Piece, PositionedPiece};
+
+/// Every rule an action breaks. No violations means the action is legal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveDiagnosis {
+    pub action: Action,
+    pub violations: Vec<Violation>,
+}
+
+impl MoveDiagnosis {
+    pub fn is_legal(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A rule broken by an action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Violation {
+    GameOver,
+    /// Pieces can only be turned 0 to 3 times.
+    BadRotation(u8),
+    /// A half of the piece would be outside of the board.
+    OutsideBoard(BoardPosition),
+    /// The halves of the piece would lie on stacks of different heights.
+    UnevenHeight {
+        first: (BoardPosition, u32),
+        second: (BoardPosition, u32),
+    },
+    /// The piece would cover exactly one piece, with both halves on it.
+    OnSinglePiece(BoardPosition, BoardPosition),
+    /// The piece isn't on top of any deck.
+    NotInMarket {
+        piece: Piece,
+        market: [Option<Piece>; 3],
+    },
+    /// Edge slots go from 0 to 23.
+    NoSuchSlot(u8),
+    NoMarker(u8),
+    /// The marker belongs to another player.
+    NotYourMarker {
+        slot: u8,
+        owner: u8,
+    },
+    /// There is already a marker where the marker would go.
+    Occupied {
+        slot: u8,
+        owner: u8,
+    },
+    /// The marker would jump over more than one empty slot, whichever way
+    /// around the board it went. These are the empty slots the shorter way.
+    TooFar {
+        from: u8,
+        to: u8,
+        empty: Vec<u8>,
+    },
+    RedrawNotAllowed,
+    /// There is no such deck, or it is empty.
+    EmptyDeck(u8),
+    NoDrawOffer,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::GameOver => write!(f, "The game is over."),
+            Violation::BadRotation(rotation) => {
+                write!(f, "{} is not a rotation, they go from 0 to 3.", rotation)
+            }
+            Violation::OutsideBoard(pos) => write!(f, "{} is outside of the board.", pos),
+            Violation::UnevenHeight { first, second } => write!(
+                f,
+                "The stack at {} is {} high but the one at {} is {} high.",
+                first.0, first.1, second.0, second.1
+            ),
+            Violation::OnSinglePiece(pos1, pos2) => write!(
+                f,
+                "{} and {} are the same piece, a piece can't go directly on top of another.",
+                pos1, pos2
+            ),
+            Violation::NotInMarket { piece, .. } => {
+                write!(f, "{} is not on top of any of the decks.", piece)
+            }
+            Violation::NoSuchSlot(slot) => write!(f, "There is no slot {}.", slot),
+            Violation::NoMarker(slot) => write!(f, "There is no player marker at {}.", slot),
+            Violation::NotYourMarker { slot, owner } => {
+                write!(
+                    f,
+                    "The player marker at {} belongs to player {}.",
+                    slot, owner
+                )
+            }
+            Violation::Occupied { slot, owner } => {
+                write!(f, "Player {} already has a marker at {}.", owner, slot)
+            }
+            Violation::TooFar { from, to, empty } => write!(
+                f,
+                "Moving from {} to {} passes {} empty slots, at most one is allowed.",
+                from,
+                to,
+                empty.len()
+            ),
+            Violation::RedrawNotAllowed => {
+                write!(f, "The rules don't allow discarding market pieces.")
+            }
+            Violation::EmptyDeck(deck) => write!(f, "There is no deck {} or it is empty.", deck),
+            Violation::NoDrawOffer => write!(f, "There is no draw on offer."),
+        }
+    }
+}
+
+impl Game {
+    /// Checks the action for the player to move against every rule, rather
+    /// than stopping at the first one broken like [`do_action`](Game::do_action).
+    pub fn explain(&self, action: &Action) -> MoveDiagnosis {
+        let mut violations = Vec::new();
+        if self.is_finished() {
+            violations.push(Violation::GameOver);
+        }
+        match action {
+            Action::PlacePiece(piece) => self.explain_placement(piece, &mut violations),
+            &Action::MovePlayerMarker(from, to) => self.explain_move(from, to, &mut violations),
+            &Action::RedrawMarket(deck) => {
+                if !self.rules.allow_redraw {
+                    violations.push(Violation::RedrawNotAllowed);
+                }
+                match self.decks.get(deck as usize) {
+                    Some(pieces) if !pieces.is_empty() => {}
+                    _ => violations.push(Violation::EmptyDeck(deck)),
+                }
+            }
+            Action::AcceptDraw if self.draw_offer.is_empty() => {
+                violations.push(Violation::NoDrawOffer)
+            }
+            Action::Resign | Action::OfferDraw | Action::AcceptDraw => {}
+        }
+        MoveDiagnosis {
+            action: action.clone(),
+            violations,
+        }
+    }
+
+    fn explain_placement(&self, piece: &PositionedPiece, violations: &mut Vec<Violation>) {
+        let market = self.market();
+        if !market.contains(&Some(piece.piece)) {
+            violations.push(Violation::NotInMarket {
+                piece: piece.piece,
+                market,
+            });
+        }
+        if piece.rotation > 3 {
+            violations.push(Violation::BadRotation(piece.rotation));
+            return;
+        }
+
+        let (pos1, pos2) = piece.positions();
+        let outside: Vec<Violation> = [pos1, pos2]
+            .iter()
+            .filter(|pos| !pos.valid())
+            .map(|&pos| Violation::OutsideBoard(pos))
+            .collect();
+        if !outside.is_empty() {
+            violations.extend(outside);
+            return;
+        }
+
+        let board = &self.board;
+        if board.height(pos1) != board.height(pos2) {
+            violations.push(Violation::UnevenHeight {
+                first: (pos1, board.height(pos1)),
+                second: (pos2, board.height(pos2)),
+            });
+        }
+        if board.tile_id(pos1) != 0 && board.tile_id(pos1) == board.tile_id(pos2) {
+            violations.push(Violation::OnSinglePiece(pos1, pos2));
+        }
+    }
+
+    fn explain_move(&self, from: u8, to: u8, violations: &mut Vec<Violation>) {
+        let out_of_range: Vec<Violation> = [from, to]
+            .iter()
+            .filter(|&&slot| slot > 23)
+            .map(|&slot| Violation::NoSuchSlot(slot))
+            .collect();
+        if !out_of_range.is_empty() {
+            violations.extend(out_of_range);
+            return;
+        }
+
+        match self.player_markers[from as usize] {
+            None => violations.push(Violation::NoMarker(from)),
+            Some(owner) if owner != self.next_player() => {
+                violations.push(Violation::NotYourMarker { slot: from, owner })
+            }
+            Some(_) => {}
+        }
+        if let Some(owner) = self.player_markers[to as usize] {
+            violations.push(Violation::Occupied { slot: to, owner });
+        }
+
+        let (min, max) = (from.min(to), from.max(to));
+        let empty = |slots: &mut dyn Iterator<Item = u8>| -> Vec<u8> {
+            slots
+                .filter(|&slot| self.player_markers[slot as usize].is_none())
+                .collect()
+        };
+        let short = empty(&mut (min + 1..max));
+        let long = empty(&mut (max + 1..min + 24).map(|slot| slot % 24));
+        if short.len() > 1 && long.len() > 1 {
+            let empty = if short.len() <= long.len() {
+                short
+            } else {
+                long
+            };
+            violations.push(Violation::TooFar { from, to, empty });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn agrees_with_do_action() {
+        let game = Game::new_with_rules(Ruleset::new(3), 8);
+        let mut actions = game.legal_actions();
+        for from in 0..24 {
+            for to in 0..24 {
+                actions.push(Action::MovePlayerMarker(from, to));
+            }
+        }
+        for piece in [Piece::Red, Piece::Blue, game.market()[0].unwrap()].iter() {
+            for position in BoardPosition::all() {
+                for rotation in 0..4 {
+                    actions.push(Action::PlacePiece(PositionedPiece {
+                        piece: *piece,
+                        rotation,
+                        position,
+                    }));
+                }
+            }
+        }
+        actions.extend((0..4).map(Action::RedrawMarket));
+
+        for action in actions {
+            let diagnosis = game.explain(&action);
+            let result = game.clone().do_action(action.clone());
+            assert_eq!(diagnosis.is_legal(), result.is_ok(), "{:?}", diagnosis);
+        }
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 8);
+        let piece = game.market()[0].unwrap();
+        let first = PositionedPiece {
+            piece,
+            rotation: 0,
+            position: BoardPosition::new(0, 0),
+        };
+        game.do_action(Action::PlacePiece(first)).unwrap();
+
+        let missing = [Piece::Red, Piece::Green, Piece::Yellow, Piece::Blue]
+            .iter()
+            .copied()
+            .find(|piece| !game.market().contains(&Some(*piece)))
+            .unwrap();
+        let diagnosis = game.explain(&Action::PlacePiece(PositionedPiece {
+            piece: missing,
+            rotation: 0,
+            position: BoardPosition::new(1, 0),
+        }));
+        assert_eq!(
+            diagnosis.violations,
+            vec![
+                Violation::NotInMarket {
+                    piece: missing,
+                    market: game.market(),
+                },
+                Violation::UnevenHeight {
+                    first: (BoardPosition::new(1, 0), 1),
+                    second: (BoardPosition::new(2, 0), 0),
+                },
+            ]
+        );
+
+        // Player 0 moving player 1's marker onto their own one, too far away.
+        let diagnosis = game.explain(&Action::MovePlayerMarker(1, 6));
+        assert_eq!(
+            diagnosis.violations,
+            vec![
+                Violation::NotYourMarker { slot: 1, owner: 1 },
+                Violation::Occupied { slot: 6, owner: 0 },
+                Violation::TooFar {
+                    from: 1,
+                    to: 6,
+                    empty: vec![2, 3, 4, 5]
+                },
+            ]
+        );
+        assert_eq!(
+            diagnosis.violations[0].to_string(),
+            "The player marker at 1 belongs to player 1."
+        );
+
+        let diagnosis = game.explain(&Action::MovePlayerMarker(0, 4));
+        assert_eq!(
+            diagnosis.violations,
+            vec![Violation::TooFar {
+                from: 0,
+                to: 4,
+                empty: vec![2, 3]
+            }]
+        );
+        assert!(game.explain(&Action::MovePlayerMarker(0, 2)).is_legal());
+    }
+}
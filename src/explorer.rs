@@ -0,0 +1,223 @@
+//! The opening explorer: for an early position, the turns played in it by a
+//! database of stored games, how often each was played and how the games
+//! went for the player who played it. The data behind an opening browser.
+//!
+//! Positions are told apart by what is on the board and who moves next, in
+//! canonical form, and not by the market. The markets of two games hardly
+//! ever match, but their boards do for the first few turns. Turns are merged
+//! by what they leave on the board, as in the opening book.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::game::{Game, Turn};
+use crate::replay::{Replay, ReplayError};
+
+/// Turns at the start of each game the explorer looks at by default.
+pub const EXPLORER_TURNS: usize = 10;
+
+/// The key of a position in the explorer, the same for its turned and
+/// flipped versions whatever the market.
+pub fn position_key(game: &Game) -> u64 {
+    let canonical = game.canonical();
+    let mover = (game.next_player() as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    canonical.board.hash() ^ canonical.markers_hash ^ mover
+}
+
+/// What a turn left on the board.
+fn result_key(after: &Game) -> u64 {
+    let canonical = after.canonical();
+    canonical.board.hash() ^ canonical.markers_hash
+}
+
+/// A turn played in a position and how the games went after it, for the
+/// player who played it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExplorerMove {
+    /// The turn as it was first played. In a turned or flipped version of
+    /// the position it is another turn.
+    pub turn: Turn,
+    pub games: u32,
+    /// The share of the games in the position that went on with this turn.
+    pub frequency: f64,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// The player's average final score.
+    pub average_score: f64,
+}
+
+impl ExplorerMove {
+    /// Wins and half the draws, as a share of the games.
+    pub fn performance(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Entry {
+    result: u64,
+    turn: Option<Turn>,
+    games: u32,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    total_score: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpeningExplorer {
+    turns: usize,
+    positions: HashMap<u64, Vec<Entry>>,
+    games: usize,
+}
+
+impl OpeningExplorer {
+    /// An empty explorer looking at the first [`EXPLORER_TURNS`] turns of
+    /// each game.
+    pub fn new() -> OpeningExplorer {
+        OpeningExplorer::with_turns(EXPLORER_TURNS)
+    }
+
+    pub fn with_turns(turns: usize) -> OpeningExplorer {
+        OpeningExplorer {
+            turns,
+            positions: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    /// An explorer over all the games.
+    pub fn from_replays<'a>(
+        replays: impl IntoIterator<Item = &'a Replay>,
+    ) -> Result<OpeningExplorer, ReplayError> {
+        let mut explorer = OpeningExplorer::new();
+        for replay in replays {
+            explorer.add_replay(replay)?;
+        }
+        Ok(explorer)
+    }
+
+    /// Adds the first turns of a game. Who won is decided by the final
+    /// scores of the replay.
+    pub fn add_replay(&mut self, replay: &Replay) -> Result<(), ReplayError> {
+        replay.rules.validate()?;
+        let scores = replay.final_scores()?;
+        let best = scores.iter().copied().max().unwrap_or(0);
+        let leaders = scores.iter().filter(|&&score| score == best).count();
+
+        let mut game = replay.initial_game();
+        for (i, turn) in replay.turns.iter().take(self.turns).enumerate() {
+            let player = game.next_player() as usize;
+            let key = position_key(&game);
+            game.play_turn(turn.clone())
+                .map_err(|err| ReplayError::IllegalTurn(i, err))?;
+
+            let entries = self.positions.entry(key).or_default();
+            let result = result_key(&game);
+            let index = match entries.iter().position(|entry| entry.result == result) {
+                Some(index) => index,
+                None => {
+                    entries.push(Entry {
+                        result,
+                        turn: Some(turn.clone()),
+                        ..Entry::default()
+                    });
+                    entries.len() - 1
+                }
+            };
+            let entry = &mut entries[index];
+            entry.games += 1;
+            entry.total_score += scores[player] as u64;
+            match (scores[player] == best, leaders) {
+                (true, 1) => entry.wins += 1,
+                (true, _) => entry.draws += 1,
+                (false, _) => entry.losses += 1,
+            }
+        }
+        self.games += 1;
+        Ok(())
+    }
+
+    /// The turns played in the position, the most played first.
+    pub fn explore(&self, game: &Game) -> Vec<ExplorerMove> {
+        let entries = match self.positions.get(&position_key(game)) {
+            Some(entries) => entries,
+            None => return Vec::new(),
+        };
+        let total: u32 = entries.iter().map(|entry| entry.games).sum();
+        let mut moves: Vec<ExplorerMove> = entries
+            .iter()
+            .map(|entry| ExplorerMove {
+                turn: entry.turn.clone().expect("Entries are made with a turn"),
+                games: entry.games,
+                frequency: entry.games as f64 / total as f64,
+                wins: entry.wins,
+                draws: entry.draws,
+                losses: entry.losses,
+                average_score: entry.total_score as f64 / entry.games as f64,
+            })
+            .collect();
+        moves.sort_by_key(|explored| Reverse(explored.games));
+        moves
+    }
+
+    /// Games added so far.
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// Positions with at least one turn.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+impl Default for OpeningExplorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    /// A game whose first turn is the `first`th legal one, then the first
+    /// legal turn each time.
+    fn replay(seed: u64, first: usize) -> Replay {
+        let mut replay = Replay::new(vec!["A".into(), "B".into()], Ruleset::new(2), seed);
+        let mut game = replay.initial_game();
+        for i in 0..4 {
+            let index = if i == 0 { first } else { 0 };
+            let turn = game.legal_turns().remove(index);
+            replay.play(&mut game, turn).unwrap();
+        }
+        replay
+    }
+
+    #[test]
+    fn explores_openings() {
+        let replays = vec![replay(1, 0), replay(1, 0), replay(1, 5)];
+        let explorer = OpeningExplorer::from_replays(&replays).unwrap();
+        assert_eq!(explorer.games(), 3);
+
+        let start = replays[0].initial_game();
+        let moves = explorer.explore(&start);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].games, 2);
+        assert_eq!(moves[0].turn, replays[0].turns[0]);
+        assert!((moves[0].frequency - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(moves[0].wins + moves[0].draws + moves[0].losses, 2);
+
+        let after = replays[0].game_at(1).unwrap();
+        assert_eq!(explorer.explore(&after)[0].games, 2);
+        assert!(explorer.explore(&replays[0].game_at(4).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+//! A flat C interface to the rules engine, for C and C++ programs and game
+//! engines. `include/passtally.h` is generated from this file by cbindgen
+//! whenever the crate is built with the `ffi` feature.
+//!
+//! Games are opaque pointers from [`passtally_new`] and friends, and have to
+//! be freed with [`passtally_free`]. Strings going in are null terminated
+//! UTF-8. Strings coming out are owned by the caller and freed with
+//! [`passtally_string_free`]. Actions and turns are in the text notation of
+//! [`crate::notation`], like `P:Pink@c3r1+M:4>7`, and whole games in that
+//! notation or in JSON.
+//!
+//! Functions failing return null or a negative number, and
+//! [`passtally_last_error`] says why. Panics are caught before they reach
+//! the caller and reported the same way. Game engines wanting thread-safe
+//! handles and status codes instead should use [`handles`].
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::game::{Game, Turn};
+use crate::rules::Ruleset;
+
+pub mod handles;
+
+/// A game of passtally.
+pub struct PasstallyGame {
+    game: Game,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_error(error: impl ToString) {
+    let message = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// The string, or `None` with the error set when it's null or not UTF-8.
+unsafe fn read_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        set_error("The string is null.");
+        return None;
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(error) => {
+            set_error(error);
+            None
+        }
+    }
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    // Notation and JSON never hold a null byte.
+    CString::new(s).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+fn into_handle(game: Game) -> *mut PasstallyGame {
+    Box::into_raw(Box::new(PasstallyGame { game }))
+}
+
+/// Runs the body of a function, returning `failed` with the error set if
+/// it panics, as unwinding into C would abort the program.
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|_| {
+        set_error("The engine hit a bug.");
+        failed
+    })
+}
+
+/// A new game between 1 to 4 players with the decks shuffled by the seed, or
+/// null for any other amount of players.
+#[no_mangle]
+pub extern "C" fn passtally_new(players: u8, seed: u64) -> *mut PasstallyGame {
+    guard(ptr::null_mut(), || {
+        if !(1..=4).contains(&players) {
+            set_error("Games are for 1 to 4 players.");
+            return ptr::null_mut();
+        }
+        into_handle(Game::new_with_rules(Ruleset::new(players), seed))
+    })
+}
+
+/// # Safety
+///
+/// `game` has to be null or a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_free(game: *mut PasstallyGame) {
+    guard((), || {
+        if !game.is_null() {
+            drop(Box::from_raw(game));
+        }
+    })
+}
+
+/// # Safety
+///
+/// `s` has to be null or a string from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_string_free(s: *mut c_char) {
+    guard((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+/// Why the last function to fail on this thread failed. The string is owned
+/// by the library and valid until the next call failing on the thread.
+#[no_mangle]
+pub extern "C" fn passtally_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// The game in the notation, or null if it isn't valid.
+///
+/// # Safety
+///
+/// `notation` has to be null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_from_notation(notation: *const c_char) -> *mut PasstallyGame {
+    guard(ptr::null_mut(), || {
+        let game = read_str(notation)
+            .and_then(|notation| Game::from_notation(notation).map_err(set_error).ok());
+        game.map_or(ptr::null_mut(), into_handle)
+    })
+}
+
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_to_notation(game: *const PasstallyGame) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        into_c_string((*game).game.to_notation())
+    })
+}
+
+/// The game in JSON, as saved by the serde support, or null if it isn't
+/// valid.
+///
+/// # Safety
+///
+/// `json` has to be null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_from_json(json: *const c_char) -> *mut PasstallyGame {
+    guard(ptr::null_mut(), || {
+        let game = read_str(json).and_then(|json| {
+            serde_json::from_str::<Game>(json)
+                .map_err(|error| error.to_string())
+                .and_then(|game| {
+                    game.validate()
+                        .map(|()| game)
+                        .map_err(|error| error.to_string())
+                })
+                .map_err(set_error)
+                .ok()
+        });
+        game.map_or(ptr::null_mut(), into_handle)
+    })
+}
+
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_to_json(game: *const PasstallyGame) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        match serde_json::to_string(&(*game).game) {
+            Ok(json) => into_c_string(json),
+            Err(error) => {
+                set_error(error);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// The player to move, or 255 if the engine hit a bug.
+///
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_next_player(game: *const PasstallyGame) -> u8 {
+    guard(u8::MAX, || (*game).game.next_player())
+}
+
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_round(game: *const PasstallyGame) -> u32 {
+    guard(0, || (*game).game.round())
+}
+
+/// The player's score, or -1 if there is no such player.
+///
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_score(game: *const PasstallyGame, player: u8) -> i64 {
+    guard(-1, || match (*game).game.scores().get(player as usize) {
+        Some(&score) => score as i64,
+        None => {
+            set_error(format!("There is no player {}.", player));
+            -1
+        }
+    })
+}
+
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_is_finished(game: *const PasstallyGame) -> bool {
+    guard(false, || (*game).game.is_finished())
+}
+
+/// Every turn the player to move could play, as a JSON array of turns in
+/// notation.
+///
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_legal_turns(game: *const PasstallyGame) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let turns: Vec<String> = (*game)
+            .game
+            .legal_turns_iter()
+            .map(|turn| turn.to_string())
+            .collect();
+        into_c_string(serde_json::to_string(&turns).expect("Strings always serialize"))
+    })
+}
+
+/// Plays the turn, in notation, for the player to move. Returns the points
+/// they scored, or -1 if the turn isn't legal, in which case nothing is
+/// played.
+///
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet, and `turn` null
+/// or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_play_turn(game: *mut PasstallyGame, turn: *const c_char) -> i64 {
+    guard(-1, || {
+        let turn = match read_str(turn).map(str::parse::<Turn>) {
+            Some(Ok(turn)) => turn,
+            Some(Err(error)) => {
+                set_error(error);
+                return -1;
+            }
+            None => return -1,
+        };
+        match (*game).game.play_turn(turn) {
+            Ok(outcome) => outcome.points as i64,
+            Err(error) => {
+                set_error(error);
+                -1
+            }
+        }
+    })
+}
+
+/// Takes a single action in notation, such as `resign`. Returns 0, or -1 if
+/// the action isn't legal.
+///
+/// # Safety
+///
+/// `game` has to be a game from this library not freed yet, and `action`
+/// null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_do_action(
+    game: *mut PasstallyGame,
+    action: *const c_char,
+) -> i32 {
+    guard(-1, || {
+        let result = match read_str(action) {
+            Some(action) => action
+                .parse()
+                .map_err(|error: crate::notation::NotationError| error.to_string())
+                .and_then(|action| {
+                    (*game)
+                        .game
+                        .do_action(action)
+                        .map_err(|error| error.to_string())
+                }),
+            None => return -1,
+        };
+        match result {
+            Ok(()) => 0,
+            Err(error) => {
+                set_error(error);
+                -1
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_through_the_c_interface() {
+        unsafe {
+            let game = passtally_new(2, 5);
+            let turns = passtally_legal_turns(game);
+            let json = CStr::from_ptr(turns).to_str().unwrap();
+            let first: Vec<String> = serde_json::from_str(json).unwrap();
+            passtally_string_free(turns);
+
+            let turn = CString::new(first[0].as_str()).unwrap();
+            assert!(passtally_play_turn(game, turn.as_ptr()) >= 0);
+            assert_eq!(passtally_round(game), 1);
+            let illegal = CString::new("M:0>1+M:0>1").unwrap();
+            assert_eq!(passtally_play_turn(game, illegal.as_ptr()), -1);
+            assert!(!CStr::from_ptr(passtally_last_error()).to_bytes().is_empty());
+
+            let notation = passtally_to_notation(game);
+            let copy = passtally_from_notation(notation);
+            assert_eq!(passtally_round(copy), 1);
+            passtally_string_free(notation);
+            passtally_free(copy);
+            passtally_free(game);
+            assert!(passtally_new(5, 0).is_null());
+
+            let broken = CString::new(r#"{"rules": {"player_count": 0}}"#).unwrap();
+            assert!(passtally_from_json(broken.as_ptr()).is_null());
+            let mut json: serde_json::Value =
+                serde_json::to_value(Game::new_with_rules(Ruleset::new(2), 1)).unwrap();
+            json["rules"]["player_count"] = 0.into();
+            let broken = CString::new(json.to_string()).unwrap();
+            assert!(passtally_from_json(broken.as_ptr()).is_null());
+            let error = CStr::from_ptr(passtally_last_error()).to_str().unwrap();
+            assert!(error.contains("not 0"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! A second C interface for embedding the engine in game engines such as
+//! Unity, which call in from several threads and can't take a crash.
+//!
+//! Games are numbered handles rather than pointers, looked up in a table,
+//! so a stale or made up handle is an error rather than undefined
+//! behaviour, and handles are never reused. Each game has its own lock, so
+//! handles can be used from any thread, different games at the same time.
+//! Every function returns a [`PasstallyStatus`], writes its results through
+//! out pointers, and copies strings into buffers the caller owns, so
+//! nothing needs freeing but the game. There is no state per thread and
+//! panics are caught before they reach the caller.
+
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::game::{Action, Game, Turn};
+use crate::rules::Ruleset;
+
+/// A game, from [`passtally_game_create`] and friends. 0 is never a game.
+pub type PasstallyHandle = u64;
+
+/// What happened, returned by every function.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasstallyStatus {
+    Ok = 0,
+    /// The handle isn't a game, or the game was destroyed.
+    InvalidHandle = 1,
+    /// A pointer was null, a string wasn't UTF-8 or a number was out of range.
+    InvalidArgument = 2,
+    /// The notation couldn't be read.
+    BadNotation = 3,
+    /// The move isn't legal right now. Nothing was played.
+    IllegalMove = 4,
+    /// The buffer is too small, the length needed was written.
+    BufferTooSmall = 5,
+    /// The engine hit a bug. The game may be left in any state.
+    Panic = 6,
+}
+
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+static GAMES: Mutex<BTreeMap<PasstallyHandle, Arc<Mutex<Game>>>> = Mutex::new(BTreeMap::new());
+
+type Result<T> = std::result::Result<T, PasstallyStatus>;
+
+/// Runs the body of a function, turning panics into a status.
+fn guard(body: impl FnOnce() -> Result<()>) -> PasstallyStatus {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => PasstallyStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => PasstallyStatus::Panic,
+    }
+}
+
+fn games() -> Result<std::sync::MutexGuard<'static, BTreeMap<PasstallyHandle, Arc<Mutex<Game>>>>> {
+    GAMES.lock().map_err(|_| PasstallyStatus::Panic)
+}
+
+fn insert(game: Game) -> Result<PasstallyHandle> {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    games()?.insert(handle, Arc::new(Mutex::new(game)));
+    Ok(handle)
+}
+
+/// Runs `f` on the game, holding only its own lock.
+fn with_game<T>(handle: PasstallyHandle, f: impl FnOnce(&mut Game) -> Result<T>) -> Result<T> {
+    let game = games()?
+        .get(&handle)
+        .cloned()
+        .ok_or(PasstallyStatus::InvalidHandle)?;
+    let mut game = game.lock().map_err(|_| PasstallyStatus::Panic)?;
+    f(&mut game)
+}
+
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(PasstallyStatus::InvalidArgument);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| PasstallyStatus::InvalidArgument)
+}
+
+unsafe fn write<T>(out: *mut T, value: T) -> Result<()> {
+    if out.is_null() {
+        return Err(PasstallyStatus::InvalidArgument);
+    }
+    out.write(value);
+    Ok(())
+}
+
+/// Copies the string and a null byte into the buffer, writing the length
+/// that takes to `needed` whether or not it fits.
+unsafe fn write_str(s: &str, buffer: *mut c_char, length: usize, needed: *mut usize) -> Result<()> {
+    write(needed, s.len() + 1)?;
+    if buffer.is_null() || length < s.len() + 1 {
+        return Err(PasstallyStatus::BufferTooSmall);
+    }
+    ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, buffer, s.len());
+    buffer.add(s.len()).write(0);
+    Ok(())
+}
+
+/// Starts a game between 1 to 4 players with the decks shuffled by the
+/// seed.
+///
+/// # Safety
+///
+/// `handle` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_create(
+    players: u8,
+    seed: u64,
+    handle: *mut PasstallyHandle,
+) -> PasstallyStatus {
+    guard(|| {
+        if !(1..=4).contains(&players) {
+            return Err(PasstallyStatus::InvalidArgument);
+        }
+        write(
+            handle,
+            insert(Game::new_with_rules(Ruleset::new(players), seed))?,
+        )
+    })
+}
+
+/// Sets up the game in the notation.
+///
+/// # Safety
+///
+/// `notation` has to be a null terminated string and `handle` has to point
+/// to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_from_notation(
+    notation: *const c_char,
+    handle: *mut PasstallyHandle,
+) -> PasstallyStatus {
+    guard(|| {
+        let game =
+            Game::from_notation(read_str(notation)?).map_err(|_| PasstallyStatus::BadNotation)?;
+        write(handle, insert(game)?)
+    })
+}
+
+/// A new game in the same position as the game, to try moves on.
+///
+/// # Safety
+///
+/// `copy` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_clone(
+    handle: PasstallyHandle,
+    copy: *mut PasstallyHandle,
+) -> PasstallyStatus {
+    guard(|| {
+        let game = with_game(handle, |game| Ok(game.clone()))?;
+        write(copy, insert(game)?)
+    })
+}
+
+/// Frees the game. The handle is invalid from then on.
+#[no_mangle]
+pub extern "C" fn passtally_game_destroy(handle: PasstallyHandle) -> PasstallyStatus {
+    guard(|| match games()?.remove(&handle) {
+        Some(_) => Ok(()),
+        None => Err(PasstallyStatus::InvalidHandle),
+    })
+}
+
+/// The game in notation. Pass a null buffer to find the length needed.
+///
+/// # Safety
+///
+/// `buffer` has to be null or `length` bytes of writable memory, and
+/// `needed` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_to_notation(
+    handle: PasstallyHandle,
+    buffer: *mut c_char,
+    length: usize,
+    needed: *mut usize,
+) -> PasstallyStatus {
+    guard(|| {
+        let notation = with_game(handle, |game| Ok(game.to_notation()))?;
+        write_str(&notation, buffer, length, needed)
+    })
+}
+
+/// Every turn the player to move could play, in notation, one per line.
+/// Pass a null buffer to find the length needed.
+///
+/// # Safety
+///
+/// `buffer` has to be null or `length` bytes of writable memory, and
+/// `needed` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_legal_turns(
+    handle: PasstallyHandle,
+    buffer: *mut c_char,
+    length: usize,
+    needed: *mut usize,
+) -> PasstallyStatus {
+    guard(|| {
+        let turns = with_game(handle, |game| {
+            let turns: Vec<String> = game
+                .legal_turns_iter()
+                .map(|turn| turn.to_string())
+                .collect();
+            Ok(turns.join("\n"))
+        })?;
+        write_str(&turns, buffer, length, needed)
+    })
+}
+
+/// Plays the turn, in notation, for the player to move and writes the
+/// points they scored.
+///
+/// # Safety
+///
+/// `turn` has to be a null terminated string and `points` has to point to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_play_turn(
+    handle: PasstallyHandle,
+    turn: *const c_char,
+    points: *mut u32,
+) -> PasstallyStatus {
+    guard(|| {
+        let turn: Turn = read_str(turn)?
+            .parse()
+            .map_err(|_| PasstallyStatus::BadNotation)?;
+        let outcome = with_game(handle, |game| {
+            game.play_turn(turn)
+                .map_err(|_| PasstallyStatus::IllegalMove)
+        })?;
+        write(points, outcome.points)
+    })
+}
+
+/// Takes a single action in notation, such as `resign`.
+///
+/// # Safety
+///
+/// `action` has to be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_do_action(
+    handle: PasstallyHandle,
+    action: *const c_char,
+) -> PasstallyStatus {
+    guard(|| {
+        let action: Action = read_str(action)?
+            .parse()
+            .map_err(|_| PasstallyStatus::BadNotation)?;
+        with_game(handle, |game| {
+            game.do_action(action)
+                .map_err(|_| PasstallyStatus::IllegalMove)
+        })
+    })
+}
+
+/// # Safety
+///
+/// `player` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_next_player(
+    handle: PasstallyHandle,
+    player: *mut u8,
+) -> PasstallyStatus {
+    guard(|| write(player, with_game(handle, |game| Ok(game.next_player()))?))
+}
+
+/// # Safety
+///
+/// `round` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_round(
+    handle: PasstallyHandle,
+    round: *mut u32,
+) -> PasstallyStatus {
+    guard(|| write(round, with_game(handle, |game| Ok(game.round()))?))
+}
+
+/// # Safety
+///
+/// `score` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_score(
+    handle: PasstallyHandle,
+    player: u8,
+    score: *mut u32,
+) -> PasstallyStatus {
+    guard(|| {
+        let points = with_game(handle, |game| {
+            game.scores()
+                .get(player as usize)
+                .copied()
+                .ok_or(PasstallyStatus::InvalidArgument)
+        })?;
+        write(score, points)
+    })
+}
+
+/// # Safety
+///
+/// `finished` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn passtally_game_is_finished(
+    handle: PasstallyHandle,
+    finished: *mut bool,
+) -> PasstallyStatus {
+    guard(|| write(finished, with_game(handle, |game| Ok(game.is_finished()))?))
+}
+
+/// What the status means, in English. The string is static.
+#[no_mangle]
+pub extern "C" fn passtally_status_message(status: PasstallyStatus) -> *const c_char {
+    let message: &'static [u8] = match status {
+        PasstallyStatus::Ok => b"Ok.\0",
+        PasstallyStatus::InvalidHandle => b"The handle isn't a game.\0",
+        PasstallyStatus::InvalidArgument => b"An argument is null or out of range.\0",
+        PasstallyStatus::BadNotation => b"The notation couldn't be read.\0",
+        PasstallyStatus::IllegalMove => b"The move isn't legal.\0",
+        PasstallyStatus::BufferTooSmall => b"The buffer is too small.\0",
+        PasstallyStatus::Panic => b"The engine hit a bug.\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::thread;
+
+    #[test]
+    fn handles_are_checked_and_shared_between_threads() {
+        unsafe {
+            let mut handle = 0;
+            assert_eq!(
+                passtally_game_create(2, 5, &mut handle),
+                PasstallyStatus::Ok
+            );
+
+            let mut needed = 0;
+            assert_eq!(
+                passtally_game_legal_turns(handle, ptr::null_mut(), 0, &mut needed),
+                PasstallyStatus::BufferTooSmall
+            );
+            let mut buffer = vec![0 as c_char; needed];
+            assert_eq!(
+                passtally_game_legal_turns(handle, buffer.as_mut_ptr(), needed, &mut needed),
+                PasstallyStatus::Ok
+            );
+            let turns = CStr::from_ptr(buffer.as_ptr())
+                .to_str()
+                .unwrap()
+                .to_string();
+
+            let players: Vec<_> = turns
+                .lines()
+                .take(4)
+                .map(|turn| {
+                    let turn = CString::new(turn).unwrap();
+                    thread::spawn(move || {
+                        let mut points = 0;
+                        passtally_game_play_turn(handle, turn.as_ptr(), &mut points)
+                    })
+                })
+                .collect();
+            let played = players
+                .into_iter()
+                .map(|player| player.join().unwrap())
+                .filter(|status| *status == PasstallyStatus::Ok)
+                .count();
+            let mut round = 0;
+            assert_eq!(
+                passtally_game_round(handle, &mut round),
+                PasstallyStatus::Ok
+            );
+            assert_eq!(round as usize, played);
+
+            assert_eq!(passtally_game_destroy(handle), PasstallyStatus::Ok);
+            assert_eq!(
+                passtally_game_round(handle, &mut round),
+                PasstallyStatus::InvalidHandle
+            );
+            assert_eq!(
+                passtally_game_create(9, 0, &mut handle),
+                PasstallyStatus::InvalidArgument
+            );
+        }
+    }
+}
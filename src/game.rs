@@ -1,90 +1,659 @@
-use rand::{prelude::SliceRandom, thread_rng};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use rand::{prelude::SliceRandom, SeedableRng};
+#[cfg(feature = "std")]
+use rand::{thread_rng, Rng};
+use rand_chacha::ChaCha8Rng;
+use smallvec::SmallVec;
 use thiserror::Error;
 
-use crate::board::{Board, BoardPosition};
+use crate::board::{Board, BoardPosition, PlacementUndo};
+use crate::clock::{Clock, TimeControl};
+use crate::movegen::{LegalActions, LegalTurns};
 use crate::piece::{Piece, PositionedPiece};
+use crate::rules::{Ruleset, StalemateRule, MAX_PLAYERS};
+use crate::zobrist;
+
+/// A deck of pieces, the last one on top. Decks start at 14 pieces, which
+/// are kept inline so setting up a game doesn't allocate for them.
+pub type Deck = SmallVec<[Piece; 14]>;
 
-/// A complete passtally game.
+/// A complete passtally game. Games compare equal when they are in the same
+/// position: see [`Board`]'s `PartialEq` for what that means for the board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Game {
     pub board: Board,
-    player_markers: [Option<u8>; 24],
-    player_count: u8,
+    pub(crate) player_markers: [Option<u8>; 24],
+    /// Zobrist hash of the markers, kept up to date as they move
+    pub(crate) markers_hash: u64,
+    pub(crate) rules: Ruleset,
+    /// Points scored so far by each player
+    pub(crate) scores: Vec<u32>,
     /// Amount of rounds played
-    round: u32,
+    pub(crate) round: u32,
     /// The three decks. Each deck starts at 14 cards for a total of 42.
-    decks: [Vec<Piece>; 3],
+    /// The last piece of each deck is the one on top.
+    pub(crate) decks: [Deck; 3],
+    /// Whether the game ended because nobody could play.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) stalemate: bool,
+    /// The players' clocks, for games with a time control.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) clock: Option<Clock>,
+    /// The player who ran out of time, if any.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) time_forfeit: Option<u8>,
+    /// Every line that scored, in the order they were scored.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) score_events: Vec<ScoreEvent>,
+    /// Players who resigned, in the order they did.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) resigned: Vec<u8>,
+    /// Players agreeing to the draw on offer, the one who offered it first.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) draw_offer: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) mode: GameMode,
 }
 
 impl Game {
+    /// A game with the standard rules, dealt from a random seed.
+    #[cfg(feature = "std")]
     pub fn new(player_count: u8) -> Game {
+        Game::new_with_rules(Ruleset::new(player_count), thread_rng().gen())
+    }
+
+    /// Sets up a game. The same rules and seed always give the same decks.
+    pub fn new_with_rules(rules: Ruleset, seed: u64) -> Game {
         use Piece::*;
-        let mut rng = thread_rng();
-        let mut deck1 = [Red, Green, Yellow, Blue, Cyan, Pink].repeat(7);
-        deck1.shuffle(&mut rng);
-        let mut deck2 = deck1.split_off(14);
-        let deck3 = deck2.split_off(14);
+        assert!(matches!(rules.player_count, 1..=4));
+        assert!(!rules.teams || rules.player_count == 4);
 
-        let mut player_markers = [None; 24];
-        player_markers[0] = Some(0);
-        player_markers[6] = Some(0);
-        player_markers[12] = Some(0);
-        player_markers[18] = Some(0);
+        // All 42 pieces in one array, shuffled in place and dealt in three
+        // without allocating. The same seed deals the same decks as ever.
+        // Dealing stays eager: the market needs the top of every deck, and
+        // the shuffle settles deck 0 last, so dealing lazily would run
+        // nearly all of it before the first turn anyway.
+        let mut pieces = [Red; 42];
+        for (i, piece) in pieces.iter_mut().enumerate() {
+            *piece = [Red, Green, Yellow, Blue, Cyan, Pink][i % 6];
+        }
+        pieces.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+        let deck = |i: usize| Deck::from_slice(&pieces[i * 14..(i + 1) * 14]);
 
-        player_markers[1] = Some(1);
-        player_markers[7] = Some(1);
-        player_markers[13] = Some(1);
-        player_markers[19] = Some(1);
+        // Every player starts with a marker at the same place on each side.
+        let mut player_markers = [None; 24];
+        for side in 0..4 {
+            for player in 0..rules.player_count {
+                player_markers[side * 6 + player as usize] = Some(player);
+            }
+        }
+        let mut scores = vec![0; rules.player_count as usize];
+        for (player, handicap) in rules.handicap.iter().enumerate().take(scores.len()) {
+            scores[player] = handicap.points;
+            for extra in 0..handicap.extra_markers as usize {
+                let free = (0..6)
+                    .rev()
+                    .flat_map(|offset| (0..4).map(move |side| (side + extra) % 4 * 6 + offset))
+                    .find(|&slot| player_markers[slot].is_none());
+                if let Some(slot) = free {
+                    player_markers[slot] = Some(player as u8);
+                }
+            }
+        }
 
         Game {
             board: Board::default(),
+            markers_hash: zobrist::markers(&player_markers),
             player_markers,
-            player_count,
+            scores,
+            rules,
             round: 0,
-            decks: [deck1, deck2, deck3],
+            decks: [deck(0), deck(1), deck(2)],
+            stalemate: false,
+            clock: None,
+            time_forfeit: None,
+            score_events: Vec::new(),
+            resigned: Vec::new(),
+            draw_offer: Vec::new(),
+            mode: GameMode::Standard,
+        }
+    }
+
+    /// A solo puzzle: player 0 has `turns` turns to score `target` points,
+    /// starting from the given board, markers and decks. Markers of the
+    /// other seats are in the way but never move. The decks can be [`Deck`]s or
+    /// `Vec`s, with the top piece last.
+    pub fn new_puzzle<D: Into<Deck>>(
+        board: Board,
+        player_markers: [Option<u8>; 24],
+        decks: [D; 3],
+        turns: u32,
+        target: u32,
+    ) -> Game {
+        Game {
+            board,
+            markers_hash: zobrist::markers(&player_markers),
+            player_markers,
+            scores: vec![0],
+            decks: decks.map(Into::into),
+            mode: GameMode::Puzzle { turns, target },
+            ..Game::new_with_rules(Ruleset::new(1), 0)
+        }
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Checks a game read from outside, through serde, is one the engine
+    /// can play without panicking. Games set up by the engine always are.
+    pub fn validate(&self) -> Result<(), InvalidGame> {
+        self.rules.validate()?;
+        let players = self.rules.player_count;
+        if self.scores.len() != players as usize {
+            return Err(InvalidGame::Scores(self.scores.len(), players));
+        }
+        // A puzzle keeps the markers of the seats not playing as blockers.
+        let seats = match self.mode {
+            GameMode::Standard => players,
+            GameMode::Puzzle { .. } if players == 1 => MAX_PLAYERS,
+            GameMode::Puzzle { .. } => return Err(InvalidGame::Puzzle(players)),
+        };
+        let mut markers = self.player_markers.iter().flatten();
+        if let Some(&seat) = markers.find(|&&seat| seat >= seats) {
+            return Err(InvalidGame::NoSuchPlayer(seat));
+        }
+        let pieces: usize = self.decks.iter().map(|deck| deck.len()).sum();
+        if pieces > 42 {
+            return Err(InvalidGame::Decks(pieces));
+        }
+        let stacked = self.board.pieces_stacked().ok_or(InvalidGame::Heights)?;
+        if stacked as usize + pieces > 42 {
+            return Err(InvalidGame::Pieces(stacked, pieces));
+        }
+        if self.board.next_id as usize + pieces > u16::MAX as usize {
+            return Err(InvalidGame::NextId(self.board.next_id, pieces));
+        }
+        let mut seen = self
+            .resigned
+            .iter()
+            .chain(&self.draw_offer)
+            .chain(&self.time_forfeit)
+            .chain(self.score_events.iter().map(|event| &event.player));
+        if let Some(&player) = seen.find(|&&player| player >= players) {
+            return Err(InvalidGame::NoSuchPlayer(player));
+        }
+        let bad_event = self.score_events.iter().any(|event| {
+            event.entry >= 24 || event.exit >= 24 || !event.path.iter().all(BoardPosition::valid)
+        });
+        if bad_event {
+            return Err(InvalidGame::ScoreEvent);
+        }
+        if let Some(clock) = &self.clock {
+            if clock.player_count() != players as usize {
+                return Err(InvalidGame::Clock(clock.player_count(), players));
+            }
+        }
+        if !self.board.is_consistent() {
+            return Err(InvalidGame::Board);
+        }
+        Ok(())
+    }
+
+    /// Whether the puzzle has been solved, `None` if the game isn't one. A
+    /// puzzle is solved once the target score is reached, within the turns
+    /// given.
+    pub fn puzzle_solved(&self) -> Option<bool> {
+        match self.mode {
+            GameMode::Standard => None,
+            GameMode::Puzzle { target, .. } => Some(self.scores[0] >= target),
         }
     }
 
+    /// Puts the game on a clock, starting now with the full base time for
+    /// every player.
+    pub fn set_time_control(&mut self, control: TimeControl) {
+        self.clock = Some(Clock::new(control, self.rules.player_count));
+    }
+
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
     pub fn next_player(&self) -> u8 {
-        (self.round % (self.player_count as u32)) as u8
+        (self.round % (self.rules.player_count as u32)) as u8
     }
 
-    pub fn play_turn(&mut self, turn: Turn) -> Result<(), PasstallyError> {
-        let backup = (self.board.clone(), self.player_markers);
+    pub fn rules(&self) -> &Ruleset {
+        &self.rules
+    }
 
-        let Turn(action1, action2) = turn;
-        let res = self
-            .do_action(action1)
-            .and_then(|_| self.do_action(action2));
+    pub fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Every line that scored so far, oldest first.
+    pub fn score_events(&self) -> &[ScoreEvent] {
+        &self.score_events
+    }
+
+    /// The pieces on top of the three decks, which are the ones that can be placed.
+    pub fn market(&self) -> [Option<Piece>; 3] {
+        [
+            self.decks[0].last().copied(),
+            self.decks[1].last().copied(),
+            self.decks[2].last().copied(),
+        ]
+    }
+
+    /// The amount of pieces left in each deck.
+    pub fn decks_remaining(&self) -> [usize; 3] {
+        [
+            self.decks[0].len(),
+            self.decks[1].len(),
+            self.decks[2].len(),
+        ]
+    }
+
+    /// An independent copy of the game to explore variations on.
+    pub fn fork(&self) -> Game {
+        self.clone()
+    }
+
+    /// Like [`fork`](Game::fork), but the pieces nobody has seen yet are
+    /// shuffled again, so analysis doesn't peek at the real deck order. The
+    /// market and the size of each deck stay the same.
+    pub fn fork_reshuffled(&self, seed: u64) -> Game {
+        self.determinize(&mut ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Zobrist hash of the position: the board, the markers, the player to move
+    /// and what is left of the decks. The scores and the order of the pieces
+    /// further down the decks aren't part of it.
+    pub fn hash(&self) -> u64 {
+        let mut hash = self.board.hash() ^ self.markers_hash ^ zobrist::to_move(self.next_player());
+        for (i, deck) in self.decks.iter().enumerate() {
+            hash ^= zobrist::deck(i, deck.last().copied(), deck.len());
+        }
+        hash
+    }
+
+    /// The same game with the board turned or flipped into its canonical form.
+    /// See [`Board::canonicalize`].
+    pub fn canonical(&self) -> Game {
+        let (board, player_markers, _) = self.board.canonicalize(&self.player_markers);
+        Game {
+            board,
+            player_markers,
+            markers_hash: zobrist::markers(&player_markers),
+            ..self.clone()
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        if let Some(player) = self.time_forfeit {
+            Phase::Finished(EndReason::TimeForfeit(player))
+        } else if !self.resigned.is_empty() && self.one_team_left() {
+            Phase::Finished(EndReason::Resignation)
+        } else if self
+            .active_players()
+            .all(|player| self.draw_offer.contains(&player))
+        {
+            Phase::Finished(EndReason::DrawAgreed)
+        } else if matches!(self.mode, GameMode::Puzzle { turns, .. } if self.round >= turns) {
+            Phase::Finished(EndReason::TurnLimit)
+        } else if self.decks.iter().all(|deck| deck.is_empty()) {
+            Phase::Finished(EndReason::DecksEmpty)
+        } else if self.stalemate {
+            Phase::Finished(EndReason::Stalemate)
+        } else {
+            Phase::Playing
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase() != Phase::Playing
+    }
+
+    /// Players who haven't resigned.
+    pub fn active_players(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.rules.player_count).filter(move |player| !self.resigned.contains(player))
+    }
+
+    fn one_team_left(&self) -> bool {
+        let mut teams = self
+            .active_players()
+            .map(|player| self.rules.team_of(player));
+        let first = teams.next();
+        teams.all(|team| Some(team) == first)
+    }
 
-        match res {
-            Ok(_) => {
-                self.round += 1;
-                Ok(())
+    /// The summed scores of the team's players.
+    pub fn team_score(&self, team: u8) -> u32 {
+        (0..self.rules.player_count)
+            .filter(|&player| self.rules.team_of(player) == team)
+            .map(|player| self.scores[player as usize])
+            .sum()
+    }
+
+    /// The players from first to last place, players sharing a place grouped
+    /// together. Teammates always share their place. Players who ran out of
+    /// time or resigned come last, the latest to resign first. After an agreed
+    /// draw everyone else shares first place, otherwise higher scores come
+    /// first. Before the game is over these are the current standings.
+    pub fn final_ranking(&self) -> Vec<Vec<u8>> {
+        let drawn = self.phase() == Phase::Finished(EndReason::DrawAgreed);
+        let place = |player: u8| {
+            let team = self.rules.team_of(player);
+            let is_team = |other: &u8| self.rules.team_of(*other) == team;
+            let out = if self.time_forfeit.iter().any(is_team) {
+                1
+            } else {
+                match self.resigned.iter().position(is_team) {
+                    Some(i) => 1 + self.resigned.len() - i,
+                    None => 0,
+                }
+            };
+            let score = if drawn && out == 0 {
+                0
+            } else {
+                self.team_score(team)
+            };
+            (out, core::cmp::Reverse(score))
+        };
+
+        let mut players: Vec<u8> = (0..self.rules.player_count).collect();
+        players.sort_by_key(|&player| place(player));
+        let mut ranking: Vec<Vec<u8>> = Vec::new();
+        for player in players {
+            match ranking.last_mut() {
+                Some(shared) if place(shared[0]) == place(player) => shared.push(player),
+                _ => ranking.push(vec![player]),
+            }
+        }
+        ranking
+    }
+
+    /// The player gives up, along with their teammate. Once only one team is
+    /// left that ends the game, otherwise the others play on and the player's
+    /// turns are skipped.
+    pub fn resign(&mut self, player: u8) -> Result<(), PasstallyError> {
+        self.check_can_act(player)?;
+        let team = self.rules.team_of(player);
+        let resigning: Vec<u8> = self
+            .active_players()
+            .filter(|&other| self.rules.team_of(other) == team)
+            .collect();
+        for player in resigning {
+            self.resigned.push(player);
+            self.draw_offer.retain(|&agreeing| agreeing != player);
+        }
+        self.skip_resigned();
+        Ok(())
+    }
+
+    /// Offers the other players a draw, or agrees to the one on offer. The
+    /// offer stands until a player who hasn't agreed plays a turn.
+    pub fn offer_draw(&mut self, player: u8) -> Result<(), PasstallyError> {
+        self.check_can_act(player)?;
+        if !self.draw_offer.contains(&player) {
+            self.draw_offer.push(player);
+        }
+        Ok(())
+    }
+
+    /// Agrees to the draw on offer. The game is drawn once every player still
+    /// playing has agreed.
+    pub fn accept_draw(&mut self, player: u8) -> Result<(), PasstallyError> {
+        self.check_can_act(player)?;
+        if self.draw_offer.is_empty() {
+            return Err(PasstallyError::NoDrawOffer);
+        }
+        self.offer_draw(player)
+    }
+
+    fn check_can_act(&self, player: u8) -> Result<(), PasstallyError> {
+        if self.is_finished() {
+            Err(PasstallyError::GameOver)
+        } else if player >= self.rules.player_count {
+            Err(PasstallyError::NoSuchPlayer(player))
+        } else if self.resigned.contains(&player) {
+            Err(PasstallyError::Resigned(player))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Moves on past the turns of players who resigned.
+    fn skip_resigned(&mut self) {
+        while !self.is_finished() && self.resigned.contains(&self.next_player()) {
+            self.round += 1;
+        }
+    }
+
+    /// Ends the game if the player to move has run out of time, for when they
+    /// don't play at all. Returns whether they lost on time.
+    pub fn claim_time_forfeit(&mut self) -> bool {
+        let elapsed = self
+            .clock
+            .as_ref()
+            .map_or(Duration::from_secs(0), Clock::elapsed);
+        !self.is_finished() && self.forfeit_if_out_of_time(elapsed)
+    }
+
+    fn forfeit_if_out_of_time(&mut self, elapsed: Duration) -> bool {
+        let player = self.next_player();
+        match &self.clock {
+            Some(clock) if clock.is_out_of_time(player, elapsed) => {
+                self.time_forfeit = Some(player);
+                true
             }
+            _ => false,
+        }
+    }
+
+    /// Plays both actions of the turn and scores the player's lines. If either
+    /// action fails, the game is left as it was. With a clock, the time since
+    /// the last turn is charged to the player, see
+    /// [`play_timed_turn`](Game::play_timed_turn).
+    pub fn play_turn(&mut self, turn: Turn) -> Result<TurnOutcome, PasstallyError> {
+        let elapsed = self
+            .clock
+            .as_ref()
+            .map_or(Duration::from_secs(0), Clock::elapsed);
+        self.play_timed_turn(turn, elapsed)
+    }
+
+    /// Like [`play_turn`](Game::play_turn), with the time the player took for
+    /// the turn given rather than measured. A player who took longer than the
+    /// time they had left loses on time and the turn isn't played. Without a
+    /// clock the time is ignored.
+    pub fn play_timed_turn(
+        &mut self,
+        turn: Turn,
+        elapsed: Duration,
+    ) -> Result<TurnOutcome, PasstallyError> {
+        if self.is_finished() {
+            return Err(PasstallyError::GameOver);
+        }
+        if [&turn.0, &turn.1]
+            .iter()
+            .any(|action| action.is_standalone())
+        {
+            return Err(PasstallyError::NotPartOfTurn);
+        }
+        let player = self.next_player();
+        if self.forfeit_if_out_of_time(elapsed) {
+            return Err(PasstallyError::TimeForfeit(player));
+        }
+
+        // A failed action changes nothing, so only the first action has to
+        // be taken back when the second fails.
+        let Turn(action1, action2) = turn;
+        let undo = self.do_action_undoably(action1)?;
+        match self.do_action(action2) {
+            Ok(_) => Ok(self.end_turn(player, elapsed)),
             Err(err) => {
-                self.board = backup.0;
-                self.player_markers = backup.1;
+                self.undo_action(undo);
                 Err(err)
             }
         }
     }
 
+    /// Like [`do_action`](Game::do_action) for the actions of a turn,
+    /// returning what it takes to undo the action.
+    fn do_action_undoably(&mut self, action: Action) -> Result<ActionUndo, PasstallyError> {
+        match action {
+            Action::PlacePiece(piece) => {
+                let kind = piece.piece;
+                let deck = self.market_deck(kind)?;
+                let placement = self.board.place_piece_undoably(piece)?;
+                self.decks[deck].pop();
+                Ok(ActionUndo::PlacePiece(placement, deck, kind))
+            }
+            Action::MovePlayerMarker(from, to) => {
+                self.move_player_marker(from, to)?;
+                Ok(ActionUndo::MovePlayerMarker(from, to))
+            }
+            Action::RedrawMarket(deck) => {
+                self.can_redraw_market(deck)?;
+                let piece = self.decks[deck as usize].pop().unwrap();
+                Ok(ActionUndo::RedrawMarket(deck as usize, piece))
+            }
+            _ => Err(PasstallyError::NotPartOfTurn),
+        }
+    }
+
+    fn undo_action(&mut self, undo: ActionUndo) {
+        match undo {
+            ActionUndo::PlacePiece(placement, deck, piece) => {
+                self.board.undo_placement(placement);
+                self.decks[deck].push(piece);
+            }
+            ActionUndo::MovePlayerMarker(from, to) => {
+                let player = self.player_markers[to as usize].take().unwrap();
+                self.player_markers[from as usize] = Some(player);
+                self.markers_hash ^= zobrist::marker(from, player) ^ zobrist::marker(to, player);
+            }
+            ActionUndo::RedrawMarket(deck, piece) => self.decks[deck].push(piece),
+        }
+    }
+
+    /// Scores the lines of the player who just did both actions of their
+    /// turn and passes the turn on. For searches that do the actions one at a
+    /// time with [`do_action`](Game::do_action).
+    pub(crate) fn end_turn(&mut self, player: u8, elapsed: Duration) -> TurnOutcome {
+        let mut points = 0;
+        let markers = self.team_markers(&self.player_markers);
+        let team = self.rules.team_of(player);
+        for line in self.board.connected_lines(&markers, team) {
+            points += line.points;
+            if line.points > 0 {
+                self.score_events.push(ScoreEvent {
+                    turn: self.round,
+                    player,
+                    entry: line.entry,
+                    exit: line.exit,
+                    path: line.path.to_vec(),
+                    points: line.points,
+                });
+            }
+        }
+        self.scores[player as usize] += points;
+        if let Some(clock) = &mut self.clock {
+            clock.charge(player, elapsed);
+        }
+        if !self.draw_offer.contains(&player) {
+            self.draw_offer.clear();
+        }
+        self.round += 1;
+        self.skip_resigned();
+        self.settle_stalemate();
+        TurnOutcome { player, points }
+    }
+
     pub fn do_action(&mut self, action: Action) -> Result<(), PasstallyError> {
         match action {
-            Action::PlacePiece(piece) => self.board.place_piece(piece),
+            Action::PlacePiece(piece) => self.place_piece(piece),
             Action::MovePlayerMarker(from, to) => self.move_player_marker(from, to),
+            Action::RedrawMarket(deck) => self.redraw_market(deck),
+            Action::Resign => self.resign(self.next_player()),
+            Action::OfferDraw => self.offer_draw(self.next_player()),
+            Action::AcceptDraw => self.accept_draw(self.next_player()),
+        }
+    }
+
+    fn redraw_market(&mut self, deck: u8) -> Result<(), PasstallyError> {
+        self.can_redraw_market(deck)?;
+        self.decks[deck as usize].pop();
+        Ok(())
+    }
+
+    /// Checks whether the top piece of the deck could be discarded, without discarding it.
+    pub fn can_redraw_market(&self, deck: u8) -> Result<(), PasstallyError> {
+        if !self.rules.allow_redraw {
+            return Err(PasstallyError::RedrawNotAllowed);
+        }
+        match self.decks.get(deck as usize) {
+            Some(pieces) if !pieces.is_empty() => Ok(()),
+            _ => Err(PasstallyError::EmptyDeck(deck)),
         }
     }
 
+    /// The deck with the piece on top.
+    fn market_deck(&self, piece: Piece) -> Result<usize, PasstallyError> {
+        self.decks
+            .iter()
+            .position(|deck| deck.last() == Some(&piece))
+            .ok_or(PasstallyError::NotInMarket(piece))
+    }
+
+    fn place_piece(&mut self, piece: PositionedPiece) -> Result<(), PasstallyError> {
+        let deck = self.market_deck(piece.piece)?;
+        self.board.place_piece(piece)?;
+        self.decks[deck].pop();
+        Ok(())
+    }
+
     fn move_player_marker(&mut self, from: u8, to: u8) -> Result<(), PasstallyError> {
-        assert!(matches!(from, 0..=23));
-        assert!(matches!(to, 0..=23));
+        self.can_move_player_marker(from, to)?;
+
+        // Move player marker
+        let player = self.player_markers[from as usize].take().unwrap();
+        self.player_markers[to as usize] = Some(player);
+        self.markers_hash ^= zobrist::marker(from, player) ^ zobrist::marker(to, player);
+        Ok(())
+    }
+
+    /// Checks whether the player to move could move the marker, without moving it.
+    pub fn can_move_player_marker(&self, from: u8, to: u8) -> Result<(), PasstallyError> {
+        if let Some(slot) = [from, to].iter().find(|&&slot| slot > 23) {
+            return Err(PasstallyError::NoSuchSlot(*slot));
+        }
 
         // Check that "from" isn't empty
         if self.player_markers[from as usize].is_none() {
             return Err(PasstallyError::NoPlayerMarker(from));
         }
 
+        // Check that the marker belongs to the player whose turn it is
+        if self.player_markers[from as usize] != Some(self.next_player()) {
+            return Err(PasstallyError::NotYourPlayerMarker(from));
+        }
+
         // Check that "to" isn't occupied
         if self.player_markers[to as usize].is_some() {
             return Err(PasstallyError::HasPlayerMarker(to));
@@ -112,7 +681,6 @@ impl Game {
 
             // Iter between min and max the short way
             let empty_spaces = (min + 1..max)
-                .into_iter()
                 .filter(|&i| self.player_markers[i as usize].is_none())
                 .count();
             if empty_spaces <= 1 {
@@ -120,7 +688,6 @@ impl Game {
             } else {
                 // Iter between them the long way
                 let empty_spaces = (max + 1..min + 24)
-                    .into_iter()
                     .map(|v| v % 24)
                     .filter(|&i| self.player_markers[i as usize].is_none())
                     .count();
@@ -132,11 +699,77 @@ impl Game {
             return Err(PasstallyError::TooFar);
         }
 
-        // Move player marker
-        self.player_markers[to as usize] = self.player_markers[from as usize].take();
         Ok(())
     }
 
+    /// Every action the player to move could take right now, other than
+    /// resigning and draw offers.
+    pub fn legal_actions(&self) -> Vec<Action> {
+        self.legal_actions_iter().collect()
+    }
+
+    /// Like [`legal_actions`](Game::legal_actions), but found one at a time
+    /// as they are asked for, without allocating.
+    pub fn legal_actions_iter(&self) -> LegalActions {
+        LegalActions::new(self)
+    }
+
+    /// Every turn the player to move could play right now.
+    pub fn legal_turns(&self) -> Vec<Turn> {
+        self.legal_turns_iter().collect()
+    }
+
+    /// Like [`legal_turns`](Game::legal_turns), but found one at a time as
+    /// they are asked for, without allocating.
+    pub fn legal_turns_iter(&self) -> LegalTurns {
+        LegalTurns::new(self)
+    }
+
+    /// Whether the player to move has any legal turn. Cheaper than checking
+    /// [`legal_turns`](Game::legal_turns) as it stops at the first one.
+    pub fn has_legal_turn(&self) -> bool {
+        self.legal_turns_iter().next().is_some()
+    }
+
+    /// Deals with the player to move being stuck, as the rules say.
+    fn settle_stalemate(&mut self) {
+        let mut passes = 0;
+        while !self.has_legal_turn() && !self.is_finished() {
+            match self.rules.stalemate {
+                StalemateRule::EndGame => self.stalemate = true,
+                StalemateRule::PassTurn => {
+                    passes += 1;
+                    if passes >= self.active_players().count() {
+                        self.stalemate = true;
+                    } else {
+                        self.round += 1;
+                        self.skip_resigned();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Points the player's connected lines are currently worth. With teams,
+    /// lines to their teammate's markers count too.
+    pub fn line_points(&self, player: u8) -> u32 {
+        self.board.connected_points(
+            &self.team_markers(&self.player_markers),
+            self.rules.team_of(player),
+        )
+    }
+
+    /// The markers with the team that owns them rather than the player.
+    pub(crate) fn team_markers(&self, player_markers: &[Option<u8>; 24]) -> [Option<u8>; 24] {
+        let mut markers = *player_markers;
+        if self.rules.teams {
+            for marker in markers.iter_mut().flatten() {
+                *marker = self.rules.team_of(*marker);
+            }
+        }
+        markers
+    }
+
     pub fn player_markers(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
         self.player_markers
             .iter()
@@ -146,36 +779,679 @@ impl Game {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Action {
     PlacePiece(PositionedPiece),
     MovePlayerMarker(u8, u8), // 0..=23
+    /// Discards the top piece of a deck (0..=2), if the rules allow it.
+    RedrawMarket(u8),
+    /// The player to move gives up, see [`Game::resign`].
+    Resign,
+    /// See [`Game::offer_draw`].
+    OfferDraw,
+    /// See [`Game::accept_draw`].
+    AcceptDraw,
+}
+
+impl Action {
+    /// Whether the action is taken on its own rather than as half of a turn:
+    /// resigning and draw offers.
+    pub fn is_standalone(&self) -> bool {
+        matches!(
+            self,
+            Action::Resign | Action::OfferDraw | Action::AcceptDraw
+        )
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Turn(pub Action, pub Action);
 
+/// What kind of game is being played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub enum GameMode {
+    /// Played until the decks run out, the highest score wins.
+    #[default]
+    Standard,
+    /// One player trying to reach a score within a number of turns, see
+    /// [`Game::new_puzzle`].
+    Puzzle { turns: u32, target: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phase {
+    Playing,
+    Finished(EndReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EndReason {
+    /// Every piece has been played.
+    DecksEmpty,
+    /// Nobody could play, see [`StalemateRule`].
+    Stalemate,
+    /// The player ran out of time.
+    TimeForfeit(u8),
+    /// Everyone but one player, or one team, resigned.
+    Resignation,
+    /// Every player still playing agreed to a draw.
+    DrawAgreed,
+    /// The puzzle's turns are used up.
+    TurnLimit,
+}
+
+/// What happened when a turn was played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurnOutcome {
+    pub player: u8,
+    /// Points scored by the player's connected lines.
+    pub points: u32,
+}
+
+/// What an action of a turn changed, to take it back when the other action
+/// of the turn fails.
+enum ActionUndo {
+    PlacePiece(PlacementUndo, usize, Piece),
+    MovePlayerMarker(u8, u8),
+    RedrawMarket(usize, Piece),
+}
+
+/// A line scoring for a player at the end of their turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct ScoreEvent {
+    /// The round the turn was played in, counting from 0.
+    pub turn: u32,
+    pub player: u8,
+    /// The edge slots of the player's markers at the ends of the line.
+    pub entry: u8,
+    pub exit: u8,
+    /// The squares the line passes through, from `entry` to `exit`.
+    pub path: Vec<BoardPosition>,
+    pub points: u32,
+}
+
+/// Why a game read through serde can't be played, see [`Game::validate`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidGame {
+    #[error("Games are for 1 to 4 players, not {0}.")]
+    PlayerCount(u8),
+    #[error("Team games are for 4 players.")]
+    Teams,
+    #[error("There are {0} scores for {1} players.")]
+    Scores(usize, u8),
+    #[error("The decks hold {0} pieces, more than the 42 there are.")]
+    Decks(usize),
+    #[error("The board's heights can't be stacked from whole pieces.")]
+    Heights,
+    #[error("The board holds {0} pieces and the decks {1}, more than the 42 there are.")]
+    Pieces(u32, usize),
+    #[error("Piece ids run out after {0}, before the {1} pieces left are placed.")]
+    NextId(u16, usize),
+    #[error("Player {0} isn't in the game.")]
+    NoSuchPlayer(u8),
+    #[error("Puzzles are for 1 player, not {0}.")]
+    Puzzle(u8),
+    #[error("A scored line runs off the board.")]
+    ScoreEvent,
+    #[error("The clock keeps time for {0} players, not {1}.")]
+    Clock(usize, u8),
+    #[error("The board's lines don't match its pieces.")]
+    Board,
+}
+
 #[derive(Error, Debug)]
 pub enum PasstallyError {
     #[error("The piece is outside of the board.")]
     InvalidPosition(BoardPosition),
+    #[error("Pieces are rotated 0 to 3 times, not {0}.")]
+    BadRotation(u8),
     #[error("The height for the two positions aren't the same.")]
     BadHeight,
     #[error("You cannot place a piece directly ontop of another piece.")]
     BadPiece,
+    #[error("There is no edge slot {0}.")]
+    NoSuchSlot(u8),
     #[error("There is no player marker at position {0}.")]
     NoPlayerMarker(u8),
+    #[error("The player marker at position {0} belongs to another player.")]
+    NotYourPlayerMarker(u8),
     #[error("There is already a player marker at position {0}.")]
     HasPlayerMarker(u8),
     #[error("There is more than one empty player marker field between the from and to position.")]
     TooFar,
+    #[error("{0:?} is not on top of any of the decks.")]
+    NotInMarket(Piece),
+    #[error("The rules don't allow discarding market pieces.")]
+    RedrawNotAllowed,
+    #[error("There is no deck {0} or it is empty.")]
+    EmptyDeck(u8),
+    #[error("There is no draw on offer.")]
+    NoDrawOffer,
+    #[error("There is no player {0}.")]
+    NoSuchPlayer(u8),
+    #[error("Player {0} has resigned.")]
+    Resigned(u8),
+    #[error("Resigning and draw offers are done on their own, not as part of a turn.")]
+    NotPartOfTurn,
+    #[error("Player {0} ran out of time.")]
+    TimeForfeit(u8),
+    #[error("The game is over.")]
+    GameOver,
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::rules::Handicap;
 
+    #[cfg(feature = "std")]
     #[test]
     fn construct_game() {
         let _game = Game::new(2);
     }
+
+    #[test]
+    fn legal_moves() {
+        let game = Game::new_with_rules(Ruleset::new(2), 0);
+        let actions = game.legal_actions();
+
+        let placements = actions
+            .iter()
+            .filter(|action| matches!(action, Action::PlacePiece(_)))
+            .count();
+        let mut market: Vec<Piece> = game.market().iter().flatten().copied().collect();
+        market.sort_by_key(|piece| piece.index());
+        market.dedup();
+        // On an empty board a piece fits anywhere it doesn't stick out: 2 * 6 * 5 ways lying
+        // down, each from either end.
+        assert_eq!(placements, market.len() * 2 * 2 * 6 * 5);
+
+        // Markers jump over other markers, but only over one free slot.
+        let moves: Vec<&Action> = actions
+            .iter()
+            .filter(|action| matches!(action, Action::MovePlayerMarker(_, _)))
+            .collect();
+        assert!(moves.contains(&&Action::MovePlayerMarker(0, 23)));
+        assert!(moves.contains(&&Action::MovePlayerMarker(0, 2)));
+        assert!(moves.contains(&&Action::MovePlayerMarker(0, 3)));
+        assert!(!moves.contains(&&Action::MovePlayerMarker(0, 4)));
+        assert!(!moves.contains(&&Action::MovePlayerMarker(1, 2)));
+
+        for action in &actions {
+            let mut game = game.clone();
+            game.do_action(action.clone()).unwrap();
+        }
+
+        let turns = game.legal_turns();
+        assert!(turns.len() > actions.len());
+        let mut played = game.clone();
+        played.play_turn(turns[turns.len() / 2].clone()).unwrap();
+    }
+
+    #[test]
+    fn teams() {
+        let mut game = Game::new_with_rules(Ruleset::teams(), 0);
+        let piece = game.market()[0].unwrap();
+        game.board
+            .place_piece(PositionedPiece {
+                piece,
+                rotation: 0,
+                position: BoardPosition::new(2, 0),
+            })
+            .unwrap();
+        let line = (0..24)
+            .map(|slot| game.board.trace(slot))
+            .find(|line| line.points > 0 && line.exit > line.entry)
+            .unwrap();
+        game.player_markers = [None; 24];
+        game.player_markers[line.entry as usize] = Some(0);
+        game.player_markers[line.exit as usize] = Some(2);
+
+        // The line is player 0's and player 2's, but not the other team's.
+        assert_eq!(game.line_points(0), line.points);
+        assert_eq!(game.line_points(2), line.points);
+        assert_eq!(game.line_points(1), 0);
+
+        game.scores = vec![3, 1, 4, 0];
+        assert_eq!(game.team_score(0), 7);
+        assert_eq!(game.final_ranking(), vec![vec![0, 2], vec![1, 3]]);
+        game.resign(2).unwrap();
+        assert_eq!(game.phase(), Phase::Finished(EndReason::Resignation));
+        assert_eq!(game.final_ranking(), vec![vec![1, 3], vec![0, 2]]);
+    }
+
+    #[test]
+    fn puzzle() {
+        let mut start = Game::new_with_rules(Ruleset::new(2), 4);
+        for _ in 0..4 {
+            let turn = start.legal_turns().remove(0);
+            start.play_turn(turn).unwrap();
+        }
+
+        let mut game =
+            Game::new_puzzle(start.board, start.player_markers, start.decks.clone(), 2, 1);
+        assert_eq!(game.puzzle_solved(), Some(false));
+        for _ in 0..2 {
+            assert_eq!(game.next_player(), 0);
+            let turn = game.legal_turns().remove(0);
+            game.play_turn(turn).unwrap();
+        }
+        assert_eq!(game.phase(), Phase::Finished(EndReason::TurnLimit));
+        assert_eq!(game.puzzle_solved(), Some(game.scores()[0] >= 1));
+        assert_eq!(start.puzzle_solved(), None);
+
+        let decks = start.decks.clone().map(|deck| deck.to_vec());
+        let from_vecs = Game::new_puzzle(start.board, start.player_markers, decks, 2, 1);
+        assert_eq!(from_vecs.decks, start.decks);
+    }
+
+    #[test]
+    fn handicap() {
+        let rules = Ruleset {
+            handicap: vec![
+                Handicap::default(),
+                Handicap {
+                    points: 5,
+                    extra_markers: 2,
+                },
+            ],
+            ..Ruleset::new(2)
+        };
+        let game = Game::new_with_rules(rules, 0);
+        assert_eq!(game.scores(), &[0, 5]);
+        assert_eq!(game.player_markers().filter(|&(_, p)| p == 0).count(), 4);
+        let markers: Vec<usize> = game
+            .player_markers()
+            .filter(|&(_, player)| player == 1)
+            .map(|(slot, _)| slot)
+            .collect();
+        assert_eq!(markers, vec![1, 5, 7, 11, 13, 19]);
+        assert_eq!(game.markers_hash, zobrist::markers(&game.player_markers));
+    }
+
+    #[test]
+    fn redraw_market() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        assert!(matches!(
+            game.do_action(Action::RedrawMarket(0)),
+            Err(PasstallyError::RedrawNotAllowed)
+        ));
+        assert!(!game.legal_actions().contains(&Action::RedrawMarket(0)));
+
+        let rules = Ruleset {
+            allow_redraw: true,
+            ..Ruleset::new(2)
+        };
+        let mut game = Game::new_with_rules(rules, 0);
+        assert!(game.legal_actions().contains(&Action::RedrawMarket(2)));
+        let below = game.decks[1][12];
+        game.do_action(Action::RedrawMarket(1)).unwrap();
+        assert_eq!(game.market()[1], Some(below));
+        assert_eq!(game.decks_remaining(), [14, 13, 14]);
+
+        assert!(matches!(
+            game.do_action(Action::RedrawMarket(3)),
+            Err(PasstallyError::EmptyDeck(3))
+        ));
+        game.decks[0].clear();
+        assert!(matches!(
+            game.do_action(Action::RedrawMarket(0)),
+            Err(PasstallyError::EmptyDeck(0))
+        ));
+    }
+
+    #[test]
+    fn out_of_range_actions_are_refused() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        assert!(matches!(
+            game.do_action(Action::MovePlayerMarker(0, 200)),
+            Err(PasstallyError::NoSuchSlot(200))
+        ));
+        let piece = |x, rotation| PositionedPiece {
+            piece: game.market()[0].unwrap(),
+            position: BoardPosition::new(x, 0),
+            rotation,
+        };
+        let (wide, turned) = (piece(127, 0), piece(0, 9));
+        assert!(matches!(
+            game.do_action(Action::PlacePiece(wide)),
+            Err(PasstallyError::InvalidPosition(_))
+        ));
+        assert!(matches!(
+            game.do_action(Action::PlacePiece(turned)),
+            Err(PasstallyError::BadRotation(9))
+        ));
+    }
+
+    /// Heights in a checkerboard pattern, so no piece fits anywhere, and a
+    /// single free marker slot. Player 0 has no markers, so they are stuck.
+    fn stuck_position(stalemate: StalemateRule) -> Game {
+        let row = |y| {
+            (0..6)
+                .map(|x| format!("A{}*", 1 + (x + y) % 2))
+                .collect::<String>()
+        };
+        let board: Vec<String> = (0..6).map(row).collect();
+        let notation = format!("{} -{} R1,G1,Y1: 0,0 1", board.join("/"), "1".repeat(23));
+
+        let mut game = Game::from_notation(&notation).unwrap();
+        game.rules.stalemate = stalemate;
+        game
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut game = stuck_position(StalemateRule::EndGame);
+        assert_eq!(game.phase(), Phase::Playing);
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        assert_eq!(game.phase(), Phase::Finished(EndReason::Stalemate));
+        assert!(game.legal_turns().is_empty());
+        assert!(matches!(
+            game.play_turn(Turn(
+                Action::MovePlayerMarker(1, 0),
+                Action::MovePlayerMarker(2, 1)
+            )),
+            Err(PasstallyError::GameOver)
+        ));
+
+        // Passing skips player 0, and player 1 keeps playing.
+        let mut game = stuck_position(StalemateRule::PassTurn);
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        assert_eq!(game.phase(), Phase::Playing);
+        assert_eq!((game.round(), game.next_player()), (3, 1));
+
+        // Once nobody can play, the game ends.
+        game.player_markers = [Some(1); 24];
+        let mut stuck = game.clone();
+        stuck.settle_stalemate();
+        assert_eq!(stuck.phase(), Phase::Finished(EndReason::Stalemate));
+        assert_eq!(stuck.round(), game.round() + 1);
+    }
+
+    #[test]
+    fn clones_diverge() {
+        let game = Game::new_with_rules(Ruleset::new(2), 0);
+        let mut clone = game.clone();
+        assert_eq!(clone, game);
+
+        let turn = clone.legal_turns().remove(0);
+        clone.play_turn(turn.clone()).unwrap();
+        assert_ne!(clone, game);
+        assert_eq!(game.round, 0);
+        assert_eq!(game.decks_remaining(), [14, 14, 14]);
+
+        // Playing the same turn on the original catches it up.
+        let mut original = game;
+        original.play_turn(turn).unwrap();
+        assert_eq!(original, clone);
+    }
+
+    #[test]
+    fn failed_turns_change_nothing() {
+        let rules = Ruleset {
+            allow_redraw: true,
+            ..Ruleset::new(2)
+        };
+        let game = Game::new_with_rules(rules, 0);
+        let actions = game.legal_actions();
+        let first = |matches: fn(&Action) -> bool| actions.iter().find(|a| matches(a)).unwrap();
+        for action in &[
+            first(|action| matches!(action, Action::PlacePiece(_))),
+            first(|action| matches!(action, Action::MovePlayerMarker(_, _))),
+            first(|action| matches!(action, Action::RedrawMarket(_))),
+        ] {
+            let mut failed = game.clone();
+            let turn = Turn((*action).clone(), Action::RedrawMarket(3));
+            assert!(failed.play_turn(turn).is_err());
+            assert_eq!(failed, game);
+            assert_eq!(failed.hash(), game.hash());
+            assert_eq!(failed.to_full_notation(), game.to_full_notation());
+
+            let turn = failed.legal_turns().remove(0);
+            let mut played = game.clone();
+            played.play_turn(turn.clone()).unwrap();
+            failed.play_turn(turn).unwrap();
+            assert_eq!(failed, played);
+        }
+    }
+
+    #[test]
+    fn equality_ignores_placement_order() {
+        let pieces = [
+            PositionedPiece {
+                piece: Piece::Red,
+                rotation: 0,
+                position: BoardPosition::new(0, 0),
+            },
+            PositionedPiece {
+                piece: Piece::Blue,
+                rotation: 1,
+                position: BoardPosition::new(4, 2),
+            },
+        ];
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        let mut other = game.clone();
+        for piece in pieces.iter() {
+            game.board.place_piece(piece.clone()).unwrap();
+        }
+        for piece in pieces.iter().rev() {
+            other.board.place_piece(piece.clone()).unwrap();
+        }
+        assert_eq!(game, other);
+
+        other.scores[1] += 1;
+        assert_ne!(game, other);
+    }
+
+    #[test]
+    fn forks() {
+        let game = Game::new_with_rules(Ruleset::new(2), 0);
+        let mut fork = game.fork();
+        fork.play_turn(fork.legal_turns().remove(0)).unwrap();
+        assert_eq!(game.round, 0);
+
+        let sorted = |game: &Game| {
+            let mut pieces: Vec<u32> = game.decks.iter().flatten().map(Piece::index).collect();
+            pieces.sort_unstable();
+            pieces
+        };
+        let reshuffled = fork.fork_reshuffled(1);
+        assert_eq!(reshuffled.market(), fork.market());
+        assert_eq!(reshuffled.decks_remaining(), fork.decks_remaining());
+        assert_eq!(sorted(&reshuffled), sorted(&fork));
+        assert_ne!(reshuffled.decks, fork.decks);
+        assert_eq!(reshuffled.hash(), fork.hash());
+    }
+
+    #[test]
+    fn resignation() {
+        let mut game = Game::new_with_rules(Ruleset::new(3), 0);
+        game.scores = vec![4, 9, 4];
+        game.resign(1).unwrap();
+        assert!(matches!(game.resign(1), Err(PasstallyError::Resigned(1))));
+        assert_eq!(game.final_ranking(), vec![vec![0, 2], vec![1]]);
+
+        // Player 1's turns are skipped.
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        assert_eq!(game.next_player(), 2);
+        assert!(matches!(
+            game.play_turn(Turn(Action::Resign, Action::OfferDraw)),
+            Err(PasstallyError::NotPartOfTurn)
+        ));
+
+        game.do_action(Action::Resign).unwrap();
+        assert_eq!(game.phase(), Phase::Finished(EndReason::Resignation));
+        assert_eq!(game.final_ranking()[0], vec![0]);
+        assert_eq!(game.final_ranking()[1..], [vec![2], vec![1]]);
+    }
+
+    #[test]
+    fn draw_agreement() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        assert!(matches!(
+            game.do_action(Action::AcceptDraw),
+            Err(PasstallyError::NoDrawOffer)
+        ));
+
+        // Playing on declines the offer, unless you made it.
+        game.do_action(Action::OfferDraw).unwrap();
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        assert_eq!(game.draw_offer, vec![0]);
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        assert!(game.draw_offer.is_empty());
+
+        game.offer_draw(1).unwrap();
+        assert_eq!(game.phase(), Phase::Playing);
+        game.do_action(Action::AcceptDraw).unwrap();
+        assert_eq!(game.phase(), Phase::Finished(EndReason::DrawAgreed));
+        assert_eq!(game.final_ranking(), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn time_control() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        game.set_time_control(TimeControl::new(
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+        ));
+        let turn = game.legal_turns().remove(0);
+        game.play_timed_turn(turn, Duration::from_secs(20)).unwrap();
+        let clock = game.clock().unwrap();
+        assert_eq!(clock.remaining(0), Duration::from_secs(15));
+        assert_eq!(clock.remaining(1), Duration::from_secs(30));
+        assert!(!game.claim_time_forfeit());
+
+        // Running out of time loses the game without playing the turn.
+        let turn = game.legal_turns().remove(0);
+        assert!(matches!(
+            game.play_timed_turn(turn.clone(), Duration::from_secs(31)),
+            Err(PasstallyError::TimeForfeit(1))
+        ));
+        assert_eq!(game.round(), 1);
+        assert_eq!(game.phase(), Phase::Finished(EndReason::TimeForfeit(1)));
+        assert!(matches!(
+            game.play_turn(turn),
+            Err(PasstallyError::GameOver)
+        ));
+    }
+
+    #[test]
+    fn hash() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        let start = game.hash();
+        game.do_action(Action::MovePlayerMarker(0, 23)).unwrap();
+        assert_ne!(game.hash(), start);
+        assert_eq!(game.markers_hash, zobrist::markers(&game.player_markers));
+        game.do_action(Action::MovePlayerMarker(23, 0)).unwrap();
+        assert_eq!(game.hash(), start);
+
+        // Failed turns leave the hash alone.
+        let turn = Turn(
+            Action::MovePlayerMarker(0, 23),
+            Action::MovePlayerMarker(1, 2),
+        );
+        assert!(game.play_turn(turn).is_err());
+        assert_eq!(game.hash(), start);
+
+        // The same position with the other player to move is different.
+        let turn = game.legal_turns().remove(0);
+        game.play_turn(turn).unwrap();
+        let mut other = Game::from_notation(&game.to_notation()).unwrap();
+        assert_eq!(other.hash(), game.hash());
+        other.round += 1;
+        assert_ne!(other.hash(), game.hash());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        game.do_action(Action::MovePlayerMarker(0, 2)).unwrap();
+
+        let json = serde_json::to_string(&game).unwrap();
+        let restored: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        assert_eq!(restored.player_markers, game.player_markers);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn games_read_through_serde_are_validated() {
+        let game = Game::new_with_rules(Ruleset::new(2), 0);
+        assert_eq!(game.validate(), Ok(()));
+
+        let broken = |path: &[&str], value: serde_json::Value| {
+            let mut json = serde_json::to_value(&game).unwrap();
+            let (last, path) = path.split_last().unwrap();
+            let parent = path.iter().fold(&mut json, |json, key| &mut json[*key]);
+            match last.parse::<usize>() {
+                Ok(i) => parent[i] = value,
+                Err(_) => parent[*last] = value,
+            }
+            serde_json::from_value::<Game>(json).unwrap().validate()
+        };
+        assert_eq!(
+            broken(&["rules", "player_count"], 0.into()),
+            Err(InvalidGame::PlayerCount(0))
+        );
+        assert_eq!(
+            broken(&["player_markers", "3"], 2.into()),
+            Err(InvalidGame::NoSuchPlayer(2))
+        );
+        assert_eq!(
+            broken(&["scores"], serde_json::json!([0])),
+            Err(InvalidGame::Scores(1, 2))
+        );
+        assert_eq!(
+            broken(&["board", "top_pieces", "7"], 200.into()),
+            Err(InvalidGame::Board)
+        );
+        assert_eq!(
+            broken(&["board", "exits", "0"], 30.into()),
+            Err(InvalidGame::Board)
+        );
+        assert_eq!(
+            broken(&["board", "height", "0"], 1.into()),
+            Err(InvalidGame::Heights)
+        );
+        assert_eq!(
+            broken(&["board", "height", "0"], 2.into()),
+            Err(InvalidGame::Heights)
+        );
+        let pieces = game.decks_remaining().iter().sum();
+        assert_eq!(
+            broken(&["board", "next_id"], u16::MAX.into()),
+            Err(InvalidGame::NextId(u16::MAX, pieces))
+        );
+
+        let mut json = serde_json::to_value(&game).unwrap();
+        json["board"]["height"][0] = 1.into();
+        json["board"]["height"][1] = 1.into();
+        assert_eq!(
+            serde_json::from_value::<Game>(json).unwrap().validate(),
+            Err(InvalidGame::Pieces(1, 42))
+        );
+    }
 }
@@ -0,0 +1,168 @@
+//! Heatmaps of where things happen on the board over many games: which
+//! squares pieces are placed on, each kind of piece on its own, and which
+//! squares the scoring lines run through. Each is a 6 by 6 matrix to be
+//! drawn by whatever does the visualization.
+
+use std::fmt;
+
+use crate::board::BoardPosition;
+use crate::game::Action;
+use crate::piece::Piece;
+use crate::replay::{Replay, ReplayError};
+
+/// A number for each square of the board, rows from the top and squares
+/// from the left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Heatmap {
+    pub cells: [[u32; 6]; 6],
+}
+
+impl Heatmap {
+    pub fn get(&self, pos: BoardPosition) -> u32 {
+        self.cells[pos.y as usize][pos.x as usize]
+    }
+
+    pub fn add(&mut self, pos: BoardPosition, amount: u32) {
+        self.cells[pos.y as usize][pos.x as usize] += amount;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.cells.iter().flatten().sum()
+    }
+
+    pub fn max(&self) -> u32 {
+        self.cells.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// Each square as a share of the busiest one, between 0 and 1.
+    pub fn normalized(&self) -> [[f64; 6]; 6] {
+        let max = self.max().max(1) as f64;
+        let mut normalized = [[0.0; 6]; 6];
+        for (row, cells) in normalized.iter_mut().zip(&self.cells) {
+            for (normalized, &cell) in row.iter_mut().zip(cells) {
+                *normalized = cell as f64 / max;
+            }
+        }
+        normalized
+    }
+
+    /// The matrix as comma separated values, a row per line.
+    pub fn to_csv(&self) -> String {
+        self.cells
+            .iter()
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(u32::to_string).collect();
+                format!("{}\n", row.join(","))
+            })
+            .collect()
+    }
+}
+
+/// The matrix with its columns lined up.
+impl fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.max().to_string().len();
+        for row in &self.cells {
+            let row: Vec<String> = row
+                .iter()
+                .map(|cell| format!("{:>width$}", cell, width = width))
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlacementStats {
+    pub games: usize,
+    /// Pieces placed covering each square, counting both halves.
+    pub placements: Heatmap,
+    /// The same for each kind of piece, by [`Piece::index`].
+    pub pieces: [Heatmap; 6],
+    /// Points scored by lines through each square.
+    pub scoring: Heatmap,
+}
+
+impl PlacementStats {
+    pub fn new() -> PlacementStats {
+        PlacementStats::default()
+    }
+
+    pub fn from_replays<'a>(
+        replays: impl IntoIterator<Item = &'a Replay>,
+    ) -> Result<PlacementStats, ReplayError> {
+        let mut stats = PlacementStats::new();
+        for replay in replays {
+            stats.add_replay(replay)?;
+        }
+        Ok(stats)
+    }
+
+    /// Adds a game, after checking that it plays back.
+    pub fn add_replay(&mut self, replay: &Replay) -> Result<(), ReplayError> {
+        let game = replay.play_back()?;
+        for turn in &replay.turns {
+            for action in &[&turn.0, &turn.1] {
+                if let Action::PlacePiece(piece) = action {
+                    let (first, second) = piece.positions();
+                    for &pos in &[first, second] {
+                        self.placements.add(pos, 1);
+                        self.pieces[piece.piece.index() as usize].add(pos, 1);
+                    }
+                }
+            }
+        }
+        for event in game.score_events() {
+            for &pos in &event.path {
+                self.scoring.add(pos, event.points);
+            }
+        }
+        self.games += 1;
+        Ok(())
+    }
+
+    pub fn piece(&self, piece: Piece) -> &Heatmap {
+        &self.pieces[piece.index() as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn counts_placements_and_lines() {
+        let mut replay = Replay::new(vec!["A".into(), "B".into()], Ruleset::new(2), 8);
+        let mut game = replay.initial_game();
+        for _ in 0..10 {
+            let places = |action: &Action| matches!(action, Action::PlacePiece(_));
+            let turn = game
+                .legal_turns()
+                .into_iter()
+                .rev()
+                .find(|turn| places(&turn.0) && places(&turn.1))
+                .unwrap();
+            replay.play(&mut game, turn).unwrap();
+        }
+        let stats = PlacementStats::from_replays(&[replay.clone(), replay]).unwrap();
+
+        assert_eq!(stats.games, 2);
+        let placed = stats.placements.total();
+        assert_eq!(placed, 2 * 10 * 2 * 2);
+        let by_piece: u32 = stats.pieces.iter().map(Heatmap::total).sum();
+        assert_eq!(by_piece, placed);
+        let scored: u32 = game.score_events().iter().map(|event| event.points).sum();
+        assert_eq!(scored > 0, stats.scoring.total() > 0);
+
+        let csv = stats.placements.to_csv();
+        assert_eq!(csv.lines().count(), 6);
+        assert!(csv.lines().all(|row| row.split(',').count() == 6));
+        let normalized = stats.placements.normalized();
+        let busiest = normalized.iter().flatten().copied().fold(0.0, f64::max);
+        assert_eq!(busiest, 1.0);
+    }
+}
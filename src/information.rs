@@ -0,0 +1,188 @@
+//! What the players know about a game. Everyone sees the board, the markers,
+//! the scores and the market, but nobody knows the order of the pieces further
+//! down the decks. Bots that shouldn't cheat, and network clients, only get an
+//! [`InformationSet`], and can sample full games that agree with it.
+
+use alloc::vec::Vec;
+
+use rand::{prelude::SliceRandom, Rng};
+use thiserror::Error;
+
+use crate::board::Board;
+use crate::clock::Clock;
+use crate::game::{Deck, EndReason, Game, GameMode, Phase, ScoreEvent};
+use crate::piece::Piece;
+use crate::rules::Ruleset;
+use crate::zobrist;
+
+/// A game as one player sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InformationSet {
+    /// The player looking at the game.
+    pub player: u8,
+    pub board: Board,
+    pub player_markers: [Option<u8>; 24],
+    pub rules: Ruleset,
+    pub mode: GameMode,
+    pub scores: Vec<u32>,
+    pub score_events: Vec<ScoreEvent>,
+    pub round: u32,
+    pub phase: Phase,
+    pub resigned: Vec<u8>,
+    pub draw_offer: Vec<u8>,
+    pub clock: Option<Clock>,
+    pub market: [Option<Piece>; 3],
+    pub decks_remaining: [usize; 3],
+    /// The pieces under the market, sorted so their order gives nothing away.
+    pub unseen: Vec<Piece>,
+}
+
+impl Game {
+    pub fn information_set(&self, player: u8) -> InformationSet {
+        let mut unseen: Vec<Piece> = self
+            .decks
+            .iter()
+            .flat_map(|deck| deck.iter().rev().skip(1))
+            .copied()
+            .collect();
+        unseen.sort_by_key(|piece| piece.index());
+
+        InformationSet {
+            player,
+            board: self.board,
+            player_markers: self.player_markers,
+            rules: self.rules.clone(),
+            mode: self.mode,
+            scores: self.scores.clone(),
+            score_events: self.score_events.clone(),
+            round: self.round,
+            phase: self.phase(),
+            resigned: self.resigned.clone(),
+            draw_offer: self.draw_offer.clone(),
+            clock: self.clock.clone(),
+            market: self.market(),
+            decks_remaining: self.decks_remaining(),
+            unseen,
+        }
+    }
+
+    /// A game that looks the same to the player to move, with the unseen
+    /// pieces in a random order.
+    pub fn determinize(&self, rng: &mut impl Rng) -> Game {
+        self.information_set(self.next_player())
+            .determinize(rng)
+            .expect("A game's own information set should be consistent")
+    }
+}
+
+impl InformationSet {
+    /// A full game consistent with what the player sees, with the unseen pieces
+    /// dealt into the decks in a random order. Fails if the deck sizes don't
+    /// fit the market and the unseen pieces.
+    pub fn determinize(&self, rng: &mut impl Rng) -> Result<Game, InconsistentInformation> {
+        for deck in 0..3 {
+            if self.market[deck].is_some() != (self.decks_remaining[deck] > 0) {
+                return Err(InconsistentInformation::Market(deck));
+            }
+        }
+        let below: usize = self
+            .decks_remaining
+            .iter()
+            .map(|remaining| remaining.saturating_sub(1))
+            .sum();
+        if below != self.unseen.len() {
+            return Err(InconsistentInformation::Unseen(below, self.unseen.len()));
+        }
+
+        let mut unseen = self.unseen.clone();
+        unseen.shuffle(rng);
+
+        let mut decks = [Deck::new(), Deck::new(), Deck::new()];
+        for (i, deck) in decks.iter_mut().enumerate() {
+            let below = self.decks_remaining[i].saturating_sub(1);
+            deck.extend(unseen.drain(unseen.len() - below..));
+            deck.extend(self.market[i]);
+        }
+
+        Ok(Game {
+            board: self.board,
+            player_markers: self.player_markers,
+            markers_hash: zobrist::markers(&self.player_markers),
+            rules: self.rules.clone(),
+            mode: self.mode,
+            scores: self.scores.clone(),
+            score_events: self.score_events.clone(),
+            round: self.round,
+            decks,
+            stalemate: self.phase == Phase::Finished(EndReason::Stalemate),
+            clock: self.clock.clone(),
+            time_forfeit: match self.phase {
+                Phase::Finished(EndReason::TimeForfeit(player)) => Some(player),
+                _ => None,
+            },
+            resigned: self.resigned.clone(),
+            draw_offer: self.draw_offer.clone(),
+        })
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum InconsistentInformation {
+    #[error("The market piece of deck {0} doesn't match whether the deck is empty.")]
+    Market(usize),
+    #[error("The decks hold {0} pieces under the market, but {1} are unseen.")]
+    Unseen(usize, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn determinized_games_look_the_same() {
+        let mut game = Game::new_with_rules(Ruleset::new(3), 5);
+        for _ in 0..4 {
+            let turn = game.legal_turns().remove(0);
+            game.play_turn(turn).unwrap();
+        }
+
+        let seen = game.information_set(1);
+        assert_eq!(
+            seen.unseen.len(),
+            game.decks_remaining().iter().sum::<usize>() - 3
+        );
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut differs = false;
+        for _ in 0..5 {
+            let sample = seen.determinize(&mut rng).unwrap();
+            assert_eq!(sample.information_set(1), seen);
+            assert_eq!(sample.hash(), game.hash());
+            assert_eq!(sample.legal_actions(), game.legal_actions());
+            differs |= sample != game;
+        }
+        assert!(differs);
+
+        // Different deck orders give the same view.
+        assert_eq!(
+            game.fork_reshuffled(9).information_set(0),
+            game.information_set(0)
+        );
+
+        let mut short = seen.clone();
+        short.unseen.pop();
+        assert!(matches!(
+            short.determinize(&mut rng),
+            Err(InconsistentInformation::Unseen(_, _))
+        ));
+        let mut empty = seen;
+        empty.decks_remaining[0] = 0;
+        assert_eq!(
+            empty.determinize(&mut rng),
+            Err(InconsistentInformation::Market(0))
+        );
+    }
+}
@@ -1,3 +1,54 @@
+//! The rules engine builds without the standard library, on `alloc` alone,
+//! when the `std` feature is off. Everything else needs `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "ai")]
+pub mod ai;
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+#[cfg(feature = "bitboard")]
+pub mod bitboard;
 pub mod board;
+#[cfg(feature = "std")]
+pub mod capabilities;
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod daily;
+#[cfg(feature = "std")]
+pub mod diagnosis;
+#[cfg(feature = "std")]
+pub mod explorer;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod game;
+#[cfg(feature = "std")]
+pub mod heatmap;
+pub mod information;
+pub mod movegen;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod notation;
+#[cfg(feature = "std")]
+pub mod overlay;
+#[cfg(feature = "paths")]
+pub mod paths;
 pub mod piece;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "json")]
+pub mod rpc;
+pub mod rules;
+#[cfg(feature = "binary")]
+pub mod snapshot;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod zobrist;
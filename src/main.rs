@@ -1,18 +1,26 @@
 use std::{f32::consts::PI, time::Duration};
 
-use bevy::{prelude::*, render::camera::Camera};
+use bevy::prelude::*;
 use bevy_easings::{Ease, EaseFunction, EasingType, EasingsPlugin};
+use overlay_view::OverlayPlugin;
 use passtally_rs::{
     board::BoardPosition,
     game::{Action, Game as PasstallyGame},
-    piece::{Piece, PositionedPiece},
+    paths::DataDirs,
+    piece::PositionedPiece,
 };
 use rand::{thread_rng, Rng};
+use toast::{Notification, ToastPlugin};
+
+mod overlay_view;
+mod toast;
 
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
         .add_plugin(EasingsPlugin)
+        .add_plugin(ToastPlugin)
+        .add_plugin(OverlayPlugin)
         .add_plugin(GamePlugin)
         .run();
 }
@@ -25,12 +33,18 @@ impl Plugin for GamePlugin {
             .add_event::<Action>()
             .add_system(debug_keyboard.system())
             .add_system(process_passtally_move.system())
+            .add_system(update_market.system())
             .add_system(fit_camera_to_screen.system())
             .add_system(selection_system.system());
     }
 }
 
 struct Board;
+/// The camera looking at the board, as opposed to the one drawing the UI.
+struct MainCamera;
+/// Where the game is saved after every action, if there is anywhere to.
+struct Autosave(Option<DataDirs>);
+
 const SCREEN_SIZE: Vec2 = Vec2 { x: 192.0, y: 128.0 }; //in pixels
 const BOARD_POSITION: Vec2 = Vec2 {
     x: -SCREEN_SIZE.x / 2.0 + 64.0,
@@ -52,10 +66,25 @@ fn setup(
             transform: Transform::from_scale(Vec3::splat(1.0 / 6.0)),
             ..Default::default()
         })
+        .with(MainCamera)
         .current_entity()
         .unwrap();
     commands.insert_resource(SelectionSystemState { camera_e: camera });
 
+    match DataDirs::locate() {
+        Some(dirs) => {
+            if let Err(e) = dirs.create_all() {
+                warn!("Couldn't create the data directories: {}", e);
+            }
+            commands.insert_resource(Autosave(Some(dirs.clone())));
+            commands.insert_resource(dirs);
+        }
+        None => {
+            warn!("Couldn't find where to keep settings and saves.");
+            commands.insert_resource(Autosave(None));
+        }
+    }
+
     let board_texture = asset_server.load("passtally_board.png");
     commands
         .spawn(SpriteBundle {
@@ -95,7 +124,7 @@ fn setup(
             });
     }
 
-    let mut rng = thread_rng();
+    let market = passtally.market();
     for i in 0..3 {
         let mut transform = Transform::from_translation(
             Vec2::new(144.0 - 96.0, (40 * i) as f32 + 24.0 - 64.0).extend(-1.0),
@@ -105,10 +134,11 @@ fn setup(
         commands
             .spawn(SpriteSheetBundle {
                 texture_atlas: texture_atlases.get_handle("pieces"),
-                sprite: TextureAtlasSprite::new(rng.gen_range(0..6)),
+                sprite: TextureAtlasSprite::new(market[i].map_or(0, |piece| piece.index())),
                 transform,
                 ..Default::default()
             })
+            .with(MarketSlot(i))
             .with(Clickable {
                 bounding_box: Size::new(16.0, 32.0),
             });
@@ -129,7 +159,7 @@ fn setup(
     commands.insert_resource(passtally);
 }
 
-fn fit_camera_to_screen(windows: Res<Windows>, mut query: Query<&mut Transform, With<Camera>>) {
+fn fit_camera_to_screen(windows: Res<Windows>, mut query: Query<&mut Transform, With<MainCamera>>) {
     // Only one camera thanks.
     assert_eq!(query.iter_mut().count(), 1);
     for mut pos in query.iter_mut() {
@@ -143,22 +173,20 @@ fn fit_camera_to_screen(windows: Res<Windows>, mut query: Query<&mut Transform,
     }
 }
 
-fn debug_keyboard(keyboard: Res<Input<KeyCode>>, mut events: ResMut<Events<Action>>) {
+fn debug_keyboard(
+    keyboard: Res<Input<KeyCode>>,
+    passtally_game: Res<PasstallyGame>,
+    mut events: ResMut<Events<Action>>,
+) {
     let mut rng = thread_rng();
     if keyboard.pressed(KeyCode::A) {
-        events.send(Action::PlacePiece(PositionedPiece {
-            piece: match rng.gen_range(0..6) {
-                0 => Piece::Red,
-                1 => Piece::Green,
-                2 => Piece::Yellow,
-                3 => Piece::Blue,
-                4 => Piece::Cyan,
-                5 => Piece::Pink,
-                _ => unreachable!(),
-            },
-            position: BoardPosition::new(rng.gen_range(0..6), rng.gen_range(0..6)),
-            rotation: rng.gen_range(0..4),
-        }));
+        if let Some(piece) = passtally_game.market()[rng.gen_range(0..3)] {
+            events.send(Action::PlacePiece(PositionedPiece {
+                piece,
+                position: BoardPosition::new(rng.gen_range(0..6), rng.gen_range(0..6)),
+                rotation: rng.gen_range(0..4),
+            }));
+        }
     }
     if keyboard.pressed(KeyCode::B) {
         events.send(Action::MovePlayerMarker(
@@ -168,6 +196,26 @@ fn debug_keyboard(keyboard: Res<Input<KeyCode>>, mut events: ResMut<Events<Actio
     }
 }
 
+/// The top piece of one of the decks.
+struct MarketSlot(usize);
+
+/// Shows the pieces currently on top of the decks.
+fn update_market(
+    passtally_game: ChangedRes<PasstallyGame>,
+    mut query: Query<(&MarketSlot, &mut TextureAtlasSprite, &mut Visible)>,
+) {
+    let market = passtally_game.market();
+    for (slot, mut sprite, mut visible) in query.iter_mut() {
+        match market[slot.0] {
+            Some(piece) => {
+                sprite.index = piece.index();
+                visible.is_visible = true;
+            }
+            None => visible.is_visible = false,
+        }
+    }
+}
+
 struct PlayerMarker {
     pos: u8,
     player: u8,
@@ -175,17 +223,27 @@ struct PlayerMarker {
 
 impl PlayerMarker {
     fn world_pos(&self) -> Vec3 {
-        let pos = match self.pos {
-            0..=5 => Vec2::new(self.pos as f32, 0.0) * 16.0 + Vec2::new(0.0, -13.0),
-            6..=11 => Vec2::new(5.0, (self.pos % 6) as f32) * 16.0 + Vec2::new(13.0, 0.0),
-            12..=17 => Vec2::new((5 - (self.pos % 6)) as f32, 5.0) * 16.0 + Vec2::new(0.0, 13.0),
-            18..=23 => Vec2::new(0.0, (5 - (self.pos % 6)) as f32) * 16.0 + Vec2::new(-13.0, 0.0),
-            _ => unreachable!(),
-        };
-        (BOARD_BOTTOM_LEFT + pos).extend(0.0)
+        slot_world_pos(self.pos).extend(0.0)
     }
 }
 
+/// Where the edge slot is drawn.
+fn slot_world_pos(slot: u8) -> Vec2 {
+    let pos = match slot {
+        0..=5 => Vec2::new(slot as f32, 0.0) * 16.0 + Vec2::new(0.0, -13.0),
+        6..=11 => Vec2::new(5.0, (slot % 6) as f32) * 16.0 + Vec2::new(13.0, 0.0),
+        12..=17 => Vec2::new((5 - (slot % 6)) as f32, 5.0) * 16.0 + Vec2::new(0.0, 13.0),
+        18..=23 => Vec2::new(0.0, (5 - (slot % 6)) as f32) * 16.0 + Vec2::new(-13.0, 0.0),
+        _ => unreachable!(),
+    };
+    BOARD_BOTTOM_LEFT + pos
+}
+
+/// The centre of the square.
+fn square_world_pos(pos: BoardPosition) -> Vec2 {
+    BOARD_BOTTOM_LEFT + Vec2::new(pos.x as f32, pos.y as f32) * 16.0
+}
+
 fn process_passtally_move(
     commands: &mut Commands,
     events: Res<Events<Action>>,
@@ -193,11 +251,13 @@ fn process_passtally_move(
     mut passtally_game: ResMut<PasstallyGame>,
     texture_atlases: Res<Assets<TextureAtlas>>,
     mut player_marker_query: Query<(Entity, &mut PlayerMarker, &Transform)>,
+    mut notifications: ResMut<Events<Notification>>,
+    autosave: Res<Autosave>,
 ) {
     for action in reader.iter(&events) {
-        trace!("Handling {:?}", action);
+        trace!("Handling {}", action);
         match passtally_game.do_action(action.clone()) {
-            Err(e) => trace!("{}", e),
+            Err(e) => notifications.send(Notification::IllegalMove(e.to_string())),
             Ok(_) => {
                 // Add
                 match action {
@@ -241,6 +301,21 @@ fn process_passtally_move(
                             }
                         }
                     }
+                    Action::RedrawMarket(deck) => debug!("Discarded the top of deck {}", deck),
+                    Action::OfferDraw => notifications.send(Notification::DrawOffered(format!(
+                        "Player {}",
+                        passtally_game.next_player()
+                    ))),
+                    Action::Resign | Action::AcceptDraw => {
+                        info!("{} ({:?})", action, passtally_game.phase())
+                    }
+                }
+
+                if let Some(dirs) = &autosave.0 {
+                    match dirs.autosave(&passtally_game) {
+                        Ok(()) => notifications.send(Notification::AutosaveCompleted),
+                        Err(e) => warn!("Couldn't save the game: {}", e),
+                    }
                 }
             }
         }
@@ -264,7 +339,9 @@ fn selection_system(
     windows: Res<Windows>,
     // query to get camera components
     camera_query: Query<&Transform>,
-    query: Query<(&Clickable, &Transform)>,
+    query: Query<(&Clickable, &Transform, Option<&MarketSlot>)>,
+    passtally_game: Res<PasstallyGame>,
+    mut events: ResMut<Events<Action>>,
 ) {
     if mouse.just_pressed(MouseButton::Left) {
         let window = windows.get_primary().unwrap();
@@ -282,7 +359,7 @@ fn selection_system(
             let world_position = world_position.truncate().truncate();
             debug!("World coords: {}/{}", world_position.x, world_position.y);
 
-            for (clickable, transform) in query.iter() {
+            for (clickable, transform, market_slot) in query.iter() {
                 let click_pos = transform.translation.truncate();
                 let bounding_box = clickable.bounding_box;
                 let left = click_pos.x - bounding_box.width / 2.0;
@@ -296,6 +373,13 @@ fn selection_system(
                     && world_position.y < top
                 {
                     info!("Clicked!!");
+                    // With redraws allowed, clicking a market piece discards it.
+                    if let Some(slot) = market_slot {
+                        let redraw = passtally_game.rules().allow_redraw;
+                        if redraw && passtally_game.market()[slot.0].is_some() {
+                            events.send(Action::RedrawMarket(slot.0 as u8));
+                        }
+                    }
                 }
             }
         }
@@ -0,0 +1,381 @@
+//! Legal action and turn generation without allocating. The generators copy
+//! what the actions depend on, the board, the markers and the tops of the
+//! decks, into a small position of their own and walk through the candidate
+//! actions one at a time. The second action of each turn is found on a copy
+//! of that position rather than a clone of the whole game.
+//!
+//! Actions come in the same order as they always have: placements by piece,
+//! square and rotation, then marker moves, then redraws.
+
+use crate::board::{Board, BoardPosition};
+use crate::game::{Action, Deck, Game, Turn};
+use crate::piece::{Piece, PositionedPiece};
+
+/// What the actions of the player to move depend on.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    board: Board,
+    player_markers: [Option<u8>; 24],
+    player: u8,
+    /// The top two pieces of each deck and how many pieces it has.
+    decks: [(Option<Piece>, Option<Piece>, usize); 3],
+    allow_redraw: bool,
+}
+
+impl Position {
+    fn of(game: &Game) -> Position {
+        let deck = |pieces: &Deck| {
+            let mut top = pieces.iter().rev();
+            (top.next().copied(), top.next().copied(), pieces.len())
+        };
+        Position {
+            board: game.board,
+            player_markers: game.player_markers,
+            player: game.next_player(),
+            decks: [
+                deck(&game.decks[0]),
+                deck(&game.decks[1]),
+                deck(&game.decks[2]),
+            ],
+            allow_redraw: game.rules.allow_redraw,
+        }
+    }
+
+    /// The different pieces in the market, by index, the gaps at the end.
+    fn market(&self) -> [Option<Piece>; 3] {
+        let mut market = [self.decks[0].0, self.decks[1].0, self.decks[2].0];
+        market.sort_by_key(|piece| piece.map_or(u32::MAX, |piece| piece.index()));
+        for i in (1..3).rev() {
+            if market[i].is_some() && market[..i].contains(&market[i]) {
+                market[i..].rotate_left(1);
+                market[2] = None;
+            }
+        }
+        market
+    }
+
+    /// The squares and rotations pieces can be placed with, a bit each at
+    /// `square * 4 + rotation` with the squares row by row. The same for
+    /// every piece, and for both ways round of placing on a pair of squares.
+    fn placeable(&self) -> [u64; 3] {
+        // To the right and back, and down and back up.
+        let (right, down) = self.board.placeable_pairs();
+        let rotations = [right, down, right << 1, down << 6];
+        // Sixteen squares of four rotations fill a word.
+        let mut placeable = [0; 3];
+        for (word, bits) in placeable.iter_mut().enumerate() {
+            for (rotation, squares) in rotations.iter().enumerate() {
+                *bits |= spread((squares >> (16 * word)) & 0xffff) << rotation;
+            }
+        }
+        placeable
+    }
+
+    /// The slots the marker can move to, a bit each: empty ones with at most
+    /// one empty slot between them and the marker, either way round.
+    fn reachable(&self, from: u8) -> u32 {
+        if self.player_markers[from as usize] != Some(self.player) {
+            return 0;
+        }
+        let mut reachable = 0;
+        for &step in &[1, 23] {
+            let mut passed = 0;
+            let mut slot = (from + step) % 24;
+            while slot != from && passed < 2 {
+                if self.player_markers[slot as usize].is_none() {
+                    reachable |= 1 << slot;
+                    passed += 1;
+                }
+                slot = (slot + step) % 24;
+            }
+        }
+        reachable
+    }
+
+    /// Takes the action, which has to be legal.
+    fn apply(&mut self, action: &Action) {
+        let drawn = match action {
+            Action::PlacePiece(piece) => {
+                (0..3).find(|&deck| self.decks[deck].0 == Some(piece.piece))
+            }
+            Action::RedrawMarket(deck) => Some(*deck as usize),
+            _ => None,
+        };
+        if let Some(deck) = drawn {
+            let (_, below, len) = self.decks[deck];
+            self.decks[deck] = (below, None, len - 1);
+        }
+        match action {
+            Action::PlacePiece(piece) => self
+                .board
+                .place_piece(piece.clone())
+                .expect("Legal placements should be placeable"),
+            Action::MovePlayerMarker(from, to) => {
+                self.player_markers[*to as usize] = self.player_markers[*from as usize].take();
+            }
+            Action::RedrawMarket(_) | Action::Resign | Action::OfferDraw | Action::AcceptDraw => {}
+        }
+    }
+}
+
+/// Moves the low 16 bits apart to every fourth bit.
+fn spread(bits: u64) -> u64 {
+    let bits = (bits | bits << 24) & 0x0000_00ff_0000_00ff;
+    let bits = (bits | bits << 12) & 0x000f_000f_000f_000f;
+    let bits = (bits | bits << 6) & 0x0303_0303_0303_0303;
+    (bits | bits << 3) & 0x1111_1111_1111_1111
+}
+
+/// The legal actions of the player to move, see
+/// [`Game::legal_actions_iter`].
+#[derive(Debug, Clone)]
+pub struct LegalActions {
+    position: Position,
+    market: [Option<Piece>; 3],
+    placeable: [u64; 3],
+    /// Pieces of the market whose placements have been looked at, the last
+    /// of them still to be placed as `remaining` says.
+    pieces: usize,
+    remaining: [u64; 3],
+    /// Markers whose moves have been looked at, the last of them still
+    /// able to reach the slots left in `reachable`.
+    markers: u8,
+    reachable: u32,
+    /// Decks whose redraw has been looked at.
+    redraws: u8,
+}
+
+impl LegalActions {
+    pub(crate) fn new(game: &Game) -> LegalActions {
+        LegalActions::from_position(Position::of(game))
+    }
+
+    fn from_position(position: Position) -> LegalActions {
+        LegalActions {
+            market: position.market(),
+            placeable: position.placeable(),
+            position,
+            pieces: 0,
+            remaining: [0; 3],
+            markers: 0,
+            reachable: 0,
+            redraws: 0,
+        }
+    }
+
+    /// How many actions are left, counted without finding them.
+    fn left(&self) -> usize {
+        let bits = |words: &[u64; 3]| words.iter().map(|bits| bits.count_ones()).sum::<u32>();
+        let pieces = self.market[self.pieces.min(3)..].iter().flatten().count() as u32;
+        let placements = bits(&self.remaining) + pieces * bits(&self.placeable);
+        let moves = self.reachable.count_ones()
+            + (self.markers..24)
+                .map(|from| self.position.reachable(from).count_ones())
+                .sum::<u32>();
+        let redraws = (self.redraws..3)
+            .filter(|&deck| self.position.allow_redraw && self.position.decks[deck as usize].2 > 0)
+            .count();
+        (placements + moves) as usize + redraws
+    }
+
+    /// Finds nothing more.
+    fn finish(&mut self) {
+        self.pieces = 3;
+        self.remaining = [0; 3];
+        self.markers = 24;
+        self.reachable = 0;
+        self.redraws = 3;
+    }
+}
+
+impl Iterator for LegalActions {
+    type Item = Action;
+
+    fn next(&mut self) -> Option<Action> {
+        loop {
+            if let Some(word) = self.remaining.iter().position(|&bits| bits != 0) {
+                let bits = &mut self.remaining[word];
+                let bit = word * 64 + bits.trailing_zeros() as usize;
+                *bits &= *bits - 1;
+                let square = bit / 4;
+                return Some(Action::PlacePiece(PositionedPiece {
+                    piece: self.market[self.pieces - 1].unwrap(),
+                    rotation: (bit % 4) as u8,
+                    position: BoardPosition::new((square % 6) as i8, (square / 6) as i8),
+                }));
+            }
+            if self.pieces == 3 || self.market[self.pieces].is_none() {
+                self.pieces = 3;
+                break;
+            }
+            self.remaining = self.placeable;
+            self.pieces += 1;
+        }
+        loop {
+            if self.reachable != 0 {
+                let to = self.reachable.trailing_zeros() as u8;
+                self.reachable &= self.reachable - 1;
+                return Some(Action::MovePlayerMarker(self.markers - 1, to));
+            }
+            if self.markers == 24 {
+                break;
+            }
+            self.reachable = self.position.reachable(self.markers);
+            self.markers += 1;
+        }
+        while self.redraws < 3 {
+            let deck = self.redraws;
+            self.redraws += 1;
+            if self.position.allow_redraw && self.position.decks[deck as usize].2 > 0 {
+                return Some(Action::RedrawMarket(deck));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.left();
+        (left, Some(left))
+    }
+
+    fn count(self) -> usize {
+        self.left()
+    }
+}
+
+impl ExactSizeIterator for LegalActions {}
+
+/// The legal turns of the player to move, see [`Game::legal_turns_iter`].
+#[derive(Debug, Clone)]
+pub struct LegalTurns {
+    first: LegalActions,
+    /// The first action of the turns being found, and the second actions
+    /// that can follow it.
+    current: Option<(Action, LegalActions)>,
+}
+
+impl LegalTurns {
+    pub(crate) fn new(game: &Game) -> LegalTurns {
+        let mut first = LegalActions::new(game);
+        if game.is_finished() {
+            first.finish();
+        }
+        LegalTurns {
+            first,
+            current: None,
+        }
+    }
+}
+
+impl LegalTurns {
+    /// Moves on to the turns starting with the next first action, if there
+    /// is one.
+    fn advance(&mut self) -> Option<()> {
+        let first = self.first.next()?;
+        let mut after = self.first.position;
+        after.apply(&first);
+        self.current = Some((first, LegalActions::from_position(after)));
+        Some(())
+    }
+}
+
+impl Iterator for LegalTurns {
+    type Item = Turn;
+
+    fn next(&mut self) -> Option<Turn> {
+        loop {
+            if let Some((first, seconds)) = &mut self.current {
+                if let Some(second) = seconds.next() {
+                    return Some(Turn(first.clone(), second));
+                }
+            }
+            self.advance()?;
+        }
+    }
+
+    /// Skips whole first actions, counting their second actions rather than
+    /// finding them, for picking a turn at random without finding them all.
+    fn nth(&mut self, mut n: usize) -> Option<Turn> {
+        loop {
+            if let Some((first, seconds)) = &mut self.current {
+                let left = seconds.len();
+                if n < left {
+                    return Some(Turn(first.clone(), seconds.nth(n)?));
+                }
+                n -= left;
+            }
+            self.advance()?;
+        }
+    }
+
+    fn count(mut self) -> usize {
+        let mut count = 0;
+        loop {
+            if let Some((_, seconds)) = &self.current {
+                count += seconds.len();
+            }
+            if self.advance().is_none() {
+                return count;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    /// Every action `do_action` takes, in the order they are generated.
+    fn playable(game: &Game) -> Vec<Action> {
+        let mut pieces: Vec<Piece> = game.market().iter().flatten().copied().collect();
+        pieces.sort_by_key(Piece::index);
+        pieces.dedup();
+        let placements = pieces.into_iter().flat_map(|piece| {
+            BoardPosition::all().flat_map(move |position| {
+                (0..4).map(move |rotation| {
+                    Action::PlacePiece(PositionedPiece {
+                        piece,
+                        rotation,
+                        position,
+                    })
+                })
+            })
+        });
+        let moves =
+            (0..24).flat_map(|from| (0..24).map(move |to| Action::MovePlayerMarker(from, to)));
+        placements
+            .chain(moves)
+            .chain((0..3).map(Action::RedrawMarket))
+            .filter(|action| game.clone().do_action(action.clone()).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_playable_action() {
+        let rules = Ruleset {
+            allow_redraw: true,
+            ..Ruleset::new(3)
+        };
+        let mut game = Game::new_with_rules(rules, 5);
+        for _ in 0..3 {
+            assert_eq!(game.legal_actions(), playable(&game));
+
+            let mut turns = Vec::new();
+            for first in playable(&game) {
+                let mut after = game.clone();
+                after.do_action(first.clone()).unwrap();
+                for second in playable(&after) {
+                    turns.push(Turn(first.clone(), second));
+                }
+            }
+            assert_eq!(game.legal_turns(), turns);
+            assert_eq!(game.legal_actions_iter().len(), game.legal_actions().len());
+            assert_eq!(game.legal_turns_iter().count(), turns.len());
+            for &n in &[0, turns.len() / 2, turns.len() - 1, turns.len()] {
+                assert_eq!(game.legal_turns_iter().nth(n).as_ref(), turns.get(n));
+            }
+            game.play_turn(turns[turns.len() / 3].clone()).unwrap();
+        }
+    }
+}
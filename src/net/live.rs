@@ -0,0 +1,625 @@
+//! Games played live over WebSockets at `/live`. Clients send a
+//! [`LiveClientMessage::Join`] saying how many players they want a game
+//! for, and are seated in the next game of that size. Once every seat is
+//! taken the game starts, and every turn played is checked by the server
+//! and its outcome broadcast to everyone in the game. Players are sent the
+//! game as they see it, without the order of the pieces down the decks.
+//!
+//! Players who lose their connection to a started game can take their seat
+//! back with a [`LiveClientMessage::Rejoin`], giving the token they were
+//! sent when they joined.
+//!
+//! Messages are JSON, tagged with their `type` like those of
+//! [`protocol`](super::protocol). Turns are [`Turn`]s as serialized with
+//! serde.
+//!
+//! Finished games are recorded in the lobby's [`ReplayStore`](super::ReplayStore),
+//! if it has one.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use super::replays::{self, SharedStore};
+use crate::game::{Game, Turn, TurnOutcome};
+use crate::information::InformationSet;
+use crate::replay::Replay;
+use crate::rules::Ruleset;
+
+/// Messages queued for a client that's slow to read before it starts
+/// missing them.
+const EVENT_BUFFER: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveClientMessage {
+    /// Takes a seat in the next game for this many players.
+    Join {
+        name: String,
+        players: u8,
+    },
+    /// Takes back the seat a player lost their connection to.
+    Rejoin {
+        token: u64,
+    },
+    PlayTurn {
+        turn: Turn,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveServerMessage {
+    /// To the client joining, the seat it plays from and the token to
+    /// rejoin with.
+    Joined { seat: u8, token: u64 },
+    /// To everyone, once every seat is taken, the game as they see it. To a
+    /// client rejoining, the game as it stands.
+    Started {
+        names: Vec<String>,
+        game: Box<InformationSet>,
+    },
+    /// To everyone, after the server has played the turn.
+    TurnPlayed {
+        seat: u8,
+        turn: Turn,
+        outcome: TurnOutcome,
+    },
+    /// To everyone, when a player disconnects from a started game.
+    PlayerLeft { seat: u8 },
+    /// To everyone, when a player who left takes their seat back.
+    PlayerReconnected { seat: u8 },
+    /// To the client whose message couldn't be acted on.
+    Error { message: String },
+}
+
+/// One game and who is sitting where.
+#[derive(Debug, Clone)]
+pub struct Room {
+    names: Vec<Option<String>>,
+    connected: Vec<bool>,
+    game: Option<(Game, Replay)>,
+}
+
+impl Room {
+    pub fn new(players: u8) -> Self {
+        Room {
+            names: vec![None; players as usize],
+            connected: vec![false; players as usize],
+            game: None,
+        }
+    }
+
+    /// Seats the player, starting the game with the seed if that was the
+    /// last seat.
+    pub fn join(&mut self, name: String, seed: u64) -> Result<u8, LiveError> {
+        if self.game.is_some() {
+            return Err(LiveError::Full);
+        }
+        let seat = self
+            .names
+            .iter()
+            .position(Option::is_none)
+            .ok_or(LiveError::Full)?;
+        self.names[seat] = Some(name);
+        self.connected[seat] = true;
+        if self.names.iter().all(Option::is_some) {
+            let rules = Ruleset::new(self.names.len() as u8);
+            let replay = Replay::new(self.names(), rules, seed);
+            self.game = Some((replay.initial_game(), replay));
+        }
+        Ok(seat as u8)
+    }
+
+    /// Frees the seat if the game hasn't started yet, or keeps it for the
+    /// player to rejoin if it has.
+    pub fn leave(&mut self, seat: u8) {
+        if self.game.is_none() {
+            self.names[seat as usize] = None;
+        }
+        self.connected[seat as usize] = false;
+    }
+
+    /// Gives a player who left a started game their seat back.
+    pub fn rejoin(&mut self, seat: u8) -> Result<(), LiveError> {
+        if self.game.is_none() {
+            return Err(LiveError::NotStarted);
+        }
+        let connected = self
+            .connected
+            .get_mut(seat as usize)
+            .ok_or(LiveError::UnknownSeat)?;
+        if *connected {
+            return Err(LiveError::SeatTaken);
+        }
+        *connected = true;
+        Ok(())
+    }
+
+    /// Whether anyone is still playing from the room.
+    pub fn is_empty(&self) -> bool {
+        !self.connected.contains(&true)
+    }
+
+    /// The names of the seated players, in seat order.
+    pub fn names(&self) -> Vec<String> {
+        self.names.iter().flatten().cloned().collect()
+    }
+
+    /// The game, once every seat is taken.
+    pub fn game(&self) -> Option<&Game> {
+        self.game.as_ref().map(|(game, _)| game)
+    }
+
+    /// The turns played so far, once every seat is taken.
+    pub fn replay(&self) -> Option<&Replay> {
+        self.game.as_ref().map(|(_, replay)| replay)
+    }
+
+    /// Plays the turn for the player in the seat, if it's theirs to play.
+    pub fn play_turn(&mut self, seat: u8, turn: Turn) -> Result<TurnOutcome, LiveError> {
+        let (game, replay) = self.game.as_mut().ok_or(LiveError::NotStarted)?;
+        if game.next_player() != seat {
+            return Err(LiveError::NotYourTurn);
+        }
+        replay
+            .play(game, turn)
+            .map_err(|err| LiveError::IllegalTurn(err.to_string()))
+    }
+}
+
+/// What happened in a room, for each connection to tell its player about.
+#[derive(Debug, Clone)]
+enum Event {
+    Started { names: Vec<String>, game: Box<Game> },
+    Message(LiveServerMessage),
+}
+
+impl Event {
+    /// The message for the player in the seat, who only gets to see their
+    /// own [`InformationSet`].
+    fn for_seat(self, seat: u8) -> LiveServerMessage {
+        match self {
+            Event::Started { names, game } => LiveServerMessage::Started {
+                names,
+                game: Box::new(game.information_set(seat)),
+            },
+            Event::Message(message) => message,
+        }
+    }
+}
+
+/// A room and the channel its players hear about it on.
+#[derive(Debug)]
+struct LiveRoom {
+    room: Mutex<Room>,
+    events: broadcast::Sender<Event>,
+}
+
+/// A player's seat in a room, and what they need to hear first.
+#[derive(Debug)]
+struct Seated {
+    live: Arc<LiveRoom>,
+    seat: u8,
+    token: u64,
+    events: broadcast::Receiver<Event>,
+    /// For a player rejoining, the game as it stands.
+    catch_up: Option<Event>,
+}
+
+/// The games waiting for players, one for each size, and the seats players
+/// can rejoin, by their token.
+#[derive(Debug, Default)]
+pub struct Lobby {
+    waiting: Mutex<BTreeMap<u8, Arc<LiveRoom>>>,
+    seats: Mutex<BTreeMap<u64, (Arc<LiveRoom>, u8)>>,
+    store: Option<SharedStore>,
+}
+
+/// The lobby, shared between connections.
+pub type SharedLobby = Arc<Lobby>;
+
+impl Lobby {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A lobby whose games are recorded in the store once finished.
+    pub fn with_store(store: SharedStore) -> Self {
+        Lobby {
+            store: Some(store),
+            ..Self::default()
+        }
+    }
+
+    /// Records the room's game if it's finished.
+    fn record(&self, room: &Room) -> Result<(), LiveError> {
+        let (game, replay) = match &room.game {
+            Some(played) => played,
+            None => return Ok(()),
+        };
+        match &self.store {
+            Some(store) if game.is_finished() => replays::record(store, replay.clone())
+                .map(|_| ())
+                .map_err(|err| LiveError::NotStored(err.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn join(&self, name: String, players: u8) -> Result<Seated, LiveError> {
+        if !(2..=4).contains(&players) {
+            return Err(LiveError::BadPlayers(players));
+        }
+        let mut waiting = lock(&self.waiting);
+        let live = waiting
+            .entry(players)
+            .or_insert_with(|| {
+                Arc::new(LiveRoom {
+                    room: Mutex::new(Room::new(players)),
+                    events: broadcast::channel(EVENT_BUFFER).0,
+                })
+            })
+            .clone();
+        let events = live.events.subscribe();
+        let mut room = lock(&live.room);
+        let seat = room.join(name, rand::random())?;
+        if let Some(game) = room.game() {
+            waiting.remove(&players);
+            let _ = live.events.send(Event::Started {
+                names: room.names(),
+                game: Box::new(game.clone()),
+            });
+        }
+        drop(room);
+        drop(waiting);
+        let token = rand::random();
+        lock(&self.seats).insert(token, (live.clone(), seat));
+        Ok(Seated {
+            live,
+            seat,
+            token,
+            events,
+            catch_up: None,
+        })
+    }
+
+    fn rejoin(&self, token: u64) -> Result<Seated, LiveError> {
+        let (live, seat) = lock(&self.seats)
+            .get(&token)
+            .cloned()
+            .ok_or(LiveError::UnknownSeat)?;
+        let mut room = lock(&live.room);
+        room.rejoin(seat)?;
+        let _ = live
+            .events
+            .send(Event::Message(LiveServerMessage::PlayerReconnected {
+                seat,
+            }));
+        let events = live.events.subscribe();
+        let catch_up = room.game().map(|game| Event::Started {
+            names: room.names(),
+            game: Box::new(game.clone()),
+        });
+        drop(room);
+        Ok(Seated {
+            live,
+            seat,
+            token,
+            events,
+            catch_up,
+        })
+    }
+
+    /// Takes the player out of their seat. Their token is forgotten if the
+    /// seat is freed, or once nobody is left in the game to come back to.
+    fn leave(&self, live: &Arc<LiveRoom>, seat: u8) {
+        let mut room = lock(&live.room);
+        room.leave(seat);
+        let started = room.game().is_some();
+        if started {
+            let _ = live
+                .events
+                .send(Event::Message(LiveServerMessage::PlayerLeft { seat }));
+        }
+        let empty = room.is_empty();
+        drop(room);
+        let forget = |other_seat: u8| if started { empty } else { other_seat == seat };
+        lock(&self.seats)
+            .retain(|_, (other, other_seat)| !(Arc::ptr_eq(other, live) && forget(*other_seat)));
+    }
+}
+
+/// Locks the lobby or a room. A connection that panicked can't have left a
+/// game half played, as turns are played whole or not at all, so they are
+/// used even if poisoned.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The `/live` route, on the lobby.
+pub fn router(lobby: SharedLobby) -> Router {
+    Router::new().route("/live", get(connect)).with_state(lobby)
+}
+
+async fn connect(State(lobby): State<SharedLobby>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| play(socket, lobby))
+}
+
+async fn play(socket: WebSocket, lobby: SharedLobby) {
+    let (mut sender, mut receiver) = socket.split();
+
+    let Seated {
+        live,
+        seat,
+        token,
+        mut events,
+        catch_up,
+    } = loop {
+        let seated = match next_message(&mut receiver).await {
+            None => return,
+            Some(Ok(LiveClientMessage::Join { name, players })) => lobby.join(name, players),
+            Some(Ok(LiveClientMessage::Rejoin { token })) => lobby.rejoin(token),
+            Some(Ok(LiveClientMessage::PlayTurn { .. })) => Err(LiveError::NotJoined),
+            Some(Err(err)) => Err(err),
+        };
+        let error = match seated {
+            Ok(seated) => break seated,
+            Err(err) => err,
+        };
+        if send(&mut sender, &error.into()).await.is_err() {
+            return;
+        }
+    };
+
+    let started = catch_up.map(|event| event.for_seat(seat));
+    let greeted = send(&mut sender, &LiveServerMessage::Joined { seat, token })
+        .await
+        .is_ok()
+        && match &started {
+            Some(started) => send(&mut sender, started).await.is_ok(),
+            None => true,
+        };
+    if greeted {
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        if send(&mut sender, &event.for_seat(seat)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = next_message(&mut receiver) => {
+                    let result = match message {
+                        None => break,
+                        Some(Ok(LiveClientMessage::PlayTurn { turn })) => {
+                            let mut room = lock(&live.room);
+                            room.play_turn(seat, turn.clone()).and_then(|outcome| {
+                                let _ = live.events.send(Event::Message(
+                                    LiveServerMessage::TurnPlayed {
+                                        seat,
+                                        turn,
+                                        outcome,
+                                    },
+                                ));
+                                lobby.record(&room)
+                            })
+                        }
+                        Some(Ok(LiveClientMessage::Join { .. }))
+                        | Some(Ok(LiveClientMessage::Rejoin { .. })) => Err(LiveError::AlreadyJoined),
+                        Some(Err(err)) => Err(err),
+                    };
+                    if let Err(err) = result {
+                        if send(&mut sender, &err.into()).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    lobby.leave(&live, seat);
+}
+
+async fn send(
+    sender: &mut SplitSink<WebSocket, Message>,
+    message: &LiveServerMessage,
+) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).expect("Messages should serialize");
+    sender.send(Message::Text(text.into())).await
+}
+
+/// The next message from the client, skipping pings, or nothing once it
+/// has gone.
+async fn next_message(
+    receiver: &mut SplitStream<WebSocket>,
+) -> Option<Result<LiveClientMessage, LiveError>> {
+    loop {
+        match receiver.next().await? {
+            Ok(Message::Text(text)) => {
+                return Some(
+                    serde_json::from_str(&text)
+                        .map_err(|err| LiveError::BadMessage(err.to_string())),
+                )
+            }
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LiveError {
+    #[error("The message can't be read: {0}")]
+    BadMessage(String),
+    #[error("Live games are for 2 to 4 players, not {0}.")]
+    BadPlayers(u8),
+    #[error("Every seat in the game is taken.")]
+    Full,
+    #[error("Join a game before playing.")]
+    NotJoined,
+    #[error("You are already in a game.")]
+    AlreadyJoined,
+    #[error("There is no seat to rejoin with that token.")]
+    UnknownSeat,
+    #[error("Someone is already playing from that seat.")]
+    SeatTaken,
+    #[error("The game starts once every seat is taken.")]
+    NotStarted,
+    #[error("It's not your turn.")]
+    NotYourTurn,
+    #[error("The turn can't be played: {0}")]
+    IllegalTurn(String),
+    #[error("The game is over but couldn't be stored: {0}")]
+    NotStored(String),
+}
+
+impl From<LiveError> for LiveServerMessage {
+    fn from(err: LiveError) -> Self {
+        LiveServerMessage::Error {
+            message: err.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{ReplayStore, RetentionPolicy};
+
+    #[test]
+    fn rooms_seat_players_and_check_turns() {
+        let mut room = Room::new(2);
+        assert_eq!(room.join("Alice".into(), 3).unwrap(), 0);
+        room.leave(0);
+        assert_eq!(room.join("Carol".into(), 3).unwrap(), 0);
+        assert!(room.game().is_none());
+        assert_eq!(room.join("Bob".into(), 3).unwrap(), 1);
+        assert!(room.game().is_some());
+        room.leave(1);
+        assert!(matches!(room.join("Dave".into(), 3), Err(LiveError::Full)));
+        assert_eq!(room.names(), ["Carol", "Bob"]);
+        assert!(matches!(room.rejoin(0), Err(LiveError::SeatTaken)));
+        room.rejoin(1).unwrap();
+        assert!(matches!(room.rejoin(1), Err(LiveError::SeatTaken)));
+
+        let turn = room.game().unwrap().legal_turns_iter().next().unwrap();
+        assert!(matches!(
+            room.play_turn(1, turn.clone()),
+            Err(LiveError::NotYourTurn)
+        ));
+        assert_eq!(room.play_turn(0, turn.clone()).unwrap().player, 0);
+        assert!(matches!(
+            room.play_turn(1, turn),
+            Err(LiveError::IllegalTurn(_))
+        ));
+    }
+
+    #[test]
+    fn players_rejoin_with_their_token() {
+        let lobby = Lobby::new();
+        let alice = lobby.join("Alice".into(), 2).unwrap();
+        lobby.leave(&alice.live, alice.seat);
+        assert!(matches!(
+            lobby.rejoin(alice.token),
+            Err(LiveError::UnknownSeat)
+        ));
+
+        let carol = lobby.join("Carol".into(), 2).unwrap();
+        let mut bob = lobby.join("Bob".into(), 2).unwrap();
+        assert!(matches!(bob.events.try_recv(), Ok(Event::Started { .. })));
+        lobby.leave(&bob.live, bob.seat);
+        assert!(matches!(
+            lobby.rejoin(carol.token),
+            Err(LiveError::SeatTaken)
+        ));
+        let rejoined = lobby.rejoin(bob.token).unwrap();
+        assert_eq!(rejoined.seat, 1);
+        match rejoined.catch_up.unwrap().for_seat(1) {
+            LiveServerMessage::Started { names, .. } => assert_eq!(names, ["Carol", "Bob"]),
+            message => panic!("Unexpected message {:?}", message),
+        }
+        let mut events = carol.live.events.subscribe();
+        lobby.leave(&carol.live, carol.seat);
+        lobby.leave(&rejoined.live, rejoined.seat);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(Event::Message(LiveServerMessage::PlayerLeft { seat: 0 }))
+        ));
+        assert!(matches!(
+            lobby.rejoin(bob.token),
+            Err(LiveError::UnknownSeat)
+        ));
+    }
+
+    #[test]
+    fn finished_games_are_stored() {
+        let store = SharedStore::new(Mutex::new(ReplayStore::in_memory(
+            RetentionPolicy::default(),
+        )));
+        let lobby = Lobby::with_store(store.clone());
+        let mut room = Room::new(2);
+        room.join("Alice".into(), 5).unwrap();
+        room.join("Bob".into(), 5).unwrap();
+        while !room.game().unwrap().is_finished() {
+            lobby.record(&room).unwrap();
+            assert!(store.lock().unwrap().is_empty());
+            let game = room.game().unwrap();
+            let turn = game.legal_turns_iter().next().unwrap();
+            room.play_turn(game.next_player(), turn).unwrap();
+        }
+        lobby.record(&room).unwrap();
+        let store = store.lock().unwrap();
+        assert_eq!(store.get(1), room.replay());
+        assert_eq!(store.get(1).unwrap().players, ["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn messages_are_tagged_json() {
+        let message: LiveClientMessage =
+            serde_json::from_str(r#"{"type": "join", "name": "Alice", "players": 2}"#).unwrap();
+        assert_eq!(
+            message,
+            LiveClientMessage::Join {
+                name: "Alice".into(),
+                players: 2
+            }
+        );
+        let message =
+            serde_json::to_value(LiveServerMessage::Joined { seat: 1, token: 7 }).unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({"type": "joined", "seat": 1, "token": 7})
+        );
+    }
+
+    #[test]
+    fn players_only_see_their_information_set() {
+        let game = Game::new_with_rules(Ruleset::new(2), 8);
+        let started = Event::Started {
+            names: vec!["Alice".into(), "Bob".into()],
+            game: Box::new(game.clone()),
+        };
+        match started.for_seat(1) {
+            LiveServerMessage::Started { game: seen, .. } => {
+                assert_eq!(*seen, game.information_set(1));
+                let json = serde_json::to_value(seen).unwrap();
+                assert!(json.get("decks").is_none());
+            }
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+}
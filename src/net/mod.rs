@@ -0,0 +1,13 @@
+//! Networked play.
+
+#[cfg(feature = "server")]
+pub mod live;
+pub mod protocol;
+#[cfg(feature = "server")]
+pub mod replays;
+#[cfg(feature = "server")]
+pub mod rest;
+pub mod store;
+
+pub use protocol::{ClientMessage, ServerMessage};
+pub use store::{ReplayStore, ReplaySummary, RetentionPolicy, StoreError};
@@ -0,0 +1,174 @@
+//! Messages between clients and the server, sent as JSON, and the same requests
+//! as plain HTTP endpoints for clients that only want to browse replays:
+//!
+//! - `GET /replays?limit=N`: the `N` most recent games, newest first.
+//! - `GET /replays/<id>`: a whole replay.
+
+use serde::{Deserialize, Serialize};
+
+use super::store::{ReplayStore, ReplaySummary};
+use crate::capabilities::{self, BotInfo};
+use crate::replay::Replay;
+
+/// Games listed when a client doesn't say how many it wants.
+pub const DEFAULT_RECENT: usize = 20;
+/// The most games listed in one response.
+pub const MAX_RECENT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// What the server offers, see [`capabilities`](crate::capabilities).
+    Capabilities,
+    RecentGames {
+        limit: usize,
+    },
+    FetchReplay {
+        id: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Capabilities { bots: Vec<BotInfo>, analysis: bool },
+    RecentGames { games: Vec<ReplaySummary> },
+    Replay { id: u64, replay: Replay },
+    Error { message: String },
+}
+
+impl ReplayStore {
+    /// The server's answer to a client's request.
+    pub fn respond(&self, message: &ClientMessage) -> ServerMessage {
+        match *message {
+            ClientMessage::Capabilities => ServerMessage::Capabilities {
+                bots: capabilities::available_bots(),
+                analysis: capabilities::supports_analysis(),
+            },
+            ClientMessage::RecentGames { limit } => ServerMessage::RecentGames {
+                games: self.recent(limit.min(MAX_RECENT)),
+            },
+            ClientMessage::FetchReplay { id } => match self.get(id) {
+                Some(replay) => ServerMessage::Replay {
+                    id,
+                    replay: replay.clone(),
+                },
+                None => ServerMessage::Error {
+                    message: format!("There is no replay with id {}.", id),
+                },
+            },
+        }
+    }
+
+    /// Answers a `GET` request for one of the replay endpoints with a status
+    /// code and a JSON body.
+    pub fn http_get(&self, path: &str) -> (u16, String) {
+        let (status, response) = match parse_path(path) {
+            Some(message) => {
+                let response = self.respond(&message);
+                let status = match response {
+                    ServerMessage::Error { .. } => 404,
+                    _ => 200,
+                };
+                (status, response)
+            }
+            None => (
+                404,
+                ServerMessage::Error {
+                    message: format!("Unknown path {}.", path),
+                },
+            ),
+        };
+        let body = serde_json::to_string(&response).expect("Responses should serialize");
+        (status, body)
+    }
+}
+
+fn parse_path(path: &str) -> Option<ClientMessage> {
+    let (path, query) = match path.find('?') {
+        Some(i) => (&path[..i], Some(&path[i + 1..])),
+        None => (path, None),
+    };
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["replays"] => {
+            let limit = query
+                .into_iter()
+                .flat_map(|query| query.split('&'))
+                .find_map(|pair| pair.strip_prefix("limit="))
+                .map(|limit| limit.parse().ok())
+                .unwrap_or(Some(DEFAULT_RECENT))?;
+            Some(ClientMessage::RecentGames { limit })
+        }
+        ["replays", id] => Some(ClientMessage::FetchReplay {
+            id: id.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::store::RetentionPolicy;
+    use crate::rules::Ruleset;
+
+    fn store() -> ReplayStore {
+        let mut store = ReplayStore::in_memory(RetentionPolicy::default());
+        for seed in 0..3 {
+            let players = vec!["Alice".into(), "Bob".into()];
+            store
+                .insert(Replay::new(players, Ruleset::new(2), seed))
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn messages() {
+        let store = store();
+        let request: ClientMessage =
+            serde_json::from_str(r#"{"type": "recent_games", "limit": 2}"#).unwrap();
+        match store.respond(&request) {
+            ServerMessage::RecentGames { games } => {
+                assert_eq!(games.iter().map(|game| game.id).collect::<Vec<_>>(), [3, 2]);
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+
+        let response = store.respond(&ClientMessage::FetchReplay { id: 1 });
+        assert!(matches!(response, ServerMessage::Replay { id: 1, .. }));
+        let response = store.respond(&ClientMessage::FetchReplay { id: 9 });
+        assert!(matches!(response, ServerMessage::Error { .. }));
+
+        let request = serde_json::from_str(r#"{"type": "capabilities"}"#).unwrap();
+        let response = store.respond(&request);
+        assert!(
+            matches!(response, ServerMessage::Capabilities { analysis, .. } if analysis == cfg!(feature = "ai"))
+        );
+    }
+
+    #[test]
+    fn http_endpoints() {
+        let store = store();
+
+        let (status, body) = store.http_get("/replays?limit=1");
+        assert_eq!(status, 200);
+        let response: ServerMessage = serde_json::from_str(&body).unwrap();
+        assert!(matches!(response, ServerMessage::RecentGames { games } if games.len() == 1));
+
+        let (status, body) = store.http_get("/replays/2");
+        assert_eq!(status, 200);
+        let response: ServerMessage = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            response,
+            store.respond(&ClientMessage::FetchReplay { id: 2 })
+        );
+
+        assert_eq!(store.http_get("/replays").0, 200);
+        assert_eq!(store.http_get("/replays/9").0, 404);
+        assert_eq!(store.http_get("/replays/x").0, 404);
+        assert_eq!(store.http_get("/replays?limit=x").0, 404);
+        assert_eq!(store.http_get("/games").0, 404);
+    }
+}
@@ -0,0 +1,45 @@
+//! The replay endpoints of [`protocol`](super::protocol) as routes, on a
+//! [`ReplayStore`] the server also keeps its finished games in.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use axum::extract::State;
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+
+use super::store::{ReplayStore, StoreError};
+use crate::replay::Replay;
+
+/// The store, shared between requests and the games being played.
+pub type SharedStore = Arc<Mutex<ReplayStore>>;
+
+/// Stores a finished game and returns its id.
+pub fn record(store: &SharedStore, replay: Replay) -> Result<u64, StoreError> {
+    lock(store).insert(replay)
+}
+
+/// Locks the store. Replays are checked before they are added, so a request
+/// that panicked can't have left a bad one behind, and the store is used
+/// even if poisoned.
+fn lock(store: &SharedStore) -> MutexGuard<'_, ReplayStore> {
+    store.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// `GET /replays` and `GET /replays/{id}`, on the store.
+pub fn router(store: SharedStore) -> Router {
+    Router::new()
+        .route("/replays", get(serve))
+        .route("/replays/{id}", get(serve))
+        .with_state(store)
+}
+
+async fn serve(State(store): State<SharedStore>, uri: Uri) -> Response {
+    let path = uri
+        .path_and_query()
+        .map_or(uri.path(), |path| path.as_str());
+    let (status, body) = lock(&store).http_get(path);
+    let status = StatusCode::from_u16(status).expect("Replay endpoints answer with real statuses");
+    (status, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
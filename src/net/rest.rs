@@ -0,0 +1,273 @@
+//! Games as REST resources, for web pages and scripts that want to play
+//! without keeping a connection open:
+//!
+//! - `POST /games`, `{"players": 2, "seed": 7}`: starts a game, the seed is
+//!   random if left out. Answers `201` with its id and state.
+//! - `POST /games/{id}/turns`, `{"turn": "P:Pink@c3r1+M:4>7"}`: plays the
+//!   turn for the player to move. Answers with the [`TurnOutcome`] and the
+//!   new state.
+//! - `GET /games/{id}`: the state.
+//!
+//! Finished games are recorded in the table's [`ReplayStore`](super::ReplayStore),
+//! if it has one.
+//!
+//! States are games as serialized with serde. Errors are
+//! [`ServerMessage::Error`]s, with `400` for requests that can't be read,
+//! `404` for unknown games and `422` for illegal turns.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::replays::{self, SharedStore};
+use super::ServerMessage;
+use crate::game::{Game, Turn, TurnOutcome};
+use crate::replay::Replay;
+use crate::rules::Ruleset;
+
+/// The games being played, by id.
+#[derive(Debug, Default)]
+pub struct GameTable {
+    games: BTreeMap<u64, (Game, Replay)>,
+    next_id: u64,
+    store: Option<SharedStore>,
+}
+
+/// The games, shared between requests.
+pub type SharedGames = Arc<Mutex<GameTable>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewGame {
+    pub players: u8,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewTurn {
+    /// The turn in notation.
+    pub turn: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreatedGame {
+    pub id: u64,
+    pub game: Game,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayedTurn {
+    pub outcome: TurnOutcome,
+    pub game: Game,
+}
+
+impl GameTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table that records its finished games in the store.
+    pub fn with_store(store: SharedStore) -> Self {
+        GameTable {
+            store: Some(store),
+            ..Self::default()
+        }
+    }
+
+    /// Starts a game and returns its id.
+    pub fn create(&mut self, players: u8, seed: u64) -> Result<u64, ApiError> {
+        if !(1..=4).contains(&players) {
+            return Err(ApiError::BadPlayers(players));
+        }
+        self.next_id += 1;
+        let names = (1..=players)
+            .map(|seat| format!("Player {}", seat))
+            .collect();
+        let replay = Replay::new(names, Ruleset::new(players), seed);
+        let game = replay.initial_game();
+        self.games.insert(self.next_id, (game, replay));
+        Ok(self.next_id)
+    }
+
+    pub fn get(&self, id: u64) -> Result<&Game, ApiError> {
+        let (game, _) = self.games.get(&id).ok_or(ApiError::NoSuchGame(id))?;
+        Ok(game)
+    }
+
+    /// Plays the turn in the game, recording the game in the store if that
+    /// finished it.
+    pub fn play_turn(&mut self, id: u64, turn: Turn) -> Result<TurnOutcome, ApiError> {
+        let (game, replay) = self.games.get_mut(&id).ok_or(ApiError::NoSuchGame(id))?;
+        let outcome = replay
+            .play(game, turn)
+            .map_err(|err| ApiError::IllegalTurn(err.to_string()))?;
+        if let (true, Some(store)) = (game.is_finished(), &self.store) {
+            replays::record(store, replay.clone())
+                .map_err(|err| ApiError::NotStored(err.to_string()))?;
+        }
+        Ok(outcome)
+    }
+}
+
+/// The turn in notation.
+pub fn parse_turn(turn: &str) -> Result<Turn, ApiError> {
+    turn.parse()
+        .map_err(|err| ApiError::BadTurn(format!("{}", err)))
+}
+
+/// Locks the table. A request that panicked can't have left a game half
+/// played, as turns are played whole or not at all, so the table is used
+/// even if poisoned.
+fn lock(games: &SharedGames) -> MutexGuard<'_, GameTable> {
+    games.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The routes above, on the games.
+pub fn router(games: SharedGames) -> Router {
+    Router::new()
+        .route("/games", post(create_game))
+        .route("/games/{id}", get(get_game))
+        .route("/games/{id}/turns", post(play_turn))
+        .with_state(games)
+}
+
+async fn create_game(
+    State(games): State<SharedGames>,
+    Json(request): Json<NewGame>,
+) -> Result<(StatusCode, Json<CreatedGame>), ApiError> {
+    let seed = request.seed.unwrap_or_else(rand::random);
+    let mut games = lock(&games);
+    let id = games.create(request.players, seed)?;
+    let game = games.get(id)?.clone();
+    Ok((StatusCode::CREATED, Json(CreatedGame { id, game })))
+}
+
+async fn get_game(
+    State(games): State<SharedGames>,
+    Path(id): Path<u64>,
+) -> Result<Json<Game>, ApiError> {
+    let games = lock(&games);
+    Ok(Json(games.get(id)?.clone()))
+}
+
+async fn play_turn(
+    State(games): State<SharedGames>,
+    Path(id): Path<u64>,
+    Json(request): Json<NewTurn>,
+) -> Result<Json<PlayedTurn>, ApiError> {
+    let turn = parse_turn(&request.turn)?;
+    let mut games = lock(&games);
+    let outcome = games.play_turn(id, turn)?;
+    let game = games.get(id)?.clone();
+    Ok(Json(PlayedTurn { outcome, game }))
+}
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("There is no game with id {0}.")]
+    NoSuchGame(u64),
+    #[error("Games are for 1 to 4 players, not {0}.")]
+    BadPlayers(u8),
+    #[error("The turn can't be read: {0}")]
+    BadTurn(String),
+    #[error("The turn can't be played: {0}")]
+    IllegalTurn(String),
+    #[error("The game is over but couldn't be stored: {0}")]
+    NotStored(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NoSuchGame(_) => StatusCode::NOT_FOUND,
+            ApiError::BadPlayers(_) | ApiError::BadTurn(_) => StatusCode::BAD_REQUEST,
+            ApiError::IllegalTurn(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::NotStored(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let message = ServerMessage::Error {
+            message: self.to_string(),
+        };
+        (self.status(), Json(message)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{ReplayStore, RetentionPolicy};
+
+    #[test]
+    fn games_are_created_and_played() {
+        let mut games = GameTable::new();
+        let id = games.create(2, 4).unwrap();
+        assert_eq!(games.create(2, 4).unwrap(), id + 1);
+
+        let turn = games.get(id).unwrap().legal_turns_iter().next().unwrap();
+        let outcome = games.play_turn(id, turn.clone()).unwrap();
+        assert_eq!(outcome.player, 0);
+        assert_eq!(games.get(id).unwrap().round(), 1);
+        assert_eq!(games.get(id + 1).unwrap().round(), 0);
+
+        let errors = [
+            games.play_turn(id, turn.clone()).unwrap_err(),
+            parse_turn("resign").unwrap_err(),
+            games.play_turn(9, turn).unwrap_err(),
+            games.create(5, 0).unwrap_err(),
+        ];
+        let statuses: Vec<_> = errors.iter().map(ApiError::status).collect();
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::UNPROCESSABLE_ENTITY,
+                StatusCode::BAD_REQUEST,
+                StatusCode::NOT_FOUND,
+                StatusCode::BAD_REQUEST,
+            ]
+        );
+    }
+
+    #[test]
+    fn finished_games_are_stored() {
+        let store = SharedStore::new(Mutex::new(ReplayStore::in_memory(
+            RetentionPolicy::default(),
+        )));
+        let mut games = GameTable::with_store(store.clone());
+        let id = games.create(2, 4).unwrap();
+        while !games.get(id).unwrap().is_finished() {
+            assert!(store.lock().unwrap().is_empty());
+            let turn = games.get(id).unwrap().legal_turns_iter().next().unwrap();
+            games.play_turn(id, turn).unwrap();
+        }
+        let store = store.lock().unwrap();
+        let replay = store.get(1).unwrap();
+        assert_eq!(replay.players, ["Player 1", "Player 2"]);
+        assert_eq!(&replay.play_back().unwrap(), games.get(id).unwrap());
+    }
+
+    #[test]
+    fn a_panicking_request_leaves_the_table_usable() {
+        let games = SharedGames::default();
+        lock(&games).create(2, 0).unwrap();
+        let poisoner = games.clone();
+        std::thread::spawn(move || {
+            let _games = poisoner.lock();
+            panic!("A request panicking");
+        })
+        .join()
+        .unwrap_err();
+        assert!(games.is_poisoned());
+        assert!(lock(&games).get(1).is_ok());
+    }
+}
@@ -0,0 +1,252 @@
+//! Finished games kept on the server, so clients can list recent games and
+//! open them in the replay viewer.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::explorer::OpeningExplorer;
+use crate::paths::DataDirs;
+use crate::replay::{Replay, ReplayError};
+
+/// What the recent games list shows about a game, without the turns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplaySummary {
+    pub id: u64,
+    pub players: Vec<String>,
+    pub scores: Vec<u32>,
+    pub turns: usize,
+}
+
+/// How many replays the server keeps. Once full, the oldest replay is dropped
+/// to make room for a new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_replays: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy { max_replays: 1000 }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayStore {
+    /// Where replays are kept as `<id>.json`, or `None` to keep them in memory.
+    dir: Option<PathBuf>,
+    policy: RetentionPolicy,
+    /// Oldest first.
+    replays: VecDeque<(u64, Replay)>,
+    next_id: u64,
+}
+
+impl ReplayStore {
+    pub fn in_memory(policy: RetentionPolicy) -> Self {
+        ReplayStore {
+            dir: None,
+            policy,
+            replays: VecDeque::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Keeps the replays in the directory, loading the ones already there.
+    /// They are checked like inserted ones, so a replay edited or corrupted
+    /// on disk fails to open rather than crashing the server later.
+    pub fn open(dir: impl Into<PathBuf>, policy: RetentionPolicy) -> Result<Self, StoreError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut replays = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str()?.parse().ok());
+            if let Some(id) = id {
+                let replay = Replay::load(&path)?;
+                check(&replay)?;
+                replays.push((id, replay));
+            }
+        }
+        replays.sort_by_key(|(id, _)| *id);
+
+        let mut store = ReplayStore {
+            dir: Some(dir),
+            policy,
+            next_id: replays.last().map_or(1, |(id, _)| id + 1),
+            replays: replays.into(),
+        };
+        store.evict()?;
+        Ok(store)
+    }
+
+    /// Keeps the replays in the usual replays directory, see [`DataDirs`].
+    pub fn open_default(policy: RetentionPolicy) -> Result<Self, StoreError> {
+        let dirs = DataDirs::locate().ok_or(StoreError::NoDataDir)?;
+        Self::open(dirs.replays(), policy)
+    }
+
+    /// Stores a replay after checking that it plays back, and returns its id.
+    pub fn insert(&mut self, replay: Replay) -> Result<u64, StoreError> {
+        check(&replay)?;
+
+        let id = self.next_id;
+        if let Some(dir) = &self.dir {
+            replay.save(dir.join(format!("{}.json", id)))?;
+        }
+        self.next_id += 1;
+        self.replays.push_back((id, replay));
+        self.evict()?;
+        Ok(id)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Replay> {
+        self.replays
+            .iter()
+            .find(|(replay_id, _)| *replay_id == id)
+            .map(|(_, replay)| replay)
+    }
+
+    /// The most recently stored games, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<ReplaySummary> {
+        self.replays
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|(id, replay)| ReplaySummary {
+                id: *id,
+                players: replay.players.clone(),
+                scores: replay
+                    .final_scores()
+                    .expect("Stored replays are checked when inserted"),
+                turns: replay.turns.len(),
+            })
+            .collect()
+    }
+
+    /// The opening explorer over the stored games.
+    pub fn explorer(&self) -> OpeningExplorer {
+        let mut explorer = OpeningExplorer::new();
+        for (_, replay) in &self.replays {
+            explorer
+                .add_replay(replay)
+                .expect("Stored replays are checked when inserted");
+        }
+        explorer
+    }
+
+    pub fn len(&self) -> usize {
+        self.replays.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replays.is_empty()
+    }
+
+    fn evict(&mut self) -> Result<(), StoreError> {
+        while self.replays.len() > self.policy.max_replays {
+            let (id, _) = self.replays.pop_front().unwrap();
+            if let Some(dir) = &self.dir {
+                fs::remove_file(dir.join(format!("{}.json", id)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the replay plays back as recorded, which [`ReplayStore::recent`]
+/// and [`ReplayStore::explorer`] count on.
+fn check(replay: &Replay) -> Result<(), StoreError> {
+    replay.play_back()?;
+    replay.final_scores()?;
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Couldn't access the replay directory: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't find a directory to keep the replays in.")]
+    NoDataDir,
+    #[error("Bad replay: {0}")]
+    Replay(#[from] ReplayError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    fn finished_replay(seed: u64, turns: usize) -> Replay {
+        let mut replay = Replay::new(vec!["Alice".into(), "Bob".into()], Ruleset::new(2), seed);
+        let mut game = replay.initial_game();
+        for _ in 0..turns {
+            let turn = game.legal_turns().pop().unwrap();
+            replay.play(&mut game, turn).unwrap();
+        }
+        replay
+    }
+
+    #[test]
+    fn retention() {
+        let mut store = ReplayStore::in_memory(RetentionPolicy { max_replays: 2 });
+        let first = store.insert(finished_replay(1, 1)).unwrap();
+        let second = store.insert(finished_replay(2, 2)).unwrap();
+        let third = store.insert(finished_replay(3, 3)).unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get(first).is_none());
+        assert_eq!(store.get(third).unwrap().seed, 3);
+
+        let recent = store.recent(10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!((recent[0].id, recent[0].turns), (third, 3));
+        assert_eq!(recent[1].id, second);
+        assert_eq!(store.recent(1).len(), 1);
+        assert_eq!(store.explorer().games(), 2);
+    }
+
+    #[test]
+    fn tampered_replays_are_rejected() {
+        let mut replay = finished_replay(1, 2);
+        replay.outcomes[1].points += 1;
+        let mut store = ReplayStore::in_memory(RetentionPolicy::default());
+        assert!(matches!(store.insert(replay), Err(StoreError::Replay(_))));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn stored_on_disk() {
+        let dir = std::env::temp_dir().join(format!("passtally-replays-{}", std::process::id()));
+        let policy = RetentionPolicy { max_replays: 2 };
+
+        let mut store = ReplayStore::open(&dir, policy.clone()).unwrap();
+        for seed in 0..3 {
+            store.insert(finished_replay(seed, 1)).unwrap();
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        let reopened = ReplayStore::open(&dir, policy.clone()).unwrap();
+        assert_eq!(reopened.recent(10), store.recent(10));
+        assert_eq!(reopened.next_id, store.next_id);
+
+        // A replay edited on disk stops the store from opening.
+        let mut tampered = finished_replay(5, 2);
+        tampered.outcomes[1].points += 1;
+        tampered.save(dir.join("9.json")).unwrap();
+        assert!(matches!(
+            ReplayStore::open(&dir, policy),
+            Err(StoreError::Replay(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,841 @@
+//! Compact, human-pasteable text encodings of the game state.
+//!
+//! A game is written as five space separated fields, similar to FEN in chess,
+//! and optionally a sixth:
+//!
+//! ```text
+//! A1>A1<4/6/6/6/6/6 01----01----01----01---- B4,P2,R3:RCGYYY 0,3 1 RGYB,CP,YYR
+//! ```
+//!
+//! 1. The board, one row per `/` from the top (y = 0) down. Each row lists its
+//!    squares from left to right. A digit is a run of empty squares. An occupied
+//!    square is the routing of its top piece (`A`, `B` or `C`, see [`Piece`]),
+//!    its height, and the direction of the other half of the top piece (`^`,
+//!    `>`, `v`, `<`), or `*` if that half has been covered.
+//! 2. The 24 player marker slots, clockwise from the top left. `-` is an empty
+//!    slot, otherwise the digit of the player owning it. In a puzzle the
+//!    markers of the seats not playing are blockers.
+//! 3. What the players know about the decks: for each deck, separated by
+//!    `,`, the letter of the piece it offers and how many pieces it holds,
+//!    or `-` once it's empty. Then `:` and the pieces under the market,
+//!    sorted by color so their order gives nothing away.
+//! 4. The score of each player separated by `,`, which also gives the amount
+//!    of players. A puzzle has one player, and their score is followed by `/`
+//!    and the target, like `3/12`.
+//! 5. The amount of rounds played, which also tells whose turn it is. In a
+//!    puzzle it's followed by `/` and the turns the puzzle gives, like `1/2`.
+//! 6. The order of the three decks, separated by `,`, bottom to top, one
+//!    letter per piece. Nobody playing knows it, so it's only written by
+//!    [`Game::to_full_notation`], for saving a game as it is. Without it the
+//!    pieces under the market are dealt in the order they are listed.
+//!
+//! Actions are written as `P:Pink@c3r1` (place a pink piece with its first half
+//! on column c, row 3, rotated once), `M:4>7` (move the player marker in slot
+//! 4 to slot 7) and `R:1` (discard the top piece of deck 1). A turn is its two
+//! actions joined by `+`, like `P:Pink@c3r1+M:4>7`. Resigning and draw offers
+//! are written `resign`, `offer-draw` and `accept-draw`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use thiserror::Error;
+
+use crate::board::{Board, BoardPosition};
+use crate::game::{Action, Deck, Game, GameMode, Turn};
+use crate::piece::{PartialPiece, Piece, PositionedPiece, RotatedPartialPiece, Side};
+use crate::rules::{Ruleset, MAX_PLAYERS};
+use crate::zobrist;
+
+impl Game {
+    /// The game as the players see it, without the order of the decks.
+    pub fn to_notation(&self) -> String {
+        let markers: String = self
+            .player_markers
+            .iter()
+            .map(|marker| match marker {
+                Some(player) => (b'0' + player) as char,
+                None => '-',
+            })
+            .collect();
+        let offers: Vec<String> = self
+            .decks
+            .iter()
+            .map(|deck| match deck.last() {
+                Some(&piece) => format!("{}{}", piece_letter(piece), deck.len()),
+                None => "-".to_string(),
+            })
+            .collect();
+        let unseen: String = unseen(&self.decks).into_iter().map(piece_letter).collect();
+
+        let scores: Vec<String> = self.scores.iter().map(|score| score.to_string()).collect();
+        let (target, turns) = match self.mode {
+            GameMode::Standard => (String::new(), String::new()),
+            GameMode::Puzzle { turns, target } => (format!("/{}", target), format!("/{}", turns)),
+        };
+
+        format!(
+            "{} {} {}:{} {}{} {}{}",
+            board_notation(&self.board),
+            markers,
+            offers.join(","),
+            unseen,
+            scores.join(","),
+            target,
+            self.round,
+            turns
+        )
+    }
+
+    /// The game with the order of the decks, so reading it back gives the
+    /// same game.
+    pub fn to_full_notation(&self) -> String {
+        let decks: Vec<String> = self
+            .decks
+            .iter()
+            .map(|deck| deck.iter().map(|&piece| piece_letter(piece)).collect())
+            .collect();
+        format!("{} {}", self.to_notation(), decks.join(","))
+    }
+
+    pub fn from_notation(s: &str) -> Result<Game, NotationError> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if !matches!(fields.len(), 5 | 6) {
+            return Err(NotationError::FieldCount(fields.len()));
+        }
+
+        let board = parse_board(fields[0])?;
+
+        let (scores, target) = split_puzzle(fields[3])?;
+        let (round, turns) = split_puzzle(fields[4])?;
+        let scores: Vec<u32> = scores
+            .split(',')
+            .map(|score| score.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| NotationError::BadNumber(fields[3].to_string()))?;
+        if !(1..=MAX_PLAYERS as usize).contains(&scores.len()) {
+            return Err(NotationError::BadNumber(fields[3].to_string()));
+        }
+        let player_count = scores.len() as u8;
+        let round: u32 = round
+            .parse()
+            .map_err(|_| NotationError::BadNumber(fields[4].to_string()))?;
+        let mode = match (target, turns) {
+            (None, None) => GameMode::Standard,
+            (Some(target), Some(turns)) if player_count == 1 => GameMode::Puzzle { turns, target },
+            _ => return Err(NotationError::BadPuzzle(fields[3..5].join(" "))),
+        };
+        // A puzzle's blockers are the markers of the seats not playing.
+        let seats = match mode {
+            GameMode::Standard => player_count,
+            GameMode::Puzzle { .. } => MAX_PLAYERS,
+        };
+
+        let mut player_markers = [None; 24];
+        if fields[1].chars().count() != 24 {
+            return Err(NotationError::BadMarkers(fields[1].to_string()));
+        }
+        for (slot, c) in player_markers.iter_mut().zip(fields[1].chars()) {
+            *slot = match c {
+                '-' => None,
+                c => match c.to_digit(10) {
+                    Some(player) if player < seats as u32 => Some(player as u8),
+                    _ => return Err(NotationError::BadMarkers(fields[1].to_string())),
+                },
+            };
+        }
+
+        let decks = parse_decks(fields[2])?;
+        let pieces: usize = decks.iter().map(|deck| deck.len()).sum();
+        match board.pieces_stacked() {
+            Some(stacked) if stacked as usize + pieces <= 42 => {}
+            _ => return Err(NotationError::BadHeights(fields[0].to_string())),
+        }
+        let decks = match fields.get(5) {
+            None => decks,
+            Some(order) => {
+                let ordered = parse_deck_order(order)
+                    .ok_or_else(|| NotationError::DeckOrder(order.to_string()))?;
+                let agrees = ordered.iter().zip(&decks).all(|(ordered, deck)| {
+                    ordered.len() == deck.len() && ordered.last() == deck.last()
+                }) && unseen(&ordered) == unseen(&decks);
+                if !agrees {
+                    return Err(NotationError::DeckOrder(order.to_string()));
+                }
+                ordered
+            }
+        };
+
+        let mut game = Game {
+            board,
+            markers_hash: zobrist::markers(&player_markers),
+            player_markers,
+            rules: Ruleset::new(player_count),
+            scores,
+            round,
+            decks,
+            stalemate: false,
+            clock: None,
+            time_forfeit: None,
+            score_events: Vec::new(),
+            resigned: Vec::new(),
+            draw_offer: Vec::new(),
+            mode,
+        };
+        game.stalemate = !game.is_finished() && !game.has_legal_turn();
+        Ok(game)
+    }
+}
+
+/// Splits the `/` and the number a puzzle adds to the scores or the round
+/// off the field.
+fn split_puzzle(field: &str) -> Result<(&str, Option<u32>), NotationError> {
+    match field.split_once('/') {
+        None => Ok((field, None)),
+        Some((field, number)) => match number.parse() {
+            Ok(number) => Ok((field, Some(number))),
+            Err(_) => Err(NotationError::BadNumber(number.to_string())),
+        },
+    }
+}
+
+/// The pieces under the market, sorted by color.
+fn unseen(decks: &[Deck; 3]) -> Vec<Piece> {
+    let mut unseen: Vec<Piece> = decks
+        .iter()
+        .flat_map(|deck| deck.iter().rev().skip(1))
+        .copied()
+        .collect();
+    unseen.sort_by_key(|piece| piece.index());
+    unseen
+}
+
+/// The decks as the players know them, with the pieces under the market
+/// dealt in the order they are listed.
+fn parse_decks(s: &str) -> Result<[Deck; 3], NotationError> {
+    let bad_decks = || NotationError::BadDecks(s.to_string());
+    let (offers, unseen) = s.split_once(':').ok_or_else(bad_decks)?;
+    let offers: Vec<&str> = offers.split(',').collect();
+    if offers.len() != 3 {
+        return Err(bad_decks());
+    }
+    let mut unseen = unseen
+        .chars()
+        .map(piece_from_letter)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(bad_decks)?
+        .into_iter();
+
+    let mut decks = [Deck::new(), Deck::new(), Deck::new()];
+    for (deck, offer) in decks.iter_mut().zip(offers) {
+        if offer == "-" {
+            continue;
+        }
+        let mut chars = offer.chars();
+        let top = chars
+            .next()
+            .and_then(piece_from_letter)
+            .ok_or_else(bad_decks)?;
+        let size: usize = chars.as_str().parse().map_err(|_| bad_decks())?;
+        if size == 0 {
+            return Err(bad_decks());
+        }
+        for _ in 1..size {
+            deck.push(unseen.next().ok_or_else(bad_decks)?);
+        }
+        deck.push(top);
+    }
+    if unseen.next().is_some() {
+        return Err(bad_decks());
+    }
+    Ok(decks)
+}
+
+/// The decks in the optional sixth field.
+fn parse_deck_order(s: &str) -> Option<[Deck; 3]> {
+    let fields: Vec<&str> = s.split(',').collect();
+    if fields.len() != 3 {
+        return None;
+    }
+    let mut decks = [Deck::new(), Deck::new(), Deck::new()];
+    for (deck, field) in decks.iter_mut().zip(fields) {
+        *deck = field
+            .chars()
+            .map(piece_from_letter)
+            .collect::<Option<_>>()?;
+    }
+    Some(decks)
+}
+
+fn board_notation(board: &Board) -> String {
+    let rows: Vec<String> = (0..6)
+        .map(|y| {
+            let mut row = String::new();
+            let mut empty = 0;
+            for x in 0..6 {
+                let pos = BoardPosition::new(x, y);
+                let height = board.height(pos);
+                if height == 0 {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    row.push_str(&empty.to_string());
+                    empty = 0;
+                }
+
+                let partner = board.partner(pos);
+
+                row.push(routing_letter(board.top_piece(pos).routing()));
+                row.push_str(&height.to_string());
+                row.push(match partner {
+                    Some(side) => side_char(side),
+                    None => '*',
+                });
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            row
+        })
+        .collect();
+    rows.join("/")
+}
+
+fn parse_board(s: &str) -> Result<Board, NotationError> {
+    let rows: Vec<&str> = s.split('/').collect();
+    if rows.len() != 6 {
+        return Err(NotationError::BadBoard(s.to_string()));
+    }
+
+    let mut board = Board::default();
+    // The direction of each square's partner, filled in while reading the rows.
+    let mut partners = [[None; 6]; 6];
+
+    for (y, row) in rows.iter().enumerate() {
+        let bad_row = || NotationError::BadRow(y, row.to_string());
+        let mut chars = row.chars().peekable();
+        let mut x = 0;
+
+        while let Some(c) = chars.next() {
+            if let Some(run) = c.to_digit(10) {
+                x += run as usize;
+                continue;
+            }
+
+            let routing = routing_from_letter(c).ok_or_else(bad_row)?;
+            let mut height = String::new();
+            while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                height.push(*digit);
+                chars.next();
+            }
+            // Boards keep heights in a byte.
+            let height: u8 = height.parse().map_err(|_| bad_row())?;
+            let partner = match chars.next() {
+                Some('*') => None,
+                Some(c) => Some(side_from_char(c).ok_or_else(bad_row)?),
+                None => return Err(bad_row()),
+            };
+
+            if x >= 6 || height == 0 {
+                return Err(bad_row());
+            }
+            let pos = BoardPosition::new(x as i8, y as i8);
+            board.set_top_piece(pos, RotatedPartialPiece::new(routing, 0));
+            board.set_height(pos, height as u32);
+            partners[x][y] = partner;
+            x += 1;
+        }
+
+        if x != 6 {
+            return Err(bad_row());
+        }
+    }
+
+    // Give every top piece its tile id, pairing up halves that point at each other.
+    let mut next_id = 1;
+    for x in 0..6 {
+        for y in 0..6 {
+            let pos = BoardPosition::new(x, y);
+            if board.height(pos) == 0 || board.tile_id(pos) != 0 {
+                continue;
+            }
+
+            board.set_tile_id(pos, next_id);
+            if let Some(side) = partners[x as usize][y as usize] {
+                let partner = pos.step(side);
+                if !partner.valid()
+                    || partners[partner.x as usize][partner.y as usize] != Some(side.opposite())
+                    || board.height(partner) != board.height(pos)
+                {
+                    return Err(NotationError::UnpairedPiece(pos));
+                }
+                board.set_tile_id(partner, next_id);
+            }
+            next_id += 1;
+        }
+    }
+
+    // Every piece covers two squares, so this is the amount of pieces ever placed.
+    let placed: u32 = BoardPosition::all()
+        .map(|pos| board.height(pos))
+        .sum::<u32>()
+        / 2;
+    board.next_id = next_id.max(placed as u16 + 1);
+    board.rehash();
+
+    Ok(board)
+}
+
+fn side_char(side: Side) -> char {
+    match side {
+        Side::Top => '^',
+        Side::Right => '>',
+        Side::Bottom => 'v',
+        Side::Left => '<',
+    }
+}
+
+fn side_from_char(c: char) -> Option<Side> {
+    match c {
+        '^' => Some(Side::Top),
+        '>' => Some(Side::Right),
+        'v' => Some(Side::Bottom),
+        '<' => Some(Side::Left),
+        _ => None,
+    }
+}
+
+fn routing_letter(routing: PartialPiece) -> char {
+    match routing {
+        PartialPiece::TopBottom_LeftRight => 'A',
+        PartialPiece::TopLeft_BottomRight => 'B',
+        PartialPiece::TopRight_BottomLeft => 'C',
+    }
+}
+
+fn routing_from_letter(c: char) -> Option<PartialPiece> {
+    match c {
+        'A' => Some(PartialPiece::TopBottom_LeftRight),
+        'B' => Some(PartialPiece::TopLeft_BottomRight),
+        'C' => Some(PartialPiece::TopRight_BottomLeft),
+        _ => None,
+    }
+}
+
+pub(crate) fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::Red => 'R',
+        Piece::Green => 'G',
+        Piece::Yellow => 'Y',
+        Piece::Blue => 'B',
+        Piece::Cyan => 'C',
+        Piece::Pink => 'P',
+    }
+}
+
+pub(crate) fn piece_from_letter(c: char) -> Option<Piece> {
+    match c {
+        'R' => Some(Piece::Red),
+        'G' => Some(Piece::Green),
+        'Y' => Some(Piece::Yellow),
+        'B' => Some(Piece::Blue),
+        'C' => Some(Piece::Cyan),
+        'P' => Some(Piece::Pink),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Piece {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Piece::*;
+        [Red, Green, Yellow, Blue, Cyan, Pink]
+            .iter()
+            .copied()
+            .find(|piece| piece.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| NotationError::BadPiece(s.to_string()))
+    }
+}
+
+/// Columns are the letters a-f from the left, rows are 1-6 from the top.
+impl fmt::Display for BoardPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.x as u8) as char, self.y + 1)
+    }
+}
+
+impl FromStr for BoardPosition {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad_position = || NotationError::BadPosition(s.to_string());
+        let mut chars = s.chars();
+        let column = chars.next().ok_or_else(bad_position)?;
+        let row: u8 = chars.as_str().parse().map_err(|_| bad_position())?;
+        if !('a'..='f').contains(&column) || !(1..=6).contains(&row) {
+            return Err(bad_position());
+        }
+        Ok(BoardPosition::new(column as i8 - b'a' as i8, row as i8 - 1))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::PlacePiece(piece) => {
+                write!(f, "P:{}@{}r{}", piece.piece, piece.position, piece.rotation)
+            }
+            Action::MovePlayerMarker(from, to) => write!(f, "M:{}>{}", from, to),
+            Action::RedrawMarket(deck) => write!(f, "R:{}", deck),
+            Action::Resign => write!(f, "resign"),
+            Action::OfferDraw => write!(f, "offer-draw"),
+            Action::AcceptDraw => write!(f, "accept-draw"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad_action = || NotationError::BadAction(s.to_string());
+
+        match s {
+            "resign" => return Ok(Action::Resign),
+            "offer-draw" => return Ok(Action::OfferDraw),
+            "accept-draw" => return Ok(Action::AcceptDraw),
+            _ => {}
+        }
+        if let Some(placement) = s.strip_prefix("P:") {
+            let mut parts = placement.splitn(2, '@');
+            let piece = parts.next().ok_or_else(bad_action)?.parse()?;
+            let mut parts = parts.next().ok_or_else(bad_action)?.splitn(2, 'r');
+            let position = parts.next().ok_or_else(bad_action)?.parse()?;
+            let rotation = parts
+                .next()
+                .and_then(|r| r.parse().ok())
+                .filter(|r| *r < 4)
+                .ok_or_else(bad_action)?;
+
+            Ok(Action::PlacePiece(PositionedPiece {
+                piece,
+                rotation,
+                position,
+            }))
+        } else if let Some(movement) = s.strip_prefix("M:") {
+            let mut slots = movement.splitn(2, '>').map(|slot| slot.parse::<u8>().ok());
+            match (slots.next().flatten(), slots.next().flatten()) {
+                (Some(from), Some(to)) if from < 24 && to < 24 => {
+                    Ok(Action::MovePlayerMarker(from, to))
+                }
+                _ => Err(bad_action()),
+            }
+        } else if let Some(deck) = s.strip_prefix("R:") {
+            match deck.parse() {
+                Ok(deck) if deck < 3 => Ok(Action::RedrawMarket(deck)),
+                _ => Err(bad_action()),
+            }
+        } else {
+            Err(bad_action())
+        }
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}", self.0, self.1)
+    }
+}
+
+impl FromStr for Turn {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut actions = s.splitn(2, '+');
+        match (actions.next(), actions.next()) {
+            (Some(first), Some(second)) => Ok(Turn(first.parse()?, second.parse()?)),
+            _ => Err(NotationError::BadTurn(s.to_string())),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum NotationError {
+    #[error("Expected 5 or 6 fields but found {0}.")]
+    FieldCount(usize),
+    #[error("The board {0:?} doesn't have 6 rows.")]
+    BadBoard(String),
+    #[error("Row {0} of the board ({1:?}) is malformed.")]
+    BadRow(usize, String),
+    #[error("The piece at {0:?} points at a square that doesn't point back.")]
+    UnpairedPiece(BoardPosition),
+    #[error("The stacks on the board {0:?} can't be built from the pieces not in the decks.")]
+    BadHeights(String),
+    #[error("The player markers {0:?} are malformed.")]
+    BadMarkers(String),
+    #[error("The decks {0:?} are malformed.")]
+    BadDecks(String),
+    #[error("The order of the decks {0:?} doesn't match the decks on offer.")]
+    DeckOrder(String),
+    #[error("The puzzle {0:?} needs one player, a target and a number of turns.")]
+    BadPuzzle(String),
+    #[error("{0:?} is not a valid number.")]
+    BadNumber(String),
+    #[error("{0:?} is not a piece.")]
+    BadPiece(String),
+    #[error("{0:?} is not a position on the board.")]
+    BadPosition(String),
+    #[error("{0:?} is not a valid action.")]
+    BadAction(String),
+    #[error("{0:?} is not two actions joined by '+'.")]
+    BadTurn(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_game_round_trip() {
+        let game = Game::new_with_rules(Ruleset::new(2), 0);
+        let notation = game.to_notation();
+        assert!(notation.starts_with("6/6/6/6/6/6 01----01----01----01---- "));
+        assert!(notation.ends_with(" 0,0 0"));
+
+        let restored = Game::from_notation(&notation).unwrap();
+        assert_eq!(restored.to_notation(), notation);
+        assert_eq!(restored.market(), game.market());
+        assert_eq!(restored.decks_remaining(), game.decks_remaining());
+
+        let full = game.to_full_notation();
+        assert!(full.starts_with(&notation));
+        let restored = Game::from_notation(&full).unwrap();
+        assert_eq!(restored.to_full_notation(), full);
+        assert_eq!(restored.decks, game.decks);
+    }
+
+    #[test]
+    fn puzzles_round_trip() {
+        let mut start = Game::new_with_rules(Ruleset::new(2), 4);
+        for _ in 0..4 {
+            let turn = start.legal_turns().remove(0);
+            start.play_turn(turn).unwrap();
+        }
+        let puzzle = Game::new_puzzle(start.board, start.player_markers, start.decks, 2, 7);
+        assert!(puzzle.player_markers.contains(&Some(1)));
+        assert_eq!(puzzle.validate(), Ok(()));
+
+        let notation = puzzle.to_full_notation();
+        assert!(notation.contains(" 0/7 0/2 "));
+        let restored = Game::from_notation(&notation).unwrap();
+        assert_eq!(
+            restored.mode(),
+            GameMode::Puzzle {
+                turns: 2,
+                target: 7
+            }
+        );
+        assert_eq!(restored.validate(), Ok(()));
+        assert_eq!(restored.to_full_notation(), notation);
+        assert_eq!(restored.legal_turns(), puzzle.legal_turns());
+
+        let two_players = notation.replace(" 0/7 ", " 0,0/7 ");
+        assert!(matches!(
+            Game::from_notation(&two_players),
+            Err(NotationError::BadPuzzle(_))
+        ));
+        let no_turns = notation.replace(" 0/2 ", " 0 ");
+        assert!(matches!(
+            Game::from_notation(&no_turns),
+            Err(NotationError::BadPuzzle(_))
+        ));
+    }
+
+    #[test]
+    fn deck_order_stays_hidden() {
+        let game = Game::new_with_rules(Ruleset::new(2), 0);
+        let other = game.fork_reshuffled(1);
+        assert_ne!(other.decks, game.decks);
+        assert_eq!(other.to_notation(), game.to_notation());
+        assert_ne!(other.to_full_notation(), game.to_full_notation());
+
+        let notation = "6/6/6/6/6/6 01----01----01----01---- B4,P2,R3:RCGYYY 0,0 0";
+        let game = Game::from_notation(notation).unwrap();
+        assert_eq!(game.to_notation(), notation);
+        let full = format!("{} GYRB,CP,YYR", notation);
+        assert_eq!(Game::from_notation(&full).unwrap().to_full_notation(), full);
+        for order in &["RGYB,CP,YYB", "RGYB,CP,YR", "RGYB,CP", "RGYB,CP,YYX"] {
+            assert!(matches!(
+                Game::from_notation(&format!("{} {}", notation, order)),
+                Err(NotationError::DeckOrder(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn placed_pieces_round_trip() {
+        let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+        for (x, y, rotation) in &[(0, 0, 0), (1, 1, 2), (0, 0, 1), (3, 4, 3)] {
+            game.board
+                .place_piece(PositionedPiece {
+                    piece: Piece::Pink,
+                    position: BoardPosition::new(*x, *y),
+                    rotation: *rotation,
+                })
+                .unwrap();
+            // The piece placed comes out of the decks.
+            game.decks[0].pop();
+        }
+
+        let notation = game.to_notation();
+        assert!(notation.starts_with("B2vB1*4/C2^C1*4/6/3C1v2/3B1^2/6 "));
+
+        let mut restored = Game::from_notation(&notation).unwrap();
+        assert_eq!(restored.to_notation(), notation);
+        assert_eq!(restored.board.next_id, game.board.next_id);
+
+        // The restored board still knows which squares belong to the same piece.
+        let on_top = PositionedPiece {
+            piece: Piece::Red,
+            position: BoardPosition::new(0, 0),
+            rotation: 1,
+        };
+        assert!(restored.board.place_piece(on_top).is_err());
+    }
+
+    #[test]
+    fn malformed_notation() {
+        assert!(matches!(
+            Game::from_notation("6/6/6/6/6/6 01----01----01----01---- -,-,-: 0,0"),
+            Err(NotationError::FieldCount(4))
+        ));
+        assert!(matches!(
+            Game::from_notation("7/6/6/6/6/6 01----01----01----01---- -,-,-: 0,0 0"),
+            Err(NotationError::BadRow(0, _))
+        ));
+        assert!(matches!(
+            Game::from_notation("A1>5/6/6/6/6/6 01----01----01----01---- -,-,-: 0,0 0"),
+            Err(NotationError::UnpairedPiece(_))
+        ));
+        assert!(Game::from_notation("6/6/6/6/6/6 01----01----01----01---- -,-,-: 0,0 0").is_ok());
+        // Stacks taller than the pieces there are, up to where a byte wraps.
+        for stack in &["A43>A43<4", "A255>A255<4"] {
+            let notation = format!("{}/6/6/6/6/6 01----01----01----01---- -,-,-: 0,0 0", stack);
+            assert!(matches!(
+                Game::from_notation(&notation),
+                Err(NotationError::BadHeights(_))
+            ));
+        }
+        assert!(matches!(
+            Game::from_notation(
+                "A21>A21<4/6/6/6/6/6 01----01----01----01---- R22,-,-:RRRRRRRRRRRRRRRRRRRRR 0,0 0"
+            ),
+            Err(NotationError::BadHeights(_))
+        ));
+        assert!(matches!(
+            Game::from_notation("6/6/6/6/6/6 21----01----01----01---- -,-,-: 0,0 0"),
+            Err(NotationError::BadMarkers(_))
+        ));
+        assert!(matches!(
+            Game::from_notation("6/6/6/6/6/6 01----01----01----01---- -,-,-: 0,x 0"),
+            Err(NotationError::BadNumber(_))
+        ));
+        for decks in &[
+            "-,-,-", "R2,-,-:", "R1,-,-:G", "R0,-,-:", "R1,-:", "X1,-,-:",
+        ] {
+            let notation = format!("6/6/6/6/6/6 01----01----01----01---- {} 0,0 0", decks);
+            assert!(
+                matches!(
+                    Game::from_notation(&notation),
+                    Err(NotationError::BadDecks(_))
+                ),
+                "{:?} parsed",
+                decks
+            );
+        }
+    }
+
+    #[test]
+    fn action_notation() {
+        let place = Action::PlacePiece(PositionedPiece {
+            piece: Piece::Pink,
+            position: BoardPosition::new(2, 2),
+            rotation: 1,
+        });
+        assert_eq!(place.to_string(), "P:Pink@c3r1");
+        assert_eq!("P:Pink@c3r1".parse::<Action>().unwrap(), place);
+        assert_eq!("P:pink@c3r1".parse::<Action>().unwrap(), place);
+
+        let movement = Action::MovePlayerMarker(4, 7);
+        assert_eq!(movement.to_string(), "M:4>7");
+        assert_eq!("M:4>7".parse::<Action>().unwrap(), movement);
+
+        let redraw = Action::RedrawMarket(1);
+        assert_eq!(redraw.to_string(), "R:1");
+        assert_eq!("R:1".parse::<Action>().unwrap(), redraw);
+
+        for action in &[Action::Resign, Action::OfferDraw, Action::AcceptDraw] {
+            assert_eq!(&action.to_string().parse::<Action>().unwrap(), action);
+        }
+
+        let turn = Turn(place, movement);
+        assert_eq!(turn.to_string(), "P:Pink@c3r1+M:4>7");
+        assert_eq!("P:Pink@c3r1+M:4>7".parse::<Turn>().unwrap(), turn);
+    }
+
+    #[test]
+    fn every_placement_round_trips() {
+        use Piece::*;
+        for &piece in &[Red, Green, Yellow, Blue, Cyan, Pink] {
+            for position in BoardPosition::all() {
+                for rotation in 0..4 {
+                    let action = Action::PlacePiece(PositionedPiece {
+                        piece,
+                        rotation,
+                        position,
+                    });
+                    assert_eq!(action.to_string().parse::<Action>().unwrap(), action);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_actions() {
+        for bad in &[
+            "",
+            "P:Pink",
+            "P:Purple@c3r1",
+            "P:Pink@g3r1",
+            "P:Pink@c7r1",
+            "P:Pink@c0r1",
+            "P:Pink@c3r4",
+            "P:Pink@C3r1",
+            "P:Pink@a-128r1",
+            "P:Pink@À3r1",
+            "M:4",
+            "M:4>24",
+            "M:-1>3",
+            "R:3",
+            "R:",
+            "X:4>7",
+            "Resign",
+        ] {
+            assert!(bad.parse::<Action>().is_err(), "{:?} parsed", bad);
+        }
+        assert!(matches!(
+            "M:4>7".parse::<Turn>(),
+            Err(NotationError::BadTurn(_))
+        ));
+    }
+}
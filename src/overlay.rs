@@ -0,0 +1,234 @@
+//! Annotations drawn on top of a position: arrows between edge slots,
+//! highlighted squares and text labels. Tutorials, puzzles and coaching notes
+//! are built out of these, and they can be saved with replay bookmarks,
+//! shown by the GUI or exported as SVG.
+
+use std::fmt::Write;
+
+use crate::board::{edge_square, BoardPosition};
+use crate::game::Game;
+use crate::piece::Side;
+
+/// What an annotation is trying to say, which decides its colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tone {
+    Neutral,
+    Good,
+    Bad,
+}
+
+impl Tone {
+    /// The colour as a CSS hex string.
+    pub fn color(self) -> &'static str {
+        match self {
+            Tone::Neutral => "#3b82f6",
+            Tone::Good => "#22c55e",
+            Tone::Bad => "#ef4444",
+        }
+    }
+}
+
+/// Where an annotation is pinned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Anchor {
+    Square(BoardPosition),
+    /// An edge slot, 0..=23.
+    Slot(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arrow {
+    pub from: u8,
+    pub to: u8,
+    pub tone: Tone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Highlight {
+    pub square: BoardPosition,
+    pub tone: Tone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub anchor: Anchor,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Overlay {
+    pub arrows: Vec<Arrow>,
+    pub highlights: Vec<Highlight>,
+    pub labels: Vec<Label>,
+}
+
+/// Size of a square in the SVG export.
+const CELL: f32 = 40.0;
+
+impl Overlay {
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.highlights.is_empty() && self.labels.is_empty()
+    }
+
+    pub fn arrow(mut self, from: u8, to: u8, tone: Tone) -> Self {
+        self.arrows.push(Arrow { from, to, tone });
+        self
+    }
+
+    pub fn highlight(mut self, square: BoardPosition, tone: Tone) -> Self {
+        self.highlights.push(Highlight { square, tone });
+        self
+    }
+
+    pub fn label(mut self, anchor: Anchor, text: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            anchor,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Draws the board, the markers and the annotations as an SVG image. Each
+    /// square shows its height.
+    pub fn to_svg(&self, game: &Game) -> String {
+        let size = CELL * 8.0;
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}" font-family="sans-serif" text-anchor="middle">"#,
+            size
+        );
+        svg.push_str(concat!(
+            r#"<defs><marker id="head" markerWidth="6" markerHeight="6" refX="3" refY="3" orient="auto">"#,
+            r#"<path d="M0,0 L6,3 L0,6 z" fill="context-stroke"/></marker></defs>"#,
+            "\n"
+        ));
+
+        for pos in BoardPosition::all() {
+            let (x, y) = corner(pos);
+            let fill = match self.highlights.iter().rev().find(|h| h.square == pos) {
+                Some(highlight) => highlight.tone.color(),
+                None => "#f5f0e6",
+            };
+            let _ = writeln!(
+                svg,
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="#555"/>"##,
+                x, y, CELL, CELL, fill
+            );
+            let height = game.board.height(pos);
+            if height > 0 {
+                let (cx, cy) = square_center(pos);
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-size="14">{}</text>"#,
+                    cx,
+                    cy + 5.0,
+                    height
+                );
+            }
+        }
+
+        for (slot, player) in game.player_markers() {
+            let (x, y) = slot_point(slot as u8);
+            let _ = writeln!(
+                svg,
+                r##"<circle cx="{}" cy="{}" r="8" fill="#222"/><text x="{}" y="{}" font-size="10" fill="#fff">{}</text>"##,
+                x,
+                y,
+                x,
+                y + 4.0,
+                player
+            );
+        }
+
+        for arrow in &self.arrows {
+            let (x1, y1) = slot_point(arrow.from);
+            let (x2, y2) = slot_point(arrow.to);
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="3" marker-end="url(#head)"/>"#,
+                x1,
+                y1,
+                x2,
+                y2,
+                arrow.tone.color()
+            );
+        }
+
+        for label in &self.labels {
+            let (x, y) = match label.anchor {
+                Anchor::Square(pos) => square_center(pos),
+                Anchor::Slot(slot) => slot_point(slot),
+            };
+            let _ = writeln!(
+                svg,
+                r##"<text x="{}" y="{}" font-size="12" fill="#111">{}</text>"##,
+                x,
+                y - 12.0,
+                escape(&label.text)
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// The top left corner of the square in the SVG export. The board is drawn
+/// with a one square margin for the markers.
+fn corner(pos: BoardPosition) -> (f32, f32) {
+    (CELL * (pos.x as f32 + 1.0), CELL * (pos.y as f32 + 1.0))
+}
+
+fn square_center(pos: BoardPosition) -> (f32, f32) {
+    let (x, y) = corner(pos);
+    (x + CELL / 2.0, y + CELL / 2.0)
+}
+
+fn slot_point(slot: u8) -> (f32, f32) {
+    let (pos, side) = edge_square(slot);
+    let (x, y) = square_center(pos);
+    let out = CELL * 0.75;
+    match side {
+        Side::Top => (x, y - out),
+        Side::Right => (x + out, y),
+        Side::Bottom => (x, y + out),
+        Side::Left => (x - out, y),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_export() {
+        let game = Game::new(2);
+        let overlay = Overlay::default()
+            .arrow(0, 23, Tone::Good)
+            .highlight(BoardPosition::new(2, 3), Tone::Bad)
+            .label(Anchor::Slot(0), "Move <here>")
+            .label(Anchor::Square(BoardPosition::new(2, 3)), "Avoid");
+        assert!(!overlay.is_empty());
+
+        let svg = overlay.to_svg(&game);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 36);
+        assert_eq!(svg.matches("<circle").count(), 8);
+        assert_eq!(svg.matches(Tone::Bad.color()).count(), 1);
+        assert!(svg.contains("<line"));
+        assert!(svg.contains("Move &lt;here&gt;"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Draws overlay annotations on top of the board.
+
+use bevy::prelude::*;
+use passtally_rs::overlay::{Anchor, Overlay, Tone};
+
+use crate::{slot_world_pos, square_world_pos};
+
+/// The annotations on the board. Replace it to redraw them.
+#[derive(Default)]
+pub struct CurrentOverlay(pub Overlay);
+
+/// Marks everything spawned for the overlay, so it can be cleared.
+struct OverlayPart;
+
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CurrentOverlay>()
+            .add_system(draw_overlay.system());
+    }
+}
+
+fn tone_color(tone: Tone) -> Color {
+    match tone {
+        Tone::Neutral => Color::rgba(0.23, 0.51, 0.96, 0.6),
+        Tone::Good => Color::rgba(0.13, 0.77, 0.37, 0.6),
+        Tone::Bad => Color::rgba(0.94, 0.27, 0.27, 0.6),
+    }
+}
+
+fn draw_overlay(
+    commands: &mut Commands,
+    overlay: ChangedRes<CurrentOverlay>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    parts: Query<Entity, With<OverlayPart>>,
+) {
+    for entity in parts.iter() {
+        commands.despawn(entity);
+    }
+
+    for highlight in &overlay.0.highlights {
+        commands
+            .spawn(SpriteBundle {
+                material: materials.add(tone_color(highlight.tone).into()),
+                sprite: Sprite::new(Vec2::new(16.0, 16.0)),
+                transform: Transform::from_translation(
+                    square_world_pos(highlight.square).extend(1.0),
+                ),
+                ..Default::default()
+            })
+            .with(OverlayPart);
+    }
+
+    for arrow in &overlay.0.arrows {
+        let from = slot_world_pos(arrow.from);
+        let to = slot_world_pos(arrow.to);
+        let delta = to - from;
+
+        let mut transform = Transform::from_translation(((from + to) / 2.0).extend(2.0));
+        transform.rotate(Quat::from_rotation_z(delta.y.atan2(delta.x)));
+        commands
+            .spawn(SpriteBundle {
+                material: materials.add(tone_color(arrow.tone).into()),
+                sprite: Sprite::new(Vec2::new(delta.length(), 2.0)),
+                transform,
+                ..Default::default()
+            })
+            .with(OverlayPart);
+    }
+
+    for label in &overlay.0.labels {
+        let pos = match label.anchor {
+            Anchor::Square(pos) => square_world_pos(pos),
+            Anchor::Slot(slot) => slot_world_pos(slot),
+        };
+        let mut transform = Transform::from_translation((pos + Vec2::new(0.0, 8.0)).extend(3.0));
+        transform.scale = Vec3::splat(0.25);
+        commands
+            .spawn(Text2dBundle {
+                text: Text {
+                    value: label.text.clone(),
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    style: TextStyle {
+                        font_size: 24.0,
+                        color: Color::BLACK,
+                        ..Default::default()
+                    },
+                },
+                transform,
+                ..Default::default()
+            })
+            .with(OverlayPart);
+    }
+}
@@ -0,0 +1,168 @@
+//! Where the game keeps its files.
+//!
+//! By default everything goes where the platform expects it, e.g.
+//! `~/.config/passtally` and `~/.local/share/passtally` on Linux,
+//! `~/Library/Application Support/passtally` on macOS and
+//! `%APPDATA%\passtally` on Windows. Setting `PASSTALLY_HOME` puts everything
+//! under that directory instead, for portable installs.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+
+#[cfg(feature = "json")]
+use crate::game::Game;
+
+/// The environment variable that overrides where all the files go.
+pub const HOME_VAR: &str = "PASSTALLY_HOME";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataDirs {
+    config: PathBuf,
+    saves: PathBuf,
+    replays: PathBuf,
+    cache: PathBuf,
+}
+
+impl DataDirs {
+    /// The directories to use on this machine. `None` if neither
+    /// `PASSTALLY_HOME` is set nor a home directory could be found.
+    pub fn locate() -> Option<DataDirs> {
+        Self::locate_with(std::env::var_os(HOME_VAR))
+    }
+
+    fn locate_with(home: Option<OsString>) -> Option<DataDirs> {
+        match home {
+            Some(home) if !home.is_empty() => Some(Self::portable(home)),
+            _ => {
+                let dirs = ProjectDirs::from("", "", "passtally")?;
+                Some(DataDirs {
+                    config: dirs.config_dir().to_path_buf(),
+                    saves: dirs.data_dir().join("saves"),
+                    replays: dirs.data_dir().join("replays"),
+                    cache: dirs.cache_dir().to_path_buf(),
+                })
+            }
+        }
+    }
+
+    /// Everything in subdirectories of one directory.
+    pub fn portable(root: impl Into<PathBuf>) -> DataDirs {
+        let root = root.into();
+        DataDirs {
+            config: root.join("config"),
+            saves: root.join("saves"),
+            replays: root.join("replays"),
+            cache: root.join("cache"),
+        }
+    }
+
+    /// Settings, profiles and bot presets.
+    pub fn config(&self) -> &Path {
+        &self.config
+    }
+
+    /// Saved and autosaved games.
+    pub fn saves(&self) -> &Path {
+        &self.saves
+    }
+
+    pub fn replays(&self) -> &Path {
+        &self.replays
+    }
+
+    /// Files that can be rebuilt, like opening books and lookup tables.
+    pub fn cache(&self) -> &Path {
+        &self.cache
+    }
+
+    pub fn settings_file(&self) -> PathBuf {
+        self.config.join("settings.toml")
+    }
+
+    pub fn profiles(&self) -> PathBuf {
+        self.config.join("profiles")
+    }
+
+    pub fn autosave_file(&self) -> PathBuf {
+        self.saves.join("autosave.json")
+    }
+
+    /// Creates any of the directories that don't exist yet.
+    pub fn create_all(&self) -> io::Result<()> {
+        for dir in &[
+            &self.config,
+            &self.profiles(),
+            &self.saves,
+            &self.replays,
+            &self.cache,
+        ] {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the game to the [`autosave_file`](DataDirs::autosave_file), as
+    /// JSON.
+    #[cfg(feature = "json")]
+    pub fn autosave(&self, game: &Game) -> io::Result<()> {
+        fs::write(self.autosave_file(), serde_json::to_string(game)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portable_override() {
+        let dirs = DataDirs::locate_with(Some("/games/passtally".into())).unwrap();
+        assert_eq!(dirs, DataDirs::portable("/games/passtally"));
+        assert_eq!(
+            dirs.settings_file(),
+            Path::new("/games/passtally/config/settings.toml")
+        );
+        assert_eq!(
+            dirs.autosave_file(),
+            Path::new("/games/passtally/saves/autosave.json")
+        );
+
+        // An empty override is the same as none.
+        assert_eq!(
+            DataDirs::locate_with(Some("".into())),
+            DataDirs::locate_with(None)
+        );
+    }
+
+    #[test]
+    fn create_all() {
+        let root = std::env::temp_dir().join(format!("passtally-home-{}", std::process::id()));
+        let dirs = DataDirs::portable(&root);
+        dirs.create_all().unwrap();
+        assert!(dirs.profiles().is_dir());
+        assert!(dirs.cache().is_dir());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn autosave_after_an_action() {
+        let root = std::env::temp_dir().join(format!("passtally-save-{}", std::process::id()));
+        let dirs = DataDirs::portable(&root);
+        dirs.create_all().unwrap();
+
+        // The GUI plays the actions of a turn one at a time and saves after
+        // each of them.
+        let mut game = Game::new(2);
+        let action = game.legal_actions().into_iter().next().unwrap();
+        game.do_action(action).unwrap();
+        dirs.autosave(&game).unwrap();
+        let saved: Game =
+            serde_json::from_str(&fs::read_to_string(dirs.autosave_file()).unwrap()).unwrap();
+        assert_eq!(saved, game);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
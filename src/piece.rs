@@ -1,11 +1,11 @@
 use num_enum::TryFromPrimitive;
-use std::convert::TryFrom;
 
 use crate::board::BoardPosition;
 use Side::*;
 
 #[derive(Copy, Clone, PartialEq, Debug, TryFromPrimitive)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Side {
     Top = 0,
     Right = 1,
@@ -14,7 +14,7 @@ pub enum Side {
 }
 
 impl Side {
-    pub fn opposite(self) -> Self {
+    pub const fn opposite(self) -> Self {
         match self {
             Top => Bottom,
             Bottom => Top,
@@ -24,13 +24,20 @@ impl Side {
     }
 
     /// Rotation is clockwise and 0..=3
-    pub fn rotate(self, n: u8) -> Self {
-        Self::try_from((self as u8 + n) % 4).unwrap()
+    pub const fn rotate(self, n: u8) -> Self {
+        match (self as u8 + n) % 4 {
+            0 => Top,
+            1 => Right,
+            2 => Bottom,
+            _ => Left,
+        }
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, TryFromPrimitive)]
+#[repr(u8)]
 #[allow(non_camel_case_types, clippy::enum_variant_names)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PartialPiece {
     TopBottom_LeftRight, // Pipes top to bottom and left to right
     TopLeft_BottomRight, // Pipes top to left and bottom to right
@@ -39,7 +46,7 @@ pub enum PartialPiece {
 
 impl PartialPiece {
     // returns which side we are leaving from when we pass through this partial piece
-    pub fn pass(&self, side: Side) -> Side {
+    pub const fn pass(&self, side: Side) -> Side {
         use PartialPiece::*;
 
         match self {
@@ -60,31 +67,89 @@ impl PartialPiece {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
-pub struct RotatedPartialPiece {
-    partial_piece: PartialPiece,
-    rotation: u8,
-}
+/// A partial piece and its rotation in a byte, the partial piece times 4
+/// plus the rotation, so boards store them as they are.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct RotatedPartialPiece(u8);
+
+/// Where a line leaves each partial piece in each rotation, by the side it
+/// enters from, worked out once instead of on every hop of every trace.
+/// Indexed by the byte of the [`RotatedPartialPiece`].
+const PASS: [[Side; 4]; 12] = {
+    const PARTIAL_PIECES: [PartialPiece; 3] = [
+        PartialPiece::TopBottom_LeftRight,
+        PartialPiece::TopLeft_BottomRight,
+        PartialPiece::TopRight_BottomLeft,
+    ];
+    let mut table = [[Top; 4]; 12];
+    let mut piece = 0;
+    while piece < 3 {
+        let mut rotation = 0;
+        while rotation < 4 {
+            let mut side = 0;
+            while side < 4 {
+                // Rotate into the piece's own sides, pass through and rotate back.
+                let local = Top.rotate(side as u8 + 4 - rotation as u8);
+                let exit = PARTIAL_PIECES[piece].pass(local);
+                table[piece * 4 + rotation][side] = exit.rotate(rotation as u8);
+                side += 1;
+            }
+            rotation += 1;
+        }
+        piece += 1;
+    }
+    table
+};
 
 impl RotatedPartialPiece {
     pub fn new(partial_piece: PartialPiece, rotation: u8) -> Self {
-        RotatedPartialPiece {
-            partial_piece,
-            rotation,
-        }
+        RotatedPartialPiece(partial_piece as u8 * 4 + rotation % 4)
+    }
+
+    /// Whether the byte is one [`RotatedPartialPiece::new`] could have made,
+    /// which only pieces read through serde might not be.
+    pub(crate) fn is_valid(&self) -> bool {
+        (self.0 as usize) < PASS.len()
+    }
+
+    pub fn partial_piece(&self) -> PartialPiece {
+        PartialPiece::try_from_primitive(self.0 / 4).unwrap()
+    }
+
+    pub fn rotation(&self) -> u8 {
+        self.0 % 4
     }
 
     pub fn pass(&self, side: Side) -> Side {
-        // Rotate into local side
-        let local_side = side.rotate(4 - self.rotation);
-        // Pass through piece
-        let exit_side = self.partial_piece.pass(local_side);
-        // Rotate back to global
-        exit_side.rotate(self.rotation)
+        PASS[self.0 as usize][side as usize]
+    }
+
+    /// The unrotated partial piece that routes lines the same way as this one.
+    pub fn routing(&self) -> PartialPiece {
+        use PartialPiece::*;
+
+        match (self.partial_piece(), self.rotation() % 2) {
+            (TopBottom_LeftRight, _) => TopBottom_LeftRight,
+            (TopLeft_BottomRight, 0) | (TopRight_BottomLeft, 1) => TopLeft_BottomRight,
+            (TopRight_BottomLeft, 0) | (TopLeft_BottomRight, 1) => TopRight_BottomLeft,
+            _ => unreachable!(),
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub enum Piece {
     // A is TopBottom_LeftRight,
     // B is TopLeft_BottomRight,
@@ -103,7 +168,8 @@ impl Piece {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PositionedPiece {
     pub piece: Piece,
     pub rotation: u8,
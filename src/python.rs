@@ -0,0 +1,264 @@
+//! The `passtally` Python module, for scripting simulations and training
+//! bots from Python. Build and install it into the active virtualenv with
+//! `maturin develop --release`, see `pyproject.toml`.
+//!
+//! Actions and turns go back and forth in the text notation of
+//! [`crate::notation`], like `P:Pink@c3r1+M:4>7`. Illegal moves and bad
+//! notation raise `ValueError`.
+//!
+//! ```python
+//! import passtally
+//!
+//! game = passtally.Game(2, seed=7)
+//! bot = passtally.Bot("mcts:default", seed=1)
+//! while not game.is_finished:
+//!     game.play_turn(bot.choose_turn(game, nodes=1000))
+//! print(game.scores)
+//! ```
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::ai::encoding::{position_features, ACTION_SPACE};
+use crate::ai::{Agent, AgentSpec, Limits, PasstallyEnv};
+use crate::game::{Action, Game, Turn};
+use crate::rules::Ruleset;
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn rules(players: u8) -> PyResult<Ruleset> {
+    if !(1..=4).contains(&players) {
+        return Err(value_error("Games are for 1 to 4 players."));
+    }
+    Ok(Ruleset::new(players))
+}
+
+/// A game of passtally.
+#[pyclass(name = "Game", module = "passtally")]
+#[derive(Clone)]
+pub struct PyGame {
+    game: Game,
+}
+
+#[pymethods]
+impl PyGame {
+    /// A new game between 1 to 4 players, with the decks shuffled by the seed
+    /// or at random without one.
+    #[new]
+    #[pyo3(signature = (players = 2, seed = None))]
+    fn new(players: u8, seed: Option<u64>) -> PyResult<PyGame> {
+        let rules = rules(players)?;
+        let game = match seed {
+            Some(seed) => Game::new_with_rules(rules, seed),
+            None => Game::new(players),
+        };
+        Ok(PyGame { game })
+    }
+
+    #[staticmethod]
+    fn from_notation(notation: &str) -> PyResult<PyGame> {
+        let game = Game::from_notation(notation).map_err(value_error)?;
+        Ok(PyGame { game })
+    }
+
+    fn to_notation(&self) -> String {
+        self.game.to_notation()
+    }
+
+    #[getter]
+    fn next_player(&self) -> u8 {
+        self.game.next_player()
+    }
+
+    #[getter]
+    fn round(&self) -> u32 {
+        self.game.round()
+    }
+
+    #[getter]
+    fn scores(&self) -> Vec<u32> {
+        self.game.scores().to_vec()
+    }
+
+    /// The pieces on top of the three decks, `None` for empty decks.
+    #[getter]
+    fn market(&self) -> Vec<Option<String>> {
+        self.game
+            .market()
+            .iter()
+            .map(|piece| piece.map(|piece| piece.to_string()))
+            .collect()
+    }
+
+    #[getter]
+    fn is_finished(&self) -> bool {
+        self.game.is_finished()
+    }
+
+    /// Every action the player to move could take as half of their turn.
+    fn legal_actions(&self) -> Vec<String> {
+        self.game
+            .legal_actions_iter()
+            .map(|action| action.to_string())
+            .collect()
+    }
+
+    /// Every turn the player to move could play.
+    fn legal_turns(&self) -> Vec<String> {
+        self.game
+            .legal_turns_iter()
+            .map(|turn| turn.to_string())
+            .collect()
+    }
+
+    /// Plays the turn of the player to move, returning the points they
+    /// scored. If either action is illegal nothing is played.
+    fn play_turn(&mut self, turn: &str) -> PyResult<u32> {
+        let turn: Turn = turn.parse().map_err(value_error)?;
+        let outcome = self.game.play_turn(turn).map_err(value_error)?;
+        Ok(outcome.points)
+    }
+
+    /// Takes a single action, such as resigning or offering a draw.
+    fn do_action(&mut self, action: &str) -> PyResult<()> {
+        let action: Action = action.parse().map_err(value_error)?;
+        self.game.do_action(action).map_err(value_error)
+    }
+
+    /// The numbers the bots' networks see of the position.
+    fn features(&self) -> Vec<f32> {
+        position_features(&self.game)
+    }
+
+    fn copy(&self) -> PyGame {
+        self.clone()
+    }
+
+    fn __copy__(&self) -> PyGame {
+        self.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Game.from_notation({:?})", self.game.to_notation())
+    }
+}
+
+/// A computer opponent, written as on the command line, like `greedy`,
+/// `mcts:default` or `alphabeta:default:2`.
+#[pyclass(name = "Bot", module = "passtally", unsendable)]
+pub struct PyBot {
+    agent: Box<dyn Agent + Send>,
+}
+
+#[pymethods]
+impl PyBot {
+    #[new]
+    #[pyo3(signature = (spec, seed = 0))]
+    fn new(spec: &str, seed: u64) -> PyResult<PyBot> {
+        let spec: AgentSpec = spec.parse().map_err(value_error)?;
+        Ok(PyBot {
+            agent: spec.build(seed).map_err(value_error)?,
+        })
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.agent.name().to_string()
+    }
+
+    /// The turn the bot would play, searching at most `nodes` nodes or to
+    /// `depth` if given.
+    #[pyo3(signature = (game, nodes = None, depth = None))]
+    fn choose_turn(
+        &mut self,
+        game: &PyGame,
+        nodes: Option<u64>,
+        depth: Option<u32>,
+    ) -> PyResult<String> {
+        if !game.game.has_legal_turn() {
+            return Err(value_error("The player to move has no legal turn."));
+        }
+        let limits = match (nodes, depth) {
+            (Some(nodes), _) => Limits::nodes(nodes),
+            (None, Some(depth)) => Limits::depth(depth),
+            (None, None) => Limits::none(),
+        };
+        Ok(self.agent.choose_turn(&game.game, &limits).to_string())
+    }
+}
+
+/// The game as a reinforcement learning environment, with the learner in
+/// the first seat and a bot in the others. Actions are numbered below
+/// `action_space`.
+#[pyclass(name = "Env", module = "passtally", unsendable)]
+pub struct PyEnv {
+    env: PasstallyEnv,
+}
+
+#[pymethods]
+impl PyEnv {
+    #[new]
+    #[pyo3(signature = (opponent = "greedy", players = 2, seed = 0))]
+    fn new(opponent: &str, players: u8, seed: u64) -> PyResult<PyEnv> {
+        let spec: AgentSpec = opponent.parse().map_err(value_error)?;
+        let agent = spec.build(seed).map_err(value_error)?;
+        let env = PasstallyEnv::new(rules(players)?, agent, seed);
+        Ok(PyEnv { env })
+    }
+
+    #[classattr]
+    fn action_space() -> u32 {
+        ACTION_SPACE
+    }
+
+    #[getter]
+    fn game(&self) -> PyGame {
+        PyGame {
+            game: self.env.game().clone(),
+        }
+    }
+
+    /// Starts a new episode and gives the first observation.
+    fn reset(&mut self) -> Vec<f32> {
+        self.env.reset()
+    }
+
+    /// Takes the action, giving the observation, the reward and whether the
+    /// episode is over.
+    fn step(&mut self, action: u32) -> PyResult<(Vec<f32>, f32, bool)> {
+        self.env.step(action).map_err(value_error)
+    }
+
+    fn action_mask(&self) -> Vec<bool> {
+        self.env.action_mask()
+    }
+}
+
+#[pymodule]
+fn passtally(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<PyBot>()?;
+    module.add_class::<PyEnv>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bots_play_games() {
+        let mut game = PyGame::new(2, Some(3)).unwrap();
+        let mut bot = PyBot::new("greedy", 0).unwrap();
+        for _ in 0..4 {
+            let turn = bot.choose_turn(&game, None, None).unwrap();
+            assert!(game.legal_turns().contains(&turn));
+            game.play_turn(&turn).unwrap();
+        }
+        assert_eq!(game.round(), 4);
+        let copy = PyGame::from_notation(&game.to_notation()).unwrap();
+        assert_eq!(copy.to_notation(), game.to_notation());
+    }
+}
@@ -0,0 +1,258 @@
+//! Recorded games that can be saved and played back.
+
+use thiserror::Error;
+
+use crate::game::{Game, InvalidGame, PasstallyError, Turn, TurnOutcome};
+use crate::overlay::Overlay;
+use crate::rules::Ruleset;
+
+/// Everything needed to reproduce a game: the setup and every turn played.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay {
+    /// Display names, in seat order.
+    pub players: Vec<String>,
+    /// Seed the decks were shuffled with.
+    pub seed: u64,
+    pub rules: Ruleset,
+    pub turns: Vec<Turn>,
+    /// The outcome of each turn, in the same order as `turns`.
+    pub outcomes: Vec<TurnOutcome>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// A note on the position after a turn, with annotations to show on the board.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bookmark {
+    /// The number of turns played before the position, so 0 is the start.
+    pub turn: usize,
+    pub note: String,
+    pub overlay: Overlay,
+}
+
+impl Replay {
+    pub fn new(players: Vec<String>, rules: Ruleset, seed: u64) -> Self {
+        Replay {
+            players,
+            seed,
+            rules,
+            turns: Vec::new(),
+            outcomes: Vec::new(),
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// The game as it was before the first turn.
+    pub fn initial_game(&self) -> Game {
+        Game::new_with_rules(self.rules.clone(), self.seed)
+    }
+
+    /// Plays the turn on the game and records it if it was legal.
+    pub fn play(&mut self, game: &mut Game, turn: Turn) -> Result<TurnOutcome, PasstallyError> {
+        let outcome = game.play_turn(turn.clone())?;
+        self.turns.push(turn);
+        self.outcomes.push(outcome.clone());
+        Ok(outcome)
+    }
+
+    /// Reproduces the final game, checking that every turn has the recorded outcome.
+    pub fn play_back(&self) -> Result<Game, ReplayError> {
+        self.rules.validate()?;
+        let mut game = self.initial_game();
+        for (i, turn) in self.turns.iter().enumerate() {
+            let outcome = game
+                .play_turn(turn.clone())
+                .map_err(|err| ReplayError::IllegalTurn(i, err))?;
+            if let Some(expected) = self.outcomes.get(i) {
+                if *expected != outcome {
+                    return Err(ReplayError::OutcomeMismatch(i));
+                }
+            }
+        }
+        Ok(game)
+    }
+
+    /// The game as it was after the first `turns` turns.
+    pub fn game_at(&self, turns: usize) -> Result<Game, ReplayError> {
+        self.rules.validate()?;
+        let mut game = self.initial_game();
+        for (i, turn) in self.turns.iter().take(turns).enumerate() {
+            game.play_turn(turn.clone())
+                .map_err(|err| ReplayError::IllegalTurn(i, err))?;
+        }
+        Ok(game)
+    }
+
+    /// The final score of each player, counting the points their handicap
+    /// started them with.
+    pub fn final_scores(&self) -> Result<Vec<u32>, ReplayError> {
+        let mut scores = vec![0; self.rules.player_count as usize];
+        for (score, handicap) in scores.iter_mut().zip(&self.rules.handicap) {
+            *score = handicap.points;
+        }
+        for (i, outcome) in self.outcomes.iter().enumerate() {
+            let score = scores
+                .get_mut(outcome.player as usize)
+                .ok_or(ReplayError::NoSuchPlayer(i, outcome.player))?;
+            *score += outcome.points;
+        }
+        Ok(scores)
+    }
+}
+
+#[cfg(feature = "json")]
+impl Replay {
+    pub fn to_json(&self) -> Result<String, ReplayError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a replay, checking that its rules can set up a game.
+    pub fn from_json(s: &str) -> Result<Self, ReplayError> {
+        let replay: Replay = serde_json::from_str(s)?;
+        replay.rules.validate()?;
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), ReplayError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ReplayError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Turn {0} of the replay is illegal: {1}")]
+    IllegalTurn(usize, PasstallyError),
+    #[error("Turn {0} of the replay didn't have the recorded outcome.")]
+    OutcomeMismatch(usize),
+    #[error("Turn {0} of the replay was played by player {1}, who isn't in the game.")]
+    NoSuchPlayer(usize, u8),
+    #[error("The replay's rules can't set up a game: {0}")]
+    Rules(#[from] InvalidGame),
+    #[error("Couldn't access the replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "json")]
+    #[error("Couldn't read the replay: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardPosition;
+    use crate::game::Action;
+    use crate::piece::PositionedPiece;
+    use crate::rules::Handicap;
+
+    /// Plays a few turns placing the first market piece wherever it fits.
+    fn recorded_replay() -> (Replay, Game) {
+        let mut replay = Replay::new(vec!["Alice".into(), "Bob".into()], Ruleset::new(2), 42);
+        let mut game = replay.initial_game();
+        for i in 0..4 {
+            let piece = game.market()[0].unwrap();
+            let place = Action::PlacePiece(PositionedPiece {
+                piece,
+                position: BoardPosition::new(i, 0),
+                rotation: 1,
+            });
+            let (from, to) = [(0, 23), (1, 2), (6, 5), (7, 8)][i as usize];
+            let turn = Turn(place, Action::MovePlayerMarker(from, to));
+            replay.play(&mut game, turn).unwrap();
+        }
+        (replay, game)
+    }
+
+    #[test]
+    fn play_back_reproduces_the_game() {
+        let (replay, game) = recorded_replay();
+        let played_back = replay.play_back().unwrap();
+        assert_eq!(played_back.to_full_notation(), game.to_full_notation());
+        assert_eq!(replay.final_scores().unwrap(), game.scores());
+        assert_eq!(replay.game_at(replay.turns.len()).unwrap(), game);
+        assert_eq!(replay.game_at(0).unwrap(), replay.initial_game());
+    }
+
+    #[test]
+    fn tampered_replays_fail() {
+        let (mut replay, _) = recorded_replay();
+        replay.outcomes[1].points += 1;
+        assert!(matches!(
+            replay.play_back(),
+            Err(ReplayError::OutcomeMismatch(1))
+        ));
+
+        replay.outcomes[1].points -= 1;
+        replay.turns[2].1 = Action::MovePlayerMarker(7, 8);
+        assert!(matches!(
+            replay.play_back(),
+            Err(ReplayError::IllegalTurn(
+                2,
+                PasstallyError::NotYourPlayerMarker(7)
+            ))
+        ));
+    }
+
+    #[test]
+    fn final_scores_count_handicaps() {
+        let mut rules = Ruleset::new(2);
+        rules.handicap = vec![
+            Handicap::default(),
+            Handicap {
+                points: 10,
+                extra_markers: 0,
+            },
+        ];
+        let mut replay = Replay::new(vec!["Alice".into(), "Bob".into()], rules, 42);
+        let mut game = replay.initial_game();
+        let piece = game.market()[0].unwrap();
+        let place = Action::PlacePiece(PositionedPiece {
+            piece,
+            position: BoardPosition::new(0, 0),
+            rotation: 1,
+        });
+        replay
+            .play(&mut game, Turn(place, Action::MovePlayerMarker(0, 23)))
+            .unwrap();
+        assert_eq!(replay.final_scores().unwrap(), game.scores());
+        assert_eq!(replay.final_scores().unwrap()[1], 10);
+
+        replay.outcomes[0].player = 5;
+        assert!(matches!(
+            replay.final_scores(),
+            Err(ReplayError::NoSuchPlayer(0, 5))
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        use crate::overlay::Tone;
+
+        let (mut replay, _) = recorded_replay();
+        replay.bookmarks.push(Bookmark {
+            turn: 2,
+            note: "Blue should have connected 6 and 11 here.".into(),
+            overlay: Overlay::default().arrow(6, 11, Tone::Good),
+        });
+        let json = replay.to_json().unwrap();
+        assert_eq!(Replay::from_json(&json).unwrap(), replay);
+
+        // Replays saved before bookmarks existed still load.
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value.as_object_mut().unwrap().remove("bookmarks");
+        let old = Replay::from_json(&value.to_string()).unwrap();
+        assert!(old.bookmarks.is_empty());
+
+        value["rules"]["player_count"] = 0.into();
+        assert!(matches!(
+            Replay::from_json(&value.to_string()),
+            Err(ReplayError::Rules(InvalidGame::PlayerCount(0)))
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+//! JSON-RPC 2.0 for driving a game from another process, one request a line,
+//! as `passtally-engine --json-rpc` serves on stdin and stdout. The methods
+//! are:
+//!
+//! - `new_game`, `{"players": 2, "seed": 7}`: starts a game, the seed is
+//!   random if left out. Returns the state.
+//! - `legal_turns`: every turn the player to move could play, in notation.
+//! - `play_turn`, `{"turn": "P:Pink@c3r1+M:4>7"}`: plays it and returns the
+//!   [`TurnOutcome`].
+//! - `state`: the game as serialized with serde.
+//!
+//! Batches are answered with a batch, and notifications, without an `id`,
+//! aren't answered at all.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::game::{Game, Turn, TurnOutcome};
+use crate::rules::Ruleset;
+
+/// A game being played over JSON-RPC.
+#[derive(Debug, Default)]
+pub struct RpcServer {
+    game: Option<Game>,
+}
+
+#[derive(Deserialize)]
+struct NewGame {
+    players: u8,
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct PlayTurn {
+    turn: String,
+}
+
+impl RpcServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The game being played, if one has been started.
+    pub fn game(&self) -> Option<&Game> {
+        self.game.as_ref()
+    }
+
+    /// The response to a line of JSON, or nothing if it was only
+    /// notifications.
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        let response = match serde_json::from_str(line) {
+            Ok(Value::Array(requests)) if !requests.is_empty() => {
+                let responses: Vec<Value> = requests
+                    .into_iter()
+                    .filter_map(|request| self.handle(request))
+                    .collect();
+                if responses.is_empty() {
+                    return None;
+                }
+                Value::Array(responses)
+            }
+            Ok(request) => self.handle(request)?,
+            Err(err) => error_response(Value::Null, &RpcError::Parse(err.to_string())),
+        };
+        Some(response.to_string())
+    }
+
+    /// The response to a single request, or nothing for a notification.
+    fn handle(&mut self, request: Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let method = match (request.get("jsonrpc"), request.get("method")) {
+            (Some(version), Some(Value::String(method))) if version == "2.0" => method,
+            _ => {
+                let id = id.unwrap_or(Value::Null);
+                return Some(error_response(id, &RpcError::InvalidRequest));
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.call(method, params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => error_response(id, &err),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "new_game" => {
+                let params: NewGame = parse_params(params)?;
+                if !(1..=4).contains(&params.players) {
+                    return Err(RpcError::InvalidParams(format!(
+                        "Games are for 1 to 4 players, not {}.",
+                        params.players
+                    )));
+                }
+                let seed = params.seed.unwrap_or_else(rand::random);
+                let game = Game::new_with_rules(Ruleset::new(params.players), seed);
+                let state = to_value(&game);
+                self.game = Some(game);
+                Ok(state)
+            }
+            "legal_turns" => {
+                let game = self.game.as_ref().ok_or(RpcError::NoGame)?;
+                Ok(game
+                    .legal_turns_iter()
+                    .map(|turn| Value::String(turn.to_string()))
+                    .collect())
+            }
+            "play_turn" => {
+                let params: PlayTurn = parse_params(params)?;
+                let turn: Turn = params
+                    .turn
+                    .parse()
+                    .map_err(|err| RpcError::InvalidParams(format!("{}", err)))?;
+                let game = self.game.as_mut().ok_or(RpcError::NoGame)?;
+                let outcome: TurnOutcome = game
+                    .play_turn(turn)
+                    .map_err(|err| RpcError::IllegalTurn(err.to_string()))?;
+                Ok(to_value(&outcome))
+            }
+            "state" => Ok(to_value(self.game.as_ref().ok_or(RpcError::NoGame)?)),
+            _ => Err(RpcError::MethodNotFound(method.to_string())),
+        }
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::InvalidParams(err.to_string()))
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).expect("Games should always serialize")
+}
+
+fn error_response(id: Value, err: &RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": err.code(), "message": err.to_string() },
+        "id": id,
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum RpcError {
+    #[error("The request isn't JSON: {0}")]
+    Parse(String),
+    #[error("The request isn't a JSON-RPC 2.0 request.")]
+    InvalidRequest,
+    #[error("There is no method {0}.")]
+    MethodNotFound(String),
+    #[error("Bad parameters: {0}")]
+    InvalidParams(String),
+    #[error("No game has been started, call new_game first.")]
+    NoGame,
+    #[error("The turn can't be played: {0}")]
+    IllegalTurn(String),
+}
+
+impl RpcError {
+    /// The error code of the response, those of the JSON-RPC specification
+    /// and then positive ones for the game.
+    pub fn code(&self) -> i64 {
+        match self {
+            RpcError::Parse(_) => -32700,
+            RpcError::InvalidRequest => -32600,
+            RpcError::MethodNotFound(_) => -32601,
+            RpcError::InvalidParams(_) => -32602,
+            RpcError::NoGame => 1,
+            RpcError::IllegalTurn(_) => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(server: &mut RpcServer, request: Value) -> Value {
+        serde_json::from_str(&server.handle_line(&request.to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn plays_a_game_over_json_rpc() {
+        let mut server = RpcServer::new();
+        let response = call(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "state", "id": 1}),
+        );
+        assert_eq!(response["error"]["code"], 1);
+
+        call(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "new_game", "params": {"players": 2, "seed": 3}, "id": 2}),
+        );
+        let turns = call(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "legal_turns", "id": 3}),
+        );
+        let turn = turns["result"][0].clone();
+        let response = call(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "play_turn", "params": {"turn": turn}, "id": "four"}),
+        );
+        assert_eq!(response["id"], "four");
+        assert_eq!(response["result"]["player"], 0);
+        assert_eq!(server.game().unwrap().round(), 1);
+
+        let state = call(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "state", "id": 5}),
+        );
+        let game: Game = serde_json::from_value(state["result"].clone()).unwrap();
+        assert_eq!(&game, server.game().unwrap());
+    }
+
+    #[test]
+    fn answers_errors_and_skips_notifications() {
+        let mut server = RpcServer::new();
+        let response: Value = serde_json::from_str(&server.handle_line("{").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+        assert_eq!(
+            call(
+                &mut server,
+                json!({"jsonrpc": "2.0", "method": "fly", "id": 1})
+            )["error"]["code"],
+            -32601
+        );
+        assert_eq!(
+            server.handle_line(
+                r#"{"jsonrpc": "2.0", "method": "new_game", "params": {"players": 2}}"#
+            ),
+            None
+        );
+        assert!(server.game().is_some());
+        let batch = call(
+            &mut server,
+            json!([
+                {"jsonrpc": "2.0", "method": "play_turn", "params": {"turn": "resign"}, "id": 1},
+                {"jsonrpc": "2.0", "method": "legal_turns"},
+                {"jsonrpc": "1.0", "method": "state", "id": 2},
+            ]),
+        );
+        assert_eq!(batch.as_array().unwrap().len(), 2);
+        assert_eq!(batch[0]["error"]["code"], -32602);
+        assert_eq!(batch[1]["error"]["code"], -32600);
+    }
+}
@@ -0,0 +1,134 @@
+//! The rules a game is played with.
+//!
+//! Each turn the player to move takes two actions. They can place the top
+//! piece of one of the three decks (the market) or move one of their markers
+//! along the edge, past at most one empty slot. After the turn, every line
+//! connecting two of the player's markers scores the sum of the heights of the
+//! squares it passes through. The game ends once all three decks are empty.
+//!
+//! With [`Ruleset::allow_redraw`], an action can also be spent discarding the
+//! top piece of a deck, revealing the one below it.
+//!
+//! A player with no legal turn is stuck, for example when nothing on offer fits
+//! on the board and their markers can't move. What happens then is up to
+//! [`Ruleset::stalemate`].
+//!
+//! Weaker players can be given a [`Handicap`]: points to start with, extra
+//! markers, or both.
+//!
+//! With [`Ruleset::teams`], four players play two against two. A line between
+//! two markers of the same team scores for whichever of them plays the turn,
+//! and teams are ranked by the sum of their scores.
+
+use alloc::vec::Vec;
+
+use crate::game::InvalidGame;
+
+/// The most players a game is for, and the seats whose markers can stand
+/// on the board.
+pub const MAX_PLAYERS: u8 = 4;
+
+/// Options chosen when setting up a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct Ruleset {
+    /// 1..=4 players.
+    pub player_count: u8,
+    /// Whether an action can be spent discarding a market piece.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_redraw: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stalemate: StalemateRule,
+    /// Each player's handicap, by player. Players without an entry have none.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub handicap: Vec<Handicap>,
+    /// Players 0 and 2 play together against players 1 and 3.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub teams: bool,
+}
+
+/// A head start for a weaker player.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct Handicap {
+    /// Points the player starts with.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub points: u32,
+    /// Markers the player starts with on top of the usual one per side. They
+    /// go on the free slots furthest along each side, as long as there are
+    /// any.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_markers: u8,
+}
+
+/// What happens when the player to move has no legal turn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub enum StalemateRule {
+    /// The game ends, and the scores stand as they are.
+    #[default]
+    EndGame,
+    /// The player passes. The game ends when nobody can play.
+    PassTurn,
+}
+
+impl Ruleset {
+    pub fn new(player_count: u8) -> Self {
+        Ruleset {
+            player_count,
+            allow_redraw: false,
+            stalemate: StalemateRule::default(),
+            handicap: Vec::new(),
+            teams: false,
+        }
+    }
+
+    /// Two against two.
+    pub fn teams() -> Self {
+        Ruleset {
+            teams: true,
+            ..Ruleset::new(4)
+        }
+    }
+
+    /// Checks that a game can be set up with the rules.
+    pub fn validate(&self) -> Result<(), InvalidGame> {
+        if !(1..=MAX_PLAYERS).contains(&self.player_count) {
+            return Err(InvalidGame::PlayerCount(self.player_count));
+        }
+        if self.teams && self.player_count != 4 {
+            return Err(InvalidGame::Teams);
+        }
+        Ok(())
+    }
+
+    /// The player's team. Without teams, every player is a team of their own.
+    pub fn team_of(&self, player: u8) -> u8 {
+        if self.teams {
+            player % 2
+        } else {
+            player
+        }
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset::new(2)
+    }
+}
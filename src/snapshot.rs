@@ -0,0 +1,162 @@
+//! Compact binary encoding of games and replays, for storing lots of them.
+//!
+//! Every encoding starts with a four byte tag saying what it holds and a
+//! format version, followed by the bincode encoding of the value.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::game::{Game, InvalidGame};
+use crate::replay::Replay;
+
+/// Game snapshots hold the board, so they change whenever its layout does.
+const GAME_VERSION: u8 = 2;
+const REPLAY_VERSION: u8 = 1;
+const GAME_TAG: &[u8; 4] = b"PTGS";
+const REPLAY_TAG: &[u8; 4] = b"PTRP";
+
+fn encode<T: Serialize>(tag: &[u8; 4], version: u8, value: &T) -> Result<Vec<u8>, SnapshotError> {
+    let mut bytes = tag.to_vec();
+    bytes.push(version);
+    bincode::serialize_into(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+fn decode<T: DeserializeOwned>(
+    tag: &[u8; 4],
+    version: u8,
+    bytes: &[u8],
+) -> Result<T, SnapshotError> {
+    if bytes.len() < 5 || &bytes[..4] != tag {
+        return Err(SnapshotError::WrongTag);
+    }
+    if bytes[4] != version {
+        return Err(SnapshotError::UnsupportedVersion(bytes[4]));
+    }
+    Ok(bincode::deserialize(&bytes[5..])?)
+}
+
+impl Game {
+    pub fn to_snapshot(&self) -> Result<Vec<u8>, SnapshotError> {
+        encode(GAME_TAG, GAME_VERSION, self)
+    }
+
+    /// The game in the snapshot, checked with [`Game::validate`] so a
+    /// damaged or edited snapshot can't make the engine panic later.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Game, SnapshotError> {
+        let game: Game = decode(GAME_TAG, GAME_VERSION, bytes)?;
+        game.validate()?;
+        Ok(game)
+    }
+}
+
+impl Replay {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        encode(REPLAY_TAG, REPLAY_VERSION, self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, SnapshotError> {
+        decode(REPLAY_TAG, REPLAY_VERSION, bytes)
+    }
+
+    pub fn save_binary(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load_binary(path: impl AsRef<Path>) -> Result<Replay, SnapshotError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("The data isn't the expected kind of snapshot.")]
+    WrongTag,
+    #[error("Snapshot format version {0} isn't supported.")]
+    UnsupportedVersion(u8),
+    #[error("Couldn't access the snapshot file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't encode or decode the snapshot: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("The snapshot holds a game that can't be played: {0}")]
+    InvalidGame(#[from] InvalidGame),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardPosition;
+    use crate::game::{Action, Turn};
+    use crate::piece::PositionedPiece;
+    use crate::rules::Ruleset;
+
+    fn replay() -> (Replay, Game) {
+        let mut replay = Replay::new(vec!["Alice".into(), "Bob".into()], Ruleset::new(2), 3);
+        let mut game = replay.initial_game();
+        for (i, &(from, to)) in [(0, 23), (1, 2)].iter().enumerate() {
+            let place = Action::PlacePiece(PositionedPiece {
+                piece: game.market()[1].unwrap(),
+                position: BoardPosition::new(2, i as i8),
+                rotation: 0,
+            });
+            replay
+                .play(&mut game, Turn(place, Action::MovePlayerMarker(from, to)))
+                .unwrap();
+        }
+        (replay, game)
+    }
+
+    #[test]
+    fn game_round_trip() {
+        let (_, game) = replay();
+        let bytes = game.to_snapshot().unwrap();
+        assert_eq!(
+            Game::from_snapshot(&bytes).unwrap().to_full_notation(),
+            game.to_full_notation()
+        );
+    }
+
+    #[test]
+    fn replay_round_trip() {
+        let (replay, _) = replay();
+        let bytes = replay.to_bytes().unwrap();
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+    }
+
+    #[test]
+    fn tags_and_versions_are_checked() {
+        let (replay, game) = replay();
+        let mut bytes = replay.to_bytes().unwrap();
+        assert!(matches!(
+            Game::from_snapshot(&bytes),
+            Err(SnapshotError::WrongTag)
+        ));
+
+        bytes[4] = REPLAY_VERSION + 1;
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+
+        let bytes = game.to_snapshot().unwrap();
+        assert!(matches!(
+            Game::from_snapshot(&bytes[..bytes.len() - 1]),
+            Err(SnapshotError::Bincode(_))
+        ));
+    }
+
+    #[test]
+    fn impossible_games_are_rejected() {
+        let (_, mut game) = replay();
+        game.scores.push(0);
+        let bytes = game.to_snapshot().unwrap();
+        assert!(matches!(
+            Game::from_snapshot(&bytes),
+            Err(SnapshotError::InvalidGame(InvalidGame::Scores(3, 2)))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! Short on-screen notifications for things the player should know about but
+//! that don't need an answer, stacked in the top right corner of the window.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+/// Something worth telling the player about. Send these as events and the
+/// toast plugin takes care of showing them.
+#[derive(Debug, Clone)]
+pub enum Notification {
+    DrawOffered(String),
+    AutosaveCompleted,
+    IllegalMove(String),
+}
+
+impl Notification {
+    fn text(&self) -> String {
+        match self {
+            Notification::DrawOffered(name) => format!("{} offers a draw", name),
+            Notification::AutosaveCompleted => "Game saved".to_string(),
+            Notification::IllegalMove(reason) => reason.clone(),
+        }
+    }
+}
+
+/// How long toasts stay up and how many are shown at once. Insert this before
+/// adding the plugin to change it.
+pub struct ToastSettings {
+    /// Seconds a toast stays on screen.
+    pub duration: f32,
+    /// Toasts beyond this wait until one of the shown ones goes away.
+    pub max_visible: usize,
+}
+
+impl Default for ToastSettings {
+    fn default() -> Self {
+        ToastSettings {
+            duration: 3.0,
+            max_visible: 4,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ToastQueue {
+    pending: VecDeque<Notification>,
+}
+
+struct Toast {
+    /// Row in the stack, counted from the top.
+    slot: usize,
+    remaining: f32,
+}
+
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Notification>()
+            .init_resource::<ToastSettings>()
+            .init_resource::<ToastQueue>()
+            .add_startup_system(setup.system())
+            .add_system(queue_notifications.system())
+            .add_system(expire_toasts.system())
+            .add_system(show_toasts.system());
+    }
+}
+
+fn setup(commands: &mut Commands) {
+    commands.spawn(CameraUiBundle::default());
+}
+
+fn queue_notifications(
+    events: Res<Events<Notification>>,
+    mut reader: Local<EventReader<Notification>>,
+    mut queue: ResMut<ToastQueue>,
+) {
+    for notification in reader.iter(&events) {
+        info!("{}", notification.text());
+        queue.pending.push_back(notification.clone());
+    }
+}
+
+fn expire_toasts(commands: &mut Commands, time: Res<Time>, mut query: Query<(Entity, &mut Toast)>) {
+    for (entity, mut toast) in query.iter_mut() {
+        toast.remaining -= time.delta_seconds();
+        if toast.remaining <= 0.0 {
+            commands.despawn(entity);
+        }
+    }
+}
+
+fn show_toasts(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<ToastSettings>,
+    mut queue: ResMut<ToastQueue>,
+    query: Query<&Toast>,
+) {
+    let mut taken: Vec<usize> = query
+        .iter()
+        .filter(|toast| toast.remaining > 0.0)
+        .map(|toast| toast.slot)
+        .collect();
+
+    while let Some(slot) = (0..settings.max_visible).find(|slot| !taken.contains(slot)) {
+        let notification = match queue.pending.pop_front() {
+            Some(notification) => notification,
+            None => break,
+        };
+        taken.push(slot);
+
+        commands
+            .spawn(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(8.0 + 28.0 * slot as f32),
+                        right: Val::Px(8.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text {
+                    value: notification.text(),
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    style: TextStyle {
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            })
+            .with(Toast {
+                slot,
+                remaining: settings.duration,
+            });
+    }
+}
@@ -0,0 +1,211 @@
+//! JavaScript bindings, for web frontends to play on the real rules engine.
+//!
+//! `Game` and `Action` are exported as classes. Actions and turns go back and
+//! forth in the text notation of [`crate::notation`], which is also how
+//! games are saved and loaded. Build the package with
+//!
+//! ```text
+//! cargo rustc --release --lib --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/passtally_rs.wasm
+//! ```
+//!
+//! which also writes the TypeScript definitions to `pkg/passtally_rs.d.ts`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::board::BoardPosition;
+use crate::game::{Action, Game, Turn};
+use crate::piece::PositionedPiece;
+use crate::rules::Ruleset;
+
+/// A game of passtally.
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    /// A new game between 1 to 4 players, with the decks shuffled by the seed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(players: u8, seed: u64) -> Result<WasmGame, JsError> {
+        if !(1..=4).contains(&players) {
+            return Err(JsError::new("Games are for 1 to 4 players."));
+        }
+        Ok(WasmGame {
+            game: Game::new_with_rules(Ruleset::new(players), seed),
+        })
+    }
+
+    #[wasm_bindgen(js_name = fromNotation)]
+    pub fn from_notation(notation: &str) -> Result<WasmGame, JsError> {
+        Ok(WasmGame {
+            game: Game::from_notation(notation)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = toNotation)]
+    pub fn to_notation(&self) -> String {
+        self.game.to_notation()
+    }
+
+    #[wasm_bindgen(getter, js_name = nextPlayer)]
+    pub fn next_player(&self) -> u8 {
+        self.game.next_player()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn round(&self) -> u32 {
+        self.game.round()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn scores(&self) -> Vec<u32> {
+        self.game.scores().to_vec()
+    }
+
+    /// The pieces on top of the three decks, an empty string for empty decks.
+    #[wasm_bindgen(getter)]
+    pub fn market(&self) -> Vec<String> {
+        self.game
+            .market()
+            .iter()
+            .map(|piece| piece.map_or_else(String::new, |piece| piece.to_string()))
+            .collect()
+    }
+
+    #[wasm_bindgen(getter, js_name = isFinished)]
+    pub fn is_finished(&self) -> bool {
+        self.game.is_finished()
+    }
+
+    /// Every action the player to move could take as half of their turn.
+    #[wasm_bindgen(js_name = legalActions)]
+    pub fn legal_actions(&self) -> Vec<WasmAction> {
+        self.game
+            .legal_actions_iter()
+            .map(|action| WasmAction { action })
+            .collect()
+    }
+
+    /// Every turn the player to move could play, in turn notation such as
+    /// `P:Pink@c3r1+M:4>7`.
+    #[wasm_bindgen(js_name = legalTurns)]
+    pub fn legal_turns(&self) -> Vec<String> {
+        self.game
+            .legal_turns_iter()
+            .map(|turn| turn.to_string())
+            .collect()
+    }
+
+    /// Plays both actions as the turn of the player to move, returning the
+    /// points they scored. If either action is illegal nothing is played.
+    #[wasm_bindgen(js_name = playTurn)]
+    pub fn play_turn(&mut self, first: &WasmAction, second: &WasmAction) -> Result<u32, JsError> {
+        let turn = Turn(first.action.clone(), second.action.clone());
+        Ok(self.game.play_turn(turn)?.points)
+    }
+
+    /// Like `playTurn`, with the turn in turn notation.
+    #[wasm_bindgen(js_name = playTurnNotation)]
+    pub fn play_turn_notation(&mut self, turn: &str) -> Result<u32, JsError> {
+        Ok(self.game.play_turn(turn.parse()?)?.points)
+    }
+
+    /// Takes a single action, such as resigning or offering a draw.
+    #[wasm_bindgen(js_name = doAction)]
+    pub fn do_action(&mut self, action: &WasmAction) -> Result<(), JsError> {
+        Ok(self.game.do_action(action.action.clone())?)
+    }
+}
+
+/// Half of a turn, or resigning or a draw offer.
+#[wasm_bindgen(js_name = Action)]
+pub struct WasmAction {
+    action: Action,
+}
+
+#[wasm_bindgen(js_class = Action)]
+impl WasmAction {
+    /// Places the piece, named like `Pink`, with its first half at the
+    /// column and row counted from the top left, rotated clockwise 0 to 3
+    /// times.
+    pub fn place(piece: &str, x: i8, y: i8, rotation: u8) -> Result<WasmAction, JsError> {
+        let action = Action::PlacePiece(PositionedPiece {
+            piece: piece.parse()?,
+            position: BoardPosition::new(x, y),
+            rotation,
+        });
+        Ok(WasmAction { action })
+    }
+
+    /// Moves the player marker between edge slots, 0 to 23 clockwise from
+    /// the top left.
+    #[wasm_bindgen(js_name = moveMarker)]
+    pub fn move_marker(from: u8, to: u8) -> WasmAction {
+        WasmAction {
+            action: Action::MovePlayerMarker(from, to),
+        }
+    }
+
+    /// Discards the top piece of the deck, 0 to 2.
+    pub fn redraw(deck: u8) -> WasmAction {
+        WasmAction {
+            action: Action::RedrawMarket(deck),
+        }
+    }
+
+    pub fn resign() -> WasmAction {
+        WasmAction {
+            action: Action::Resign,
+        }
+    }
+
+    #[wasm_bindgen(js_name = offerDraw)]
+    pub fn offer_draw() -> WasmAction {
+        WasmAction {
+            action: Action::OfferDraw,
+        }
+    }
+
+    #[wasm_bindgen(js_name = acceptDraw)]
+    pub fn accept_draw() -> WasmAction {
+        WasmAction {
+            action: Action::AcceptDraw,
+        }
+    }
+
+    /// The action in notation, such as `P:Pink@c3r1` or `M:4>7`.
+    pub fn parse(notation: &str) -> Result<WasmAction, JsError> {
+        Ok(WasmAction {
+            action: notation.parse()?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_notation(&self) -> String {
+        self.action.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_through_the_bindings() {
+        let mut game = WasmGame::new(2, 4).unwrap();
+        let actions = game.legal_actions();
+        let turn = game.legal_turns().remove(0);
+        assert!(actions
+            .iter()
+            .any(|action| turn.starts_with(&action.to_notation())));
+
+        game.play_turn_notation(&turn).unwrap();
+        assert_eq!(game.round(), 1);
+        let copy = WasmGame::from_notation(&game.to_notation()).unwrap();
+        assert_eq!(copy.to_notation(), game.to_notation());
+    }
+}
@@ -0,0 +1,75 @@
+//! Keys for Zobrist hashing of positions.
+//!
+//! Every feature of a position (what lies on a square, where a marker stands,
+//! who is to move, ...) gets a fixed pseudo random key, and the hash of a
+//! position is the xor of the keys of its features. Changing a feature then only
+//! takes two xors: one to remove the old key and one to add the new one.
+//!
+//! The keys are derived from the feature itself, so they are the same in every
+//! build and hashes can be stored and compared across runs.
+
+use crate::board::BoardPosition;
+use crate::piece::{PartialPiece, Piece, Side};
+
+const SQUARE: u64 = 1;
+const MARKER: u64 = 2;
+const TO_MOVE: u64 = 3;
+const DECK: u64 = 4;
+#[cfg(feature = "ai")]
+const SCORE: u64 = 5;
+
+fn key(kind: u64, feature: u64) -> u64 {
+    // splitmix64
+    let mut z = (kind << 56 | feature).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A square holding a piece half. Empty squares have no key.
+pub(crate) fn square(
+    pos: BoardPosition,
+    height: u32,
+    routing: PartialPiece,
+    partner: Option<Side>,
+) -> u64 {
+    if height == 0 {
+        return 0;
+    }
+    let partner = partner.map_or(4, |side| side as u64);
+    let feature =
+        (pos.index() as u64) << 40 | (height as u64) << 8 | (routing as u64) << 4 | partner;
+    key(SQUARE, feature)
+}
+
+/// A player's marker on an edge slot.
+pub(crate) fn marker(slot: u8, player: u8) -> u64 {
+    key(MARKER, (slot as u64) << 8 | player as u64)
+}
+
+/// The player to move.
+pub(crate) fn to_move(player: u8) -> u64 {
+    key(TO_MOVE, player as u64)
+}
+
+/// The piece on top of a deck and how many pieces are left in it.
+pub(crate) fn deck(deck: usize, top: Option<Piece>, remaining: usize) -> u64 {
+    let top = top.map_or(0, |piece| piece.index() as u64 + 1);
+    key(DECK, (deck as u64) << 40 | (remaining as u64) << 8 | top)
+}
+
+/// A player's score. Not part of the hash of a game, but searches valuing
+/// positions by the scores need it in their keys.
+#[cfg(feature = "ai")]
+pub(crate) fn score(player: u8, score: u32) -> u64 {
+    key(SCORE, (player as u64) << 32 | score as u64)
+}
+
+/// The hash of all the markers, for when they are set up rather than moved.
+pub(crate) fn markers(player_markers: &[Option<u8>; 24]) -> u64 {
+    player_markers
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, player)| player.map(|player| marker(slot as u8, player)))
+        .fold(0, |hash, key| hash ^ key)
+}
@@ -0,0 +1,98 @@
+//! Every bot built from the same seed has to pick the same turn in the same
+//! position under the same node or depth limits, so games between bots can
+//! be replayed and regressions bisected. Time limits are left out, how far a
+//! search gets in a second depends on the machine, and so are parallel
+//! searches on more than one thread.
+
+use passtally_rs::ai::network::UniformNetwork;
+use passtally_rs::ai::{
+    difficulty::difficulty_seeded, Agent, AgentSpec, Difficulty, Limits, NetworkAgent, Preset,
+};
+use passtally_rs::game::Game;
+use passtally_rs::rules::Ruleset;
+
+/// The opening position and one a few turns into the game.
+fn positions() -> Vec<Game> {
+    let mut game = Game::new_with_rules(Ruleset::new(2), 11);
+    let mut bot = AgentSpec::Greedy.build(0).unwrap();
+    let mut positions = vec![game.clone()];
+    for _ in 0..6 {
+        let turn = bot.choose_turn(&game, &Limits::none());
+        game.play_turn(turn).unwrap();
+    }
+    positions.push(game);
+    positions
+}
+
+/// Builds the bot twice and has both copies play through the positions,
+/// comparing every turn.
+fn check(
+    positions: &[Game],
+    name: &str,
+    limits: &Limits,
+    build: impl Fn() -> Box<dyn Agent + Send>,
+) {
+    let (mut first, mut second) = (build(), build());
+    for (i, game) in positions.iter().enumerate() {
+        assert_eq!(
+            first.choose_turn(game, limits),
+            second.choose_turn(game, limits),
+            "{} chose differently in position {}",
+            name,
+            i
+        );
+    }
+}
+
+#[test]
+fn bots_are_deterministic() {
+    let positions = positions();
+    let preset = Preset::default();
+    let specs = vec![
+        AgentSpec::Random,
+        AgentSpec::Greedy,
+        AgentSpec::Mcts {
+            preset: preset.clone(),
+        },
+        AgentSpec::Ismcts {
+            preset: preset.clone(),
+        },
+        AgentSpec::AlphaBeta {
+            preset: preset.clone(),
+            depth: 2,
+        },
+    ];
+    for spec in &specs {
+        // Information set playouts are slow in debug builds.
+        let nodes = match spec {
+            AgentSpec::Ismcts { .. } => 5,
+            _ => 20,
+        };
+        check(&positions, &spec.to_string(), &Limits::nodes(nodes), || {
+            spec.build(7).unwrap()
+        });
+    }
+    check(&positions, "network", &Limits::nodes(10), || {
+        Box::new(NetworkAgent::new(UniformNetwork, preset.mcts.clone()))
+    });
+}
+
+#[test]
+fn difficulty_levels_are_deterministic() {
+    let positions = positions();
+    for &level in &Difficulty::ALL {
+        check(&positions, level.name(), &Limits::nodes(20), || {
+            difficulty_seeded(level, 3)
+        });
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn single_threaded_parallel_search_is_deterministic() {
+    use passtally_rs::ai::ParallelMctsAgent;
+
+    check(&positions(), "parallel mcts", &Limits::nodes(20), || {
+        Box::new(ParallelMctsAgent::new(Preset::default().mcts, 1, 7))
+    });
+}
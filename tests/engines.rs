@@ -0,0 +1,42 @@
+//! Plays the engine binary against itself through the adjudicator, the way
+//! community engines would be run.
+
+use std::time::Duration;
+
+use passtally_rs::ai::adjudicator::{play, Fault, MatchConfig};
+use passtally_rs::ai::external::ExternalEngine;
+use passtally_rs::clock::TimeControl;
+
+fn engine(bot: &str) -> ExternalEngine {
+    ExternalEngine::spawn(env!("CARGO_BIN_EXE_passtally-engine"), &[bot.to_string()]).unwrap()
+}
+
+#[test]
+fn engines_play_each_other() {
+    let mut engines = vec![engine("random"), engine("greedy")];
+    assert_eq!(engines[0].name(), "random");
+
+    let control = TimeControl::new(Duration::from_secs(60), Duration::from_secs(1));
+    let config = MatchConfig {
+        max_turns: 6,
+        ..MatchConfig::new(control, 3)
+    };
+    let game = play(&mut engines, &config);
+    assert_eq!(game.replay.players, vec!["random", "greedy"]);
+    assert_eq!(game.replay.turns.len(), 6);
+    assert!(game.faults.is_empty());
+    assert!(game.replay.play_back().is_ok());
+}
+
+#[test]
+fn slow_engines_lose_on_time() {
+    let mut engines = vec![engine("mcts:default"), engine("random")];
+    let control = TimeControl::new(Duration::from_millis(0), Duration::from_millis(0));
+    let config = MatchConfig {
+        grace: Duration::from_millis(0),
+        ..MatchConfig::new(control, 3)
+    };
+    let game = play(&mut engines, &config);
+    assert_eq!(game.faults, vec![(0, Fault::Timeout)]);
+    assert_eq!(game.rewards, vec![0.0, 1.0]);
+}
@@ -0,0 +1,108 @@
+//! Checks that each feature combination exposes what it promises. Run with
+//! e.g. `cargo test --test features --no-default-features --features serde`.
+
+use passtally_rs::game::{Action, Game};
+use passtally_rs::rules::Ruleset;
+
+/// The rules engine, which builds without `std`.
+#[test]
+fn core_engine() {
+    let mut game = Game::new_with_rules(Ruleset::new(2), 0);
+    game.do_action(Action::MovePlayerMarker(0, 2)).unwrap();
+
+    let notation = game.to_notation();
+    assert_eq!(
+        Game::from_notation(&notation).unwrap().to_notation(),
+        notation
+    );
+}
+
+/// Every feature in the manifest has a row in the README's table.
+#[test]
+fn features_are_documented() {
+    let manifest = include_str!("../Cargo.toml");
+    let readme = include_str!("../README.md");
+    let features = manifest
+        .split("[features]")
+        .nth(1)
+        .and_then(|rest| rest.split("\n[").next())
+        .unwrap();
+    for line in features.lines() {
+        let name = match line.split_once('=') {
+            Some((name, _)) if !line.starts_with('#') => name.trim(),
+            _ => continue,
+        };
+        if name != "default" {
+            assert!(readme.contains(&format!("| `{}`", name)), "{}", name);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_game_state() {
+    let game = Game::new_with_rules(Ruleset::new(2), 3);
+    let json = serde_json::to_string(&game).unwrap();
+    let restored: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.to_full_notation(), game.to_full_notation());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_replays() {
+    use passtally_rs::replay::Replay;
+
+    let replay = Replay::new(vec!["Alice".into(), "Bob".into()], Ruleset::default(), 7);
+    let json = replay.to_json().unwrap();
+    assert_eq!(Replay::from_json(&json).unwrap(), replay);
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn binary_snapshots() {
+    let game = Game::new(3);
+    let bytes = game.to_snapshot().unwrap();
+    assert_eq!(
+        Game::from_snapshot(&bytes).unwrap().to_full_notation(),
+        game.to_full_notation()
+    );
+}
+
+#[cfg(feature = "ai")]
+#[test]
+fn ai_presets() {
+    use passtally_rs::ai::Preset;
+
+    assert!(!Preset::builtin().is_empty());
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn net_replay_store() {
+    use passtally_rs::net::{ReplayStore, RetentionPolicy};
+    use passtally_rs::replay::Replay;
+
+    let mut store = ReplayStore::in_memory(RetentionPolicy::default());
+    let id = store
+        .insert(Replay::new(vec!["Alice".into()], Ruleset::new(1), 7))
+        .unwrap();
+    assert_eq!(store.http_get(&format!("/replays/{}", id)).0, 200);
+}
+
+#[cfg(feature = "paths")]
+#[test]
+fn data_dirs() {
+    use passtally_rs::paths::DataDirs;
+
+    let dirs = DataDirs::portable("passtally");
+    assert!(dirs.replays().starts_with("passtally"));
+}
+
+#[cfg(all(feature = "ai", not(feature = "serde")))]
+compile_error!("The ai feature should always enable serde.");
+
+#[cfg(all(feature = "nn", not(feature = "ai")))]
+compile_error!("The nn feature should always enable ai.");
+
+#[cfg(all(feature = "net", not(feature = "json")))]
+compile_error!("The net feature should always enable json.");
@@ -0,0 +1,111 @@
+//! Plays whole recorded games from `tests/games`, checking everyone's score
+//! after each turn, that the game ends after the last turn and who won. The
+//! scoring history has to add up to the same scores.
+//!
+//! A game file starts with `players N`, `seed S` and optionally `redraw` to
+//! allow discarding market pieces. Then comes one line per turn with the turn
+//! and the scores after it separated by `|`, and finally `winner P` or `draw`.
+//! Lines starting with `#` are comments.
+
+use std::fs;
+
+use passtally_rs::game::{EndReason, Game, Phase, Turn};
+use passtally_rs::rules::Ruleset;
+
+fn play(name: &str) {
+    let path = format!("{}/tests/games/{}.txt", env!("CARGO_MANIFEST_DIR"), name);
+    let transcript = fs::read_to_string(&path).unwrap();
+
+    let mut rules = Ruleset::default();
+    let mut seed = 0;
+    let mut game: Option<Game> = None;
+    let mut winner = None;
+    for (i, line) in transcript.lines().enumerate() {
+        let line = line.trim();
+        let context = format!("{}:{}: {}", name, i + 1, line);
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some((turn, scores)) = line.split_once('|') {
+            let game = game.get_or_insert_with(|| Game::new_with_rules(rules.clone(), seed));
+            assert!(!game.is_finished(), "{}: the game is already over", context);
+
+            let turn: Turn = turn.trim().parse().expect(&context);
+            let player = game.next_player();
+            let before = game.scores()[player as usize];
+            let events_before = game.score_events().len();
+            let outcome = game.play_turn(turn).expect(&context);
+            assert_eq!(outcome.player, player, "{}", context);
+            assert_eq!(before + outcome.points, game.scores()[player as usize]);
+
+            let events = &game.score_events()[events_before..];
+            assert_eq!(
+                events.iter().map(|event| event.points).sum::<u32>(),
+                outcome.points,
+                "{}",
+                context
+            );
+            for event in events {
+                assert_eq!((event.turn, event.player), (game.round() - 1, player));
+                assert!(event.points > 0 && !event.path.is_empty());
+            }
+
+            let scores: Vec<u32> = scores
+                .split(',')
+                .map(|score| score.trim().parse().unwrap())
+                .collect();
+            assert_eq!(game.scores(), &scores[..], "{}", context);
+        } else if let Some(count) = line.strip_prefix("players ") {
+            rules.player_count = count.parse().unwrap();
+        } else if let Some(value) = line.strip_prefix("seed ") {
+            seed = value.parse().unwrap();
+        } else if line == "redraw" {
+            rules.allow_redraw = true;
+        } else if let Some(player) = line.strip_prefix("winner ") {
+            winner = Some(Some(player.parse::<usize>().unwrap()));
+        } else if line == "draw" {
+            winner = Some(None);
+        } else {
+            panic!("{}: unknown line", context);
+        }
+    }
+
+    let game = game.expect("The game should have turns");
+    assert_eq!(game.phase(), Phase::Finished(EndReason::DecksEmpty));
+    assert!(game.legal_turns().is_empty());
+    for (player, &score) in game.scores().iter().enumerate() {
+        let history: u32 = game
+            .score_events()
+            .iter()
+            .filter(|event| event.player as usize == player)
+            .map(|event| event.points)
+            .sum();
+        assert_eq!(history, score);
+    }
+
+    let best = *game.scores().iter().max().unwrap();
+    let leaders: Vec<usize> = (0..game.scores().len())
+        .filter(|&player| game.scores()[player] == best)
+        .collect();
+    let expected = winner.expect("The game file should say who won");
+    match expected {
+        Some(player) => assert_eq!(leaders, vec![player]),
+        None => assert!(leaders.len() > 1),
+    }
+}
+
+#[test]
+fn two_players() {
+    play("two_players");
+}
+
+#[test]
+fn three_players() {
+    play("three_players");
+}
+
+#[test]
+fn four_players_redraw() {
+    play("four_players_redraw");
+}
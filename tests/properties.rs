@@ -0,0 +1,32 @@
+//! Properties of the rules engine, checked against pieces, actions and games
+//! proptest makes up. Run with
+//! `cargo test --features proptest --test properties`.
+
+use proptest::prelude::*;
+
+use passtally_rs::game::{Action, Game, Turn};
+use passtally_rs::piece::{RotatedPartialPiece, Side};
+
+proptest! {
+    /// A line passing back through a piece leaves the way it came in.
+    #[test]
+    fn pass_is_an_involution(piece: RotatedPartialPiece, side: Side) {
+        prop_assert_eq!(piece.pass(piece.pass(side)), side);
+    }
+
+    #[test]
+    fn play_turn_is_atomic_on_error(game: Game, first: Action, second: Action) {
+        let mut played = game.clone();
+        if played.play_turn(Turn(first, second)).is_err() {
+            prop_assert_eq!(played, game);
+        }
+    }
+
+    #[test]
+    fn legal_turns_can_be_played(game: Game, pick: prop::sample::Index) {
+        let count = game.legal_turns_iter().count();
+        prop_assume!(count > 0);
+        let turn = game.legal_turns_iter().nth(pick.index(count)).unwrap();
+        prop_assert!(game.clone().play_turn(turn).is_ok());
+    }
+}